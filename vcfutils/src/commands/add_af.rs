@@ -1,10 +1,24 @@
-use crate::logic::add_af;
+use crate::logic::add_af::{
+    self, SexChromosomePloidy, GRCH37_PSEUDO_AUTOSOMAL_REGIONS, GRCH38_PSEUDO_AUTOSOMAL_REGIONS,
+};
+use crate::logic::filter::load_regions;
+use crate::logic::inheritance::load_ped;
 use crate::utils;
 use anyhow::Context;
-use autocompress::io::RayonWriter;
-use clap::Args;
+use clap::{Args, ValueEnum};
 use std::collections::hash_map::RandomState;
 use std::collections::HashMap;
+use std::fs::File;
+use std::io::BufReader;
+use vcf::U8Vec;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum GenomeBuild {
+    #[value(name = "GRCh37")]
+    GRCh37,
+    #[value(name = "GRCh38")]
+    GRCh38,
+}
 
 #[derive(Debug, Args)]
 #[command(
@@ -45,16 +59,38 @@ pub struct AddAF {
         default_value = "4"
     )]
     precision: usize,
+    #[arg(
+        long,
+        help = "Add site QC metrics: HWE and ExcHet p-values, InbreedingCoeff, CallRate and F_MISSING"
+    )]
+    site_qc: bool,
+    #[arg(
+        long,
+        help = "Sample sex table (csv or tsv with sample and sex columns, or PED file). Males are counted as haploid on chromosome X and Y outside of pseudo-autosomal regions, and heterozygous calls of them are counted as missing."
+    )]
+    sex: Option<String>,
+    #[arg(
+        long,
+        help = "BED file of pseudo-autosomal regions (default: regions of --genome-build)",
+        requires = "sex"
+    )]
+    par: Option<String>,
+    #[arg(
+        long,
+        help = "Genome build to define pseudo-autosomal regions",
+        default_value = "GRCh38",
+        ignore_case = true
+    )]
+    genome_build: GenomeBuild,
+    #[arg(long, help = "# of threads (0: all CPUs)", default_value = "0")]
+    threads: usize,
 }
 
 impl AddAF {
     pub fn run(&self) -> anyhow::Result<()> {
+        utils::set_threads(self.threads).context("Failed to set # of threads")?;
         let mut vcf_reader = utils::open_vcf_from_path(self.input.as_deref())?;
-        let mut vcf_writer =
-            RayonWriter::new(autocompress::autodetect_create_or_stdout_prefer_bgzip(
-                self.output.as_deref(),
-                autocompress::CompressionLevel::Default,
-            )?);
+        let mut vcf_writer = utils::create_vcf_writer(self.output.as_deref())?;
         let af_precision: usize = self.precision;
 
         let category_to_sample = if let Some(x) = self.category.as_ref() {
@@ -73,13 +109,63 @@ impl AddAF {
             HashMap::new()
         };
 
+        let sex_chromosome_ploidy = self
+            .sex
+            .as_deref()
+            .map(|x| load_sex_chromosome_ploidy(x, self.par.as_deref(), self.genome_build))
+            .transpose()?;
+
         add_af::add_af(
             &mut vcf_reader,
             &mut vcf_writer,
             &category_to_sample,
             af_precision,
+            sex_chromosome_ploidy.as_ref(),
+            self.site_qc,
         )?;
+        vcf_writer.close()?;
 
         Ok(())
     }
 }
+
+/// Load pseudo-autosomal regions from BED file, or use regions of the genome build.
+pub fn load_pseudo_autosomal_regions(
+    par: Option<&str>,
+    genome_build: GenomeBuild,
+) -> anyhow::Result<Vec<(U8Vec, u64, u64)>> {
+    if let Some(par) = par {
+        return load_regions(par).with_context(|| format!("Failed to load BED: {}", par));
+    }
+    Ok(match genome_build {
+        GenomeBuild::GRCh37 => GRCH37_PSEUDO_AUTOSOMAL_REGIONS,
+        GenomeBuild::GRCh38 => GRCH38_PSEUDO_AUTOSOMAL_REGIONS,
+    }
+    .iter()
+    .map(|(chromosome, start, end)| (chromosome.as_bytes().to_vec(), *start, *end))
+    .collect())
+}
+
+/// Load sample sex from a sex table or PED file, and pseudo-autosomal regions.
+pub fn load_sex_chromosome_ploidy(
+    sex: &str,
+    par: Option<&str>,
+    genome_build: GenomeBuild,
+) -> anyhow::Result<SexChromosomePloidy> {
+    let sample_sex = if sex.ends_with(".ped") {
+        load_ped(BufReader::new(
+            File::open(sex).with_context(|| format!("Failed to open {}", sex))?,
+        ))
+        .with_context(|| format!("Failed to load PED file: {}", sex))?
+        .into_iter()
+        .map(|x| (x.individual, x.sex))
+        .collect()
+    } else {
+        add_af::load_sex_table(utils::auto_csv_reader_from_path(sex, true)?)
+            .with_context(|| format!("Failed to load sex table: {}", sex))?
+    };
+    Ok(SexChromosomePloidy::new(
+        sample_sex,
+        &load_pseudo_autosomal_regions(par, genome_build)?,
+    ))
+}
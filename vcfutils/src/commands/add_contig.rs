@@ -1,5 +1,5 @@
 use crate::logic::add_contig;
-use autocompress::io::{RayonReader, RayonWriter};
+use crate::utils;
 use clap::Args;
 use std::io::BufReader;
 
@@ -14,16 +14,13 @@ pub struct AddContig {
 
 impl AddContig {
     pub fn run(&self) -> anyhow::Result<()> {
-        let vcf_reader = RayonReader::new(autocompress::autodetect_open(&self.input)?);
-        let mut vcf_writer =
-            RayonWriter::new(autocompress::autodetect_create_or_stdout_prefer_bgzip(
-                self.output.as_ref(),
-                autocompress::CompressionLevel::Default,
-            )?);
+        let vcf_reader = utils::open_vcf_stream(Some(&self.input))?;
+        let mut vcf_writer = utils::create_vcf_writer(self.output.as_deref())?;
 
         let contigs = add_contig::scan_contig(&mut BufReader::new(vcf_reader))?;
-        let vcf_reader = autocompress::autodetect_open(&self.input)?;
+        let vcf_reader = utils::open_vcf_stream(Some(&self.input))?;
         add_contig::add_contig(&mut BufReader::new(vcf_reader), &mut vcf_writer, &contigs)?;
+        vcf_writer.close()?;
 
         Ok(())
     }
@@ -0,0 +1,57 @@
+use crate::logic::annotate::{annotate_vcf, open_annotation_source, AnnotationField};
+use crate::utils;
+use anyhow::Context;
+use clap::Args;
+
+#[derive(Debug, Args)]
+#[command(
+    about = "Copy INFO fields from tabix indexed VCF or TSV into matching alleles",
+    version,
+    author
+)]
+pub struct Annotate {
+    #[arg(help = "Input VCF file")]
+    input: Option<String>,
+    #[arg(short, long, help = "Output VCF file")]
+    output: Option<String>,
+    #[arg(
+        short,
+        long,
+        help = "Annotation source (bgzip compressed and tabix indexed VCF, or TSV with CHROM, POS, REF and ALT columns)"
+    )]
+    source: String,
+    #[arg(
+        short,
+        long,
+        help = "INFO fields or TSV columns to copy. Use SOURCE:TARGET to rename (e.g. AF:gnomAD_AF)",
+        value_delimiter = ',',
+        required = true
+    )]
+    fields: Vec<String>,
+    #[arg(
+        short,
+        long,
+        help = "Prefix of copied INFO fields without explicit name",
+        default_value = ""
+    )]
+    prefix: String,
+}
+
+impl Annotate {
+    pub fn run(&self) -> anyhow::Result<()> {
+        let mut vcf_reader = utils::open_vcf_from_path(self.input.as_deref())?;
+        let mut vcf_writer = utils::create_vcf_writer(self.output.as_deref())?;
+        let mut source = open_annotation_source(&self.source)
+            .with_context(|| format!("Failed to open annotation source: {}", self.source))?;
+        let fields: Vec<_> = self
+            .fields
+            .iter()
+            .map(|x| AnnotationField::parse(x, &self.prefix))
+            .collect();
+
+        let annotated = annotate_vcf(&mut vcf_reader, &mut vcf_writer, source.as_mut(), &fields)?;
+        vcf_writer.close()?;
+        log::info!("{} records are annotated", annotated);
+        Ok(())
+    }
+}
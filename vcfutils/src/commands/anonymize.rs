@@ -0,0 +1,154 @@
+use crate::logic::anonymize::{anonymize_sam_header, anonymize_vcf, PseudonymKey, PseudonymMethod};
+use crate::utils;
+use anyhow::Context;
+use clap::{Args, ValueEnum};
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum InputType {
+    Vcf,
+    SamHeader,
+    FileNames,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Method {
+    Random,
+    Hash,
+}
+
+#[derive(Debug, Args)]
+#[command(
+    about = "Replace sample names with pseudonyms and remove identifying header lines",
+    long_about = "Replace sample names with pseudonyms and remove identifying header lines. Pseudonyms are recorded in a key file, and the same key can be applied to VCF, SAM header (e.g. `samtools view -H`, and `samtools reheader` to apply) and file names. Sample names in read group IDs are also replaced, so RG tags of reads must be renamed accordingly.",
+    version,
+    author
+)]
+pub struct Anonymize {
+    #[arg(help = "Input VCF, SAM header, or list of file names")]
+    input: Option<String>,
+    #[arg(short, long, help = "Output file")]
+    output: Option<String>,
+    #[arg(
+        short,
+        long,
+        help = "Key file of original names and pseudonyms. Loaded if exists, and new pseudonyms are added. Only the owner can read the key file."
+    )]
+    key: String,
+    #[arg(
+        short = 't',
+        long,
+        help = "Input type. For file names, original and anonymized file names are written in TSV.",
+        default_value = "vcf"
+    )]
+    input_type: InputType,
+    #[arg(
+        short,
+        long,
+        help = "Pseudonym generation method. Hashed pseudonyms are HMAC-SHA256 with the secret in the key file.",
+        default_value = "random"
+    )]
+    method: Method,
+    #[arg(short, long, help = "Prefix of pseudonyms", default_value = "S")]
+    prefix: String,
+}
+
+impl Anonymize {
+    pub fn run(&self) -> anyhow::Result<()> {
+        let mut key = if Path::new(&self.key).exists() {
+            PseudonymKey::load(BufReader::new(
+                File::open(&self.key).with_context(|| format!("Failed to open {}", self.key))?,
+            ))
+            .with_context(|| format!("Failed to load key file: {}", self.key))?
+        } else {
+            PseudonymKey::default()
+        };
+        let method = match self.method {
+            Method::Random => PseudonymMethod::Random,
+            Method::Hash => PseudonymMethod::Hash,
+        };
+
+        // Pseudonyms are registered and the key file is written before output, so that output is
+        // never left without its key.
+        let mut vcf_reader = None;
+        let mut sam_header = Vec::new();
+        match self.input_type {
+            InputType::Vcf => {
+                let reader = utils::open_vcf_from_path(self.input.as_deref())?;
+                for one in reader.header().samples() {
+                    key.pseudonym(one, method, &self.prefix)?;
+                }
+                vcf_reader = Some(reader);
+            }
+            InputType::SamHeader => {
+                let reader = BufReader::new(autocompress::autodetect_open_or_stdin(
+                    self.input.as_deref(),
+                )?);
+                anonymize_sam_header(reader, &mut sam_header, &mut key, method, &self.prefix)?;
+            }
+            InputType::FileNames => (),
+        }
+        self.write_key(&key)?;
+
+        match self.input_type {
+            InputType::Vcf => {
+                let mut reader = vcf_reader.context("VCF reader is not opened")?;
+                let mut writer = utils::create_vcf_writer(self.output.as_deref())?;
+                anonymize_vcf(&mut reader, &mut writer, &mut key, method, &self.prefix)?;
+                writer.close()?;
+            }
+            InputType::SamHeader => {
+                let mut writer = autocompress::autodetect_create_or_stdout(
+                    self.output.as_deref(),
+                    autocompress::CompressionLevel::Default,
+                )?;
+                writer.write_all(&sam_header)?;
+                writer.flush()?;
+            }
+            InputType::FileNames => {
+                let reader = BufReader::new(autocompress::autodetect_open_or_stdin(
+                    self.input.as_deref(),
+                )?);
+                let mut writer = autocompress::autodetect_create_or_stdout(
+                    self.output.as_deref(),
+                    autocompress::CompressionLevel::Default,
+                )?;
+                for line in reader.lines() {
+                    let line = line?;
+                    if line.is_empty() {
+                        continue;
+                    }
+                    writeln!(writer, "{}\t{}", line, key.anonymize_file_name(&line))?;
+                }
+                writer.flush()?;
+            }
+        }
+        Ok(())
+    }
+
+    fn write_key(&self, key: &PseudonymKey) -> anyhow::Result<()> {
+        let mut options = OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+            options.mode(0o600);
+            if Path::new(&self.key).exists() {
+                std::fs::set_permissions(&self.key, std::fs::Permissions::from_mode(0o600))?;
+            }
+        }
+        let mut key_writer = BufWriter::new(
+            options
+                .open(&self.key)
+                .with_context(|| format!("Failed to create key file: {}", self.key))?,
+        );
+        key.write(&mut key_writer)?;
+        key_writer
+            .into_inner()?
+            .sync_all()
+            .with_context(|| format!("Failed to write key file: {}", self.key))?;
+        Ok(())
+    }
+}
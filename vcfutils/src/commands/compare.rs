@@ -0,0 +1,140 @@
+use super::table_output::write_table;
+use super::vcf2csv::DataType;
+use crate::logic::compare::{
+    annotate_truth, compare_query, comparison_column_types, load_truth, write_comparison_summary,
+    CompareConfig,
+};
+use crate::logic::filter::{load_regions, VariantFilter};
+use crate::utils;
+use anyhow::Context;
+use clap::Args;
+use std::io::Write;
+
+#[derive(Debug, Args)]
+#[command(
+    about = "Compare query variants and genotypes with truth VCF",
+    version,
+    author
+)]
+pub struct Compare {
+    #[arg(help = "Truth VCF file")]
+    truth: String,
+    #[arg(help = "Query VCF file")]
+    query: String,
+    #[arg(short, long, help = "Output summary file")]
+    output: Option<String>,
+    #[arg(
+        short = 't',
+        long = "type",
+        help = "Data type to output",
+        default_value = "auto"
+    )]
+    datatype: DataType,
+    #[arg(
+        long,
+        help = "Output truth VCF annotated with BENCHMARK INFO (TP or FN)"
+    )]
+    truth_output: Option<String>,
+    #[arg(
+        long,
+        help = "Output query VCF annotated with BENCHMARK INFO (TP or FP)"
+    )]
+    query_output: Option<String>,
+    #[arg(short, long, help = "BED file to stratify comparison")]
+    bed: Option<String>,
+    #[arg(long, help = "Ignore records whose FILTER is not PASS")]
+    pass_only: bool,
+    #[arg(
+        short,
+        long,
+        help = "Pairs of truth and query sample names to compare (e.g. NA12878:SAMPLE1). Samples with the same name are compared by default",
+        value_delimiter = ','
+    )]
+    sample_pairs: Vec<String>,
+    #[arg(short = 'p', long, help = "Float precision", default_value = "4")]
+    precision: usize,
+}
+
+impl Compare {
+    pub fn run(&self) -> anyhow::Result<()> {
+        let mut truth_reader = utils::open_vcf_from_path(Some(&self.truth))?;
+        let mut query_reader = utils::open_vcf_from_path(Some(&self.query))?;
+        let sample_pairs = if self.sample_pairs.is_empty() {
+            CompareConfig::common_samples(truth_reader.header(), query_reader.header())
+        } else {
+            self.sample_pairs
+                .iter()
+                .map(|x| {
+                    let (truth, query) = x
+                        .split_once(':')
+                        .with_context(|| format!("Invalid sample pair: {}", x))?;
+                    Ok((truth.as_bytes().to_vec(), query.as_bytes().to_vec()))
+                })
+                .collect::<anyhow::Result<Vec<_>>>()?
+        };
+        for (truth, query) in sample_pairs.iter() {
+            if !truth_reader.header().samples().contains(truth) {
+                return Err(anyhow::anyhow!(
+                    "Sample not found in truth VCF: {}",
+                    String::from_utf8_lossy(truth)
+                ));
+            }
+            if !query_reader.header().samples().contains(query) {
+                return Err(anyhow::anyhow!(
+                    "Sample not found in query VCF: {}",
+                    String::from_utf8_lossy(query)
+                ));
+            }
+        }
+        if sample_pairs.is_empty() {
+            log::warn!("No sample pair to compare genotypes. Sites are compared.");
+        }
+
+        let regions = self
+            .bed
+            .as_deref()
+            .map(|x| load_regions(x).with_context(|| format!("Failed to load BED: {}", x)))
+            .transpose()?;
+        let config = CompareConfig {
+            sample_pairs,
+            regions: regions
+                .as_deref()
+                .map(|x| VariantFilter::new(None, Some(x), 0)),
+            pass_only: self.pass_only,
+        };
+
+        let mut truth = load_truth(&mut truth_reader, &config)
+            .with_context(|| format!("Failed to load truth VCF: {}", self.truth))?;
+        let mut query_writer = self
+            .query_output
+            .as_deref()
+            .map(|x| utils::create_vcf_writer(Some(x)))
+            .transpose()?;
+        let summary = compare_query(
+            &mut query_reader,
+            &mut truth,
+            &config,
+            query_writer.as_mut().map(|x| x as &mut dyn Write),
+        )?;
+        if let Some(query_writer) = query_writer {
+            query_writer.close()?;
+        }
+
+        if let Some(truth_output) = self.truth_output.as_deref() {
+            let mut truth_reader = utils::open_vcf_from_path(Some(&self.truth))?;
+            let mut truth_writer = utils::create_vcf_writer(Some(truth_output))?;
+            annotate_truth(&mut truth_reader, &mut truth_writer, &truth, &config)?;
+            truth_writer.close()?;
+        }
+
+        write_table(
+            self.datatype,
+            self.output.as_deref(),
+            &comparison_column_types(),
+            0,
+            |mut writer| write_comparison_summary(&summary, self.precision, &mut writer),
+        )?;
+
+        Ok(())
+    }
+}
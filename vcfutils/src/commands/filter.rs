@@ -0,0 +1,68 @@
+use crate::logic::filter::{filter_vcf, load_regions, VariantFilter};
+use crate::utils;
+use anyhow::Context;
+use clap::Args;
+
+#[derive(Debug, Args)]
+#[command(
+    about = "Filter variants by gene list and panel regions",
+    version,
+    author
+)]
+pub struct Filter {
+    #[arg(help = "Input VCF file")]
+    input: Option<String>,
+    #[arg(short, long, help = "Output VCF file")]
+    output: Option<String>,
+    #[arg(
+        long,
+        help = "Keep variants annotated with genes in this list (gene symbol or Ensembl gene ID, one per line)",
+        required_unless_present = "bed"
+    )]
+    gene_list: Option<String>,
+    #[arg(long, help = "Keep variants overlapping regions in this BED file")]
+    bed: Option<String>,
+    #[arg(
+        long,
+        help = "Padding size of BED regions",
+        default_value = "0",
+        requires = "bed"
+    )]
+    padding: u64,
+    #[arg(
+        long,
+        help = "Pipe-delimited annotation INFO tag (default: detect ANN, CSQ or BCSQ)"
+    )]
+    annotation_key: Option<String>,
+}
+
+impl Filter {
+    pub fn run(&self) -> anyhow::Result<()> {
+        let mut vcf_reader = utils::open_vcf_from_path(self.input.as_deref())?;
+        let mut vcf_writer = utils::create_vcf_writer(self.output.as_deref())?;
+
+        let genes = self
+            .gene_list
+            .as_deref()
+            .map(|x| {
+                utils::load_list(x).with_context(|| format!("Failed to load gene list: {}", x))
+            })
+            .transpose()?;
+        let regions = self
+            .bed
+            .as_deref()
+            .map(|x| load_regions(x).with_context(|| format!("Failed to load BED: {}", x)))
+            .transpose()?;
+        let variant_filter = VariantFilter::new(genes.as_deref(), regions.as_deref(), self.padding);
+
+        let count = filter_vcf(
+            &mut vcf_reader,
+            &mut vcf_writer,
+            &variant_filter,
+            self.annotation_key.as_deref().map(|x| x.as_bytes()),
+        )?;
+        vcf_writer.close()?;
+        log::info!("{} variants are kept", count);
+        Ok(())
+    }
+}
@@ -0,0 +1,156 @@
+use super::table_output::write_table;
+use super::vcf2csv::DataType;
+use crate::logic::add_af::load_category_mapping;
+use crate::logic::gene_burden::{
+    gene_burden, gene_burden_column_types, sorted_categories, write_gene_burden, GeneBurdenConfig,
+};
+use crate::logic::vcf2table::SnpEffImpact;
+use crate::utils;
+use anyhow::Context;
+use clap::{Args, ValueEnum};
+use std::collections::hash_map::RandomState;
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Impact {
+    #[value(name = "HIGH")]
+    High,
+    #[value(name = "MODERATE")]
+    Moderate,
+    #[value(name = "LOW")]
+    Low,
+    #[value(name = "MODIFIER")]
+    Modifier,
+}
+
+#[derive(Debug, Args)]
+#[command(
+    about = "Count qualifying variants and carriers per gene and impact",
+    long_about = "Group variants by gene and the most severe impact of snpEff or VEP annotations, and count carriers and alleles of all samples and categories. Qualifying variant lists and allele counts of each sample can be written for rare-variant burden tests.",
+    version,
+    author
+)]
+pub struct GeneBurden {
+    #[arg(help = "Input VCF file")]
+    input: Option<String>,
+    #[arg(short, long, help = "Output file")]
+    output: Option<String>,
+    #[arg(
+        short = 't',
+        long = "type",
+        help = "Data type to output",
+        default_value = "auto"
+    )]
+    datatype: DataType,
+    #[arg(
+        short = 'c',
+        long,
+        help = "category mapping file (csv or tsv)",
+        requires_all = &["id", "value"]
+    )]
+    category: Option<String>,
+    #[arg(
+        short = 'i',
+        long,
+        help = "ID column name in category mapping file",
+        requires = "category"
+    )]
+    id: Option<Vec<String>>,
+    #[arg(
+        short = 'v',
+        long,
+        help = "value column name in category mapping file",
+        requires = "category"
+    )]
+    value: Option<Vec<String>>,
+    #[arg(
+        long,
+        help = "Impact classes to count",
+        value_delimiter = ',',
+        default_value = "HIGH,MODERATE",
+        ignore_case = true
+    )]
+    impact: Vec<Impact>,
+    #[arg(long, help = "Maximum allele frequency of qualifying variants")]
+    max_af: Option<f64>,
+    #[arg(
+        long,
+        help = "INFO tag of allele frequency for --max-af, such as population frequency (default: computed from genotypes)",
+        requires = "max_af"
+    )]
+    af_key: Option<String>,
+    #[arg(
+        long,
+        help = "Pipe-delimited annotation INFO tag (default: detect ANN, CSQ or BCSQ)"
+    )]
+    annotation_key: Option<String>,
+    #[arg(
+        short = 's',
+        long,
+        help = "Add qualifying allele count columns of each sample"
+    )]
+    sample_columns: bool,
+}
+
+impl GeneBurden {
+    pub fn run(&self) -> anyhow::Result<()> {
+        let category_to_sample = if let Some(x) = self.category.as_ref() {
+            load_category_mapping::<_, RandomState>(
+                &mut utils::auto_csv_reader_from_path(x, true)?,
+                self.id
+                    .as_ref()
+                    .map(|x| x.iter().map(|x| x.as_bytes().to_vec()).collect())
+                    .context("No ID column name")?,
+                self.value
+                    .as_ref()
+                    .map(|x| x.iter().map(|x| x.as_bytes().to_vec()).collect())
+                    .context("No value column name")?,
+            )?
+        } else {
+            HashMap::new()
+        };
+        let config = GeneBurdenConfig {
+            impacts: self
+                .impact
+                .iter()
+                .map(|x| match x {
+                    Impact::High => SnpEffImpact::High,
+                    Impact::Moderate => SnpEffImpact::Moderate,
+                    Impact::Low => SnpEffImpact::Low,
+                    Impact::Modifier => SnpEffImpact::Modifier,
+                })
+                .collect(),
+            max_af: self.max_af,
+            af_key: self.af_key.as_ref().map(|x| x.as_bytes().to_vec()),
+            annotation_key: self.annotation_key.as_ref().map(|x| x.as_bytes().to_vec()),
+        };
+
+        let mut vcf_reader = utils::open_vcf_from_path(self.input.as_deref())?;
+        let burden = gene_burden(&mut vcf_reader, &config)?;
+        let samples = vcf_reader.header().samples().to_vec();
+        let categories = sorted_categories(&category_to_sample);
+        let sample_columns = if self.sample_columns {
+            Some(&samples[..])
+        } else {
+            None
+        };
+
+        write_table(
+            self.datatype,
+            self.output.as_deref(),
+            &gene_burden_column_types(&categories, sample_columns),
+            2,
+            |mut writer| {
+                write_gene_burden(
+                    &burden,
+                    &samples,
+                    &category_to_sample,
+                    self.sample_columns,
+                    &mut writer,
+                )
+            },
+        )?;
+
+        Ok(())
+    }
+}
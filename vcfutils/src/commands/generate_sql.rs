@@ -25,6 +25,9 @@ impl TableConfig for GenerateSql {
     fn canonical_list(&self) -> Option<&str> {
         None
     }
+    fn canonical_only(&self) -> bool {
+        false
+    }
     fn input(&self) -> &[String] {
         &[] // TODO: FIX HERE
     }
@@ -52,6 +55,33 @@ impl TableConfig for GenerateSql {
     fn priority_info_list(&self) -> Option<&[String]> {
         None
     }
+    fn annotation_key(&self) -> Option<&str> {
+        None
+    }
+    fn annotation_fields(&self) -> Option<&[String]> {
+        None
+    }
+    fn long_format(&self) -> bool {
+        false
+    }
+    fn gene_list(&self) -> Option<&str> {
+        None
+    }
+    fn bed(&self) -> Option<&str> {
+        None
+    }
+    fn padding(&self) -> u64 {
+        0
+    }
+    fn structural_variant(&self) -> bool {
+        false
+    }
+    fn sv_gene_bed(&self) -> Option<&str> {
+        None
+    }
+    fn sample_key(&self) -> Option<&str> {
+        None
+    }
 }
 
 impl GenerateSql {
@@ -0,0 +1,30 @@
+use crate::logic::gvcf_to_vcf::gvcf_to_vcf;
+use crate::utils;
+use anyhow::Context;
+use clap::Args;
+
+#[derive(Debug, Args)]
+#[command(
+    about = "Convert gVCF to VCF by removing reference blocks and <NON_REF> alleles",
+    version,
+    author
+)]
+pub struct GvcfToVcf {
+    #[arg(help = "Input gVCF file")]
+    input: Option<String>,
+    #[arg(short, long, help = "Output VCF file")]
+    output: Option<String>,
+    #[arg(long, help = "# of threads (0: all CPUs)", default_value = "0")]
+    threads: usize,
+}
+
+impl GvcfToVcf {
+    pub fn run(&self) -> anyhow::Result<()> {
+        utils::set_threads(self.threads).context("Failed to set # of threads")?;
+        let mut vcf_reader = utils::open_vcf_from_path(self.input.as_deref())?;
+        let mut vcf_writer = utils::create_vcf_writer(self.output.as_deref())?;
+        gvcf_to_vcf(&mut vcf_reader, &mut vcf_writer)?;
+        vcf_writer.close()?;
+        Ok(())
+    }
+}
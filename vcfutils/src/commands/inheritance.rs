@@ -0,0 +1,103 @@
+use super::add_af::{load_pseudo_autosomal_regions, GenomeBuild};
+use super::table_output::write_table;
+use super::vcf2csv::DataType;
+use crate::logic::inheritance::{
+    find_inheritance_candidates, find_trios, inheritance_column_types, load_ped,
+    write_inheritance_candidates, InheritanceConfig,
+};
+use crate::utils;
+use anyhow::Context;
+use clap::Args;
+use std::fs::File;
+use std::io::BufReader;
+
+#[derive(Debug, Args)]
+#[command(
+    about = "Find de novo, recessive and X-linked candidates in trios",
+    version,
+    author
+)]
+pub struct Inheritance {
+    #[arg(help = "Input VCF file")]
+    input: Option<String>,
+    #[arg(short, long, help = "Output file")]
+    output: Option<String>,
+    #[arg(short, long, help = "PED file")]
+    ped: String,
+    #[arg(
+        short = 't',
+        long = "type",
+        help = "Data type to output",
+        default_value = "auto"
+    )]
+    datatype: DataType,
+    #[arg(
+        long,
+        help = "Minimum genotype quality (GQ) of proband and parents",
+        default_value = "0"
+    )]
+    min_gq: u64,
+    #[arg(
+        long,
+        help = "Minimum read depth (DP) of proband and parents",
+        default_value = "0"
+    )]
+    min_dp: u64,
+    #[arg(
+        long,
+        help = "Pipe-delimited annotation INFO tag to find genes (default: detect ANN, CSQ or BCSQ)"
+    )]
+    annotation_key: Option<String>,
+    #[arg(
+        long,
+        help = "BED file of pseudo-autosomal regions (default: regions of --genome-build)"
+    )]
+    par: Option<String>,
+    #[arg(
+        long,
+        help = "Genome build to define pseudo-autosomal regions",
+        default_value = "GRCh38",
+        ignore_case = true
+    )]
+    genome_build: GenomeBuild,
+}
+
+impl Inheritance {
+    pub fn run(&self) -> anyhow::Result<()> {
+        let pedigree = load_ped(BufReader::new(
+            File::open(&self.ped).with_context(|| format!("Failed to open {}", self.ped))?,
+        ))
+        .with_context(|| format!("Failed to load PED file: {}", self.ped))?;
+        let mut vcf_reader = utils::open_vcf_from_path(self.input.as_deref())?;
+        let trios = find_trios(&pedigree, vcf_reader.header().samples());
+        if trios.is_empty() {
+            return Err(anyhow::anyhow!(
+                "No trio in the PED file is found in the VCF samples"
+            ));
+        }
+        log::info!("{} trios are found", trios.len());
+
+        let pseudo_autosomal_regions =
+            load_pseudo_autosomal_regions(self.par.as_deref(), self.genome_build)?;
+        let candidates = find_inheritance_candidates(
+            &mut vcf_reader,
+            &trios,
+            &InheritanceConfig {
+                min_gq: self.min_gq,
+                min_dp: self.min_dp,
+                pseudo_autosomal_regions,
+            },
+            self.annotation_key.as_deref().map(|x| x.as_bytes()),
+        )?;
+
+        write_table(
+            self.datatype,
+            self.output.as_deref(),
+            &inheritance_column_types(),
+            0,
+            |mut writer| write_inheritance_candidates(&candidates, &mut writer),
+        )?;
+
+        Ok(())
+    }
+}
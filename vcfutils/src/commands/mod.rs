@@ -1,14 +1,29 @@
 mod add_af;
 mod add_contig;
+mod annotate;
+mod anonymize;
+mod compare;
 mod extract_canonical;
+mod filter;
+mod gene_burden;
 mod generate_sql;
+mod gvcf_to_vcf;
+mod inheritance;
 mod list_samples;
+mod reheader;
+mod relatedness;
 mod remove_nonstandard_header;
 mod replace_contig;
 mod replace_sample;
 mod rewrite_format;
 mod rewrite_info;
+mod sample_stats;
+mod sort;
+mod subset_samples;
+mod table_output;
+mod validate;
 mod vcf2csv;
+mod vcf2sqlite;
 
 use clap::Subcommand;
 
@@ -25,6 +40,20 @@ pub enum Commands {
     GenerateSql(generate_sql::GenerateSql),
     ReplaceSampleName(replace_sample::ReplaceSampleName),
     ExtractCanonical(extract_canonical::ExtractCanonical),
+    SubsetSamples(subset_samples::SubsetSamples),
+    SampleStats(sample_stats::SampleStats),
+    VCF2Sqlite(vcf2sqlite::VCF2Sqlite),
+    Filter(filter::Filter),
+    Inheritance(inheritance::Inheritance),
+    Annotate(annotate::Annotate),
+    Compare(compare::Compare),
+    Reheader(reheader::Reheader),
+    Validate(validate::Validate),
+    Sort(sort::Sort),
+    GvcfToVcf(gvcf_to_vcf::GvcfToVcf),
+    Anonymize(anonymize::Anonymize),
+    Relatedness(relatedness::Relatedness),
+    GeneBurden(gene_burden::GeneBurden),
 }
 
 impl Commands {
@@ -41,6 +70,20 @@ impl Commands {
             Commands::GenerateSql(x) => x.run(),
             Commands::ReplaceSampleName(x) => x.run(),
             Commands::ExtractCanonical(x) => x.run(),
+            Commands::SubsetSamples(x) => x.run(),
+            Commands::SampleStats(x) => x.run(),
+            Commands::VCF2Sqlite(x) => x.run(),
+            Commands::Filter(x) => x.run(),
+            Commands::Inheritance(x) => x.run(),
+            Commands::Annotate(x) => x.run(),
+            Commands::Compare(x) => x.run(),
+            Commands::Reheader(x) => x.run(),
+            Commands::Validate(x) => x.run(),
+            Commands::Sort(x) => x.run(),
+            Commands::GvcfToVcf(x) => x.run(),
+            Commands::Anonymize(x) => x.run(),
+            Commands::Relatedness(x) => x.run(),
+            Commands::GeneBurden(x) => x.run(),
         }
     }
 }
@@ -0,0 +1,124 @@
+use crate::logic::reheader::{reheader, HeaderEdit};
+use crate::utils;
+use anyhow::Context;
+use clap::Args;
+use std::io::BufRead;
+use vcf::{VCFHeader, VCFReader};
+
+#[derive(Debug, Args)]
+#[command(
+    about = "Add, remove or modify header lines, or replace header with template",
+    version,
+    author
+)]
+pub struct Reheader {
+    #[arg(help = "Input VCF file")]
+    input: Option<String>,
+    #[arg(short, long, help = "Output VCF file")]
+    output: Option<String>,
+    #[arg(
+        short,
+        long,
+        help = "Template VCF file. Header lines of the template replace the input header"
+    )]
+    template: Option<String>,
+    #[arg(
+        short,
+        long,
+        help = "Header line to add or replace (e.g. '##INFO=<ID=DP,Number=1,Type=Integer,Description=\"Depth\">')"
+    )]
+    add: Vec<String>,
+    #[arg(
+        short,
+        long,
+        help = "Header line to remove (e.g. INFO/DP, FORMAT/AD, FILTER/LowQual, contig/chrUn)"
+    )]
+    remove: Vec<String>,
+    #[arg(
+        short,
+        long,
+        help = "Header attribute to set (e.g. INFO/AF:Number=A, INFO/DP:Description=Depth)"
+    )]
+    set: Vec<String>,
+    #[arg(long, help = "Do not check records against the new header")]
+    skip_validation: bool,
+}
+
+impl Reheader {
+    pub fn run(&self) -> anyhow::Result<()> {
+        let mut vcf_reader = utils::open_vcf_from_path(self.input.as_deref())?;
+        let template = self
+            .template
+            .as_deref()
+            .map(|x| {
+                utils::open_vcf_from_path(Some(x))
+                    .map(|x| x.header().clone())
+                    .with_context(|| format!("Failed to load template: {}", x))
+            })
+            .transpose()?;
+
+        let mut edits = Vec::new();
+        for one in self.remove.iter() {
+            edits.push(HeaderEdit::parse_remove(one)?);
+        }
+        for one in self.set.iter() {
+            edits.push(HeaderEdit::parse_set(one)?);
+        }
+        for one in self.add.iter() {
+            edits.push(
+                HeaderEdit::parse_add(one)
+                    .with_context(|| format!("Failed to parse header line: {}", one))?,
+            );
+        }
+
+        // Records are written to a temporary file which is renamed after all records are
+        // validated, so that partial output is not left on error.
+        let temporary_output = self.output.as_deref().map(temporary_path);
+        let result = write_records(
+            &mut vcf_reader,
+            temporary_output.as_deref(),
+            template.as_ref(),
+            &edits,
+            !self.skip_validation,
+        );
+        let count = match (result, self.output.as_deref(), temporary_output.as_deref()) {
+            (Ok(count), Some(output), Some(temporary)) => {
+                std::fs::rename(temporary, output)
+                    .with_context(|| format!("Failed to create {}", output))?;
+                count
+            }
+            (Ok(count), _, _) => count,
+            (Err(e), _, temporary) => {
+                if let Some(temporary) = temporary {
+                    let _ = std::fs::remove_file(temporary);
+                }
+                return Err(e);
+            }
+        };
+        log::info!("{} records are written", count);
+        Ok(())
+    }
+}
+
+fn write_records<R: BufRead>(
+    vcf_reader: &mut VCFReader<R>,
+    output: Option<&str>,
+    template: Option<&VCFHeader>,
+    edits: &[HeaderEdit],
+    validate: bool,
+) -> anyhow::Result<u64> {
+    let mut vcf_writer = utils::create_vcf_writer(output)?;
+    let count = reheader(vcf_reader, &mut vcf_writer, template, edits, validate)?;
+    vcf_writer.close()?;
+    Ok(count)
+}
+
+/// Temporary file in the same directory as `path`. The extension is kept to select the output
+/// format.
+fn temporary_path(path: &str) -> String {
+    let (directory, file_name) = match path.rfind('/') {
+        Some(x) => path.split_at(x + 1),
+        None => ("", path),
+    };
+    format!("{}.tmp-{}.{}", directory, std::process::id(), file_name)
+}
@@ -0,0 +1,176 @@
+use super::add_af::{load_pseudo_autosomal_regions, GenomeBuild};
+use super::table_output::write_table;
+use super::vcf2csv::DataType;
+use crate::logic::relatedness::{
+    load_sample_column, relatedness, relatedness_column_types, sex_check_column_types,
+    write_relatedness, write_sex_check, RelatednessConfig,
+};
+use crate::logic::sample_stats::SexInferenceConfig;
+use crate::utils;
+use crate::utils::tablewriter::TSVWriter;
+use anyhow::Context;
+use clap::Args;
+use std::collections::HashMap;
+
+#[derive(Debug, Args)]
+#[command(
+    about = "Compute pairwise kinship and IBS0, and check sex from chromosome X genotypes",
+    long_about = "Compute pairwise KING-robust kinship coefficient and IBS0 ratio from common biallelic autosomal SNVs pruned for linkage disequilibrium, and infer sex from heterozygous call fraction on chromosome X. Duplicated samples, unexpected relationship in families and sex mismatches are flagged.",
+    version,
+    author
+)]
+pub struct Relatedness {
+    #[arg(help = "Input VCF file")]
+    input: Option<String>,
+    #[arg(short, long, help = "Output file of sample pairs")]
+    output: Option<String>,
+    #[arg(short = 's', long, help = "Output file of sex check")]
+    sex_check_output: Option<String>,
+    #[arg(
+        short = 't',
+        long = "type",
+        help = "Data type to output",
+        default_value = "auto"
+    )]
+    datatype: DataType,
+    #[arg(
+        short = 'c',
+        long,
+        help = "category mapping file (csv or tsv) with expected sex or family",
+        requires = "id"
+    )]
+    category: Option<String>,
+    #[arg(
+        short = 'i',
+        long,
+        help = "ID column name in category mapping file",
+        requires = "category"
+    )]
+    id: Option<String>,
+    #[arg(
+        long,
+        help = "Expected sex column name in category mapping file",
+        requires = "category"
+    )]
+    sex_column: Option<String>,
+    #[arg(
+        long,
+        help = "Family column name in category mapping file. Unrelated pairs in a family and related pairs across families are flagged.",
+        requires = "category"
+    )]
+    family_column: Option<String>,
+    #[arg(long, help = "Minimum minor allele frequency", default_value = "0.05")]
+    min_maf: f64,
+    #[arg(long, help = "Minimum call rate", default_value = "0.95")]
+    min_call_rate: f64,
+    #[arg(
+        long,
+        help = "Window size in bp for linkage disequilibrium pruning (0: disable)",
+        default_value = "500000"
+    )]
+    prune_window: u64,
+    #[arg(
+        long,
+        help = "Maximum r^2 between SNVs in a pruning window",
+        default_value = "0.2"
+    )]
+    max_r2: f64,
+    #[arg(
+        long,
+        help = "Minimum kinship coefficient of pairs to output. Flagged pairs are always written.",
+        default_value = "0.0442"
+    )]
+    min_kinship: f64,
+    #[arg(
+        long,
+        help = "Maximum chrX heterozygous call fraction to infer male",
+        default_value = "0.1"
+    )]
+    male_max_het_ratio: f64,
+    #[arg(
+        long,
+        help = "Minimum chrX heterozygous call fraction to infer female",
+        default_value = "0.3"
+    )]
+    female_min_het_ratio: f64,
+    #[arg(
+        long,
+        help = "Genome build to define pseudo-autosomal regions excluded from sex check",
+        default_value = "GRCh38",
+        ignore_case = true
+    )]
+    genome_build: GenomeBuild,
+    #[arg(short = 'p', long, help = "Float precision", default_value = "4")]
+    precision: usize,
+    #[arg(long, help = "# of threads (0: all CPUs)", default_value = "0")]
+    threads: usize,
+}
+
+impl Relatedness {
+    pub fn run(&self) -> anyhow::Result<()> {
+        utils::set_threads(self.threads).context("Failed to set # of threads")?;
+        let load_column = |column: Option<&str>| -> anyhow::Result<HashMap<_, _>> {
+            match (self.category.as_deref(), self.id.as_deref(), column) {
+                (Some(category), Some(id), Some(column)) => Ok(load_sample_column(
+                    &mut utils::auto_csv_reader_from_path(category, true)?,
+                    id,
+                    column,
+                )
+                .with_context(|| format!("Failed to load {}", category))?),
+                _ => Ok(HashMap::new()),
+            }
+        };
+        let expected_sex = load_column(self.sex_column.as_deref())?;
+        let family = load_column(self.family_column.as_deref())?;
+
+        let config = RelatednessConfig {
+            min_maf: self.min_maf,
+            min_call_rate: self.min_call_rate,
+            prune_window: self.prune_window,
+            max_r2: self.max_r2,
+            pseudo_autosomal_regions: load_pseudo_autosomal_regions(None, self.genome_build)?,
+        };
+        let mut vcf_reader = utils::open_vcf_from_path(self.input.as_deref())?;
+        let (pairs, sex_check) = relatedness(&mut vcf_reader, &config, &family, self.min_kinship)?;
+
+        write_table(
+            self.datatype,
+            self.output.as_deref(),
+            &relatedness_column_types(),
+            2,
+            |mut writer| write_relatedness(&pairs, self.precision, &mut writer),
+        )?;
+
+        let sex_inference = SexInferenceConfig {
+            male_max_het_ratio: self.male_max_het_ratio,
+            female_min_het_ratio: self.female_min_het_ratio,
+        };
+        // Sex mismatches are logged even if sex check table is not written
+        match self.sex_check_output.as_deref() {
+            Some(path) => write_table(
+                self.datatype,
+                Some(path),
+                &sex_check_column_types(),
+                1,
+                |mut writer| {
+                    write_sex_check(
+                        &sex_check,
+                        &expected_sex,
+                        &sex_inference,
+                        self.precision,
+                        &mut writer,
+                    )
+                },
+            )?,
+            None => write_sex_check(
+                &sex_check,
+                &expected_sex,
+                &sex_inference,
+                self.precision,
+                &mut TSVWriter::new(std::io::sink()),
+            )?,
+        }
+
+        Ok(())
+    }
+}
@@ -1,4 +1,4 @@
-use autocompress::io::{RayonReader, RayonWriter};
+use crate::utils;
 use clap::Args;
 use std::io::{self, BufRead, Write};
 
@@ -24,12 +24,8 @@ pub struct RemoveNonStandardHeader {
 
 impl RemoveNonStandardHeader {
     pub fn run(&self) -> anyhow::Result<()> {
-        let mut reader =
-            RayonReader::new(autocompress::autodetect_open_or_stdin(self.input.clone())?);
-        let mut writer = RayonWriter::new(autocompress::autodetect_create_or_stdout_prefer_bgzip(
-            self.input.as_deref(),
-            autocompress::CompressionLevel::Default,
-        )?);
+        let mut reader = utils::open_vcf_stream(self.input.as_deref())?;
+        let mut writer = utils::create_vcf_writer(self.output.as_deref())?;
 
         let mut header_file_format: Vec<Vec<u8>> = Vec::new();
         let mut header_info: Vec<Vec<u8>> = Vec::new();
@@ -98,6 +94,7 @@ impl RemoveNonStandardHeader {
         }
 
         io::copy(&mut reader, &mut writer)?;
+        writer.close()?;
 
         Ok(())
     }
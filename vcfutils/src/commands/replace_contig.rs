@@ -1,13 +1,36 @@
-use crate::logic::replace_contig::replace_contig;
+use crate::logic::replace_contig::{replace_contig, replace_contig_with_alias};
 use crate::utils;
+use crate::utils::contig_alias::{ContigNaming, GenomeBuild};
 use anyhow::Context;
-use autocompress::io::{RayonReader, RayonWriter};
-use clap::Args;
+use clap::{Args, ValueEnum};
 use std::collections::HashMap;
 use vcf::U8Vec;
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Naming {
+    Ucsc,
+    Ensembl,
+    Refseq,
+    Genbank,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Build {
+    #[value(name = "GRCh37")]
+    GRCh37,
+    #[value(name = "GRCh38")]
+    GRCh38,
+    #[value(name = "T2T-CHM13")]
+    T2tChm13,
+}
+
 #[derive(Debug, Args)]
-#[command(about = "Replace contig", version, author)]
+#[command(
+    about = "Replace contig",
+    long_about = "Replace contig names with a mapping file or built-in dictionaries of GRCh37, GRCh38 and T2T-CHM13. Built-in dictionaries convert primary chromosomes and mitochondrial genome between UCSC, Ensembl, RefSeq and GenBank names, and alternative, unlocalized, unplaced and GRCh38 decoy contigs between UCSC names and Ensembl/GenBank accessions. RefSeq accessions of non-primary contigs are not available, and the hs37d5 decoy has no alias. hg19 chrM (NC_001807) is not converted to GRCh37 MT (rCRS). Contigs without alias are left unchanged with a warning.",
+    version,
+    author
+)]
 pub struct ReplaceContig {
     #[arg(help = "Input VCF file")]
     input: Option<String>,
@@ -25,16 +48,46 @@ pub struct ReplaceContig {
     add_chr_prefix: bool,
     #[arg(short, long, help = "remove \"chr\" prefix to human chromosomes")]
     remove_chr_prefix: bool,
+    #[arg(
+        long,
+        help = "Target naming convention of built-in contig dictionary",
+        conflicts_with_all = ["contig_mapping", "replace_refseq", "add_chr_prefix", "remove_chr_prefix"]
+    )]
+    to: Option<Naming>,
+    #[arg(
+        long,
+        help = "Source naming convention (default: detect from header contigs)",
+        requires = "to"
+    )]
+    from: Option<Naming>,
+    #[arg(
+        long,
+        help = "Genome build of built-in contig dictionary (default: detect from header contigs; required if it cannot be detected)",
+        requires = "to",
+        ignore_case = true
+    )]
+    genome_build: Option<Build>,
 }
 
 impl ReplaceContig {
     pub fn run(&self) -> anyhow::Result<()> {
-        let mut reader =
-            RayonReader::new(autocompress::autodetect_open_or_stdin(self.input.clone())?);
-        let mut writer = RayonWriter::new(autocompress::autodetect_create_or_stdout_prefer_bgzip(
-            self.output.as_deref(),
-            autocompress::CompressionLevel::Default,
-        )?);
+        let mut reader = utils::open_vcf_stream(self.input.as_deref())?;
+        let mut writer = utils::create_vcf_writer(self.output.as_deref())?;
+        if let Some(to) = self.to {
+            replace_contig_with_alias(
+                &mut reader,
+                &mut writer,
+                self.genome_build.map(|x| match x {
+                    Build::GRCh37 => GenomeBuild::GRCh37,
+                    Build::GRCh38 => GenomeBuild::GRCh38,
+                    Build::T2tChm13 => GenomeBuild::T2tChm13,
+                }),
+                self.from.map(contig_naming),
+                contig_naming(to),
+            )?;
+            writer.close()?;
+            return Ok(());
+        }
         let mapping = if self.replace_refseq {
             refseq_replace()
         } else if self.add_chr_prefix {
@@ -52,10 +105,20 @@ impl ReplaceContig {
         };
 
         replace_contig(&mut reader, &mut writer, &mapping)?;
+        writer.close()?;
         Ok(())
     }
 }
 
+fn contig_naming(naming: Naming) -> ContigNaming {
+    match naming {
+        Naming::Ucsc => ContigNaming::Ucsc,
+        Naming::Ensembl => ContigNaming::Ensembl,
+        Naming::Refseq => ContigNaming::RefSeq,
+        Naming::Genbank => ContigNaming::GenBank,
+    }
+}
+
 fn add_chr() -> HashMap<U8Vec, U8Vec> {
     [
         (b"1".to_vec(), b"chr1".to_vec()),
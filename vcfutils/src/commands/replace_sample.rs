@@ -1,7 +1,6 @@
 use crate::error::VCFUtilsError;
 use crate::logic::replace_sample::replace_sample;
 use crate::utils::{self, Mapping};
-use autocompress::io::RayonWriter;
 use clap::Args;
 use rand::prelude::*;
 use std::collections::HashMap;
@@ -32,10 +31,7 @@ pub struct ReplaceSampleName {
 impl ReplaceSampleName {
     pub fn run(&self) -> anyhow::Result<()> {
         let mut reader = utils::open_vcf_from_path(self.input.as_deref())?;
-        let mut writer = RayonWriter::new(autocompress::autodetect_create_or_stdout_prefer_bgzip(
-            self.output.clone(),
-            autocompress::CompressionLevel::Default,
-        )?);
+        let mut writer = utils::create_vcf_writer(self.output.as_deref())?;
 
         let (mapping, order): (HashMap<U8Vec, U8Vec>, Vec<U8Vec>) = create_sample_mapping(
             &reader,
@@ -44,6 +40,7 @@ impl ReplaceSampleName {
             self.random,
         )?;
         replace_sample(&mut reader, &mut writer, &mapping, &order)?;
+        writer.close()?;
         Ok(())
     }
 }
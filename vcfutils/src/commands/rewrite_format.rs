@@ -1,6 +1,5 @@
 use crate::logic::rewrite_format::rewrite_format;
 use crate::utils;
-use autocompress::io::RayonWriter;
 use clap::Args;
 use std::collections::HashSet;
 
@@ -30,10 +29,7 @@ pub struct RewriteFormat {
 impl RewriteFormat {
     pub fn run(&self) -> anyhow::Result<()> {
         let mut vcf_reader = utils::open_vcf_from_path(self.input.as_deref())?;
-        let mut writer = RayonWriter::new(autocompress::autodetect_create_or_stdout_prefer_bgzip(
-            self.output.as_deref(),
-            autocompress::CompressionLevel::Default,
-        )?);
+        let mut writer = utils::create_vcf_writer(self.output.as_deref())?;
         let blacklist = self
             .exclude
             .as_ref()
@@ -54,6 +50,7 @@ impl RewriteFormat {
             .collect::<HashSet<_>>();
 
         rewrite_format(&mut vcf_reader, &mut writer, &format_tags)?;
+        writer.close()?;
 
         Ok(())
     }
@@ -0,0 +1,87 @@
+use super::add_af::{load_pseudo_autosomal_regions, GenomeBuild};
+use super::table_output::write_table;
+use super::vcf2csv::DataType;
+use crate::logic::sample_stats::{
+    sample_stats, sample_stats_column_types, write_sample_stats, SexInferenceConfig,
+};
+use crate::utils;
+use clap::Args;
+
+#[derive(Debug, Args)]
+#[command(about = "Summarize per-sample genotype statistics", version, author)]
+pub struct SampleStats {
+    #[arg(help = "Input VCF file")]
+    input: Option<String>,
+    #[arg(short, long, help = "Output file")]
+    output: Option<String>,
+    #[arg(
+        short = 't',
+        long = "type",
+        help = "Data type to output",
+        default_value = "auto"
+    )]
+    datatype: DataType,
+    #[arg(
+        short = 's',
+        long,
+        help = "Infer sex from heterozygous call fraction on chromosome X"
+    )]
+    infer_sex: bool,
+    #[arg(
+        long,
+        help = "Maximum chrX heterozygous call fraction to infer male",
+        default_value = "0.1",
+        requires = "infer_sex"
+    )]
+    male_max_het_ratio: f64,
+    #[arg(
+        long,
+        help = "Minimum chrX heterozygous call fraction to infer female",
+        default_value = "0.3",
+        requires = "infer_sex"
+    )]
+    female_min_het_ratio: f64,
+    #[arg(
+        long,
+        help = "BED file of pseudo-autosomal regions excluded from sex inference (default: regions of --genome-build)"
+    )]
+    par: Option<String>,
+    #[arg(
+        long,
+        help = "Genome build to define pseudo-autosomal regions",
+        default_value = "GRCh38",
+        ignore_case = true
+    )]
+    genome_build: GenomeBuild,
+    #[arg(short = 'p', long, help = "Float precision", default_value = "4")]
+    precision: usize,
+}
+
+impl SampleStats {
+    pub fn run(&self) -> anyhow::Result<()> {
+        let mut vcf_reader = utils::open_vcf_from_path(self.input.as_deref())?;
+        let pseudo_autosomal_regions =
+            load_pseudo_autosomal_regions(self.par.as_deref(), self.genome_build)?;
+        let stats = sample_stats(&mut vcf_reader, &pseudo_autosomal_regions)?;
+        let sex_inference = if self.infer_sex {
+            Some(SexInferenceConfig {
+                male_max_het_ratio: self.male_max_het_ratio,
+                female_min_het_ratio: self.female_min_het_ratio,
+            })
+        } else {
+            None
+        };
+
+        write_table(
+            self.datatype,
+            self.output.as_deref(),
+            &sample_stats_column_types(self.infer_sex),
+            1,
+            |mut writer| {
+                write_sample_stats(&stats, sex_inference.as_ref(), self.precision, &mut writer)
+            },
+        )?;
+
+        Ok(())
+    }
+}
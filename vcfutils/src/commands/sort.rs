@@ -0,0 +1,103 @@
+use crate::logic::sort::{sort_vcf, ContigOrder};
+use crate::utils;
+use crate::utils::tabix::IndexFormat;
+use anyhow::Context;
+use clap::{Args, ValueEnum};
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Write};
+use std::path::PathBuf;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum IndexType {
+    Tbi,
+    Csi,
+    None,
+}
+
+#[derive(Debug, Args)]
+#[command(
+    about = "Sort VCF by contig order and position, and write bgzip compressed VCF with index",
+    version,
+    author
+)]
+pub struct Sort {
+    #[arg(help = "Input VCF file")]
+    input: Option<String>,
+    #[arg(short, long, help = "Output bgzip compressed VCF file")]
+    output: String,
+    #[arg(
+        long,
+        help = "FASTA index (.fai) to define contig order (default: contig order in VCF header)"
+    )]
+    fai: Option<String>,
+    #[arg(
+        short = 'x',
+        long,
+        help = "Index format to write with output",
+        default_value = "tbi"
+    )]
+    index_format: IndexType,
+    #[arg(
+        short = 'm',
+        long,
+        help = "Maximum memory to hold records in MB. Sorted records are written to temporary files when exceeded.",
+        default_value = "512"
+    )]
+    max_memory: usize,
+    #[arg(
+        long,
+        help = "Directory for temporary files (default: system temporary directory)"
+    )]
+    temp_dir: Option<String>,
+    #[arg(long, help = "# of threads (0: all CPUs)", default_value = "0")]
+    threads: usize,
+}
+
+impl Sort {
+    pub fn run(&self) -> anyhow::Result<()> {
+        utils::set_threads(self.threads).context("Failed to set # of threads")?;
+        let reader = utils::open_vcf_stream(self.input.as_deref())?;
+        let contig_order = if let Some(fai) = self.fai.as_deref() {
+            ContigOrder::from_fai(BufReader::new(
+                File::open(fai).with_context(|| format!("Failed to open FASTA index: {}", fai))?,
+            ))?
+        } else {
+            ContigOrder::default()
+        };
+        let temp_dir = self
+            .temp_dir
+            .as_ref()
+            .map(PathBuf::from)
+            .unwrap_or_else(std::env::temp_dir);
+        let mut writer = BufWriter::new(
+            File::create(&self.output)
+                .with_context(|| format!("Failed to create output: {}", self.output))?,
+        );
+        let index_builder = sort_vcf(
+            reader,
+            &mut writer,
+            contig_order,
+            self.max_memory * 1024 * 1024,
+            &temp_dir,
+        )?;
+        writer
+            .flush()
+            .with_context(|| format!("Failed to write output: {}", self.output))?;
+
+        let index_format = match self.index_format {
+            IndexType::Tbi => IndexFormat::Tbi,
+            IndexType::Csi => IndexFormat::Csi,
+            IndexType::None => return Ok(()),
+        };
+        let index_path = format!("{}.{}", self.output, index_format.extension());
+        let mut index_writer = BufWriter::new(
+            File::create(&index_path)
+                .with_context(|| format!("Failed to create index: {}", index_path))?,
+        );
+        index_builder.write(&mut index_writer, index_format)?;
+        index_writer
+            .flush()
+            .with_context(|| format!("Failed to write index: {}", index_path))?;
+        Ok(())
+    }
+}
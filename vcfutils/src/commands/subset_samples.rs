@@ -0,0 +1,164 @@
+use super::add_af::{load_sex_chromosome_ploidy, GenomeBuild};
+use crate::logic::add_af;
+use crate::logic::subset_samples::{select_samples, subset_samples};
+use crate::utils;
+use anyhow::Context;
+use clap::Args;
+use std::collections::hash_map::RandomState;
+use std::collections::HashSet;
+use vcf::U8Vec;
+
+#[derive(Debug, Args)]
+#[command(about = "Keep, drop or reorder samples", version, author)]
+pub struct SubsetSamples {
+    #[arg(help = "Input VCF file")]
+    input: Option<String>,
+    #[arg(short, long, help = "Output VCF file")]
+    output: Option<String>,
+    #[arg(
+        short,
+        long,
+        help = "Sample list file (one sample name per line)",
+        long_help = "Sample list file (one sample name per line). Samples are written in the order of this file unless --exclude is specified.",
+        required_unless_present = "category",
+        conflicts_with = "category"
+    )]
+    samples: Option<String>,
+    #[arg(
+        short = 'c',
+        long,
+        help = "category mapping file (csv or tsv)",
+        requires_all = &["id", "value", "select"]
+    )]
+    category: Option<String>,
+    #[arg(
+        short = 'i',
+        long,
+        help = "ID column name in category mapping file",
+        requires = "category"
+    )]
+    id: Option<Vec<String>>,
+    #[arg(
+        short = 'v',
+        long,
+        help = "value column name in category mapping file",
+        requires = "category"
+    )]
+    value: Option<Vec<String>>,
+    #[arg(
+        long,
+        help = "Categories to select (value delimiter: ',')",
+        value_delimiter = ',',
+        requires = "category"
+    )]
+    select: Option<Vec<String>>,
+    #[arg(short, long, help = "Drop listed samples instead of keeping them")]
+    exclude: bool,
+    #[arg(
+        short = 'm',
+        long,
+        help = "Drop sites without ALT alleles in remaining samples"
+    )]
+    drop_monomorphic: bool,
+    #[arg(
+        short = 'r',
+        long,
+        help = "Recalculate AC, AN, AF, GenotypeCount and nhomalt for remaining samples"
+    )]
+    recalc_af: bool,
+    #[arg(
+        long,
+        help = "Also recalculate HWE, ExcHet, InbreedingCoeff, CallRate and F_MISSING",
+        requires = "recalc_af"
+    )]
+    site_qc: bool,
+    #[arg(
+        long,
+        help = "Sample sex table (csv or tsv with sample and sex columns, or PED file) to count males as haploid on chromosome X and Y outside of pseudo-autosomal regions",
+        requires = "recalc_af"
+    )]
+    sex: Option<String>,
+    #[arg(
+        long,
+        help = "BED file of pseudo-autosomal regions (default: regions of --genome-build)",
+        requires = "sex"
+    )]
+    par: Option<String>,
+    #[arg(
+        long,
+        help = "Genome build to define pseudo-autosomal regions",
+        default_value = "GRCh38",
+        ignore_case = true
+    )]
+    genome_build: GenomeBuild,
+    #[arg(
+        short = 'p',
+        long,
+        help = "Allele frequency precision",
+        default_value = "4"
+    )]
+    precision: usize,
+}
+
+impl SubsetSamples {
+    pub fn run(&self) -> anyhow::Result<()> {
+        let mut vcf_reader = utils::open_vcf_from_path(self.input.as_deref())?;
+        let mut vcf_writer = utils::create_vcf_writer(self.output.as_deref())?;
+
+        let sample_list: Vec<U8Vec> = if let Some(samples) = self.samples.as_ref() {
+            utils::load_list(samples)
+                .with_context(|| format!("Failed to load sample list: {}", samples))?
+        } else {
+            let category_to_sample = add_af::load_category_mapping::<_, RandomState>(
+                &mut utils::auto_csv_reader_from_path(
+                    self.category
+                        .as_deref()
+                        .context("No category mapping file")?,
+                    true,
+                )?,
+                self.id
+                    .as_ref()
+                    .map(|x| x.iter().map(|x| x.as_bytes().to_vec()).collect())
+                    .context("No ID column name")?,
+                self.value
+                    .as_ref()
+                    .map(|x| x.iter().map(|x| x.as_bytes().to_vec()).collect())
+                    .context("No value column name")?,
+            )?;
+            let mut selected_samples = HashSet::new();
+            for one in self.select.as_ref().context("No category to select")? {
+                let samples = category_to_sample
+                    .get(one.as_bytes())
+                    .with_context(|| format!("Category is not found: {}", one))?;
+                selected_samples.extend(samples.iter().cloned());
+            }
+            vcf_reader
+                .header()
+                .samples()
+                .iter()
+                .filter(|x| selected_samples.contains(*x))
+                .cloned()
+                .collect()
+        };
+
+        let samples = select_samples(vcf_reader.header().samples(), &sample_list, self.exclude)?;
+        let sex_chromosome_ploidy = self
+            .sex
+            .as_deref()
+            .map(|x| load_sex_chromosome_ploidy(x, self.par.as_deref(), self.genome_build))
+            .transpose()?;
+        subset_samples(
+            &mut vcf_reader,
+            &mut vcf_writer,
+            &samples,
+            self.drop_monomorphic,
+            self.recalc_af,
+            sex_chromosome_ploidy.as_ref(),
+            self.site_qc,
+            self.precision,
+        )?;
+        vcf_writer.close()?;
+
+        Ok(())
+    }
+}
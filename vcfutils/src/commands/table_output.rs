@@ -0,0 +1,172 @@
+use super::vcf2csv::DataType;
+use crate::error::VCFUtilsError;
+use crate::utils::arrowwriter::{ArrowFileFormat, ArrowTableWriter};
+use crate::utils::tablewriter::{
+    CSVWriter, ColumnType, TSVWriter, TableWriter, XlsxDataType, XlsxSheetWriter,
+};
+use anyhow::Context;
+use std::fs::File;
+use std::io::BufWriter;
+
+/// Table file format of output
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TableFormat {
+    Csv,
+    Tsv,
+    Xlsx,
+    Arrow(ArrowFileFormat),
+}
+
+impl TableFormat {
+    /// Select table format from data type, or from output file extension if data type is auto.
+    pub fn new(datatype: DataType, output: Option<&str>) -> Self {
+        match datatype {
+            DataType::CSV => TableFormat::Csv,
+            DataType::TSV => TableFormat::Tsv,
+            DataType::XLSX => TableFormat::Xlsx,
+            DataType::Parquet => TableFormat::Arrow(ArrowFileFormat::Parquet),
+            DataType::Arrow => TableFormat::Arrow(ArrowFileFormat::Ipc),
+            DataType::Auto => match output.map(strip_compression_extension) {
+                Some(x) if x.ends_with(".xlsx") => TableFormat::Xlsx,
+                Some(x) if x.ends_with(".parquet") => TableFormat::Arrow(ArrowFileFormat::Parquet),
+                Some(x) if x.ends_with(".arrow") || x.ends_with(".feather") => {
+                    TableFormat::Arrow(ArrowFileFormat::Ipc)
+                }
+                Some(x) if x.ends_with(".csv") => TableFormat::Csv,
+                _ => TableFormat::Tsv,
+            },
+        }
+    }
+
+    /// Output path of xlsx, parquet and arrow files, which cannot be written to standard output.
+    pub fn output_path(self, output: Option<&str>) -> anyhow::Result<&str> {
+        output.with_context(|| match self {
+            TableFormat::Xlsx => "Output path is required for xlsx output mode",
+            _ => "Output path is required for parquet and arrow output mode",
+        })
+    }
+
+    /// Create CSV or TSV writer to file or standard output.
+    pub fn create_text_writer(self, output: Option<&str>) -> anyhow::Result<Box<dyn TableWriter>> {
+        let writer = autocompress::autodetect_create_or_stdout_prefer_bgzip(
+            output,
+            autocompress::CompressionLevel::Default,
+        )?;
+        Ok(match self {
+            TableFormat::Csv => Box::new(CSVWriter::new(writer)),
+            _ => Box::new(TSVWriter::new(writer)),
+        })
+    }
+
+    /// Create parquet or arrow writer.
+    pub fn create_arrow_writer(
+        self,
+        output: Option<&str>,
+    ) -> anyhow::Result<ArrowTableWriter<BufWriter<File>>> {
+        let output_path = self.output_path(output)?;
+        let format = match self {
+            TableFormat::Arrow(x) => x,
+            _ => ArrowFileFormat::Parquet,
+        };
+        Ok(ArrowTableWriter::new(
+            BufWriter::new(
+                File::create(output_path)
+                    .with_context(|| format!("Failed to create {}", output_path))?,
+            ),
+            format,
+        ))
+    }
+}
+
+/// Remove extension of compressed text files (e.g. `out.csv.gz` to `out.csv`).
+fn strip_compression_extension(path: &str) -> &str {
+    [".gz", ".bgz", ".bz2", ".xz", ".zst"]
+        .iter()
+        .find_map(|x| path.strip_suffix(x))
+        .unwrap_or(path)
+}
+
+/// Write a single table with `write` in CSV, TSV, xlsx, parquet or arrow format. Cells of xlsx
+/// are typed by `column_types`, and the header row and `freeze_columns` columns are frozen.
+pub fn write_table(
+    datatype: DataType,
+    output: Option<&str>,
+    column_types: &[ColumnType],
+    freeze_columns: u16,
+    write: impl FnOnce(&mut dyn TableWriter) -> Result<(), VCFUtilsError>,
+) -> anyhow::Result<()> {
+    let format = TableFormat::new(datatype, output);
+    match format {
+        TableFormat::Xlsx => {
+            let workbook = xlsxwriter::Workbook::new(format.output_path(output)?)?;
+            let mut sheet = workbook.add_worksheet(None)?;
+            let mut writer = XlsxSheetWriter::new(&mut sheet);
+            let types: Vec<_> = column_types
+                .iter()
+                .map(|x| match x {
+                    ColumnType::String => XlsxDataType::String,
+                    _ => XlsxDataType::Number,
+                })
+                .collect();
+            writer.set_data_type(&types);
+            write(&mut writer)?;
+            let last_row = writer.current_row().saturating_sub(1);
+            let last_column = writer.header().len().saturating_sub(1) as u16;
+            sheet.autofilter(0, 0, last_row, last_column)?;
+            sheet.freeze_panes(1, freeze_columns);
+            workbook.close()?;
+        }
+        TableFormat::Arrow(_) => {
+            let mut writer = format.create_arrow_writer(output)?;
+            writer.set_column_types(column_types);
+            write(&mut writer)?;
+            writer.close()?;
+        }
+        TableFormat::Csv | TableFormat::Tsv => {
+            let mut writer = format.create_text_writer(output)?;
+            write(&mut *writer)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_table_format() {
+        assert_eq!(TableFormat::new(DataType::Auto, None), TableFormat::Tsv);
+        assert_eq!(
+            TableFormat::new(DataType::Auto, Some("out.feather")),
+            TableFormat::Arrow(ArrowFileFormat::Ipc)
+        );
+        assert_eq!(
+            TableFormat::new(DataType::Auto, Some("out.csv")),
+            TableFormat::Csv
+        );
+        assert_eq!(
+            TableFormat::new(DataType::Auto, Some("out.csv.gz")),
+            TableFormat::Csv
+        );
+        assert_eq!(
+            TableFormat::new(DataType::Auto, Some("out.tsv.gz")),
+            TableFormat::Tsv
+        );
+        assert_eq!(
+            TableFormat::new(DataType::Parquet, Some("out.csv")),
+            TableFormat::Arrow(ArrowFileFormat::Parquet)
+        );
+    }
+
+    #[test]
+    fn test_write_table_without_output_path() {
+        let result = write_table(DataType::XLSX, None, &[], 0, |_| Ok(()));
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "Output path is required for xlsx output mode"
+        );
+        let result = write_table(DataType::Parquet, None, &[], 0, |_| Ok(()));
+        assert!(result.is_err());
+    }
+}
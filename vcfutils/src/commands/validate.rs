@@ -0,0 +1,70 @@
+use super::table_output::write_table;
+use super::vcf2csv::DataType;
+use crate::logic::validate::{
+    validate_vcf, validation_column_types, write_validation_issues, Severity,
+};
+use crate::utils;
+use anyhow::Context;
+use bio::io::fasta::IndexedReader;
+use clap::Args;
+use std::fs::File;
+
+#[derive(Debug, Args)]
+#[command(
+    about = "Validate VCF file against header declarations and reference sequence",
+    version,
+    author
+)]
+pub struct Validate {
+    #[arg(help = "Input VCF file")]
+    input: Option<String>,
+    #[arg(short, long, help = "Output list of issues")]
+    output: Option<String>,
+    #[arg(
+        short = 't',
+        long = "type",
+        help = "Data type to output",
+        default_value = "auto"
+    )]
+    datatype: DataType,
+    #[arg(
+        short,
+        long,
+        help = "Reference FASTA to check REF alleles (.fai index is required)"
+    )]
+    reference: Option<String>,
+}
+
+impl Validate {
+    pub fn run(&self) -> anyhow::Result<()> {
+        let reader = utils::open_vcf_stream(self.input.as_deref())?;
+        let mut reference = self
+            .reference
+            .as_deref()
+            .map(|x| {
+                IndexedReader::<File>::from_file(&x)
+                    .with_context(|| format!("Failed to open reference FASTA: {}", x))
+            })
+            .transpose()?;
+        let issues = validate_vcf(reader, reference.as_mut())?;
+
+        write_table(
+            self.datatype,
+            self.output.as_deref(),
+            &validation_column_types(),
+            0,
+            |mut writer| write_validation_issues(&issues, &mut writer),
+        )?;
+
+        let errors = issues
+            .iter()
+            .filter(|x| x.severity == Severity::Error)
+            .count();
+        let warnings = issues.len() - errors;
+        log::info!("{} errors and {} warnings found", errors, warnings);
+        if errors > 0 {
+            return Err(anyhow::anyhow!("{} validation errors found", errors));
+        }
+        Ok(())
+    }
+}
@@ -1,20 +1,28 @@
+use super::table_output::TableFormat;
 use crate::error::VCFUtilsError;
+use crate::logic::anonymize::PseudonymKey;
+use crate::logic::filter::{load_named_regions, load_regions, VariantFilter};
 use crate::logic::vcf2table::{
-    create_header_line, merge_header_contents, vcf2table, vcf2table_set_data_type, VCF2CSVConfig,
+    create_header_line, merge_header_contents, vcf2table, vcf2table_column_types,
+    vcf2table_format_sheet, vcf2table_set_data_type, vcf2table_set_hyperlinks, HeaderType,
+    VCF2CSVConfig,
 };
 use crate::utils;
-use crate::utils::tablewriter::{CSVWriter, TSVWriter, TableWriter, XlsxSheetWriter};
+use crate::utils::annotation::AnnotationFormat;
+use crate::utils::structural_variant::GeneRegions;
+use crate::utils::tablewriter::XlsxWorkbookWriter;
 use anyhow::Context;
 use autocompress::io::RayonReader;
 use clap::{Args, ValueEnum};
-use std::boxed::Box;
 use std::collections::HashSet;
-use std::io::BufRead;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
 use std::path::{Path, PathBuf};
 use vcf::VCFHeader;
 
 pub trait TableConfig {
     fn canonical_list(&self) -> Option<&str>;
+    fn canonical_only(&self) -> bool;
     fn input(&self) -> &[String];
     fn group_names(&self) -> Option<&[String]>;
     fn split_multi_allelic(&self) -> bool;
@@ -24,6 +32,15 @@ pub trait TableConfig {
     fn replace_sample_name(&self) -> Option<&[String]>;
     fn priority_info_list(&self) -> Option<&[String]>;
     fn priority_format_list(&self) -> Option<&[String]>;
+    fn annotation_key(&self) -> Option<&str>;
+    fn annotation_fields(&self) -> Option<&[String]>;
+    fn long_format(&self) -> bool;
+    fn gene_list(&self) -> Option<&str>;
+    fn bed(&self) -> Option<&str>;
+    fn padding(&self) -> u64;
+    fn structural_variant(&self) -> bool;
+    fn sv_gene_bed(&self) -> Option<&str>;
+    fn sample_key(&self) -> Option<&str>;
 }
 
 #[derive(Debug, Clone, Copy, ValueEnum)]
@@ -32,6 +49,8 @@ pub enum DataType {
     CSV,
     TSV,
     XLSX,
+    Parquet,
+    Arrow,
 }
 
 #[derive(Args, Debug)]
@@ -47,6 +66,12 @@ pub struct VCF2CSV {
         help = "Canonical transcript list (created with extract-canonical command)"
     )]
     canonical_list: Option<String>,
+    #[arg(
+        long,
+        help = "Keep only canonical transcripts flagged with CANONICAL=YES by VEP",
+        conflicts_with = "canonical_list"
+    )]
+    canonical_only: bool,
     #[arg(
         short = 't',
         long = "type",
@@ -98,12 +123,89 @@ pub struct VCF2CSV {
         value_delimiter = ','
     )]
     replace_sample_name: Option<Vec<String>>,
+    #[arg(
+        long,
+        help = "Replace sample names with pseudonyms in this key file created by anonymize command",
+        conflicts_with = "replace_sample_name"
+    )]
+    sample_key: Option<String>,
+    #[arg(
+        long,
+        help = "Pipe-delimited annotation INFO tag (default: detect ANN, CSQ or BCSQ)"
+    )]
+    annotation_key: Option<String>,
+    #[arg(
+        short = 'a',
+        long,
+        help = "Annotation subfields to include (e.g. SYMBOL,Consequence,HGVSp)",
+        value_delimiter = ','
+    )]
+    annotation_fields: Option<Vec<String>>,
+    #[arg(
+        short = 'l',
+        long,
+        help = "Write one row per transcript annotation (implies --split-multi-allelic)"
+    )]
+    long_format: bool,
+    #[arg(
+        long,
+        help = "Keep variants annotated with genes in this list (gene symbol or Ensembl gene ID, one per line)"
+    )]
+    gene_list: Option<String>,
+    #[arg(long, help = "Keep variants overlapping regions in this BED file")]
+    bed: Option<String>,
+    #[arg(
+        long,
+        help = "Padding size of BED regions",
+        default_value = "0",
+        requires = "bed"
+    )]
+    padding: u64,
+    #[arg(
+        long,
+        help = "Add SV type, start, end, length and mate breakend columns (also enabled by --sv-gene-bed)"
+    )]
+    structural_variant: bool,
+    #[arg(
+        long,
+        help = "List genes overlapping structural variants with this BED file (gene name in 4th column)"
+    )]
+    sv_gene_bed: Option<String>,
+    #[arg(long, help = "# of threads (0: all CPUs)", default_value = "0")]
+    threads: usize,
+    #[arg(
+        long,
+        help = "# of leading columns to freeze in xlsx output (default: columns up to ALT)"
+    )]
+    freeze_columns: Option<u16>,
+    #[arg(
+        long,
+        help = "Hyperlink template for a column in xlsx output (e.g. ID=dbsnp, CLNVID=clinvar, SYMBOL=gnomad or Gene=https://example.com/{})"
+    )]
+    hyperlink: Vec<String>,
+    #[arg(
+        long,
+        help = "Disable default hyperlinks (ID to dbSNP, CLNVID to ClinVar and genes to gnomAD) in xlsx output"
+    )]
+    no_default_hyperlinks: bool,
+    #[arg(
+        long,
+        help = "Split rows into sheets by values of a column in xlsx output (e.g. CHROM). Rows are split by group name if --group-names is given"
+    )]
+    split_sheet_by: Option<String>,
+    #[arg(long, help = "Do not add summary sheet in xlsx output")]
+    no_summary_sheet: bool,
 }
 
+const SUMMARY_SHEET_NAME: &str = "Summary";
+
 impl TableConfig for VCF2CSV {
     fn canonical_list(&self) -> Option<&str> {
         self.canonical_list.as_deref()
     }
+    fn canonical_only(&self) -> bool {
+        self.canonical_only
+    }
     fn input(&self) -> &[String] {
         self.input.as_slice()
     }
@@ -131,52 +233,51 @@ impl TableConfig for VCF2CSV {
     fn priority_format_list(&self) -> Option<&[String]> {
         self.priority_format.as_deref()
     }
+    fn annotation_key(&self) -> Option<&str> {
+        self.annotation_key.as_deref()
+    }
+    fn annotation_fields(&self) -> Option<&[String]> {
+        self.annotation_fields.as_deref()
+    }
+    fn long_format(&self) -> bool {
+        self.long_format
+    }
+    fn gene_list(&self) -> Option<&str> {
+        self.gene_list.as_deref()
+    }
+    fn bed(&self) -> Option<&str> {
+        self.bed.as_deref()
+    }
+    fn padding(&self) -> u64 {
+        self.padding
+    }
+    fn structural_variant(&self) -> bool {
+        self.structural_variant
+    }
+    fn sv_gene_bed(&self) -> Option<&str> {
+        self.sv_gene_bed.as_deref()
+    }
+    fn sample_key(&self) -> Option<&str> {
+        self.sample_key.as_deref()
+    }
 }
 
 impl VCF2CSV {
     pub fn run(&self) -> anyhow::Result<()> {
-        let output_type = match self.datatype {
-            DataType::CSV => "csv",
-            DataType::TSV => "tsv",
-            DataType::XLSX => "xlsx",
-            DataType::Auto => {
-                if let Some(output_path) = self.output.as_ref() {
-                    if output_path.ends_with(".xlsx") {
-                        "xlsx"
-                    } else if output_path.ends_with(".csv") {
-                        "csv"
-                    } else {
-                        "tsv"
-                    }
-                } else {
-                    "tsv"
-                }
-            }
-        };
+        utils::set_threads(self.threads).context("Failed to set # of threads")?;
+        let format = TableFormat::new(self.datatype, self.output.as_deref());
 
-        if output_type == "xlsx" {
-            return Ok(self.run_xlsx_mode(&self.input)?);
+        match format {
+            TableFormat::Xlsx => return self.run_xlsx_mode(&self.input, format),
+            TableFormat::Arrow(_) => return self.run_arrow_mode(&self.input, format),
+            TableFormat::Csv | TableFormat::Tsv => (),
         }
 
         let mut vcf_reader = utils::open_vcf_from_path(self.input.get(0).map(|x| x.as_str()))
             .with_context(|| format!("Failed to open {:?}", self.input.get(0)))?;
-        let mut writer: Box<dyn TableWriter> = match output_type {
-            "csv" => Box::new(CSVWriter::new(
-                autocompress::autodetect_create_or_stdout_prefer_bgzip(
-                    self.output.as_deref(),
-                    autocompress::CompressionLevel::Default,
-                )?,
-            )),
-            "tsv" => Box::new(TSVWriter::new(
-                autocompress::autodetect_create_or_stdout_prefer_bgzip(
-                    self.output.as_deref(),
-                    autocompress::CompressionLevel::Default,
-                )?,
-            )),
-            _ => unreachable!(),
-        };
+        let mut writer = format.create_text_writer(self.output.as_deref())?;
         let config = create_config(&vcf_reader.header(), self)?;
-        let header_contents = create_header_line(&vcf_reader.header(), &config);
+        let header_contents = create_header_line(&vcf_reader.header(), &config)?;
 
         vcf2table(
             &mut vcf_reader,
@@ -192,7 +293,7 @@ impl VCF2CSV {
                 let mut vcf_reader = utils::open_vcf_from_path(Some(one_vcf_name))
                     .with_context(|| format!("Failed to open VCF: {}", one_vcf_name))?;
                 let config = create_config(&vcf_reader.header(), self)?;
-                let new_header_contents = create_header_line(&vcf_reader.header(), &config);
+                let new_header_contents = create_header_line(&vcf_reader.header(), &config)?;
                 let merged_header_contents =
                     merge_header_contents(&header_contents, &new_header_contents);
                 vcf2table(
@@ -208,26 +309,51 @@ impl VCF2CSV {
         Ok(())
     }
 
-    fn run_xlsx_mode(&self, vcf_inputs: &[String]) -> anyhow::Result<()> {
-        let workbook = xlsxwriter::Workbook::new(
-            self.output
-                .as_deref()
-                .expect("Output path is required for xlsx output mode"),
-        )?;
+    fn run_xlsx_mode(&self, vcf_inputs: &[String], format: TableFormat) -> anyhow::Result<()> {
+        let workbook = xlsxwriter::Workbook::new(format.output_path(self.output.as_deref())?)?;
 
         let mut first_vcf_reader = utils::open_vcf_from_path(vcf_inputs.get(0).map(|x| x.as_str()))
             .with_context(|| format!("Failed to open VCF: {:?}", vcf_inputs.get(0)))?;
 
-        let mut sheet = workbook.add_worksheet(None)?;
-        let mut writer = XlsxSheetWriter::new(&mut sheet);
+        let hyperlinks = self
+            .hyperlink
+            .iter()
+            .map(|x| {
+                let (column, template) = x
+                    .split_once('=')
+                    .with_context(|| format!("Invalid hyperlink option: {}", x))?;
+                Ok((column.to_string(), template.parse()?))
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        let mut summary_sheet = if self.no_summary_sheet {
+            None
+        } else {
+            Some(workbook.add_worksheet(Some(SUMMARY_SHEET_NAME))?)
+        };
+        let mut writer = XlsxWorkbookWriter::new(&workbook, "Variants");
+        writer.reserve_sheet_name(SUMMARY_SHEET_NAME);
+        let split_sheet_by = self.split_sheet_by.clone().or_else(|| {
+            self.group_names
+                .as_ref()
+                .map(|_| HeaderType::GroupName.to_string())
+        });
+        writer.set_split_column(split_sheet_by.as_deref());
+
         let config = create_config(&first_vcf_reader.header(), self)?;
-        let header_contents = create_header_line(&first_vcf_reader.header(), &config);
+        let header_contents = create_header_line(&first_vcf_reader.header(), &config)?;
         vcf2table_set_data_type(&header_contents, &mut writer)?;
+        vcf2table_set_hyperlinks(
+            &header_contents,
+            &hyperlinks,
+            !self.no_default_hyperlinks,
+            &mut writer,
+        );
 
         let column_sizes: Vec<_> = header_contents.iter().map(|x| x.column_size()).collect();
         writer.set_column_sizes(&column_sizes)?;
 
-        let mut row = vcf2table(
+        vcf2table(
             &mut first_vcf_reader,
             &header_contents,
             &config,
@@ -241,11 +367,17 @@ impl VCF2CSV {
                 let mut vcf_reader = utils::open_vcf_from_path(Some(one_vcf_name))
                     .with_context(|| format!("Failed to open VCF: {}", one_vcf_name))?;
                 let config = create_config(&vcf_reader.header(), self)?;
-                let new_header_contents = create_header_line(&vcf_reader.header(), &config);
+                let new_header_contents = create_header_line(&vcf_reader.header(), &config)?;
                 let merged_header_contents =
                     merge_header_contents(&header_contents, &new_header_contents);
                 vcf2table_set_data_type(&merged_header_contents, &mut writer)?;
-                let additional_row = vcf2table(
+                vcf2table_set_hyperlinks(
+                    &merged_header_contents,
+                    &hyperlinks,
+                    !self.no_default_hyperlinks,
+                    &mut writer,
+                );
+                vcf2table(
                     &mut vcf_reader,
                     &merged_header_contents,
                     &config,
@@ -253,14 +385,137 @@ impl VCF2CSV {
                     false,
                     &mut writer,
                 )?;
-                row += additional_row;
             }
         }
-        sheet.autofilter(0, 0, row, (header_contents.len() - 1) as u16)?;
-        sheet.freeze_panes(1, 0);
+
+        if let Some(summary_sheet) = summary_sheet.as_mut() {
+            self.write_summary_sheet(summary_sheet, &writer.sheet_rows())?;
+        }
+        for (sheet, row) in writer.worksheets_mut() {
+            vcf2table_format_sheet(sheet, &header_contents, row, self.freeze_columns)?;
+        }
+        drop(writer);
+        drop(summary_sheet);
         workbook.close()?;
         Ok(())
     }
+
+    /// Write input files, command line, filters and number of rows in each sheet.
+    fn write_summary_sheet(
+        &self,
+        sheet: &mut xlsxwriter::Worksheet,
+        sheet_rows: &[(&str, u32)],
+    ) -> anyhow::Result<()> {
+        let mut items = vec![(
+            "Command line".to_string(),
+            std::env::args().collect::<Vec<_>>().join(" "),
+        )];
+        for (i, one) in self.input.iter().enumerate() {
+            let group = self
+                .group_names
+                .as_ref()
+                .and_then(|x| x.get(i))
+                .map(|x| format!(" ({})", x))
+                .unwrap_or_default();
+            items.push(("Input".to_string(), format!("{}{}", one, group)));
+        }
+        if self.input.is_empty() {
+            items.push(("Input".to_string(), "(stdin)".to_string()));
+        }
+        if let Some(x) = self.canonical_list.as_ref() {
+            items.push(("Canonical list".to_string(), x.to_string()));
+        }
+        if self.canonical_only {
+            items.push(("Canonical list".to_string(), "CANONICAL=YES".to_string()));
+        }
+        if let Some(x) = self.gene_list.as_ref() {
+            items.push(("Gene list".to_string(), x.to_string()));
+        }
+        if let Some(x) = self.bed.as_ref() {
+            items.push((
+                "BED".to_string(),
+                format!("{} (padding: {})", x, self.padding),
+            ));
+        }
+        if let Some(x) = self.info.as_ref() {
+            items.push(("INFO".to_string(), x.join(",")));
+        }
+        if let Some(x) = self.format.as_ref() {
+            items.push(("FORMAT".to_string(), x.join(",")));
+        }
+        if let Some(x) = self.annotation_fields.as_ref() {
+            items.push(("Annotation fields".to_string(), x.join(",")));
+        }
+
+        let mut bold = xlsxwriter::Format::new();
+        bold.set_bold();
+        sheet.set_column(0, 0, 20., None)?;
+        sheet.set_column(1, 1, 60., None)?;
+        let mut row = 0;
+        for (key, value) in items {
+            sheet.write_string(row, 0, &key, Some(&bold))?;
+            sheet.write_string(row, 1, &value, None)?;
+            row += 1;
+        }
+        row += 1;
+        sheet.write_string(row, 0, "Sheet", Some(&bold))?;
+        sheet.write_string(row, 1, "Rows", Some(&bold))?;
+        for (name, count) in sheet_rows {
+            row += 1;
+            sheet.write_url(
+                row,
+                0,
+                &format!("internal:'{}'!A1", name.replace('\'', "''")),
+                None,
+            )?;
+            sheet.write_string(row, 0, name, None)?;
+            sheet.write_number(row, 1, *count as f64, None)?;
+        }
+        Ok(())
+    }
+
+    fn run_arrow_mode(&self, vcf_inputs: &[String], format: TableFormat) -> anyhow::Result<()> {
+        let mut writer = format.create_arrow_writer(self.output.as_deref())?;
+
+        let mut first_vcf_reader =
+            utils::open_vcf_from_path(vcf_inputs.first().map(|x| x.as_str()))
+                .with_context(|| format!("Failed to open VCF: {:?}", vcf_inputs.first()))?;
+        let config = create_config(first_vcf_reader.header(), self)?;
+        let header_contents = create_header_line(first_vcf_reader.header(), &config)?;
+        writer.set_column_types(&vcf2table_column_types(
+            &header_contents,
+            config.split_multi_allelic,
+        ));
+
+        vcf2table(
+            &mut first_vcf_reader,
+            &header_contents,
+            &config,
+            config.group_names.as_ref().and_then(|x| x.first()),
+            true,
+            &mut writer,
+        )?;
+
+        for (i, one_vcf_name) in vcf_inputs.iter().enumerate().skip(1) {
+            let mut vcf_reader = utils::open_vcf_from_path(Some(one_vcf_name))
+                .with_context(|| format!("Failed to open VCF: {}", one_vcf_name))?;
+            let config = create_config(vcf_reader.header(), self)?;
+            let new_header_contents = create_header_line(vcf_reader.header(), &config)?;
+            let merged_header_contents =
+                merge_header_contents(&header_contents, &new_header_contents);
+            vcf2table(
+                &mut vcf_reader,
+                &merged_header_contents,
+                &config,
+                config.group_names.as_ref().and_then(|x| x.get(i)),
+                false,
+                &mut writer,
+            )?;
+        }
+
+        writer.close()?;
+        Ok(())
+    }
 }
 
 pub fn create_config(
@@ -288,6 +543,8 @@ pub fn create_config(
         }
 
         Some(canonical_list)
+    } else if matches.canonical_only() {
+        Some(HashSet::new())
     } else {
         None
     };
@@ -392,21 +649,120 @@ pub fn create_config(
         .cloned()
         .collect();
 
+    let annotation_key = matches.annotation_key().map(|x| x.as_bytes().to_vec());
+    let annotation_fields: Vec<_> = matches
+        .annotation_fields()
+        .map(|x| x.iter().map(|y| y.as_bytes().to_vec()).collect())
+        .unwrap_or_default();
+    let annotation = AnnotationFormat::detect(header, annotation_key.as_deref());
+    if let Some(annotation_key) = matches.annotation_key() {
+        if annotation.is_none() {
+            return Err(anyhow::anyhow!(
+                "{} is not a pipe-delimited annotation INFO tag",
+                annotation_key
+            ));
+        }
+    }
+    if matches.canonical_only() && annotation.as_ref().and_then(|x| x.canonical).is_none() {
+        log::warn!("No CANONICAL annotation subfield is found. All transcripts are filtered out.");
+    }
+    if matches.long_format() && annotation.is_none() {
+        log::warn!("No annotation INFO tag is found. One row is written for each allele.");
+    }
+    if !annotation_fields.is_empty() {
+        let annotation = annotation
+            .as_ref()
+            .context("No annotation INFO tag is found")?;
+        for one in &annotation_fields {
+            if annotation.field_index(one).is_none() {
+                return Err(anyhow::anyhow!(
+                    "{} is not found in {} fields: {}",
+                    String::from_utf8_lossy(one),
+                    String::from_utf8_lossy(&annotation.key),
+                    annotation.fields.join(", ")
+                ));
+            }
+        }
+    }
+
+    let variant_filter = create_variant_filter(matches, annotation.as_ref())?;
+    let sv_gene_regions = matches
+        .sv_gene_bed()
+        .map(|x| load_named_regions(x).with_context(|| format!("Failed to load BED: {}", x)))
+        .transpose()?
+        .map(|x| GeneRegions::new(&x));
+    let structural_variant = matches.structural_variant() || sv_gene_regions.is_some();
+    if !structural_variant && header.info(b"SVTYPE").is_some() {
+        log::info!("SVTYPE INFO is declared. Use --structural-variant to add SV columns.");
+    }
+    let replace_sample_name = if let Some(sample_key) = matches.sample_key() {
+        let key = PseudonymKey::load(BufReader::new(
+            File::open(sample_key).with_context(|| format!("Failed to open {}", sample_key))?,
+        ))
+        .with_context(|| format!("Failed to load key file: {}", sample_key))?;
+        Some(
+            header
+                .samples()
+                .iter()
+                .map(|x| {
+                    key.get(x).map(|y| y.to_vec()).with_context(|| {
+                        format!("{} is not found in key file", String::from_utf8_lossy(x))
+                    })
+                })
+                .collect::<anyhow::Result<Vec<_>>>()?,
+        )
+    } else {
+        matches
+            .replace_sample_name()
+            .map(|x| x.iter().map(|y| y.as_bytes().to_vec()).collect())
+    };
+
     Ok(VCF2CSVConfig {
-        split_multi_allelic: matches.split_multi_allelic(),
+        split_multi_allelic: matches.split_multi_allelic() || matches.long_format(),
         decoded_genotype: matches.decode_genotype(),
         canonical_list,
         priority_info_list,
         priority_format_list,
         info_list,
         format_list,
-        replace_sample_name: matches
-            .replace_sample_name()
-            .map(|x| x.iter().map(|y| y.as_bytes().to_vec()).collect()),
+        replace_sample_name,
         group_names,
+        annotation_key,
+        annotation_fields,
+        long_format: matches.long_format(),
+        variant_filter,
+        structural_variant,
+        sv_gene_regions,
     })
 }
 
+pub fn create_variant_filter(
+    matches: &impl TableConfig,
+    annotation: Option<&AnnotationFormat>,
+) -> anyhow::Result<Option<VariantFilter>> {
+    if matches.gene_list().is_none() && matches.bed().is_none() {
+        return Ok(None);
+    }
+    let genes = matches
+        .gene_list()
+        .map(|x| utils::load_list(x).with_context(|| format!("Failed to load gene list: {}", x)))
+        .transpose()?;
+    if genes.is_some() && annotation.is_none() {
+        return Err(anyhow::anyhow!(
+            "Gene list filter requires ANN, CSQ or BCSQ annotation"
+        ));
+    }
+    let regions = matches
+        .bed()
+        .map(|x| load_regions(x).with_context(|| format!("Failed to load BED: {}", x)))
+        .transpose()?;
+    Ok(Some(VariantFilter::new(
+        genes.as_deref(),
+        regions.as_deref(),
+        matches.padding(),
+    )))
+}
+
 // #[cfg(test)]
 // mod test {
 //     use super::*;
@@ -0,0 +1,164 @@
+use super::vcf2csv::{create_config, TableConfig};
+use crate::logic::vcf2sqlite::{vcf2sqlite, SqliteIndexConfig};
+use crate::utils;
+use anyhow::Context;
+use clap::Args;
+
+#[derive(Debug, Args)]
+#[command(about = "Load VCF into SQLite database", version, author)]
+pub struct VCF2Sqlite {
+    #[arg(help = "Input VCF file")]
+    input: Option<String>,
+    #[arg(short, long, help = "Output SQLite database")]
+    output: String,
+    #[arg(short = 'n', long, help = "Table name", default_value = "variants")]
+    table_name: String,
+    #[arg(
+        short,
+        long,
+        help = "Canonical transcript list (created with extract-canonical command)"
+    )]
+    canonical_list: Option<String>,
+    #[arg(
+        long,
+        help = "Keep only canonical transcripts flagged with CANONICAL=YES by VEP",
+        conflicts_with = "canonical_list"
+    )]
+    canonical_only: bool,
+    #[arg(
+        short = 'm',
+        long,
+        help = "Split multi allelic sites into multiple lines"
+    )]
+    split_multi_allelic: bool,
+    #[arg(short = 'd', long, help = "Decode GT format tag number into alleles")]
+    decode_genotype: bool,
+    #[arg(short, long, help = "INFO tags to include", value_delimiter = ',')]
+    info: Option<Vec<String>>,
+    #[arg(short, long, help = "FORMAT tags to include", value_delimiter = ',')]
+    format: Option<Vec<String>>,
+    #[arg(
+        long,
+        help = "Pipe-delimited annotation INFO tag (default: detect ANN, CSQ or BCSQ)"
+    )]
+    annotation_key: Option<String>,
+    #[arg(
+        short = 'a',
+        long,
+        help = "Annotation subfields to include (e.g. SYMBOL,Consequence,HGVSp)",
+        value_delimiter = ','
+    )]
+    annotation_fields: Option<Vec<String>>,
+    #[arg(
+        long,
+        help = "Keep variants annotated with genes in this list (gene symbol or Ensembl gene ID, one per line)"
+    )]
+    gene_list: Option<String>,
+    #[arg(long, help = "Keep variants overlapping regions in this BED file")]
+    bed: Option<String>,
+    #[arg(
+        long,
+        help = "Padding size of BED regions",
+        default_value = "0",
+        requires = "bed"
+    )]
+    padding: u64,
+    #[arg(
+        long,
+        help = "Add SV type, start, end, length and mate breakend columns (also enabled by --sv-gene-bed)"
+    )]
+    structural_variant: bool,
+    #[arg(
+        long,
+        help = "List genes overlapping structural variants with this BED file (gene name in 4th column)"
+    )]
+    sv_gene_bed: Option<String>,
+    #[arg(long, help = "Create an index on CHROM and POS columns")]
+    index_position: bool,
+    #[arg(long, help = "Create indexes on gene columns")]
+    index_gene: bool,
+}
+
+impl TableConfig for VCF2Sqlite {
+    fn canonical_list(&self) -> Option<&str> {
+        self.canonical_list.as_deref()
+    }
+    fn canonical_only(&self) -> bool {
+        self.canonical_only
+    }
+    fn input(&self) -> &[String] {
+        &[]
+    }
+    fn replace_sample_name(&self) -> Option<&[String]> {
+        None
+    }
+    fn group_names(&self) -> Option<&[String]> {
+        None
+    }
+    fn split_multi_allelic(&self) -> bool {
+        self.split_multi_allelic
+    }
+    fn decode_genotype(&self) -> bool {
+        self.decode_genotype
+    }
+    fn info_list(&self) -> Option<&[String]> {
+        self.info.as_deref()
+    }
+    fn format_list(&self) -> Option<&[String]> {
+        self.format.as_deref()
+    }
+    fn priority_format_list(&self) -> Option<&[String]> {
+        None
+    }
+    fn priority_info_list(&self) -> Option<&[String]> {
+        None
+    }
+    fn annotation_key(&self) -> Option<&str> {
+        self.annotation_key.as_deref()
+    }
+    fn annotation_fields(&self) -> Option<&[String]> {
+        self.annotation_fields.as_deref()
+    }
+    fn long_format(&self) -> bool {
+        false
+    }
+    fn gene_list(&self) -> Option<&str> {
+        self.gene_list.as_deref()
+    }
+    fn bed(&self) -> Option<&str> {
+        self.bed.as_deref()
+    }
+    fn padding(&self) -> u64 {
+        self.padding
+    }
+    fn structural_variant(&self) -> bool {
+        self.structural_variant
+    }
+    fn sv_gene_bed(&self) -> Option<&str> {
+        self.sv_gene_bed.as_deref()
+    }
+    fn sample_key(&self) -> Option<&str> {
+        None
+    }
+}
+
+impl VCF2Sqlite {
+    pub fn run(&self) -> anyhow::Result<()> {
+        let mut vcf_reader = utils::open_vcf_from_path(self.input.as_deref())?;
+        let config = create_config(vcf_reader.header(), self)?;
+        let mut connection = rusqlite::Connection::open(&self.output)
+            .with_context(|| format!("Failed to open {}", self.output))?;
+        let row_count = vcf2sqlite(
+            &mut vcf_reader,
+            &mut connection,
+            &config,
+            &self.table_name,
+            &SqliteIndexConfig {
+                position: self.index_position,
+                gene: self.index_gene,
+            },
+        )?;
+        log::info!("{} rows are inserted into {}", row_count, self.table_name);
+        Ok(())
+    }
+}
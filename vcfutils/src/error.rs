@@ -20,8 +20,40 @@ pub enum VCFUtilsError {
     ParseFloatError(#[from] std::num::ParseFloatError),
     #[error("Xlsx handling Error: {0}")]
     XlsxError(#[from] xlsxwriter::XlsxError),
+    #[error("Arrow Error: {0}")]
+    ArrowError(#[from] arrow::error::ArrowError),
+    #[error("Parquet Error: {0}")]
+    ParquetError(#[from] parquet::errors::ParquetError),
+    #[error("SQLite Error: {0}")]
+    SqliteError(#[from] rusqlite::Error),
+    #[error("htslib Error: {0}")]
+    HtslibError(#[from] rust_htslib::errors::Error),
+    #[error("BGZF Error: {0}")]
+    BGZFError(#[from] bgzip::BGZFError),
     #[error("Error: {0}")]
     OtherError(&'static str),
+    #[error("Failed to convert records into table: {0:#}")]
+    TableConversionError(anyhow::Error),
     #[error("Invalid SnpEff Impact: {0}")]
     InvalidSnpEffImpact(String),
+    #[error("Sample not found: {0}")]
+    SampleNotFound(String),
+    #[error("Not defined in header: {0}")]
+    UndefinedHeaderKey(String),
+    #[error("Field not found in annotation source: {0}")]
+    AnnotationFieldNotFound(String),
+    #[error("Invalid header edit: {0}")]
+    InvalidHeaderEdit(String),
+    #[error("Record {0} is inconsistent with header: {1}")]
+    InconsistentRecord(u64, String),
+    #[error("Invalid hyperlink template: {0}")]
+    InvalidHyperlinkTemplate(String),
+    #[error("Column not found: {0}")]
+    ColumnNotFound(String),
+    #[error("Records are not sorted: {0}")]
+    UnsortedRecord(String),
+    #[error("Invalid pseudonym key: {0}")]
+    InvalidPseudonymKey(String),
+    #[error("Failed to detect contig naming convention or genome build: {0}")]
+    ContigAliasDetectionFailed(&'static str),
 }
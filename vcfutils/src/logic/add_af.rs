@@ -1,6 +1,11 @@
+use super::inheritance::Sex;
 use crate::error::VCFUtilsError;
+use crate::utils::gvcf::{is_non_ref_allele, is_reference_block};
+use crate::utils::hwe::{hwe_exact_test, inbreeding_coefficient};
 use crate::utils::recalc_af::AlleleCount;
+use crate::utils::{load_mapping, read_record_chunk, record_chunk_size};
 use itertools::concat;
+use rayon::prelude::*;
 use std::collections::{HashMap, HashSet};
 use std::io::prelude::*;
 use std::str;
@@ -11,94 +16,67 @@ pub fn add_af<R: BufRead, W: Write>(
     writer: W,
     category_to_sample: &CategoryToSamples,
     af_precision: usize,
+    sex_chromosome_ploidy: Option<&SexChromosomePloidy>,
+    site_qc: bool,
 ) -> Result<(), VCFUtilsError> {
     let mut info_keys_to_samples: Vec<(AlleleCountInfoKeys, HashSet<U8Vec>)> = category_to_sample
         .iter()
-        .map(|(k, v)| {
-            (
-                AlleleCountInfoKeys {
-                    ac: concat(vec![b"AC_".to_vec(), k.to_vec()]),
-                    an: concat(vec![b"AN_".to_vec(), k.to_vec()]),
-                    af: concat(vec![b"AF_".to_vec(), k.to_vec()]),
-                    genotype_count: concat(vec![b"GenotypeCount_".to_vec(), k.to_vec()]),
-                    alt_hom_count: concat(vec![b"nhomalt_".to_vec(), k.to_vec()]),
-                },
-                v.clone(),
-            )
-        })
+        .map(|(k, v)| (AlleleCountInfoKeys::new(Some(k)), v.clone()))
         .collect();
     info_keys_to_samples.insert(
         0,
         (
-            AlleleCountInfoKeys {
-                ac: b"AC".to_vec(),
-                an: b"AN".to_vec(),
-                af: b"AF".to_vec(),
-                genotype_count: b"GenotypeCount".to_vec(),
-                alt_hom_count: b"nhomalt".to_vec(),
-            },
+            AlleleCountInfoKeys::new(None),
             reader.header().samples().iter().cloned().collect(),
         ),
     );
 
     let mut header_items = reader.header().items().to_vec();
     for (one_info_keys, _) in info_keys_to_samples.iter() {
-        if reader.header().info(&one_info_keys.ac).is_none() {
-            header_items.push(vcf::VCFHeaderLine::from_bytes(
-                &format!(
-                    "##INFO=<ID={},Number=A,Type=Integer,Description=\"Allele count in genotypes, for each ALT allele, in the same order as listed\">\n",
-                    str::from_utf8(&one_info_keys.ac).unwrap()
-                ).into_bytes(),
-            0).unwrap());
-        }
-        if reader.header().info(&one_info_keys.an).is_none() {
-            header_items.push(vcf::VCFHeaderLine::from_bytes(
-                &format!(
-                    "##INFO=<ID={},Number=1,Type=Integer,Description=\"Total number of alleles in called genotypes\">\n",
-                    str::from_utf8(&one_info_keys.an).unwrap()
-                ).into_bytes(),
-            0).unwrap());
-        }
-        if reader.header().info(&one_info_keys.af).is_none() {
-            header_items.push(vcf::VCFHeaderLine::from_bytes(
-                &format!(
-                    "##INFO=<ID={},Number=A,Type=Float,Description=\"Allele Frequency, for each ALT allele, in the same order as listed\">\n",
-                    str::from_utf8(&one_info_keys.af).unwrap()
-                ).into_bytes(),
-            0).unwrap());
-        }
-        if reader
-            .header()
-            .info(&one_info_keys.genotype_count)
-            .is_none()
-        {
-            header_items.push(vcf::VCFHeaderLine::from_bytes(
-                &format!(
-                    "##INFO=<ID={},Number=G,Type=Integer,Description=\"Genotype count in called genotypes. Refer to Genotype Ordering section in VCF v4.3 specification for ordering of genotypes.\">\n",
-                    str::from_utf8(&one_info_keys.genotype_count).unwrap()
-                ).into_bytes(),
-            0).unwrap());
+        add_allele_count_header(reader.header(), &mut header_items, one_info_keys);
+        if site_qc {
+            add_site_qc_header(reader.header(), &mut header_items, one_info_keys);
         }
-        if reader.header().info(&one_info_keys.alt_hom_count).is_none() {
-            header_items.push(vcf::VCFHeaderLine::from_bytes(
-                &format!(
-                    "##INFO=<ID={},Number=A,Type=Integer,Description=\"Count of homozygous individuals\">\n",
-                    str::from_utf8(&one_info_keys.alt_hom_count).unwrap()
-                ).into_bytes(),
-            0).unwrap());
+        if sex_chromosome_ploidy.is_some() {
+            add_hemizygous_count_header(reader.header(), &mut header_items, one_info_keys);
         }
     }
     let new_header = vcf::VCFHeader::new(header_items, reader.header().samples().to_vec());
 
-    let mut record = vcf::VCFRecord::new(reader.header().clone());
-    let mut allele_count = AlleleCount::new(1, 2);
     let mut vcf_writer = vcf::VCFWriter::new(writer, &new_header)?;
 
-    while reader.next_record(&mut record)? {
-        for (k, v) in info_keys_to_samples.iter() {
-            add_af_to_record(&mut record, k, v, &mut allele_count, af_precision)?;
+    // Records are counted in parallel chunk by chunk, and written in the original order.
+    let chunk_size = record_chunk_size(reader.header().samples().len());
+    let mut chunk = Vec::new();
+    loop {
+        let count = read_record_chunk(reader, &mut chunk, chunk_size)?;
+        chunk[..count].par_iter_mut().try_for_each_init(
+            || AlleleCount::new(1, 2),
+            |allele_count, record| {
+                // gVCF reference blocks are not variants
+                if is_reference_block(record) {
+                    return Ok(());
+                }
+                for (k, v) in info_keys_to_samples.iter() {
+                    add_af_to_record(
+                        record,
+                        k,
+                        v,
+                        allele_count,
+                        af_precision,
+                        sex_chromosome_ploidy,
+                        site_qc,
+                    )?;
+                }
+                Ok::<_, VCFUtilsError>(())
+            },
+        )?;
+        for record in chunk[..count].iter() {
+            vcf_writer.write_record(record)?;
+        }
+        if count < chunk_size {
+            break;
         }
-        vcf_writer.write_record(&record)?;
     }
     Ok(())
 }
@@ -110,16 +88,183 @@ pub struct AlleleCountInfoKeys {
     pub af: U8Vec,
     pub genotype_count: U8Vec,
     pub alt_hom_count: U8Vec,
+    pub hemi_alt_count: U8Vec,
+    pub hwe: U8Vec,
+    pub het_excess: U8Vec,
+    pub inbreeding_coeff: U8Vec,
+    pub call_rate: U8Vec,
+    pub missing_fraction: U8Vec,
+}
+
+impl AlleleCountInfoKeys {
+    /// Create INFO keys for all samples (`None`) or for a category (`AC_<category>`, ...).
+    pub fn new(category: Option<&[u8]>) -> Self {
+        let key = |prefix: &[u8]| {
+            if let Some(category) = category {
+                concat(vec![prefix.to_vec(), b"_".to_vec(), category.to_vec()])
+            } else {
+                prefix.to_vec()
+            }
+        };
+        AlleleCountInfoKeys {
+            ac: key(b"AC"),
+            an: key(b"AN"),
+            af: key(b"AF"),
+            genotype_count: key(b"GenotypeCount"),
+            alt_hom_count: key(b"nhomalt"),
+            hemi_alt_count: key(b"nhemialt"),
+            hwe: key(b"HWE"),
+            het_excess: key(b"ExcHet"),
+            inbreeding_coeff: key(b"InbreedingCoeff"),
+            call_rate: key(b"CallRate"),
+            missing_fraction: key(b"F_MISSING"),
+        }
+    }
+}
+
+/// Append INFO header lines for `info_keys` which are not declared in `header` yet.
+pub fn add_allele_count_header(
+    header: &vcf::VCFHeader,
+    header_items: &mut Vec<vcf::VCFHeaderLine>,
+    info_keys: &AlleleCountInfoKeys,
+) {
+    if header.info(&info_keys.ac).is_none() {
+        header_items.push(vcf::VCFHeaderLine::from_bytes(
+            &format!(
+                "##INFO=<ID={},Number=A,Type=Integer,Description=\"Allele count in genotypes, for each ALT allele, in the same order as listed\">\n",
+                str::from_utf8(&info_keys.ac).unwrap()
+            ).into_bytes(),
+        0).unwrap());
+    }
+    if header.info(&info_keys.an).is_none() {
+        header_items.push(vcf::VCFHeaderLine::from_bytes(
+            &format!(
+                "##INFO=<ID={},Number=1,Type=Integer,Description=\"Total number of alleles in called genotypes\">\n",
+                str::from_utf8(&info_keys.an).unwrap()
+            ).into_bytes(),
+        0).unwrap());
+    }
+    if header.info(&info_keys.af).is_none() {
+        header_items.push(vcf::VCFHeaderLine::from_bytes(
+            &format!(
+                "##INFO=<ID={},Number=A,Type=Float,Description=\"Allele Frequency, for each ALT allele, in the same order as listed\">\n",
+                str::from_utf8(&info_keys.af).unwrap()
+            ).into_bytes(),
+        0).unwrap());
+    }
+    if header.info(&info_keys.genotype_count).is_none() {
+        header_items.push(vcf::VCFHeaderLine::from_bytes(
+            &format!(
+                "##INFO=<ID={},Number=G,Type=Integer,Description=\"Genotype count in called genotypes. Refer to Genotype Ordering section in VCF v4.3 specification for ordering of genotypes.\">\n",
+                str::from_utf8(&info_keys.genotype_count).unwrap()
+            ).into_bytes(),
+        0).unwrap());
+    }
+    if header.info(&info_keys.alt_hom_count).is_none() {
+        header_items.push(vcf::VCFHeaderLine::from_bytes(
+            &format!(
+                "##INFO=<ID={},Number=A,Type=Integer,Description=\"Count of homozygous individuals\">\n",
+                str::from_utf8(&info_keys.alt_hom_count).unwrap()
+            ).into_bytes(),
+        0).unwrap());
+    }
+}
+
+/// Append INFO header lines of site QC metrics, which are written only if requested.
+pub fn add_site_qc_header(
+    header: &vcf::VCFHeader,
+    header_items: &mut Vec<vcf::VCFHeaderLine>,
+    info_keys: &AlleleCountInfoKeys,
+) {
+    if header.info(&info_keys.hwe).is_none() {
+        header_items.push(vcf::VCFHeaderLine::from_bytes(
+            &format!(
+                "##INFO=<ID={},Number=A,Type=Float,Description=\"Hardy-Weinberg equilibrium exact test p-value, for each ALT allele\">\n",
+                str::from_utf8(&info_keys.hwe).unwrap()
+            ).into_bytes(),
+        0).unwrap());
+    }
+    if header.info(&info_keys.het_excess).is_none() {
+        header_items.push(vcf::VCFHeaderLine::from_bytes(
+            &format!(
+                "##INFO=<ID={},Number=A,Type=Float,Description=\"Excess of heterozygosity exact test p-value, for each ALT allele\">\n",
+                str::from_utf8(&info_keys.het_excess).unwrap()
+            ).into_bytes(),
+        0).unwrap());
+    }
+    if header.info(&info_keys.inbreeding_coeff).is_none() {
+        header_items.push(vcf::VCFHeaderLine::from_bytes(
+            &format!(
+                "##INFO=<ID={},Number=A,Type=Float,Description=\"Inbreeding coefficient (1 - observed heterozygotes / expected heterozygotes), for each ALT allele\">\n",
+                str::from_utf8(&info_keys.inbreeding_coeff).unwrap()
+            ).into_bytes(),
+        0).unwrap());
+    }
+    if header.info(&info_keys.call_rate).is_none() {
+        header_items.push(vcf::VCFHeaderLine::from_bytes(
+            &format!(
+                "##INFO=<ID={},Number=1,Type=Float,Description=\"Fraction of samples with called genotypes\">\n",
+                str::from_utf8(&info_keys.call_rate).unwrap()
+            ).into_bytes(),
+        0).unwrap());
+    }
+    if header.info(&info_keys.missing_fraction).is_none() {
+        header_items.push(vcf::VCFHeaderLine::from_bytes(
+            &format!(
+                "##INFO=<ID={},Number=1,Type=Float,Description=\"Fraction of samples with missing genotypes\">\n",
+                str::from_utf8(&info_keys.missing_fraction).unwrap()
+            ).into_bytes(),
+        0).unwrap());
+    }
+}
+
+/// Append INFO header line of hemizygous count, which is written only with sample sex.
+pub fn add_hemizygous_count_header(
+    header: &vcf::VCFHeader,
+    header_items: &mut Vec<vcf::VCFHeaderLine>,
+    info_keys: &AlleleCountInfoKeys,
+) {
+    if header.info(&info_keys.hemi_alt_count).is_none() {
+        header_items.push(vcf::VCFHeaderLine::from_bytes(
+            &format!(
+                "##INFO=<ID={},Number=A,Type=Integer,Description=\"Count of hemizygous individuals\">\n",
+                str::from_utf8(&info_keys.hemi_alt_count).unwrap()
+            ).into_bytes(),
+        0).unwrap());
+    }
 }
 
+/// Add allele counts to a record, and site QC metrics if `site_qc` is true. If
+/// `sex_chromosome_ploidy` is given, genotypes of males on chromosome X and Y outside of
+/// pseudo-autosomal regions are counted as haploid, and hemizygous counts are added.
 pub fn add_af_to_record<S>(
     record: &mut vcf::VCFRecord,
     info_keys: &AlleleCountInfoKeys,
     samples: &HashSet<U8Vec, S>,
     cache: &mut AlleleCount,
     af_precision: usize,
+    sex_chromosome_ploidy: Option<&SexChromosomePloidy>,
+    site_qc: bool,
 ) -> Result<(), VCFUtilsError> {
-    cache.add_record(record, 2, samples.iter())?;
+    if let Some(ploidy) = sex_chromosome_ploidy {
+        cache.add_record_with_ploidy(
+            record,
+            samples
+                .iter()
+                .map(|x| (x, ploidy.ploidy(&record.chromosome, record.position, x))),
+        )?;
+        record.insert_info(
+            &info_keys.hemi_alt_count,
+            cache
+                .hemi_count
+                .iter()
+                .skip(1)
+                .map(|x| format!("{}", x).into_bytes())
+                .collect(),
+        );
+    } else {
+        cache.add_record(record, 2, samples.iter())?;
+    }
     record.insert_info(
         &info_keys.an,
         vec![format!("{}", cache.allele_number).into_bytes()],
@@ -170,9 +315,227 @@ pub fn add_af_to_record<S>(
             .map(|x| format!("{}", x).into_bytes())
             .collect(),
     );
+
+    if site_qc {
+        add_site_qc_to_record(record, info_keys, cache, af_precision);
+    }
+
+    // Counts of gVCF non-reference alleles are not meaningful
+    let non_ref_alleles: Vec<_> = record
+        .alternative
+        .iter()
+        .enumerate()
+        .filter(|(_, x)| is_non_ref_allele(x))
+        .map(|(i, _)| i)
+        .collect();
+    if !non_ref_alleles.is_empty() {
+        // Only keys inserted above are masked; existing INFO may have other numbers of values
+        let mut keys = vec![&info_keys.ac, &info_keys.af, &info_keys.alt_hom_count];
+        if sex_chromosome_ploidy.is_some() {
+            keys.push(&info_keys.hemi_alt_count);
+        }
+        if site_qc {
+            keys.extend([
+                &info_keys.hwe,
+                &info_keys.het_excess,
+                &info_keys.inbreeding_coeff,
+            ]);
+        }
+        for key in keys {
+            if let Some(values) = record.info_mut(key) {
+                for i in non_ref_alleles.iter() {
+                    if let Some(x) = values.get_mut(*i) {
+                        *x = b".".to_vec();
+                    }
+                }
+            }
+        }
+    }
     Ok(())
 }
 
+/// Add HWE, ExcHet, InbreedingCoeff, CallRate and F_MISSING computed from counted genotypes.
+fn add_site_qc_to_record(
+    record: &mut vcf::VCFRecord,
+    info_keys: &AlleleCountInfoKeys,
+    cache: &AlleleCount,
+    af_precision: usize,
+) {
+    let biallelic_counts: Vec<_> = (1..cache.allele_count.len())
+        .map(|x| cache.biallelic_genotype_count(x))
+        .collect();
+    record.insert_info(
+        &info_keys.hwe,
+        biallelic_counts
+            .iter()
+            .map(|x| {
+                x.and_then(|(hom_ref, het, hom_alt)| hwe_exact_test(het, hom_ref, hom_alt))
+                    .map(|x| format_float(x.p_value, af_precision))
+                    .unwrap_or_else(|| b".".to_vec())
+            })
+            .collect(),
+    );
+    record.insert_info(
+        &info_keys.het_excess,
+        biallelic_counts
+            .iter()
+            .map(|x| {
+                x.and_then(|(hom_ref, het, hom_alt)| hwe_exact_test(het, hom_ref, hom_alt))
+                    .map(|x| format_float(x.het_excess_p_value, af_precision))
+                    .unwrap_or_else(|| b".".to_vec())
+            })
+            .collect(),
+    );
+    record.insert_info(
+        &info_keys.inbreeding_coeff,
+        biallelic_counts
+            .iter()
+            .map(|x| {
+                x.and_then(|(hom_ref, het, hom_alt)| inbreeding_coefficient(het, hom_ref, hom_alt))
+                    .map(|x| format_float(x, af_precision))
+                    .unwrap_or_else(|| b".".to_vec())
+            })
+            .collect(),
+    );
+
+    let called_count = cache.called_count();
+    let sample_count = called_count + cache.missing_count;
+    if sample_count == 0 {
+        record.insert_info(&info_keys.call_rate, vec![b".".to_vec()]);
+        record.insert_info(&info_keys.missing_fraction, vec![b".".to_vec()]);
+    } else {
+        record.insert_info(
+            &info_keys.call_rate,
+            vec![format_float(
+                called_count as f64 / sample_count as f64,
+                af_precision,
+            )],
+        );
+        record.insert_info(
+            &info_keys.missing_fraction,
+            vec![format_float(
+                cache.missing_count as f64 / sample_count as f64,
+                af_precision,
+            )],
+        );
+    }
+}
+
+/// Format a value with fixed precision. Small non-zero values, such as p-values,
+/// are formatted in scientific notation so that they are not rounded to zero.
+fn format_float(value: f64, precision: usize) -> U8Vec {
+    if value != 0.0 && value.abs() < 0.1f64.powi(precision as i32) {
+        format!("{:.prec$e}", value, prec = precision).into_bytes()
+    } else {
+        format!("{:.prec$}", value, prec = precision).into_bytes()
+    }
+}
+
+/// Pseudo-autosomal regions of GRCh37 in 0-based half-open coordinates
+pub const GRCH37_PSEUDO_AUTOSOMAL_REGIONS: &[(&str, u64, u64)] = &[
+    ("X", 60000, 2699520),
+    ("X", 154931043, 155260560),
+    ("Y", 10000, 2649520),
+    ("Y", 59034049, 59363566),
+];
+
+/// Pseudo-autosomal regions of GRCh38 in 0-based half-open coordinates
+pub const GRCH38_PSEUDO_AUTOSOMAL_REGIONS: &[(&str, u64, u64)] = &[
+    ("X", 10000, 2781479),
+    ("X", 155701382, 156030895),
+    ("Y", 10000, 2781479),
+    ("Y", 56887902, 57217415),
+];
+
+fn strip_chr_prefix(chromosome: &[u8]) -> &[u8] {
+    chromosome.strip_prefix(b"chr").unwrap_or(chromosome)
+}
+
+/// Test whether a 1-based position is in 0-based half-open pseudo-autosomal regions. `chr`
+/// prefix of chromosome names is ignored.
+pub fn in_pseudo_autosomal_region(
+    pseudo_autosomal_regions: &[(U8Vec, u64, u64)],
+    chromosome: &[u8],
+    position: u64,
+) -> bool {
+    let chromosome = strip_chr_prefix(chromosome);
+    pseudo_autosomal_regions.iter().any(|(x, start, end)| {
+        strip_chr_prefix(x) == chromosome && *start < position && position <= *end
+    })
+}
+
+/// Ploidy of samples on sex chromosomes
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct SexChromosomePloidy {
+    sample_sex: HashMap<U8Vec, Sex>,
+    // 0-based half-open regions. `chr` prefix of chromosome names is removed.
+    pseudo_autosomal_regions: Vec<(U8Vec, u64, u64)>,
+}
+
+impl SexChromosomePloidy {
+    pub fn new(
+        sample_sex: HashMap<U8Vec, Sex>,
+        pseudo_autosomal_regions: &[(U8Vec, u64, u64)],
+    ) -> Self {
+        SexChromosomePloidy {
+            sample_sex,
+            pseudo_autosomal_regions: pseudo_autosomal_regions
+                .iter()
+                .map(|(chromosome, start, end)| {
+                    (strip_chr_prefix(chromosome).to_vec(), *start, *end)
+                })
+                .collect(),
+        }
+    }
+
+    /// Ploidy of a sample at 1-based position. Males are haploid on chromosome X and Y outside
+    /// of pseudo-autosomal regions, and females have no chromosome Y. Samples with unknown sex
+    /// are treated as diploid.
+    pub fn ploidy(&self, chromosome: &[u8], position: u64, sample: &[u8]) -> usize {
+        let chromosome = strip_chr_prefix(chromosome);
+        let is_chrx = chromosome == b"X";
+        if !is_chrx && chromosome != b"Y" {
+            return 2;
+        }
+        let sex = self.sample_sex.get(sample).copied().unwrap_or(Sex::Unknown);
+        match sex {
+            Sex::Unknown => 2,
+            Sex::Female if is_chrx => 2,
+            Sex::Female => 0,
+            Sex::Male
+                if in_pseudo_autosomal_region(
+                    &self.pseudo_autosomal_regions,
+                    chromosome,
+                    position,
+                ) =>
+            {
+                2
+            }
+            Sex::Male => 1,
+        }
+    }
+}
+
+/// Parse sex such as `male`, `M`, `1` and `XY`.
+pub fn parse_sex(value: &[u8]) -> Sex {
+    match value.to_ascii_lowercase().as_slice() {
+        b"1" | b"m" | b"male" | b"xy" => Sex::Male,
+        b"2" | b"f" | b"female" | b"xx" => Sex::Female,
+        _ => Sex::Unknown,
+    }
+}
+
+/// Load sample sex from a table with sample and sex columns.
+pub fn load_sex_table<R: Read>(
+    reader: csv::Reader<R>,
+) -> Result<HashMap<U8Vec, Sex>, VCFUtilsError> {
+    Ok(load_mapping(reader)?
+        .mapping
+        .into_iter()
+        .map(|(sample, sex)| (sample, parse_sex(&sex)))
+        .collect())
+}
+
 type CategoryToSamples = HashMap<U8Vec, HashSet<U8Vec>>;
 
 pub fn load_category_mapping<R: Read, S: std::hash::BuildHasher>(
@@ -233,7 +596,14 @@ mod test {
                 .collect(),
         )?;
         let mut data = Vec::<u8>::new();
-        add_af(&mut vcf_reader, &mut data, &category_mapping, 10)?;
+        add_af(
+            &mut vcf_reader,
+            &mut data,
+            &category_mapping,
+            10,
+            None,
+            true,
+        )?;
         std::fs::File::create("../target/add-af.vcf")?.write_all(&data)?;
 
         let mut vcf_reader2 = vcf::VCFReader::new(&data[..])?;
@@ -244,29 +614,36 @@ mod test {
             .collect::<HashSet<_>>();
 
         // check info
-        assert_eq!(true, info_set.contains(&b"AC".to_vec()));
-        assert_eq!(true, info_set.contains(&b"AN".to_vec()));
-        assert_eq!(true, info_set.contains(&b"AF".to_vec()));
-        assert_eq!(true, info_set.contains(&b"GenotypeCount".to_vec()));
-        assert_eq!(true, info_set.contains(&b"AC_P1".to_vec()));
-        assert_eq!(true, info_set.contains(&b"AN_P1".to_vec()));
-        assert_eq!(true, info_set.contains(&b"AF_P1".to_vec()));
-        assert_eq!(true, info_set.contains(&b"GenotypeCount_P1".to_vec()));
-        assert_eq!(true, info_set.contains(&b"AC_P2".to_vec()));
-        assert_eq!(true, info_set.contains(&b"AN_P2".to_vec()));
-        assert_eq!(true, info_set.contains(&b"AF_P2".to_vec()));
-        assert_eq!(true, info_set.contains(&b"GenotypeCount_P2".to_vec()));
-        assert_eq!(true, info_set.contains(&b"AC_X1".to_vec()));
-        assert_eq!(true, info_set.contains(&b"AN_X1".to_vec()));
-        assert_eq!(true, info_set.contains(&b"AF_X1".to_vec()));
-        assert_eq!(true, info_set.contains(&b"GenotypeCount_X1".to_vec()));
-        assert_eq!(true, info_set.contains(&b"AC_X2".to_vec()));
-        assert_eq!(true, info_set.contains(&b"AN_X2".to_vec()));
-        assert_eq!(true, info_set.contains(&b"AF_X2".to_vec()));
-        assert_eq!(true, info_set.contains(&b"GenotypeCount_X2".to_vec()));
+        assert!(info_set.contains(&b"AC"[..]));
+        assert!(info_set.contains(&b"AN"[..]));
+        assert!(info_set.contains(&b"AF"[..]));
+        assert!(info_set.contains(&b"GenotypeCount"[..]));
+        assert!(info_set.contains(&b"AC_P1"[..]));
+        assert!(info_set.contains(&b"AN_P1"[..]));
+        assert!(info_set.contains(&b"AF_P1"[..]));
+        assert!(info_set.contains(&b"GenotypeCount_P1"[..]));
+        assert!(info_set.contains(&b"AC_P2"[..]));
+        assert!(info_set.contains(&b"AN_P2"[..]));
+        assert!(info_set.contains(&b"AF_P2"[..]));
+        assert!(info_set.contains(&b"GenotypeCount_P2"[..]));
+        assert!(info_set.contains(&b"AC_X1"[..]));
+        assert!(info_set.contains(&b"AN_X1"[..]));
+        assert!(info_set.contains(&b"AF_X1"[..]));
+        assert!(info_set.contains(&b"GenotypeCount_X1"[..]));
+        assert!(info_set.contains(&b"AC_X2"[..]));
+        assert!(info_set.contains(&b"AN_X2"[..]));
+        assert!(info_set.contains(&b"AF_X2"[..]));
+        assert!(info_set.contains(&b"GenotypeCount_X2"[..]));
+        assert!(info_set.contains(&b"HWE"[..]));
+        assert!(info_set.contains(&b"ExcHet"[..]));
+        assert!(info_set.contains(&b"InbreedingCoeff"[..]));
+        assert!(info_set.contains(&b"CallRate"[..]));
+        assert!(info_set.contains(&b"F_MISSING"[..]));
+        assert!(info_set.contains(&b"HWE_X2"[..]));
+        assert!(info_set.contains(&b"F_MISSING_X2"[..]));
 
         let mut vcf_record = vcf::VCFRecord::new(vcf_reader2.header().clone());
-        assert_eq!(true, vcf_reader2.next_record(&mut vcf_record)?);
+        assert!(vcf_reader2.next_record(&mut vcf_record)?);
         assert_eq!(vcf_record.position, 32872836);
         assert_eq!(vcf_record.info(b"AC_P1"), Some(&vec![b"1".to_vec()]));
         assert_eq!(vcf_record.info(b"AC_P2"), Some(&vec![b"0".to_vec()]));
@@ -279,7 +656,7 @@ mod test {
             Some(&vec![b"3".to_vec(), b"0".to_vec(), b"0".to_vec()])
         );
 
-        assert_eq!(true, vcf_reader2.next_record(&mut vcf_record)?);
+        assert!(vcf_reader2.next_record(&mut vcf_record)?);
         assert_eq!(vcf_record.position, 32872987);
         assert_eq!(vcf_record.info(b"AC_P1"), Some(&vec![b"2".to_vec()]));
         assert_eq!(vcf_record.info(b"AC_P2"), Some(&vec![b"3".to_vec()]));
@@ -291,8 +668,24 @@ mod test {
             vcf_record.info(b"GenotypeCount_P2"),
             Some(&vec![b"1".to_vec(), b"1".to_vec(), b"1".to_vec()])
         );
+        assert_eq!(
+            vcf_record.info(b"HWE"),
+            Some(&vec![b"1.0000000000".to_vec()])
+        );
+        assert_eq!(
+            vcf_record.info(b"ExcHet"),
+            Some(&vec![b"0.7619047619".to_vec()])
+        );
+        assert_eq!(
+            vcf_record.info(b"InbreedingCoeff"),
+            Some(&vec![b"-0.2000000000".to_vec()])
+        );
+        assert_eq!(
+            vcf_record.info(b"CallRate"),
+            Some(&vec![b"1.0000000000".to_vec()])
+        );
 
-        assert_eq!(true, vcf_reader2.next_record(&mut vcf_record)?);
+        assert!(vcf_reader2.next_record(&mut vcf_record)?);
         assert_eq!(vcf_record.position, 32873110);
         assert_eq!(vcf_record.info(b"AC_P1"), Some(&vec![b"2".to_vec()]));
         assert_eq!(vcf_record.info(b"AC_P2"), Some(&vec![b"1".to_vec()]));
@@ -305,14 +698,128 @@ mod test {
             Some(&vec![b"2".to_vec(), b"1".to_vec(), b"0".to_vec()])
         );
 
-        assert_eq!(true, vcf_reader2.next_record(&mut vcf_record)?);
+        assert!(vcf_reader2.next_record(&mut vcf_record)?);
         assert_eq!(vcf_record.position, 32873175);
         assert_eq!(
             vcf_record.info(b"GenotypeCount"),
             Some(&vec![b"0".to_vec(), b"0".to_vec(), b"0".to_vec()])
         );
         assert_eq!(vcf_record.info(b"AF"), Some(&vec![b".".to_vec()]));
+        assert_eq!(vcf_record.info(b"HWE"), Some(&vec![b".".to_vec()]));
+        assert_eq!(
+            vcf_record.info(b"InbreedingCoeff"),
+            Some(&vec![b".".to_vec()])
+        );
+        assert_eq!(
+            vcf_record.info(b"CallRate"),
+            Some(&vec![b"0.0000000000".to_vec()])
+        );
+        assert_eq!(
+            vcf_record.info(b"F_MISSING_P1"),
+            Some(&vec![b"1.0000000000".to_vec()])
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_add_af_gvcf() -> Result<(), VCFUtilsError> {
+        let vcf_data = include_bytes!("../../testfiles/gvcf.vcf");
+        let mut vcf_reader = vcf::VCFReader::new(&vcf_data[..])?;
+        let mut data = Vec::<u8>::new();
+        add_af(&mut vcf_reader, &mut data, &HashMap::new(), 4, None, false)?;
+
+        let mut vcf_reader2 = vcf::VCFReader::new(&data[..])?;
+        // site QC metrics are written only if requested
+        assert!(vcf_reader2.header().info(b"HWE").is_none());
+        assert!(vcf_reader2.header().info(b"CallRate").is_none());
+        let mut record = vcf::VCFRecord::new(vcf_reader2.header().clone());
+        // reference block
+        vcf_reader2.next_record(&mut record)?;
+        assert_eq!(record.info(b"AC"), None);
+        // variant with <NON_REF>
+        vcf_reader2.next_record(&mut record)?;
+        assert_eq!(record.info(b"AN"), Some(&vec![b"2".to_vec()]));
+        assert_eq!(
+            record.info(b"AC"),
+            Some(&vec![b"1".to_vec(), b".".to_vec()])
+        );
+        assert_eq!(
+            record.info(b"AF"),
+            Some(&vec![b"0.5000".to_vec(), b".".to_vec()])
+        );
+        Ok(())
+    }
 
+    #[test]
+    fn test_add_af_gvcf_existing_info() -> Result<(), VCFUtilsError> {
+        // HWE with Number=1 is left as is if site QC metrics are not computed
+        let vcf_data = String::from_utf8(include_bytes!("../../testfiles/gvcf.vcf").to_vec())
+            .unwrap()
+            .replace(
+                "##INFO=<ID=DP,",
+                "##INFO=<ID=HWE,Number=1,Type=Float,Description=\"HWE p-value\">\n##INFO=<ID=DP,",
+            )
+            .replace("DP=14;MLEAC=1,0", "DP=14;HWE=0.5;MLEAC=1,0");
+        let mut vcf_reader = vcf::VCFReader::new(vcf_data.as_bytes())?;
+        let mut data = Vec::<u8>::new();
+        add_af(&mut vcf_reader, &mut data, &HashMap::new(), 4, None, false)?;
+
+        let mut vcf_reader2 = vcf::VCFReader::new(&data[..])?;
+        let mut record = vcf::VCFRecord::new(vcf_reader2.header().clone());
+        vcf_reader2.next_record(&mut record)?;
+        vcf_reader2.next_record(&mut record)?;
+        assert_eq!(record.info(b"HWE"), Some(&vec![b"0.5".to_vec()]));
+        assert_eq!(
+            record.info(b"AC"),
+            Some(&vec![b"1".to_vec(), b".".to_vec()])
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_add_af_sex_chromosome() -> Result<(), VCFUtilsError> {
+        let vcf_data = include_bytes!("../../testfiles/sex-chromosome.vcf");
+        let mut vcf_reader = vcf::VCFReader::new(&vcf_data[..])?;
+        let sample_sex = load_sex_table(csv::Reader::from_reader(
+            &include_bytes!("../../testfiles/sex.csv")[..],
+        ))?;
+        assert_eq!(sample_sex.get(&b"M2"[..]), Some(&Sex::Male));
+        assert_eq!(sample_sex.get(&b"U1"[..]), Some(&Sex::Unknown));
+        let pseudo_autosomal_regions: Vec<_> = GRCH38_PSEUDO_AUTOSOMAL_REGIONS
+            .iter()
+            .map(|(chromosome, start, end)| (chromosome.as_bytes().to_vec(), *start, *end))
+            .collect();
+        let ploidy = SexChromosomePloidy::new(sample_sex, &pseudo_autosomal_regions);
+        let mut data = Vec::<u8>::new();
+        add_af(
+            &mut vcf_reader,
+            &mut data,
+            &HashMap::new(),
+            4,
+            Some(&ploidy),
+            true,
+        )?;
+
+        let mut vcf_reader2 = vcf::VCFReader::new(&data[..])?;
+        assert!(vcf_reader2.header().info(b"nhemialt").is_some());
+        let mut record = vcf::VCFRecord::new(vcf_reader2.header().clone());
+        // AC, AN, nhemialt and F_MISSING
+        let expected = [
+            ["4", "8", "0", "0.0000"],
+            ["4", "8", "0", "0.0000"],
+            ["2", "5", "1", "0.2500"],
+            ["1", "4", "1", "0.0000"],
+        ];
+        for values in expected {
+            vcf_reader2.next_record(&mut record)?;
+            for (key, value) in [&b"AC"[..], b"AN", b"nhemialt", b"F_MISSING"]
+                .iter()
+                .zip(values)
+            {
+                assert_eq!(record.info(key), Some(&vec![value.as_bytes().to_vec()]));
+            }
+        }
         Ok(())
     }
 
@@ -0,0 +1,595 @@
+use crate::error::VCFUtilsError;
+use rust_htslib::tbx::{self, Read as TbxRead};
+use std::collections::HashMap;
+use std::io::{BufRead, Write};
+use std::path::Path;
+use std::str;
+use vcf::{
+    Number, U8Vec, VCFHeader, VCFHeaderContent, VCFHeaderLine, VCFReader, VCFRecord, VCFWriter,
+    ValueType,
+};
+
+/// INFO field copied from annotation source. `source` is renamed to `target`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct AnnotationField {
+    pub source: U8Vec,
+    pub target: U8Vec,
+}
+
+impl AnnotationField {
+    /// Parse `SOURCE` or `SOURCE:TARGET`. `prefix` is prepended to `SOURCE` when `TARGET` is
+    /// not given.
+    pub fn parse(spec: &str, prefix: &str) -> Self {
+        if let Some((source, target)) = spec.split_once(':') {
+            AnnotationField {
+                source: source.as_bytes().to_vec(),
+                target: target.as_bytes().to_vec(),
+            }
+        } else {
+            AnnotationField {
+                source: spec.as_bytes().to_vec(),
+                target: format!("{}{}", prefix, spec).into_bytes(),
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SourceFieldDefinition {
+    pub number: Number,
+    pub value_type: ValueType,
+    pub description: U8Vec,
+}
+
+/// One ALT allele in annotation source
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct SourceVariant {
+    pub position: u64,
+    pub reference: U8Vec,
+    pub alternative: U8Vec,
+    /// Values of Number=A fields contain only this allele, and values of Number=R fields
+    /// contain REF and this allele.
+    pub values: HashMap<U8Vec, Vec<U8Vec>>,
+}
+
+pub trait AnnotationSource {
+    fn field_definition(&self, key: &[u8]) -> Option<SourceFieldDefinition>;
+    /// Fetch variants overlapping 0-based half-open region. Only values of `keys` are loaded.
+    fn fetch(
+        &mut self,
+        chromosome: &[u8],
+        start: u64,
+        end: u64,
+        keys: &[U8Vec],
+    ) -> Result<Vec<SourceVariant>, VCFUtilsError>;
+}
+
+/// Fetch region from tabix file. Returns `false` if the chromosome is not indexed.
+fn fetch_region(
+    reader: &mut tbx::Reader,
+    chromosome: &[u8],
+    start: u64,
+    end: u64,
+) -> Result<bool, VCFUtilsError> {
+    let tid = if let Ok(tid) = reader.tid(str::from_utf8(chromosome)?) {
+        tid
+    } else {
+        return Ok(false);
+    };
+    reader.fetch(tid, start, end)?;
+    Ok(true)
+}
+
+/// Tabix indexed VCF
+pub struct TabixVcfSource {
+    reader: tbx::Reader,
+    record: VCFRecord,
+}
+
+impl TabixVcfSource {
+    pub fn new(reader: tbx::Reader) -> Result<Self, VCFUtilsError> {
+        let mut header_bytes = Vec::new();
+        for one in reader.header() {
+            header_bytes.extend_from_slice(one.trim_end().as_bytes());
+            header_bytes.push(b'\n');
+        }
+        let vcf_reader = VCFReader::new(&header_bytes[..])?;
+        Ok(TabixVcfSource {
+            record: VCFRecord::new(vcf_reader.header().clone()),
+            reader,
+        })
+    }
+}
+
+impl AnnotationSource for TabixVcfSource {
+    fn field_definition(&self, key: &[u8]) -> Option<SourceFieldDefinition> {
+        self.record
+            .header()
+            .info(key)
+            .map(|x| SourceFieldDefinition {
+                number: x.number.clone(),
+                value_type: x.value_type.clone(),
+                description: x.description.to_vec(),
+            })
+    }
+
+    fn fetch(
+        &mut self,
+        chromosome: &[u8],
+        start: u64,
+        end: u64,
+        keys: &[U8Vec],
+    ) -> Result<Vec<SourceVariant>, VCFUtilsError> {
+        let mut variants = Vec::new();
+        if !fetch_region(&mut self.reader, chromosome, start, end)? {
+            return Ok(variants);
+        }
+        let numbers: Vec<_> = keys
+            .iter()
+            .map(|x| self.record.header().info(x).map(|y| y.number.clone()))
+            .collect();
+        let mut line = Vec::new();
+        while self.reader.read(&mut line)? {
+            self.record.parse_bytes(&line, 0)?;
+            for (alt_index, alt) in self.record.alternative.iter().enumerate() {
+                let mut values = HashMap::new();
+                for (key, number) in keys.iter().zip(numbers.iter()) {
+                    let record_values = if let Some(x) = self.record.info(key) {
+                        x
+                    } else {
+                        continue;
+                    };
+                    let allele_values = match number {
+                        Some(Number::Allele) => {
+                            record_values.get(alt_index).cloned().into_iter().collect()
+                        }
+                        Some(Number::Reference) => [0, alt_index + 1]
+                            .iter()
+                            .filter_map(|x| record_values.get(*x))
+                            .cloned()
+                            .collect(),
+                        _ => record_values.clone(),
+                    };
+                    values.insert(key.clone(), allele_values);
+                }
+                variants.push(SourceVariant {
+                    position: self.record.position,
+                    reference: self.record.reference.clone(),
+                    alternative: alt.clone(),
+                    values,
+                });
+            }
+        }
+        Ok(variants)
+    }
+}
+
+/// Tabix indexed TSV with a header line. CHROM, POS, REF and ALT columns are required, and
+/// other columns can be copied as INFO fields.
+pub struct TabixTsvSource {
+    reader: tbx::Reader,
+    columns: Vec<U8Vec>,
+    chromosome: usize,
+    position: usize,
+    reference: usize,
+    alternative: usize,
+}
+
+impl TabixTsvSource {
+    pub fn new(reader: tbx::Reader) -> Result<Self, VCFUtilsError> {
+        let header_line = reader.header().last().ok_or(VCFUtilsError::OtherError(
+            "No header line in annotation TSV",
+        ))?;
+        let columns: Vec<U8Vec> = header_line
+            .trim_start_matches('#')
+            .trim_end()
+            .split('\t')
+            .map(|x| x.as_bytes().to_vec())
+            .collect();
+        let find = |names: &[&str]| {
+            columns
+                .iter()
+                .position(|x| names.iter().any(|y| x.eq_ignore_ascii_case(y.as_bytes())))
+                .ok_or(VCFUtilsError::OtherError(
+                    "CHROM, POS, REF and ALT columns are required in annotation TSV",
+                ))
+        };
+        Ok(TabixTsvSource {
+            chromosome: find(&["CHROM", "CHR", "CHROMOSOME"])?,
+            position: find(&["POS", "POSITION"])?,
+            reference: find(&["REF"])?,
+            alternative: find(&["ALT"])?,
+            columns,
+            reader,
+        })
+    }
+
+    fn column_index(&self, key: &[u8]) -> Option<usize> {
+        self.columns.iter().position(|x| x == key).filter(|x| {
+            ![
+                self.chromosome,
+                self.position,
+                self.reference,
+                self.alternative,
+            ]
+            .contains(x)
+        })
+    }
+}
+
+impl AnnotationSource for TabixTsvSource {
+    fn field_definition(&self, key: &[u8]) -> Option<SourceFieldDefinition> {
+        self.column_index(key).map(|_| SourceFieldDefinition {
+            number: Number::Allele,
+            value_type: ValueType::String,
+            description: format!("{} column of annotation TSV", String::from_utf8_lossy(key))
+                .into_bytes(),
+        })
+    }
+
+    fn fetch(
+        &mut self,
+        chromosome: &[u8],
+        start: u64,
+        end: u64,
+        keys: &[U8Vec],
+    ) -> Result<Vec<SourceVariant>, VCFUtilsError> {
+        let mut variants = Vec::new();
+        if !fetch_region(&mut self.reader, chromosome, start, end)? {
+            return Ok(variants);
+        }
+        let indexes: Vec<_> = keys.iter().map(|x| self.column_index(x)).collect();
+        let mut line = Vec::new();
+        while self.reader.read(&mut line)? {
+            let elements: Vec<_> = line.split(|x| *x == b'\t').collect();
+            let column = |index: usize| {
+                elements
+                    .get(index)
+                    .copied()
+                    .ok_or(VCFUtilsError::OtherError(
+                        "Too few columns in annotation TSV",
+                    ))
+            };
+            let position: u64 = str::from_utf8(column(self.position)?)?.parse()?;
+            let reference = column(self.reference)?;
+            let alternatives: Vec<_> = column(self.alternative)?.split(|x| *x == b',').collect();
+            for (alt_index, alt) in alternatives.iter().enumerate() {
+                let mut values = HashMap::new();
+                for (key, index) in keys.iter().zip(indexes.iter()) {
+                    let value = if let Some(x) = index.and_then(|x| elements.get(x)) {
+                        *x
+                    } else {
+                        continue;
+                    };
+                    // A value is split for each allele only if the number of values matches
+                    let allele_values: Vec<_> = value.split(|x| *x == b',').collect();
+                    let value =
+                        if alternatives.len() > 1 && allele_values.len() == alternatives.len() {
+                            allele_values[alt_index]
+                        } else {
+                            value
+                        };
+                    if value.is_empty() || value == b"." {
+                        continue;
+                    }
+                    values.insert(key.clone(), vec![value.to_vec()]);
+                }
+                variants.push(SourceVariant {
+                    position,
+                    reference: reference.to_vec(),
+                    alternative: alt.to_vec(),
+                    values,
+                });
+            }
+        }
+        Ok(variants)
+    }
+}
+
+/// Open tabix indexed VCF or TSV. The file is handled as VCF if the first header line is
+/// `##fileformat=VCF...`.
+pub fn open_annotation_source<P: AsRef<Path>>(
+    path: P,
+) -> Result<Box<dyn AnnotationSource>, VCFUtilsError> {
+    let reader = tbx::Reader::from_path(path)?;
+    if reader
+        .header()
+        .first()
+        .map(|x| x.starts_with("##fileformat=VCF"))
+        .unwrap_or(false)
+    {
+        Ok(Box::new(TabixVcfSource::new(reader)?))
+    } else {
+        Ok(Box::new(TabixTsvSource::new(reader)?))
+    }
+}
+
+/// Trim bases shared by REF and ALT so that different representations of the same variant,
+/// such as a multi-allelic site and its decomposed form, can be compared.
+pub fn normalize_allele<'a>(
+    position: u64,
+    reference: &'a [u8],
+    alternative: &'a [u8],
+) -> (u64, &'a [u8], &'a [u8]) {
+    let (mut position, mut reference, mut alternative) = (position, reference, alternative);
+    while reference.len() > 1 && alternative.len() > 1 && reference.last() == alternative.last() {
+        reference = &reference[..reference.len() - 1];
+        alternative = &alternative[..alternative.len() - 1];
+    }
+    while reference.len() > 1 && alternative.len() > 1 && reference[0] == alternative[0] {
+        reference = &reference[1..];
+        alternative = &alternative[1..];
+        position += 1;
+    }
+    (position, reference, alternative)
+}
+
+fn value_type_name(value_type: &ValueType) -> String {
+    match value_type {
+        ValueType::String => "String".to_string(),
+        ValueType::Integer => "Integer".to_string(),
+        ValueType::Flag => "Flag".to_string(),
+        ValueType::Character => "Character".to_string(),
+        ValueType::Float => "Float".to_string(),
+        ValueType::Other(x) => String::from_utf8_lossy(x).to_string(),
+    }
+}
+
+/// INFO header line of annotated field. Fields other than Number=R and flags are written
+/// for each ALT allele.
+fn annotation_header_line(
+    id: &[u8],
+    definition: &SourceFieldDefinition,
+) -> Result<VCFHeaderLine, VCFUtilsError> {
+    let number = match definition.number {
+        Number::Zero => "0",
+        Number::Reference => "R",
+        _ => "A",
+    };
+    let line = format!(
+        "##INFO=<ID={},Number={},Type={},Description=\"{}\">\n",
+        str::from_utf8(id)?,
+        number,
+        value_type_name(&definition.value_type),
+        String::from_utf8_lossy(&definition.description).replace('"', "'")
+    );
+    Ok(VCFHeaderLine::from_bytes(line.as_bytes(), 0)?)
+}
+
+/// Merge values of matched source variants for ALT alleles of the target record. Returns
+/// `None` if no allele has the value.
+fn merge_values(
+    key: &[u8],
+    number: &Number,
+    matched: &[Option<&SourceVariant>],
+) -> Option<Vec<U8Vec>> {
+    let values: Vec<_> = matched
+        .iter()
+        .map(|x| x.and_then(|y| y.values.get(key)))
+        .collect();
+    if values.iter().all(|x| x.is_none()) {
+        return None;
+    }
+    match number {
+        Number::Zero => Some(Vec::new()),
+        Number::Reference => {
+            let mut merged = vec![values
+                .iter()
+                .find_map(|x| x.and_then(|y| y.first()))
+                .cloned()
+                .unwrap_or_else(|| b".".to_vec())];
+            merged.extend(values.iter().map(|x| {
+                x.and_then(|y| y.get(1))
+                    .cloned()
+                    .unwrap_or_else(|| b".".to_vec())
+            }));
+            Some(merged)
+        }
+        _ => Some(
+            values
+                .iter()
+                .map(|x| match x {
+                    Some(y) if !y.is_empty() => y.join(&b'|'),
+                    _ => b".".to_vec(),
+                })
+                .collect(),
+        ),
+    }
+}
+
+/// Copy INFO fields of matching alleles in annotation source. Existing values of target
+/// fields are replaced. Returns the number of records with at least one matching allele.
+pub fn annotate_vcf<R: BufRead, W: Write, S: AnnotationSource + ?Sized>(
+    reader: &mut VCFReader<R>,
+    writer: W,
+    source: &mut S,
+    fields: &[AnnotationField],
+) -> Result<u64, VCFUtilsError> {
+    let definitions = fields
+        .iter()
+        .map(|x| {
+            source.field_definition(&x.source).ok_or_else(|| {
+                VCFUtilsError::AnnotationFieldNotFound(
+                    String::from_utf8_lossy(&x.source).to_string(),
+                )
+            })
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let source_keys: Vec<_> = fields.iter().map(|x| x.source.clone()).collect();
+    let target_keys: Vec<_> = fields.iter().map(|x| x.target.clone()).collect();
+
+    let mut header_items: Vec<_> = reader
+        .header()
+        .items()
+        .iter()
+        .filter(|x| !matches!(x.contents(), VCFHeaderContent::INFO { id, .. } if target_keys.contains(id)))
+        .cloned()
+        .collect();
+    for (field, definition) in fields.iter().zip(definitions.iter()) {
+        header_items.push(annotation_header_line(&field.target, definition)?);
+    }
+    let new_header = VCFHeader::new(header_items, reader.header().samples().to_vec());
+    let mut vcf_writer = VCFWriter::new(writer, &new_header)?;
+    let mut record = VCFRecord::new(reader.header().clone());
+    let mut annotated = 0;
+
+    while reader.next_record(&mut record)? {
+        let start = record.position.saturating_sub(1);
+        let end = start + record.reference.len().max(1) as u64;
+        let variants = source.fetch(&record.chromosome, start, end, &source_keys)?;
+        let matched: Vec<_> = record
+            .alternative
+            .iter()
+            .map(|alt| {
+                let allele = normalize_allele(record.position, &record.reference, alt);
+                variants
+                    .iter()
+                    .find(|x| normalize_allele(x.position, &x.reference, &x.alternative) == allele)
+            })
+            .collect();
+        if matched.iter().any(|x| x.is_some()) {
+            annotated += 1;
+        }
+
+        let mut info: Vec<_> = record
+            .info
+            .iter()
+            .filter(|(key, _)| !target_keys.contains(key))
+            .cloned()
+            .collect();
+        for ((field, definition), key) in fields
+            .iter()
+            .zip(definitions.iter())
+            .zip(source_keys.iter())
+        {
+            if let Some(values) = merge_values(key, &definition.number, &matched) {
+                info.push((field.target.clone(), values));
+            }
+        }
+        record.info = info;
+        record.recreate_info_and_genotype_index();
+        vcf_writer.write_record(&record)?;
+    }
+    Ok(annotated)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_annotation_field() {
+        assert_eq!(
+            AnnotationField::parse("AF:gnomAD_AF", "ignored_"),
+            AnnotationField {
+                source: b"AF".to_vec(),
+                target: b"gnomAD_AF".to_vec()
+            }
+        );
+        assert_eq!(
+            AnnotationField::parse("AF", "gnomAD_"),
+            AnnotationField {
+                source: b"AF".to_vec(),
+                target: b"gnomAD_AF".to_vec()
+            }
+        );
+    }
+
+    #[test]
+    fn test_normalize_allele() {
+        assert_eq!(
+            normalize_allele(100, b"GAA", b"GAAA"),
+            (100, &b"G"[..], &b"GA"[..])
+        );
+        assert_eq!(
+            normalize_allele(100, b"GAA", b"G"),
+            (100, &b"GAA"[..], &b"G"[..])
+        );
+        assert_eq!(
+            normalize_allele(100, b"GAC", b"GTC"),
+            (101, &b"A"[..], &b"T"[..])
+        );
+        assert_eq!(
+            normalize_allele(100, b"A", b"C"),
+            (100, &b"A"[..], &b"C"[..])
+        );
+    }
+
+    #[test]
+    fn test_annotate_vcf_tsv() -> Result<(), VCFUtilsError> {
+        let vcf_data = include_bytes!("../../testfiles/simple1.vcf");
+        let mut vcf_reader = VCFReader::new(&vcf_data[..])?;
+        let mut source = open_annotation_source("testfiles/annotation-source.tsv.gz")?;
+        let mut write_bytes = Vec::new();
+        let annotated = annotate_vcf(
+            &mut vcf_reader,
+            &mut write_bytes,
+            source.as_mut(),
+            &[
+                AnnotationField::parse("AF", "ClinVar_"),
+                AnnotationField::parse("CLNSIG", "ClinVar_"),
+            ],
+        )?;
+        assert_eq!(annotated, 2);
+
+        let mut result_reader = VCFReader::new(&write_bytes[..])?;
+        assert!(result_reader.header().info(b"ClinVar_AF").is_some());
+        let mut record = VCFRecord::new(result_reader.header().clone());
+        let mut results = Vec::new();
+        while result_reader.next_record(&mut record)? {
+            results.push((
+                record.info(b"ClinVar_AF").cloned(),
+                record.info(b"ClinVar_CLNSIG").cloned(),
+            ));
+        }
+        assert_eq!(
+            results,
+            vec![
+                (Some(vec![b"0.5".to_vec()]), Some(vec![b"Benign".to_vec()])),
+                (None, None),
+                (
+                    Some(vec![b"0.1".to_vec(), b"0.3".to_vec()]),
+                    Some(vec![b"Pathogenic".to_vec(), b"Likely_benign".to_vec()])
+                ),
+            ]
+        );
+
+        let mut vcf_reader = VCFReader::new(&vcf_data[..])?;
+        assert!(annotate_vcf(
+            &mut vcf_reader,
+            &mut write_bytes,
+            source.as_mut(),
+            &[AnnotationField::parse("REF", "ClinVar_")]
+        )
+        .is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_annotate_vcf_vcf() -> Result<(), VCFUtilsError> {
+        let vcf_data = include_bytes!("../../testfiles/1kGP-subset.vcf");
+        let mut vcf_reader = VCFReader::new(&vcf_data[..])?;
+        let mut source = open_annotation_source("testfiles/1kGP-subset.vcf.gz")?;
+        let mut write_bytes = Vec::new();
+        let annotated = annotate_vcf(
+            &mut vcf_reader,
+            &mut write_bytes,
+            source.as_mut(),
+            &[AnnotationField::parse("MLEAF", "gnomAD_")],
+        )?;
+        assert!(annotated > 0);
+
+        let mut result_reader = VCFReader::new(&write_bytes[..])?;
+        let info = result_reader.header().info(b"gnomAD_MLEAF").unwrap();
+        assert_eq!(info.number, &Number::Allele);
+        assert_eq!(info.value_type, &ValueType::Float);
+        let mut record = VCFRecord::new(result_reader.header().clone());
+        let mut count = 0;
+        while result_reader.next_record(&mut record)? {
+            assert_eq!(record.info(b"gnomAD_MLEAF"), record.info(b"MLEAF"));
+            count += 1;
+        }
+        assert_eq!(count, annotated);
+        Ok(())
+    }
+}
@@ -0,0 +1,488 @@
+use crate::error::VCFUtilsError;
+use rand::prelude::*;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::io::{BufRead, Write};
+use std::str;
+use vcf::{U8Vec, VCFHeader, VCFReader, VCFRecord, VCFWriter};
+
+const PSEUDONYM_CHARACTERS: &[u8] = b"0123456789ABCDEFGHJKLMNPQRSTUVWXYZ";
+const RANDOM_PSEUDONYM_LENGTH: usize = 10;
+const HASH_PSEUDONYM_LENGTH: usize = 12;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PseudonymMethod {
+    /// Random characters
+    Random,
+    /// HMAC-SHA256 of original name with the secret in the key
+    Hash,
+}
+
+/// Reversible mapping from original sample names to pseudonyms. The secret is used to generate
+/// hashed pseudonyms, so that the same name is always mapped to the same pseudonym.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PseudonymKey {
+    secret: U8Vec,
+    pseudonyms: HashMap<U8Vec, U8Vec>,
+    original_names: HashMap<U8Vec, U8Vec>,
+    // original names in the order of registration
+    order: Vec<U8Vec>,
+}
+
+impl Default for PseudonymKey {
+    fn default() -> Self {
+        let mut secret = vec![0; 32];
+        rand::rng().fill_bytes(&mut secret);
+        PseudonymKey::with_secret(&secret)
+    }
+}
+
+fn hmac_sha256(key: &[u8], message: &[u8]) -> Vec<u8> {
+    let mut block = [0u8; 64];
+    if key.len() > block.len() {
+        block[..32].copy_from_slice(&Sha256::digest(key));
+    } else {
+        block[..key.len()].copy_from_slice(key);
+    }
+    let inner_pad: Vec<u8> = block.iter().map(|x| x ^ 0x36).collect();
+    let outer_pad: Vec<u8> = block.iter().map(|x| x ^ 0x5c).collect();
+    let inner = Sha256::new()
+        .chain_update(&inner_pad)
+        .chain_update(message)
+        .finalize();
+    Sha256::new()
+        .chain_update(&outer_pad)
+        .chain_update(inner)
+        .finalize()
+        .to_vec()
+}
+
+fn encode_hex(data: &[u8]) -> String {
+    data.iter().map(|x| format!("{:02x}", x)).collect()
+}
+
+fn decode_hex(data: &[u8]) -> Option<Vec<u8>> {
+    if data.len() % 2 == 1 {
+        return None;
+    }
+    data.chunks(2)
+        .map(|x| u8::from_str_radix(str::from_utf8(x).ok()?, 16).ok())
+        .collect()
+}
+
+impl PseudonymKey {
+    pub fn with_secret(secret: &[u8]) -> Self {
+        PseudonymKey {
+            secret: secret.to_vec(),
+            pseudonyms: HashMap::new(),
+            original_names: HashMap::new(),
+            order: Vec::new(),
+        }
+    }
+
+    /// Load key file. The first line is `#secret` and hex encoded secret, and the following lines
+    /// are original names and pseudonyms separated by tab.
+    pub fn load<R: BufRead>(reader: R) -> Result<Self, VCFUtilsError> {
+        let mut key: Option<PseudonymKey> = None;
+        for line in reader.split(b'\n') {
+            let mut line = line?;
+            if line.ends_with(b"\r") {
+                line.pop();
+            }
+            if line.is_empty() {
+                continue;
+            }
+            let elements: Vec<_> = line.split(|x| *x == b'\t').collect();
+            if elements.len() != 2 {
+                return Err(VCFUtilsError::InvalidPseudonymKey(
+                    String::from_utf8_lossy(&line).to_string(),
+                ));
+            }
+            match key.as_mut() {
+                None if elements[0] == b"#secret" => {
+                    let secret = decode_hex(elements[1]).ok_or_else(|| {
+                        VCFUtilsError::InvalidPseudonymKey("invalid secret".to_string())
+                    })?;
+                    key = Some(PseudonymKey::with_secret(&secret));
+                }
+                None => {
+                    return Err(VCFUtilsError::InvalidPseudonymKey(
+                        "no secret at the first line".to_string(),
+                    ))
+                }
+                Some(key) => key.insert(elements[0], elements[1])?,
+            }
+        }
+        key.ok_or_else(|| VCFUtilsError::InvalidPseudonymKey("empty key file".to_string()))
+    }
+
+    pub fn write<W: Write>(&self, mut writer: W) -> Result<(), VCFUtilsError> {
+        writeln!(writer, "#secret\t{}", encode_hex(&self.secret))?;
+        for one in self.order.iter() {
+            writer.write_all(one)?;
+            writer.write_all(b"\t")?;
+            writer.write_all(&self.pseudonyms[one])?;
+            writer.write_all(b"\n")?;
+        }
+        Ok(())
+    }
+
+    fn insert(&mut self, original: &[u8], pseudonym: &[u8]) -> Result<(), VCFUtilsError> {
+        if self.pseudonyms.contains_key(original) || self.original_names.contains_key(pseudonym) {
+            return Err(VCFUtilsError::InvalidPseudonymKey(format!(
+                "duplicated name: {}",
+                String::from_utf8_lossy(original)
+            )));
+        }
+        self.pseudonyms
+            .insert(original.to_vec(), pseudonym.to_vec());
+        self.original_names
+            .insert(pseudonym.to_vec(), original.to_vec());
+        self.order.push(original.to_vec());
+        Ok(())
+    }
+
+    pub fn get(&self, original: &[u8]) -> Option<&[u8]> {
+        self.pseudonyms.get(original).map(|x| &x[..])
+    }
+
+    pub fn original_name(&self, pseudonym: &[u8]) -> Option<&[u8]> {
+        self.original_names.get(pseudonym).map(|x| &x[..])
+    }
+
+    /// Original names registered in the key
+    pub fn names(&self) -> &[U8Vec] {
+        &self.order
+    }
+
+    /// Return the pseudonym of `original`. A new pseudonym is generated and registered if the
+    /// name is not registered yet.
+    pub fn pseudonym(
+        &mut self,
+        original: &[u8],
+        method: PseudonymMethod,
+        prefix: &str,
+    ) -> Result<U8Vec, VCFUtilsError> {
+        if let Some(x) = self.pseudonyms.get(original) {
+            return Ok(x.clone());
+        }
+        let pseudonym = match method {
+            PseudonymMethod::Random => {
+                let mut rng = rand::rng();
+                loop {
+                    let mut pseudonym = prefix.as_bytes().to_vec();
+                    pseudonym.extend(
+                        (0..RANDOM_PSEUDONYM_LENGTH)
+                            .map(|_| *PSEUDONYM_CHARACTERS.choose(&mut rng).unwrap()),
+                    );
+                    if !self.original_names.contains_key(&pseudonym) {
+                        break pseudonym;
+                    }
+                }
+            }
+            PseudonymMethod::Hash => {
+                let mut pseudonym = prefix.as_bytes().to_vec();
+                pseudonym.extend_from_slice(
+                    encode_hex(&hmac_sha256(&self.secret, original))[..HASH_PSEUDONYM_LENGTH]
+                        .to_ascii_uppercase()
+                        .as_bytes(),
+                );
+                pseudonym
+            }
+        };
+        self.insert(original, &pseudonym)?;
+        Ok(pseudonym)
+    }
+
+    /// Replace registered names in text with pseudonyms. Longer names are replaced first.
+    pub fn anonymize_text(&self, text: &[u8]) -> U8Vec {
+        let mut names: Vec<_> = self.order.iter().filter(|x| !x.is_empty()).collect();
+        names.sort_by_key(|x| std::cmp::Reverse(x.len()));
+        let mut result = Vec::new();
+        let mut position = 0;
+        while position < text.len() {
+            match names.iter().find(|x| text[position..].starts_with(x)) {
+                Some(name) => {
+                    result.extend_from_slice(&self.pseudonyms[*name]);
+                    position += name.len();
+                }
+                None => {
+                    result.push(text[position]);
+                    position += 1;
+                }
+            }
+        }
+        result
+    }
+
+    /// Replace registered names in all components of a file path with pseudonyms.
+    pub fn anonymize_file_name(&self, file_name: &str) -> String {
+        String::from_utf8_lossy(&self.anonymize_text(file_name.as_bytes())).to_string()
+    }
+
+    /// Test whether `text` contains one of registered names delimited by non-alphanumeric
+    /// characters.
+    pub fn contains_name(&self, text: &[u8]) -> bool {
+        self.order.iter().filter(|x| !x.is_empty()).any(|name| {
+            text.windows(name.len()).enumerate().any(|(i, x)| {
+                x == &name[..]
+                    && (i == 0 || !text[i - 1].is_ascii_alphanumeric())
+                    && text
+                        .get(i + name.len())
+                        .map(|y| !y.is_ascii_alphanumeric())
+                        .unwrap_or(true)
+            })
+        })
+    }
+}
+
+/// Test whether the header line may leak identities. `##SAMPLE` and `##PEDIGREE` lines, command
+/// lines containing paths, and lines containing registered sample names are identifying.
+/// Definitions of contigs, INFO, FORMAT, FILTER and ALT are kept.
+pub fn is_identifying_header_line(line: &[u8], key: &PseudonymKey) -> bool {
+    if line.starts_with(b"##SAMPLE=") || line.starts_with(b"##PEDIGREE=") {
+        return true;
+    }
+    let header_key = line
+        .strip_prefix(b"##")
+        .and_then(|x| x.split(|y| *y == b'=').next())
+        .unwrap_or_default();
+    if header_key
+        .to_ascii_lowercase()
+        .windows(7)
+        .any(|x| x == b"command")
+        && line.contains(&b'/')
+    {
+        return true;
+    }
+    let is_definition = [
+        &b"fileformat"[..],
+        b"contig",
+        b"INFO",
+        b"FORMAT",
+        b"FILTER",
+        b"ALT",
+    ]
+    .contains(&header_key);
+    !is_definition && key.contains_name(line)
+}
+
+/// Replace sample names with pseudonyms, and remove header lines which may leak identities.
+pub fn anonymize_vcf<R: BufRead, W: Write>(
+    vcf_reader: &mut VCFReader<R>,
+    writer: W,
+    key: &mut PseudonymKey,
+    method: PseudonymMethod,
+    prefix: &str,
+) -> Result<(), VCFUtilsError> {
+    let samples = vcf_reader
+        .header()
+        .samples()
+        .iter()
+        .map(|x| key.pseudonym(x, method, prefix))
+        .collect::<Result<Vec<_>, _>>()?;
+    let header_items: Vec<_> = vcf_reader
+        .header()
+        .items()
+        .iter()
+        .filter(|x| !is_identifying_header_line(x.line(), key))
+        .cloned()
+        .collect();
+    let new_header = VCFHeader::new(header_items, samples);
+    let mut vcf_writer = VCFWriter::new(writer, &new_header)?;
+    let mut record = VCFRecord::new(vcf_reader.header().clone());
+    while vcf_reader.next_record(&mut record)? {
+        vcf_writer.write_record(&record)?;
+    }
+    Ok(())
+}
+
+/// Replace sample names (`SM`) of read groups in SAM header text with pseudonyms, and registered
+/// names in `ID`, `LB`, `PU` and `DS` of read groups. Comment lines and command lines (`CL`) of
+/// programs containing paths are removed. Read group tags of reads must be renamed in the same way
+/// if read group IDs are changed.
+pub fn anonymize_sam_header<R: BufRead, W: Write>(
+    reader: R,
+    mut writer: W,
+    key: &mut PseudonymKey,
+    method: PseudonymMethod,
+    prefix: &str,
+) -> Result<(), VCFUtilsError> {
+    for line in reader.split(b'\n') {
+        let mut line = line?;
+        if line.ends_with(b"\r") {
+            line.pop();
+        }
+        if line.starts_with(b"@CO") {
+            continue;
+        }
+        let is_read_group = line.starts_with(b"@RG\t");
+        let is_program = line.starts_with(b"@PG\t");
+        if is_read_group {
+            for one in line.split(|x| *x == b'\t') {
+                if let Some(sample) = one.strip_prefix(b"SM:") {
+                    key.pseudonym(sample, method, prefix)?;
+                }
+            }
+        }
+        let mut fields = Vec::new();
+        for one in line.split(|x| *x == b'\t') {
+            if is_read_group && one.starts_with(b"SM:") {
+                let mut field = b"SM:".to_vec();
+                field.extend(key.pseudonym(&one[3..], method, prefix)?);
+                fields.push(field);
+            } else if is_read_group
+                && [&b"ID:"[..], b"LB:", b"PU:", b"DS:"]
+                    .iter()
+                    .any(|x| one.starts_with(x))
+            {
+                let mut field = one[..3].to_vec();
+                field.extend(key.anonymize_text(&one[3..]));
+                fields.push(field);
+            } else if is_program && one.starts_with(b"CL:") && one.contains(&b'/') {
+                continue;
+            } else {
+                fields.push(one.to_vec());
+            }
+        }
+        writer.write_all(&fields.join(&b'\t'))?;
+        writer.write_all(b"\n")?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_hmac_sha256() {
+        // RFC 4231 test case 2
+        assert_eq!(
+            encode_hex(&hmac_sha256(b"Jefe", b"what do ya want for nothing?")),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+
+    #[test]
+    fn test_pseudonym_key() -> Result<(), VCFUtilsError> {
+        let mut key = PseudonymKey::with_secret(b"secret");
+        let hashed = key.pseudonym(b"NA12878", PseudonymMethod::Hash, "S")?;
+        assert_eq!(hashed.len(), 1 + HASH_PSEUDONYM_LENGTH);
+        assert_eq!(
+            PseudonymKey::with_secret(b"secret").pseudonym(
+                b"NA12878",
+                PseudonymMethod::Hash,
+                "S"
+            )?,
+            hashed
+        );
+        assert_ne!(
+            PseudonymKey::with_secret(b"other").pseudonym(
+                b"NA12878",
+                PseudonymMethod::Hash,
+                "S"
+            )?,
+            hashed
+        );
+        let random = key.pseudonym(b"NA12891", PseudonymMethod::Random, "P")?;
+        assert!(random.starts_with(b"P"));
+        assert_eq!(random.len(), 1 + RANDOM_PSEUDONYM_LENGTH);
+        assert_eq!(
+            key.pseudonym(b"NA12891", PseudonymMethod::Hash, "S")?,
+            random
+        );
+
+        let mut data = Vec::new();
+        key.write(&mut data)?;
+        let loaded = PseudonymKey::load(&data[..])?;
+        assert_eq!(loaded, key);
+        assert_eq!(loaded.original_name(&random), Some(&b"NA12891"[..]));
+        assert!(PseudonymKey::load(&b"NA12878\tS1\n"[..]).is_err());
+
+        assert!(key.contains_name(b"--sample-name NA12878_R1"));
+        assert!(!key.contains_name(b"--sample-name NA128780"));
+
+        assert_eq!(
+            key.anonymize_file_name("/data/NA12878/NA12878.NA12891.bam"),
+            format!(
+                "/data/{}/{}.{}.bam",
+                str::from_utf8(&hashed).unwrap(),
+                str::from_utf8(&hashed).unwrap(),
+                str::from_utf8(&random).unwrap()
+            )
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_anonymize_vcf() -> Result<(), VCFUtilsError> {
+        let vcf_data = b"##fileformat=VCFv4.2
+##SAMPLE=<ID=NA12878,Description=\"Sample\">
+##bcftools_viewCommand=view -o /home/user/NA12878.vcf.gz; Date=Tue Jan 1 00:00:00 2019
+##GATKCommandLine=<ID=HaplotypeCaller,CommandLine=\"HaplotypeCaller --output /data/out.g.vcf\">
+##GenotypeGVCFsCommandLine=<ID=GenotypeGVCFs,CommandLine=\"GenotypeGVCFs --sample-name NA12891\">
+##source=HaplotypeCaller
+##contig=<ID=chr1,length=248956422>
+##FORMAT=<ID=GT,Number=1,Type=String,Description=\"Genotype\">
+#CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO\tFORMAT\tNA12878\tNA12891
+chr1\t100\t.\tA\tG\t.\tPASS\t.\tGT\t0/1\t1/1
+";
+        let mut key = PseudonymKey::with_secret(b"secret");
+        let mut vcf_reader = VCFReader::new(&vcf_data[..])?;
+        let mut data = Vec::new();
+        anonymize_vcf(
+            &mut vcf_reader,
+            &mut data,
+            &mut key,
+            PseudonymMethod::Hash,
+            "S",
+        )?;
+        let text = String::from_utf8(data.clone()).unwrap();
+        assert!(!text.contains("NA1"));
+        assert!(!text.contains("/data/"));
+        assert!(text.contains("##source=HaplotypeCaller"));
+        assert!(text.contains("##contig=<ID=chr1,length=248956422>"));
+        assert!(text.contains("##FORMAT=<ID=GT,"));
+
+        let mut vcf_reader = VCFReader::new(&data[..])?;
+        assert_eq!(
+            vcf_reader.header().samples(),
+            &[
+                key.get(b"NA12878").unwrap().to_vec(),
+                key.get(b"NA12891").unwrap().to_vec()
+            ]
+        );
+        let mut record = VCFRecord::new(vcf_reader.header().clone());
+        vcf_reader.next_record(&mut record)?;
+        assert_eq!(
+            record.genotype(key.get(b"NA12891").unwrap(), b"GT"),
+            Some(&vec![b"1/1".to_vec()])
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_anonymize_sam_header() -> Result<(), VCFUtilsError> {
+        let header = b"@HD\tVN:1.6\tSO:coordinate
+@SQ\tSN:chr1\tLN:248956422
+@RG\tID:NA12878.1\tLB:NA12878_lib\tSM:NA12878\tPU:FC1.NA12878\tDS:exome of NA12878\tPL:ILLUMINA
+@PG\tID:bwa\tPN:bwa\tCL:bwa mem /data/NA12878_R1.fastq.gz
+@CO\tNA12878 sequenced at somewhere
+";
+        let mut key = PseudonymKey::with_secret(b"secret");
+        let mut data = Vec::new();
+        anonymize_sam_header(&header[..], &mut data, &mut key, PseudonymMethod::Hash, "S")?;
+        let pseudonym = str::from_utf8(key.get(b"NA12878").unwrap()).unwrap();
+        let expected = format!(
+            "@HD\tVN:1.6\tSO:coordinate
+@SQ\tSN:chr1\tLN:248956422
+@RG\tID:{0}.1\tLB:{0}_lib\tSM:{0}\tPU:FC1.{0}\tDS:exome of {0}\tPL:ILLUMINA
+@PG\tID:bwa\tPN:bwa
+",
+            pseudonym
+        );
+        assert_eq!(str::from_utf8(&data).unwrap(), expected);
+        Ok(())
+    }
+}
@@ -0,0 +1,683 @@
+use super::annotate::normalize_allele;
+use super::filter::VariantFilter;
+use crate::error::VCFUtilsError;
+use crate::utils::genotype::sample_genotype;
+use crate::utils::ratio;
+use crate::utils::tablewriter::{ColumnType, TableWriter};
+use std::collections::{BTreeMap, HashMap};
+use std::io::{BufRead, Write};
+use vcf::{U8Vec, VCFHeader, VCFHeaderLine, VCFReader, VCFRecord, VCFWriter};
+
+/// Key of a normalized ALT allele (CHROM, POS, REF, ALT)
+type AlleleKey = (U8Vec, u64, U8Vec, U8Vec);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum VariantType {
+    Snv,
+    Mnp,
+    Indel,
+    /// Symbolic, breakend and spanning deletion alleles
+    Other,
+}
+
+impl VariantType {
+    const ALL: [VariantType; 4] = [
+        VariantType::Snv,
+        VariantType::Mnp,
+        VariantType::Indel,
+        VariantType::Other,
+    ];
+
+    fn from_allele(reference: &[u8], alternative: &[u8]) -> Self {
+        if alternative.starts_with(b"<")
+            || alternative.contains(&b'[')
+            || alternative.contains(&b']')
+            || alternative == b"*"
+        {
+            VariantType::Other
+        } else if reference.len() != alternative.len() {
+            VariantType::Indel
+        } else if reference.len() == 1 {
+            VariantType::Snv
+        } else {
+            VariantType::Mnp
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct CompareConfig {
+    /// Pairs of truth and query sample names to compare genotypes
+    pub sample_pairs: Vec<(U8Vec, U8Vec)>,
+    /// Regions to stratify
+    pub regions: Option<VariantFilter>,
+    /// Ignore records whose FILTER is not PASS or `.`
+    pub pass_only: bool,
+}
+
+impl CompareConfig {
+    /// Pair samples with the same name in truth and query VCFs.
+    pub fn common_samples(truth: &VCFHeader, query: &VCFHeader) -> Vec<(U8Vec, U8Vec)> {
+        truth
+            .samples()
+            .iter()
+            .filter(|x| query.samples().contains(x))
+            .map(|x| (x.clone(), x.clone()))
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct ConcordanceStats {
+    pub true_positive: u64,
+    pub false_positive: u64,
+    pub false_negative: u64,
+    pub genotype_match: u64,
+    pub genotype_mismatch: u64,
+}
+
+impl ConcordanceStats {
+    pub fn precision(&self) -> Option<f64> {
+        ratio(self.true_positive, self.true_positive + self.false_positive)
+    }
+
+    pub fn recall(&self) -> Option<f64> {
+        ratio(self.true_positive, self.true_positive + self.false_negative)
+    }
+
+    pub fn f1(&self) -> Option<f64> {
+        ratio(
+            2 * self.true_positive,
+            2 * self.true_positive + self.false_positive + self.false_negative,
+        )
+    }
+
+    /// Fraction of true positives with the same genotype
+    pub fn genotype_concordance(&self) -> Option<f64> {
+        ratio(
+            self.genotype_match,
+            self.genotype_match + self.genotype_mismatch,
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Outcome {
+    TruePositive,
+    FalsePositive,
+    FalseNegative,
+}
+
+/// Concordance statistics stratified by sample, variant type and region.
+/// Sample index 0 is site level, and genotype counts of all samples are added to it.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct ComparisonSummary {
+    pub samples: Vec<String>,
+    pub has_regions: bool,
+    pub stats: BTreeMap<(usize, Option<VariantType>, bool), ConcordanceStats>,
+}
+
+impl ComparisonSummary {
+    fn new(config: &CompareConfig) -> Self {
+        let mut samples = vec!["ALL".to_string()];
+        samples.extend(config.sample_pairs.iter().map(|(truth, query)| {
+            if truth == query {
+                String::from_utf8_lossy(truth).to_string()
+            } else {
+                format!(
+                    "{}:{}",
+                    String::from_utf8_lossy(truth),
+                    String::from_utf8_lossy(query)
+                )
+            }
+        }));
+        let mut stats = BTreeMap::new();
+        for sample_index in 0..samples.len() {
+            for variant_type in
+                std::iter::once(None).chain(VariantType::ALL.iter().copied().map(Some))
+            {
+                stats.insert(
+                    (sample_index, variant_type, false),
+                    ConcordanceStats::default(),
+                );
+                if config.regions.is_some() {
+                    stats.insert(
+                        (sample_index, variant_type, true),
+                        ConcordanceStats::default(),
+                    );
+                }
+            }
+        }
+        ComparisonSummary {
+            samples,
+            has_regions: config.regions.is_some(),
+            stats,
+        }
+    }
+
+    /// Get statistics. `in_regions` selects the stratum of regions.
+    pub fn get(
+        &self,
+        sample_index: usize,
+        variant_type: Option<VariantType>,
+        in_regions: bool,
+    ) -> Option<&ConcordanceStats> {
+        self.stats.get(&(sample_index, variant_type, in_regions))
+    }
+
+    fn strata(
+        &mut self,
+        sample_index: usize,
+        variant_type: VariantType,
+        in_regions: bool,
+    ) -> impl Iterator<Item = &mut ConcordanceStats> {
+        self.stats
+            .iter_mut()
+            .filter(move |((index, one_type, one_regions), _)| {
+                *index == sample_index
+                    && (one_type.is_none() || *one_type == Some(variant_type))
+                    && (!*one_regions || in_regions)
+            })
+            .map(|(_, v)| v)
+    }
+
+    fn add(
+        &mut self,
+        sample_index: usize,
+        variant_type: VariantType,
+        in_regions: bool,
+        outcome: Outcome,
+    ) {
+        for one in self.strata(sample_index, variant_type, in_regions) {
+            match outcome {
+                Outcome::TruePositive => one.true_positive += 1,
+                Outcome::FalsePositive => one.false_positive += 1,
+                Outcome::FalseNegative => one.false_negative += 1,
+            }
+        }
+    }
+
+    fn add_genotype(
+        &mut self,
+        sample_index: usize,
+        variant_type: VariantType,
+        in_regions: bool,
+        matched: bool,
+    ) {
+        for index in [0, sample_index] {
+            for one in self.strata(index, variant_type, in_regions) {
+                if matched {
+                    one.genotype_match += 1;
+                } else {
+                    one.genotype_mismatch += 1;
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct TruthVariant {
+    variant_type: VariantType,
+    in_regions: bool,
+    dosages: Vec<u8>,
+    matched: bool,
+}
+
+/// ALT alleles of a truth VCF
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct TruthSet {
+    variants: HashMap<AlleleKey, TruthVariant>,
+}
+
+fn is_passed(record: &VCFRecord, config: &CompareConfig) -> bool {
+    !config.pass_only || record.filter.is_empty() || record.filter.iter().all(|x| x == b"PASS")
+}
+
+/// Number of copies of each ALT allele for samples
+fn allele_dosages(record: &VCFRecord, samples: &[&U8Vec]) -> Result<Vec<Vec<u8>>, VCFUtilsError> {
+    let mut dosages = vec![vec![0; samples.len()]; record.alternative.len()];
+    for (sample_index, sample) in samples.iter().enumerate() {
+        for allele in sample_genotype(record, sample).into_iter().flatten() {
+            if let Some(x) = allele.checked_sub(1).and_then(|x| dosages.get_mut(x)) {
+                x[sample_index] += 1;
+            }
+        }
+    }
+    Ok(dosages)
+}
+
+/// Normalized alleles of the record. An allele is `None` if no sample has it; sites-only
+/// records are regarded as having all alleles.
+#[allow(clippy::type_complexity)]
+fn present_alleles(
+    record: &VCFRecord,
+    samples: &[&U8Vec],
+) -> Result<Vec<Option<(AlleleKey, VariantType, Vec<u8>)>>, VCFUtilsError> {
+    let dosages = allele_dosages(record, samples)?;
+    Ok(record
+        .alternative
+        .iter()
+        .zip(dosages)
+        .map(|(alt, dosages)| {
+            if !samples.is_empty() && dosages.iter().all(|x| *x == 0) {
+                return None;
+            }
+            let (position, reference, alternative) =
+                normalize_allele(record.position, &record.reference, alt);
+            Some((
+                (
+                    record.chromosome.clone(),
+                    position,
+                    reference.to_vec(),
+                    alternative.to_vec(),
+                ),
+                VariantType::from_allele(reference, alternative),
+                dosages,
+            ))
+        })
+        .collect())
+}
+
+fn is_in_regions(record: &VCFRecord, config: &CompareConfig) -> bool {
+    config
+        .regions
+        .as_ref()
+        .map(|x| x.is_region_matched(record))
+        .unwrap_or(false)
+}
+
+pub fn load_truth<R: BufRead>(
+    reader: &mut VCFReader<R>,
+    config: &CompareConfig,
+) -> Result<TruthSet, VCFUtilsError> {
+    let samples: Vec<_> = config.sample_pairs.iter().map(|x| &x.0).collect();
+    let mut truth = TruthSet::default();
+    let mut record = VCFRecord::new(reader.header().clone());
+    while reader.next_record(&mut record)? {
+        if !is_passed(&record, config) {
+            continue;
+        }
+        let in_regions = is_in_regions(&record, config);
+        for (key, variant_type, dosages) in
+            present_alleles(&record, &samples)?.into_iter().flatten()
+        {
+            truth.variants.insert(
+                key,
+                TruthVariant {
+                    variant_type,
+                    in_regions,
+                    dosages,
+                    matched: false,
+                },
+            );
+        }
+    }
+    Ok(truth)
+}
+
+fn benchmark_header(header: &VCFHeader) -> Result<VCFHeader, VCFUtilsError> {
+    let mut header_items: Vec<_> = header.items().to_vec();
+    if header.info(b"BENCHMARK").is_none() {
+        header_items.push(VCFHeaderLine::from_bytes(
+            b"##INFO=<ID=BENCHMARK,Number=A,Type=String,Description=\"Comparison result of each ALT allele (TP, FP or FN)\">\n",
+            0,
+        )?);
+    }
+    Ok(VCFHeader::new(header_items, header.samples().to_vec()))
+}
+
+/// Compare query VCF with truth. Matched truth alleles are marked, and query records are
+/// written with BENCHMARK INFO (TP or FP) if `writer` is given.
+pub fn compare_query<R: BufRead>(
+    reader: &mut VCFReader<R>,
+    truth: &mut TruthSet,
+    config: &CompareConfig,
+    writer: Option<&mut dyn Write>,
+) -> Result<ComparisonSummary, VCFUtilsError> {
+    let samples: Vec<_> = config.sample_pairs.iter().map(|x| &x.1).collect();
+    let mut summary = ComparisonSummary::new(config);
+    let new_header = benchmark_header(reader.header())?;
+    let mut vcf_writer = writer.map(|x| VCFWriter::new(x, &new_header)).transpose()?;
+    let mut record = VCFRecord::new(reader.header().clone());
+
+    while reader.next_record(&mut record)? {
+        let mut benchmark = vec![b".".to_vec(); record.alternative.len()];
+        if is_passed(&record, config) {
+            let in_regions = is_in_regions(&record, config);
+            for (alt_index, allele) in present_alleles(&record, &samples)?.into_iter().enumerate() {
+                let (key, variant_type, dosages) = if let Some(x) = allele {
+                    x
+                } else {
+                    continue;
+                };
+                let truth_variant = truth.variants.get_mut(&key);
+                let outcome = if truth_variant.is_some() {
+                    Outcome::TruePositive
+                } else {
+                    Outcome::FalsePositive
+                };
+                summary.add(0, variant_type, in_regions, outcome);
+                benchmark[alt_index] = if outcome == Outcome::TruePositive {
+                    b"TP".to_vec()
+                } else {
+                    b"FP".to_vec()
+                };
+
+                let truth_dosages = if let Some(x) = truth_variant {
+                    x.matched = true;
+                    x.dosages.clone()
+                } else {
+                    vec![0; dosages.len()]
+                };
+                for (sample_index, (query_dosage, truth_dosage)) in
+                    dosages.iter().zip(truth_dosages.iter()).enumerate()
+                {
+                    match (*truth_dosage > 0, *query_dosage > 0) {
+                        (true, true) => {
+                            summary.add(
+                                sample_index + 1,
+                                variant_type,
+                                in_regions,
+                                Outcome::TruePositive,
+                            );
+                            summary.add_genotype(
+                                sample_index + 1,
+                                variant_type,
+                                in_regions,
+                                truth_dosage == query_dosage,
+                            );
+                        }
+                        (true, false) => summary.add(
+                            sample_index + 1,
+                            variant_type,
+                            in_regions,
+                            Outcome::FalseNegative,
+                        ),
+                        (false, true) => summary.add(
+                            sample_index + 1,
+                            variant_type,
+                            in_regions,
+                            Outcome::FalsePositive,
+                        ),
+                        (false, false) => (),
+                    }
+                }
+            }
+        }
+        if let Some(vcf_writer) = vcf_writer.as_mut() {
+            record.insert_info(b"BENCHMARK", benchmark);
+            vcf_writer.write_record(&record)?;
+        }
+    }
+
+    for one in truth.variants.values().filter(|x| !x.matched) {
+        summary.add(0, one.variant_type, one.in_regions, Outcome::FalseNegative);
+        for (sample_index, _) in one.dosages.iter().enumerate().filter(|(_, x)| **x > 0) {
+            summary.add(
+                sample_index + 1,
+                one.variant_type,
+                one.in_regions,
+                Outcome::FalseNegative,
+            );
+        }
+    }
+
+    Ok(summary)
+}
+
+/// Write truth records with BENCHMARK INFO (TP or FN) after comparison.
+pub fn annotate_truth<R: BufRead, W: Write>(
+    reader: &mut VCFReader<R>,
+    writer: W,
+    truth: &TruthSet,
+    config: &CompareConfig,
+) -> Result<(), VCFUtilsError> {
+    let samples: Vec<_> = config.sample_pairs.iter().map(|x| &x.0).collect();
+    let new_header = benchmark_header(reader.header())?;
+    let mut vcf_writer = VCFWriter::new(writer, &new_header)?;
+    let mut record = VCFRecord::new(reader.header().clone());
+    while reader.next_record(&mut record)? {
+        let benchmark = if is_passed(&record, config) {
+            present_alleles(&record, &samples)?
+                .into_iter()
+                .map(
+                    |x| match x.and_then(|(key, _, _)| truth.variants.get(&key)) {
+                        Some(x) if x.matched => b"TP".to_vec(),
+                        Some(_) => b"FN".to_vec(),
+                        None => b".".to_vec(),
+                    },
+                )
+                .collect()
+        } else {
+            vec![b".".to_vec(); record.alternative.len()]
+        };
+        record.insert_info(b"BENCHMARK", benchmark);
+        vcf_writer.write_record(&record)?;
+    }
+    Ok(())
+}
+
+pub fn comparison_header() -> Vec<String> {
+    [
+        "Sample",
+        "Type",
+        "Region",
+        "TP",
+        "FP",
+        "FN",
+        "Precision",
+        "Recall",
+        "F1",
+        "GenotypeMatch",
+        "GenotypeMismatch",
+        "GenotypeConcordance",
+    ]
+    .iter()
+    .map(|x| x.to_string())
+    .collect()
+}
+
+pub fn comparison_column_types() -> Vec<ColumnType> {
+    comparison_header()
+        .iter()
+        .map(|x| match x.as_str() {
+            "Sample" | "Type" | "Region" => ColumnType::String,
+            "Precision" | "Recall" | "F1" | "GenotypeConcordance" => ColumnType::Float,
+            _ => ColumnType::Integer,
+        })
+        .collect()
+}
+
+pub fn write_comparison_summary(
+    summary: &ComparisonSummary,
+    float_precision: usize,
+    writer: &mut impl TableWriter,
+) -> Result<(), VCFUtilsError> {
+    let format_float = |x: Option<f64>| {
+        x.map(|x| format!("{:.prec$}", x, prec = float_precision))
+            .unwrap_or_default()
+    };
+
+    writer.set_header(&comparison_header());
+    writer.write_header()?;
+    for ((sample_index, variant_type, in_regions), stats) in summary.stats.iter() {
+        let row = [
+            summary.samples[*sample_index].clone(),
+            match variant_type {
+                None => "ALL",
+                Some(VariantType::Snv) => "SNV",
+                Some(VariantType::Mnp) => "MNP",
+                Some(VariantType::Indel) => "INDEL",
+                Some(VariantType::Other) => "OTHER",
+            }
+            .to_string(),
+            if *in_regions { "BED" } else { "ALL" }.to_string(),
+            stats.true_positive.to_string(),
+            stats.false_positive.to_string(),
+            stats.false_negative.to_string(),
+            format_float(stats.precision()),
+            format_float(stats.recall()),
+            format_float(stats.f1()),
+            stats.genotype_match.to_string(),
+            stats.genotype_mismatch.to_string(),
+            format_float(stats.genotype_concordance()),
+        ];
+        writer.write_row(&row.iter().map(|x| x.as_str()).collect::<Vec<_>>())?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::logic::filter::load_bed;
+    use crate::utils::tablewriter::TSVWriter;
+
+    #[test]
+    fn test_compare() -> Result<(), VCFUtilsError> {
+        let truth_data = include_bytes!("../../testfiles/compare-truth.vcf");
+        let query_data = include_bytes!("../../testfiles/compare-query.vcf");
+        let regions = load_bed(&include_bytes!("../../testfiles/compare-regions.bed")[..])?;
+        let mut truth_reader = VCFReader::new(&truth_data[..])?;
+        let mut query_reader = VCFReader::new(&query_data[..])?;
+        let config = CompareConfig {
+            sample_pairs: CompareConfig::common_samples(
+                truth_reader.header(),
+                query_reader.header(),
+            ),
+            regions: Some(VariantFilter::new(None, Some(&regions), 0)),
+            pass_only: true,
+        };
+        assert_eq!(config.sample_pairs, vec![(b"S1".to_vec(), b"S1".to_vec())]);
+
+        let mut truth = load_truth(&mut truth_reader, &config)?;
+        let mut query_bytes = Vec::new();
+        let summary = compare_query(
+            &mut query_reader,
+            &mut truth,
+            &config,
+            Some(&mut query_bytes),
+        )?;
+
+        assert_eq!(
+            summary.get(0, None, false),
+            Some(&ConcordanceStats {
+                true_positive: 5,
+                false_positive: 1,
+                false_negative: 1,
+                genotype_match: 4,
+                genotype_mismatch: 1,
+            })
+        );
+        assert_eq!(summary.get(1, None, false), summary.get(0, None, false));
+        assert_eq!(
+            summary.get(0, Some(VariantType::Snv), false).map(|x| (
+                x.true_positive,
+                x.false_positive,
+                x.false_negative
+            )),
+            Some((4, 1, 1))
+        );
+        assert_eq!(
+            summary
+                .get(0, Some(VariantType::Indel), false)
+                .map(|x| x.true_positive),
+            Some(1)
+        );
+        assert_eq!(
+            summary.get(0, None, true).map(|x| (
+                x.true_positive,
+                x.false_positive,
+                x.false_negative
+            )),
+            Some((2, 0, 1))
+        );
+
+        let query_output = String::from_utf8(query_bytes).unwrap();
+        assert!(query_output.contains("1\t600\t.\tG\tC\t50.0\tPASS\tBENCHMARK=FP\t"));
+        assert!(query_output.contains("1\t400\t.\tATT\tAT\t50.0\tPASS\tBENCHMARK=TP\t"));
+
+        let mut truth_reader = VCFReader::new(&truth_data[..])?;
+        let mut truth_bytes = Vec::new();
+        annotate_truth(&mut truth_reader, &mut truth_bytes, &truth, &config)?;
+        let truth_output = String::from_utf8(truth_bytes).unwrap();
+        assert!(truth_output.contains("1\t300\t.\tG\tA\t50.0\tPASS\tBENCHMARK=FN\t"));
+        assert!(truth_output.contains("1\t500\t.\tC\tG,T\t50.0\tPASS\tBENCHMARK=TP,TP\t"));
+
+        let mut write_bytes = Vec::new();
+        write_comparison_summary(&summary, 4, &mut TSVWriter::new(&mut write_bytes))?;
+        let lines: Vec<_> = write_bytes.split(|x| *x == b'\n').collect();
+        assert_eq!(
+            lines[1],
+            b"ALL\tALL\tALL\t5\t1\t1\t0.8333\t0.8333\t0.8333\t4\t1\t0.8000"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_compare_split_alleles_and_strata() -> Result<(), VCFUtilsError> {
+        let header = "##fileformat=VCFv4.2\n\
+                      ##FORMAT=<ID=GT,Number=1,Type=String,Description=\"Genotype\">\n\
+                      ##contig=<ID=1,length=249250621>\n\
+                      #CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO\tFORMAT\tS1\n";
+        let truth_data = format!(
+            "{}{}",
+            header,
+            [
+                "1\t100\t.\tC\tG,T\t50\tPASS\t.\tGT\t1/2",
+                "1\t200\t.\tA\tC\t50\tPASS\t.\tGT\t0/1",
+                "1\t200\t.\tA\tG\t50\tPASS\t.\tGT\t0/1",
+                "1\t300\t.\tAC\tGT\t50\tPASS\t.\tGT\t0/1",
+                "1\t400\t.\tTAC\tTGT\t50\tPASS\t.\tGT\t0/1",
+                "1\t500\t.\tAT\tA\t50\tPASS\t.\tGT\t0/1\n",
+            ]
+            .join("\n")
+        );
+        let query_data = format!(
+            "{}{}",
+            header,
+            [
+                "1\t100\t.\tC\tG\t50\tPASS\t.\tGT\t0/1",
+                "1\t100\t.\tC\tT\t50\tPASS\t.\tGT\t1/1",
+                "1\t200\t.\tA\tC,G\t50\tPASS\t.\tGT\t1/2",
+                "1\t300\t.\tAC\tGT\t50\tPASS\t.\tGT\t1/1",
+                "1\t401\t.\tAC\tGT\t50\tPASS\t.\tGT\t0/1",
+                "1\t500\t.\tAT\tA\t50\tPASS\t.\tGT\t0/1",
+                "1\t600\t.\tA\tAG\t50\tPASS\t.\tGT\t0/1",
+                "1\t700\t.\tA\t<DEL>\t50\tPASS\t.\tGT\t0/1\n",
+            ]
+            .join("\n")
+        );
+        let mut truth_reader = VCFReader::new(truth_data.as_bytes())?;
+        let mut query_reader = VCFReader::new(query_data.as_bytes())?;
+        let config = CompareConfig {
+            sample_pairs: CompareConfig::common_samples(
+                truth_reader.header(),
+                query_reader.header(),
+            ),
+            ..Default::default()
+        };
+        let mut truth = load_truth(&mut truth_reader, &config)?;
+        let summary = compare_query(&mut query_reader, &mut truth, &config, None)?;
+
+        let stats = |variant_type| {
+            summary.get(1, variant_type, false).map(|x| {
+                (
+                    x.true_positive,
+                    x.false_positive,
+                    x.false_negative,
+                    x.genotype_match,
+                    x.genotype_mismatch,
+                )
+            })
+        };
+        assert_eq!(stats(None), Some((7, 2, 0, 5, 2)));
+        assert_eq!(stats(Some(VariantType::Snv)), Some((4, 0, 0, 3, 1)));
+        assert_eq!(stats(Some(VariantType::Mnp)), Some((2, 0, 0, 1, 1)));
+        assert_eq!(stats(Some(VariantType::Indel)), Some((1, 1, 0, 1, 0)));
+        assert_eq!(stats(Some(VariantType::Other)), Some((0, 1, 0, 0, 0)));
+        assert_eq!(summary.get(0, None, true), None);
+        Ok(())
+    }
+}
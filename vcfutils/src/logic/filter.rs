@@ -0,0 +1,275 @@
+use crate::error::VCFUtilsError;
+use crate::utils::annotation::AnnotationFormat;
+use std::collections::{HashMap, HashSet};
+use std::io::{BufRead, BufReader, Write};
+use std::path::Path;
+use std::str;
+use vcf::{U8Vec, VCFReader, VCFRecord, VCFWriter};
+
+/// Gene list and panel region filter.
+///
+/// A variant passes the filter when one of its annotation entries has a gene symbol or gene ID
+/// in the gene list, and when it overlaps a panel region. Each condition is checked only if it
+/// is configured.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct VariantFilter {
+    genes: Option<HashSet<U8Vec>>,
+    // Merged 0-based half-open regions for each chromosome
+    regions: Option<HashMap<U8Vec, Vec<(u64, u64)>>>,
+}
+
+/// Remove version suffix from Ensembl ID (e.g. ENSG00000139618.15 to ENSG00000139618)
+fn strip_version(id: &[u8]) -> &[u8] {
+    if id.starts_with(b"ENS") {
+        if let Some(pos) = id.iter().position(|x| *x == b'.') {
+            return &id[..pos];
+        }
+    }
+    id
+}
+
+/// Load regions from a BED file, which may be compressed.
+pub fn load_regions<P: AsRef<Path>>(path: P) -> Result<Vec<(U8Vec, u64, u64)>, VCFUtilsError> {
+    load_bed(BufReader::new(autocompress::autodetect_open(path)?))
+}
+
+/// Load named regions from a BED file, which may be compressed.
+pub fn load_named_regions<P: AsRef<Path>>(
+    path: P,
+) -> Result<Vec<(U8Vec, u64, u64, U8Vec)>, VCFUtilsError> {
+    load_named_bed(BufReader::new(autocompress::autodetect_open(path)?))
+}
+
+/// Load regions from BED file. Header, track and comment lines are skipped.
+pub fn load_bed<R: BufRead>(reader: R) -> Result<Vec<(U8Vec, u64, u64)>, VCFUtilsError> {
+    Ok(load_named_bed(reader)?
+        .into_iter()
+        .map(|(chromosome, start, end, _)| (chromosome, start, end))
+        .collect())
+}
+
+/// Load regions and names (4th column) from BED file. Name is empty if not available.
+pub fn load_named_bed<R: BufRead>(
+    reader: R,
+) -> Result<Vec<(U8Vec, u64, u64, U8Vec)>, VCFUtilsError> {
+    let mut regions = Vec::new();
+    for line in reader.split(b'\n') {
+        let mut line = line?;
+        if line.ends_with(b"\r") {
+            line.pop();
+        }
+        if line.is_empty()
+            || line.starts_with(b"#")
+            || line.starts_with(b"track")
+            || line.starts_with(b"browser")
+        {
+            continue;
+        }
+        let mut elements = line.split(|x| *x == b'\t');
+        let chromosome = elements.next().unwrap_or_default();
+        let start = elements
+            .next()
+            .ok_or(VCFUtilsError::OtherError("No start column in BED file"))?;
+        let end = elements
+            .next()
+            .ok_or(VCFUtilsError::OtherError("No end column in BED file"))?;
+        let name = elements.next().unwrap_or_default();
+        regions.push((
+            chromosome.to_vec(),
+            str::from_utf8(start)?.parse()?,
+            str::from_utf8(end)?.parse()?,
+            name.to_vec(),
+        ));
+    }
+    Ok(regions)
+}
+
+impl VariantFilter {
+    pub fn new(
+        genes: Option<&[U8Vec]>,
+        regions: Option<&[(U8Vec, u64, u64)]>,
+        padding: u64,
+    ) -> Self {
+        let genes = genes.map(|x| x.iter().map(|y| strip_version(y).to_vec()).collect());
+        let regions = regions.map(|x| {
+            let mut chromosome_regions: HashMap<U8Vec, Vec<(u64, u64)>> = HashMap::new();
+            for (chromosome, start, end) in x {
+                chromosome_regions
+                    .entry(chromosome.clone())
+                    .or_default()
+                    .push((start.saturating_sub(padding), end + padding));
+            }
+            for one in chromosome_regions.values_mut() {
+                one.sort_unstable();
+                let mut merged: Vec<(u64, u64)> = Vec::with_capacity(one.len());
+                for (start, end) in one.iter() {
+                    match merged.last_mut() {
+                        Some(last) if *start <= last.1 => last.1 = last.1.max(*end),
+                        _ => merged.push((*start, *end)),
+                    }
+                }
+                *one = merged;
+            }
+            chromosome_regions
+        });
+        VariantFilter { genes, regions }
+    }
+
+    pub fn has_gene_filter(&self) -> bool {
+        self.genes.is_some()
+    }
+
+    /// Test whether the reference allele span overlaps one of regions.
+    pub fn is_region_matched(&self, record: &VCFRecord) -> bool {
+        let regions = if let Some(regions) = self.regions.as_ref() {
+            regions
+        } else {
+            return true;
+        };
+        let chromosome_regions = if let Some(x) = regions.get(&record.chromosome) {
+            x
+        } else {
+            return false;
+        };
+        let start = record.position.saturating_sub(1);
+        let end = start + (record.reference.len().max(1) as u64);
+        let index = chromosome_regions.partition_point(|x| x.1 <= start);
+        chromosome_regions
+            .get(index)
+            .map(|x| x.0 < end)
+            .unwrap_or(false)
+    }
+
+    /// Test whether the gene symbol or gene ID of an annotation entry is in the gene list.
+    pub fn is_entry_matched(&self, annotation: &AnnotationFormat, entry: &[&[u8]]) -> bool {
+        let genes = if let Some(genes) = self.genes.as_ref() {
+            genes
+        } else {
+            return true;
+        };
+        [annotation.gene, annotation.gene_id]
+            .iter()
+            .filter_map(|x| x.and_then(|y| entry.get(y)))
+            .any(|x| !x.is_empty() && genes.contains(strip_version(x)))
+    }
+
+    /// Test whether the record passes the filter. Only annotation entries for the alternative
+    /// allele are checked when `alt_index` is given.
+    pub fn is_matched(
+        &self,
+        record: &VCFRecord,
+        annotation: Option<&AnnotationFormat>,
+        alt_index: Option<usize>,
+    ) -> bool {
+        if !self.is_region_matched(record) {
+            return false;
+        }
+        if self.genes.is_none() {
+            return true;
+        }
+        if let Some(annotation) = annotation {
+            annotation
+                .entries(record, alt_index)
+                .iter()
+                .any(|x| self.is_entry_matched(annotation, x))
+        } else {
+            false
+        }
+    }
+}
+
+/// Write records which pass the filter. Returns the number of written records.
+pub fn filter_vcf<R: BufRead, W: Write>(
+    reader: &mut VCFReader<R>,
+    writer: W,
+    variant_filter: &VariantFilter,
+    annotation_key: Option<&[u8]>,
+) -> Result<u64, VCFUtilsError> {
+    let annotation = AnnotationFormat::detect(reader.header(), annotation_key);
+    if variant_filter.has_gene_filter() && annotation.is_none() {
+        return Err(VCFUtilsError::OtherError(
+            "Gene list filter requires ANN, CSQ or BCSQ annotation",
+        ));
+    }
+    let mut vcf_writer = VCFWriter::new(writer, reader.header())?;
+    let mut record = VCFRecord::new(reader.header().clone());
+    let mut count = 0;
+    while reader.next_record(&mut record)? {
+        if variant_filter.is_matched(&record, annotation.as_ref(), None) {
+            vcf_writer.write_record(&record)?;
+            count += 1;
+        }
+    }
+    Ok(count)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_load_bed() -> Result<(), VCFUtilsError> {
+        let regions =
+            load_bed(&b"track name=panel\n13\t32889000\t32890000\tBRCA2\n17\t100\t200\r\n"[..])?;
+        assert_eq!(
+            regions,
+            vec![
+                (b"13".to_vec(), 32889000, 32890000),
+                (b"17".to_vec(), 100, 200)
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_filter_vcf_region() -> Result<(), VCFUtilsError> {
+        let vcf_data = include_bytes!("../../testfiles/simple1.vcf");
+        let regions = vec![
+            (b"13".to_vec(), 32889967, 32889968),
+            (b"13".to_vec(), 32897000, 32897280),
+        ];
+
+        let variant_filter = VariantFilter::new(None, Some(&regions), 0);
+        let mut vcf_reader = VCFReader::new(&vcf_data[..])?;
+        let mut write_bytes = Vec::new();
+        assert_eq!(
+            filter_vcf(&mut vcf_reader, &mut write_bytes, &variant_filter, None)?,
+            1
+        );
+
+        // ATT at 32897288 overlaps the second region with padding
+        let variant_filter = VariantFilter::new(None, Some(&regions), 8);
+        let mut vcf_reader = VCFReader::new(&vcf_data[..])?;
+        let mut write_bytes = Vec::new();
+        assert_eq!(
+            filter_vcf(&mut vcf_reader, &mut write_bytes, &variant_filter, None)?,
+            2
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_filter_vcf_gene() -> Result<(), VCFUtilsError> {
+        let vcf_data = include_bytes!("../../testfiles/simple1-snpeff.vcf");
+        let variant_filter = VariantFilter::new(Some(&[b"ZAR1L".to_vec()]), None, 0);
+        let mut vcf_reader = VCFReader::new(&vcf_data[..])?;
+        let mut write_bytes = Vec::new();
+        assert_eq!(
+            filter_vcf(&mut vcf_reader, &mut write_bytes, &variant_filter, None)?,
+            1
+        );
+
+        let variant_filter = VariantFilter::new(Some(&[b"ENSG00000139618".to_vec()]), None, 0);
+        let mut vcf_reader = VCFReader::new(&vcf_data[..])?;
+        let mut write_bytes = Vec::new();
+        assert_eq!(
+            filter_vcf(&mut vcf_reader, &mut write_bytes, &variant_filter, None)?,
+            3
+        );
+
+        let vcf_data = include_bytes!("../../testfiles/simple1.vcf");
+        let mut vcf_reader = VCFReader::new(&vcf_data[..])?;
+        assert!(filter_vcf(&mut vcf_reader, &mut write_bytes, &variant_filter, None).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,377 @@
+use super::vcf2table::SnpEffImpact;
+use crate::error::VCFUtilsError;
+use crate::utils::annotation::AnnotationFormat;
+use crate::utils::genotype::sample_genotype;
+use crate::utils::tablewriter::{ColumnType, TableWriter};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::io::BufRead;
+use std::str;
+use vcf::{U8Vec, VCFReader, VCFRecord};
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct GeneBurdenConfig {
+    /// Impact classes to count
+    pub impacts: Vec<SnpEffImpact>,
+    /// Maximum allele frequency of qualifying variants
+    pub max_af: Option<f64>,
+    /// INFO key of allele frequency. Allele frequency is computed from genotypes if not given.
+    pub af_key: Option<U8Vec>,
+    pub annotation_key: Option<U8Vec>,
+}
+
+/// Qualifying variants of a gene and an impact class
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct GeneBurden {
+    pub gene: U8Vec,
+    pub impact: SnpEffImpact,
+    /// Variants in `CHROM:POS:REF:ALT`
+    pub variants: Vec<String>,
+    /// Number of qualifying alleles of samples with at least one qualifying allele, indexed by
+    /// sample index
+    pub sample_allele_count: BTreeMap<usize, u64>,
+}
+
+impl GeneBurden {
+    /// Number of samples with at least one qualifying allele. All samples are counted if
+    /// `samples` is `None`.
+    pub fn carriers(&self, samples: Option<&HashSet<usize>>) -> u64 {
+        self.sample_allele_count
+            .keys()
+            .filter(|x| samples.map(|y| y.contains(x)).unwrap_or(true))
+            .count() as u64
+    }
+
+    pub fn allele_count(&self, samples: Option<&HashSet<usize>>) -> u64 {
+        self.sample_allele_count
+            .iter()
+            .filter(|(x, _)| samples.map(|y| y.contains(x)).unwrap_or(true))
+            .map(|(_, count)| count)
+            .sum()
+    }
+}
+
+fn allele_frequency(
+    record: &VCFRecord,
+    genotypes: &[Vec<Option<usize>>],
+    alt_index: usize,
+    af_key: Option<&[u8]>,
+) -> f64 {
+    if let Some(af_key) = af_key {
+        // Variants without allele frequency are treated as absent in the population
+        return record
+            .info(af_key)
+            .and_then(|x| x.get(alt_index))
+            .and_then(|x| str::from_utf8(x).ok())
+            .and_then(|x| x.parse::<f64>().ok())
+            .unwrap_or(0.);
+    }
+    let called: Vec<_> = genotypes.iter().flatten().flatten().collect();
+    if called.is_empty() {
+        return 0.;
+    }
+    called.iter().filter(|x| ***x == alt_index + 1).count() as f64 / called.len() as f64
+}
+
+/// Group qualifying variants by gene and the most severe impact of the gene, and count
+/// qualifying alleles of samples. Results are sorted by gene and impact.
+pub fn gene_burden<R: BufRead>(
+    reader: &mut VCFReader<R>,
+    config: &GeneBurdenConfig,
+) -> Result<Vec<GeneBurden>, VCFUtilsError> {
+    let annotation = AnnotationFormat::detect(reader.header(), config.annotation_key.as_deref())
+        .ok_or(VCFUtilsError::OtherError(
+            "No annotation INFO tag in header",
+        ))?;
+    let impact_index = annotation.impact.ok_or(VCFUtilsError::OtherError(
+        "No impact subfield in annotation",
+    ))?;
+    let samples = reader.header().samples().to_vec();
+    let mut burden: BTreeMap<(U8Vec, SnpEffImpact), GeneBurden> = BTreeMap::new();
+    let mut record = VCFRecord::new(reader.header().clone());
+
+    while reader.next_record(&mut record)? {
+        // Malformed genotypes are treated as missing
+        let genotypes: Vec<_> = samples
+            .iter()
+            .map(|x| sample_genotype(&record, x))
+            .collect();
+
+        for (alt_index, alt) in record.alternative.iter().enumerate() {
+            if let Some(max_af) = config.max_af {
+                if allele_frequency(&record, &genotypes, alt_index, config.af_key.as_deref())
+                    > max_af
+                {
+                    continue;
+                }
+            }
+
+            let mut gene_impacts: Vec<(U8Vec, SnpEffImpact)> = Vec::new();
+            for entry in annotation.entries(&record, Some(alt_index)) {
+                let gene = [annotation.gene, annotation.gene_id]
+                    .iter()
+                    .filter_map(|x| x.and_then(|y| entry.get(y)))
+                    .find(|x| !x.is_empty());
+                let impact = entry
+                    .get(impact_index)
+                    .and_then(|x| SnpEffImpact::try_from(*x).ok());
+                if let (Some(gene), Some(impact)) = (gene, impact) {
+                    if let Some(x) = gene_impacts.iter_mut().find(|x| x.0 == *gene) {
+                        x.1 = x.1.min(impact);
+                    } else {
+                        gene_impacts.push((gene.to_vec(), impact));
+                    }
+                }
+            }
+
+            let allele_counts: Vec<(usize, u64)> = genotypes
+                .iter()
+                .map(|x| x.iter().filter(|y| **y == Some(alt_index + 1)).count() as u64)
+                .enumerate()
+                .filter(|x| x.1 > 0)
+                .collect();
+            let variant = format!(
+                "{}:{}:{}:{}",
+                String::from_utf8_lossy(&record.chromosome),
+                record.position,
+                String::from_utf8_lossy(&record.reference),
+                String::from_utf8_lossy(alt)
+            );
+            for (gene, impact) in gene_impacts
+                .into_iter()
+                .filter(|x| config.impacts.contains(&x.1))
+            {
+                let one = burden
+                    .entry((gene.clone(), impact))
+                    .or_insert_with(|| GeneBurden {
+                        gene,
+                        impact,
+                        variants: Vec::new(),
+                        sample_allele_count: BTreeMap::new(),
+                    });
+                one.variants.push(variant.clone());
+                for (sample, count) in allele_counts.iter() {
+                    *one.sample_allele_count.entry(*sample).or_insert(0) += count;
+                }
+            }
+        }
+    }
+
+    Ok(burden.into_values().collect())
+}
+
+pub fn gene_burden_header(categories: &[U8Vec], samples: Option<&[U8Vec]>) -> Vec<String> {
+    let mut header: Vec<String> = [
+        "Gene",
+        "Impact",
+        "Variants",
+        "VariantList",
+        "Carriers",
+        "AC",
+    ]
+    .iter()
+    .map(|x| x.to_string())
+    .collect();
+    for one in categories {
+        let category = String::from_utf8_lossy(one);
+        header.push(format!("Carriers_{}", category));
+        header.push(format!("AC_{}", category));
+    }
+    if let Some(samples) = samples {
+        header.extend(
+            samples
+                .iter()
+                .map(|x| String::from_utf8_lossy(x).to_string()),
+        );
+    }
+    header
+}
+
+pub fn gene_burden_column_types(
+    categories: &[U8Vec],
+    samples: Option<&[U8Vec]>,
+) -> Vec<ColumnType> {
+    gene_burden_header(categories, samples)
+        .iter()
+        .enumerate()
+        .map(|(i, _)| match i {
+            0 | 1 | 3 => ColumnType::String,
+            _ => ColumnType::Integer,
+        })
+        .collect()
+}
+
+/// Sorted categories of the category mapping
+pub fn sorted_categories(category_to_samples: &HashMap<U8Vec, HashSet<U8Vec>>) -> Vec<U8Vec> {
+    let mut categories: Vec<_> = category_to_samples.keys().cloned().collect();
+    categories.sort();
+    categories
+}
+
+/// Write a table of genes and impact classes with carriers and allele counts of all samples and
+/// categories. Qualifying allele counts of each sample are written if `sample_columns` is true.
+pub fn write_gene_burden(
+    burden: &[GeneBurden],
+    samples: &[U8Vec],
+    category_to_samples: &HashMap<U8Vec, HashSet<U8Vec>>,
+    sample_columns: bool,
+    writer: &mut impl TableWriter,
+) -> Result<(), VCFUtilsError> {
+    let categories = sorted_categories(category_to_samples);
+    let category_indexes: Vec<HashSet<usize>> = categories
+        .iter()
+        .map(|x| {
+            let members = &category_to_samples[x];
+            samples
+                .iter()
+                .enumerate()
+                .filter(|(_, y)| members.contains(*y))
+                .map(|(i, _)| i)
+                .collect()
+        })
+        .collect();
+
+    writer.set_header(&gene_burden_header(
+        &categories,
+        if sample_columns { Some(samples) } else { None },
+    ));
+    writer.write_header()?;
+    for one in burden {
+        let mut row = vec![
+            String::from_utf8_lossy(&one.gene).to_string(),
+            one.impact.to_str().to_string(),
+            one.variants.len().to_string(),
+            one.variants.join(","),
+            one.carriers(None).to_string(),
+            one.allele_count(None).to_string(),
+        ];
+        for samples in category_indexes.iter() {
+            row.push(one.carriers(Some(samples)).to_string());
+            row.push(one.allele_count(Some(samples)).to_string());
+        }
+        if sample_columns {
+            row.extend(
+                (0..samples.len())
+                    .map(|x| one.sample_allele_count.get(&x).unwrap_or(&0).to_string()),
+            );
+        }
+        writer.write_row(&row.iter().map(|x| x.as_str()).collect::<Vec<_>>())?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::utils::tablewriter::TSVWriter;
+
+    const GENE_BURDEN_VCF: &[u8] = b"##fileformat=VCFv4.2
+##INFO=<ID=gnomAD_AF,Number=A,Type=Float,Description=\"gnomAD allele frequency\">
+##INFO=<ID=ANN,Number=.,Type=String,Description=\"Functional annotations: 'Allele | Annotation | Annotation_Impact | Gene_Name | Gene_ID'\">
+##FORMAT=<ID=GT,Number=1,Type=String,Description=\"Genotype\">
+#CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO\tFORMAT\tS1\tS2\tS3\tS4
+1\t100\t.\tC\tT\t.\t.\tgnomAD_AF=0.001;ANN=T|stop_gained|HIGH|GENE1|G1,T|missense_variant|MODERATE|GENE1|G1\tGT\t0/1\t0/0\t0/0\t0/0
+1\t200\t.\tG\tA,C\t.\t.\tgnomAD_AF=0.0001,0.2;ANN=A|missense_variant|MODERATE|GENE1|G1,C|missense_variant|MODERATE|GENE1|G1\tGT\t0/1\t1/1\t0/2\t./.
+1\t300\t.\tA\tG\t.\t.\tANN=G|frameshift_variant|HIGH|GENE1|G1,G|synonymous_variant|LOW|GENE2|G2\tGT\t0/0\t0/0\t0/1\t0/0
+2\t400\t.\tT\tC\t.\t.\tANN=C|missense_variant|MODERATE|GENE2|G2\tGT\t0/1\t0/1\t0/1\t0/0
+";
+
+    #[test]
+    fn test_gene_burden() -> Result<(), VCFUtilsError> {
+        let mut vcf_reader = VCFReader::new(GENE_BURDEN_VCF)?;
+        let config = GeneBurdenConfig {
+            impacts: vec![
+                SnpEffImpact::High,
+                SnpEffImpact::Moderate,
+                SnpEffImpact::Low,
+            ],
+            max_af: Some(0.01),
+            af_key: Some(b"gnomAD_AF".to_vec()),
+            annotation_key: None,
+        };
+        let burden = gene_burden(&mut vcf_reader, &config)?;
+        assert_eq!(burden.len(), 4);
+
+        assert_eq!(burden[0].gene, b"GENE1");
+        assert_eq!(burden[0].impact, SnpEffImpact::High);
+        assert_eq!(burden[0].variants, vec!["1:100:C:T", "1:300:A:G"]);
+        assert_eq!(
+            burden[0].sample_allele_count,
+            [(0, 1), (2, 1)].into_iter().collect()
+        );
+        assert_eq!(burden[1].gene, b"GENE1");
+        assert_eq!(burden[1].impact, SnpEffImpact::Moderate);
+        assert_eq!(burden[1].variants, vec!["1:200:G:A"]);
+        assert_eq!(burden[1].carriers(None), 2);
+        assert_eq!(burden[1].allele_count(None), 3);
+        assert_eq!(burden[2].gene, b"GENE2");
+        assert_eq!(burden[2].impact, SnpEffImpact::Moderate);
+        assert_eq!(burden[2].carriers(None), 3);
+        assert_eq!(burden[3].impact, SnpEffImpact::Low);
+
+        let category_to_samples: HashMap<U8Vec, HashSet<U8Vec>> = [
+            (
+                b"case".to_vec(),
+                [b"S1".to_vec(), b"S2".to_vec()].into_iter().collect(),
+            ),
+            (
+                b"control".to_vec(),
+                [b"S3".to_vec(), b"S4".to_vec()].into_iter().collect(),
+            ),
+        ]
+        .into_iter()
+        .collect();
+        let mut output = Vec::new();
+        write_gene_burden(
+            &burden[..2],
+            vcf_reader.header().samples(),
+            &category_to_samples,
+            true,
+            &mut TSVWriter::new(&mut output),
+        )?;
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "Gene\tImpact\tVariants\tVariantList\tCarriers\tAC\tCarriers_case\tAC_case\tCarriers_control\tAC_control\tS1\tS2\tS3\tS4
+GENE1\tHIGH\t2\t1:100:C:T,1:300:A:G\t2\t2\t1\t1\t1\t1\t1\t0\t1\t0
+GENE1\tMODERATE\t1\t1:200:G:A\t2\t3\t2\t3\t0\t0\t1\t2\t0\t0
+"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_gene_burden_cohort_af() -> Result<(), VCFUtilsError> {
+        let mut vcf_reader = VCFReader::new(GENE_BURDEN_VCF)?;
+        let config = GeneBurdenConfig {
+            impacts: vec![SnpEffImpact::High, SnpEffImpact::Moderate],
+            max_af: Some(0.2),
+            ..Default::default()
+        };
+        let burden = gene_burden(&mut vcf_reader, &config)?;
+        // 1:200:G:A (AF 0.5) and 2:400:T:C (AF 0.375) are excluded
+        assert_eq!(burden.len(), 2);
+        assert_eq!(burden[0].variants, vec!["1:100:C:T", "1:300:A:G"]);
+        assert_eq!(burden[1].impact, SnpEffImpact::Moderate);
+        assert_eq!(burden[1].variants, vec!["1:200:G:C"]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_gene_burden_malformed_genotype() -> Result<(), VCFUtilsError> {
+        let vcf = String::from_utf8(GENE_BURDEN_VCF.to_vec())
+            .unwrap()
+            .replace("GT\t0/1\t0/1\t0/1\t0/0\n", "GT\t0/1\t0/1\t0/1\t0/x\n");
+        let mut vcf_reader = VCFReader::new(vcf.as_bytes())?;
+        let config = GeneBurdenConfig {
+            impacts: vec![SnpEffImpact::Moderate],
+            ..Default::default()
+        };
+        let burden = gene_burden(&mut vcf_reader, &config)?;
+        assert_eq!(burden[1].gene, b"GENE2");
+        assert_eq!(burden[1].carriers(None), 3);
+        assert!(!burden[1].sample_allele_count.contains_key(&3));
+
+        Ok(())
+    }
+}
@@ -2,60 +2,113 @@ use super::vcf2table::{create_header_line, HeaderType, VCF2CSVConfig};
 use crate::error::VCFUtilsError;
 use itertools::join;
 use nom::character::is_alphanumeric;
+use std::collections::HashSet;
 use std::string::String;
 use vcf::{self, VCFHeader, ValueType};
 
-pub fn generate_sql(
-    header: &VCFHeader,
-    config: &VCF2CSVConfig,
-    table_name: &str,
-) -> Result<String, VCFUtilsError> {
-    let header_items = create_header_line(header, config);
-    let mut sql = format!("CREATE TABLE {} (\n", table_name);
+/// A column in SQL table. `index` is the position in header contents.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SqlColumn {
+    pub index: usize,
+    pub name: String,
+    pub sql_type: &'static str,
+}
 
+/// Columns of SQL table. Names which collide after cleaning are suffixed with `_2`, `_3`, ...
+pub fn sql_columns(header_items: &[HeaderType]) -> Vec<SqlColumn> {
+    let mut columns: Vec<SqlColumn> = header_items
+        .iter()
+        .enumerate()
+        .filter(|(_, x)| **x != HeaderType::AltIndex && **x != HeaderType::VcfLine)
+        .map(|(index, x)| SqlColumn {
+            index,
+            name: String::from_utf8(clean_name(x.to_string().as_bytes())).unwrap(),
+            sql_type: match x {
+                HeaderType::GroupName => "TEXT",
+                HeaderType::VcfLine => "",
+                HeaderType::AltIndex => "",
+                HeaderType::GeneChange => "TEXT",
+                HeaderType::GeneName => "TEXT",
+                HeaderType::CanonicalChange => "TEXT",
+                HeaderType::TranscriptName => "TEXT",
+                HeaderType::AminoChange => "TEXT",
+                HeaderType::CDSChange => "TEXT",
+                HeaderType::CHROM => "TEXT",
+                HeaderType::POS => "INTEGER",
+                HeaderType::ID => "TEXT",
+                HeaderType::REF => "TEXT",
+                HeaderType::ALT => "TEXT",
+                HeaderType::SvType => "TEXT",
+                HeaderType::SvStart => "INTEGER",
+                HeaderType::SvEnd => "INTEGER",
+                HeaderType::SvLength => "INTEGER",
+                HeaderType::MateCHROM => "TEXT",
+                HeaderType::MatePOS => "INTEGER",
+                HeaderType::SvGenes => "TEXT",
+                HeaderType::QUAL => "REAL",
+                HeaderType::FILTER => "TEXT",
+                HeaderType::SnpEffHighestImpact => "TEXT",
+                HeaderType::Info(_, _, value_type, ..)
+                | HeaderType::Genotype(_, _, _, value_type, ..) => match value_type {
+                    ValueType::Integer => "INTEGER",
+                    ValueType::Float => "REAL",
+                    _ => "TEXT",
+                },
+                HeaderType::SnpEffImpact(_) => "TEXT",
+                HeaderType::SnpEff => "TEXT",
+                HeaderType::Empty => "TEXT",
+                HeaderType::VAF(_, _, _) => "TEXT",
+                HeaderType::Annotation(_, _, _) => "TEXT",
+            },
+        })
+        .collect();
+
+    // SQLite identifiers are case insensitive
+    let mut used_names = HashSet::new();
+    for one in columns.iter_mut() {
+        let base_name = one.name.clone();
+        let mut suffix = 2;
+        while !used_names.insert(one.name.to_ascii_lowercase()) {
+            one.name = format!("{}_{}", base_name, suffix);
+            suffix += 1;
+        }
+    }
+    columns
+}
+
+pub fn create_table_sql(table_name: &str, columns: &[SqlColumn]) -> String {
+    let mut sql = format!("CREATE TABLE {} (\n", quote_identifier(table_name));
     sql.push_str(&join(
-        header_items
+        columns
             .iter()
-            .filter(|x| **x != HeaderType::AltIndex && **x != HeaderType::VcfLine)
-            .map(|x| {
-                format!(
-                    "    {} {}",
-                    String::from_utf8(clean_name(x.to_string().as_bytes())).unwrap(),
-                    match x {
-                        HeaderType::GroupName => "TEXT",
-                        HeaderType::VcfLine => "",
-                        HeaderType::AltIndex => "",
-                        HeaderType::GeneChange => "TEXT",
-                        HeaderType::GeneName => "TEXT",
-                        HeaderType::CanonicalChange => "TEXT",
-                        HeaderType::TranscriptName => "TEXT",
-                        HeaderType::AminoChange => "TEXT",
-                        HeaderType::CDSChange => "TEXT",
-                        HeaderType::CHROM => "TEXT",
-                        HeaderType::POS => "INTEGER",
-                        HeaderType::ID => "TEXT",
-                        HeaderType::REF => "TEXT",
-                        HeaderType::ALT => "TEXT",
-                        HeaderType::QUAL => "REAL",
-                        HeaderType::FILTER => "TEXT",
-                        HeaderType::SnpEffHighestImpact => "TEXT",
-                        HeaderType::Info(_, _, value_type, ..)
-                        | HeaderType::Genotype(_, _, _, value_type, ..) => match value_type {
-                            ValueType::Integer => "INTEGER",
-                            ValueType::Float => "REAL",
-                            _ => "TEXT",
-                        },
-                        HeaderType::SnpEffImpact(_) => "TEXT",
-                        HeaderType::SnpEff => "TEXT",
-                        HeaderType::Empty => "TEXT",
-                        HeaderType::VAF(_, _, _) => "TEXT",
-                    }
-                )
-            }),
+            .map(|x| format!("    {} {}", quote_identifier(&x.name), x.sql_type)),
         ",\n",
     ));
     sql.push_str("\n);");
-    Ok(sql)
+    sql
+}
+
+pub fn insert_sql(table_name: &str, columns: &[SqlColumn]) -> String {
+    format!(
+        "INSERT INTO {} ({}) VALUES ({})",
+        quote_identifier(table_name),
+        join(columns.iter().map(|x| quote_identifier(&x.name)), ", "),
+        join((1..=columns.len()).map(|x| format!("?{}", x)), ", ")
+    )
+}
+
+pub fn generate_sql(
+    header: &VCFHeader,
+    config: &VCF2CSVConfig,
+    table_name: &str,
+) -> Result<String, VCFUtilsError> {
+    let header_items = create_header_line(header, config)?;
+    Ok(create_table_sql(table_name, &sql_columns(&header_items)))
+}
+
+/// Quote a table or column name as SQL identifier.
+pub fn quote_identifier(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
 }
 
 fn clean_name(name: &[u8]) -> Vec<u8> {
@@ -72,6 +125,32 @@ mod test {
         assert_eq!(clean_name(b"abc123 !-=_"), b"abc123_____");
     }
 
+    #[test]
+    fn test_quote_identifier() {
+        assert_eq!(quote_identifier("vcf_table"), "\"vcf_table\"");
+        assert_eq!(quote_identifier("my \"table\""), "\"my \"\"table\"\"\"");
+    }
+
+    #[test]
+    fn test_insert_sql() {
+        let columns = vec![
+            SqlColumn {
+                index: 2,
+                name: "CHROM".to_string(),
+                sql_type: "TEXT",
+            },
+            SqlColumn {
+                index: 3,
+                name: "POS".to_string(),
+                sql_type: "INTEGER",
+            },
+        ];
+        assert_eq!(
+            insert_sql("vcf_table", &columns),
+            r#"INSERT INTO "vcf_table" ("CHROM", "POS") VALUES (?1, ?2)"#
+        );
+    }
+
     #[test]
     fn test_generate_sql() -> Result<(), VCFUtilsError> {
         let vcf_data = include_bytes!("../../testfiles/simple1.vcf");
@@ -85,31 +164,37 @@ mod test {
             priority_format_list: Vec::new(),
             replace_sample_name: None,
             group_names: None,
+            annotation_key: None,
+            annotation_fields: Vec::new(),
+            long_format: false,
+            variant_filter: None,
+            structural_variant: false,
+            sv_gene_regions: None,
         };
         let vcf_reader = vcf::VCFReader::new(&vcf_data[..])?;
         assert_eq!(
             generate_sql(&vcf_reader.header(), &config, "vcf_table")?,
-            r#"CREATE TABLE vcf_table (
-    CHROM TEXT,
-    POS INTEGER,
-    ID TEXT,
-    REF TEXT,
-    ALT TEXT,
-    ERP001775_HiSeq2000_SAMEA1531955_1__VAF TEXT,
-    ERP001775_HiSeq2000_SAMEA1531955_2__VAF TEXT,
-    QUAL REAL,
-    FILTER TEXT,
-    AC INTEGER,
-    AN INTEGER,
-    AF REAL,
-    ERP001775_HiSeq2000_SAMEA1531955_1__GT TEXT,
-    ERP001775_HiSeq2000_SAMEA1531955_1__AD__Ref INTEGER,
-    ERP001775_HiSeq2000_SAMEA1531955_1__AD__Alt INTEGER,
-    ERP001775_HiSeq2000_SAMEA1531955_1__DP INTEGER,
-    ERP001775_HiSeq2000_SAMEA1531955_2__GT TEXT,
-    ERP001775_HiSeq2000_SAMEA1531955_2__AD__Ref INTEGER,
-    ERP001775_HiSeq2000_SAMEA1531955_2__AD__Alt INTEGER,
-    ERP001775_HiSeq2000_SAMEA1531955_2__DP INTEGER
+            r#"CREATE TABLE "vcf_table" (
+    "CHROM" TEXT,
+    "POS" INTEGER,
+    "ID" TEXT,
+    "REF" TEXT,
+    "ALT" TEXT,
+    "ERP001775_HiSeq2000_SAMEA1531955_1__VAF" TEXT,
+    "ERP001775_HiSeq2000_SAMEA1531955_2__VAF" TEXT,
+    "QUAL" REAL,
+    "FILTER" TEXT,
+    "AC" INTEGER,
+    "AN" INTEGER,
+    "AF" REAL,
+    "ERP001775_HiSeq2000_SAMEA1531955_1__GT" TEXT,
+    "ERP001775_HiSeq2000_SAMEA1531955_1__AD__Ref" INTEGER,
+    "ERP001775_HiSeq2000_SAMEA1531955_1__AD__Alt" INTEGER,
+    "ERP001775_HiSeq2000_SAMEA1531955_1__DP" INTEGER,
+    "ERP001775_HiSeq2000_SAMEA1531955_2__GT" TEXT,
+    "ERP001775_HiSeq2000_SAMEA1531955_2__AD__Ref" INTEGER,
+    "ERP001775_HiSeq2000_SAMEA1531955_2__AD__Alt" INTEGER,
+    "ERP001775_HiSeq2000_SAMEA1531955_2__DP" INTEGER
 );"#
         );
 
@@ -0,0 +1,108 @@
+use crate::error::VCFUtilsError;
+use crate::utils::gvcf::{is_gvcf_header_line, remove_non_ref_alleles};
+use crate::utils::{read_record_chunk, record_chunk_size};
+use rayon::prelude::*;
+use std::io::{BufRead, Write};
+use vcf::{VCFHeader, VCFReader, VCFWriter};
+
+/// Convert gVCF to VCF. Reference blocks are dropped, and `<NON_REF>` and `<*>` alleles are
+/// removed with their Number=A, R and G values. Returns the number of written records.
+pub fn gvcf_to_vcf<R: BufRead, W: Write>(
+    reader: &mut VCFReader<R>,
+    writer: W,
+) -> Result<u64, VCFUtilsError> {
+    let header_items: Vec<_> = reader
+        .header()
+        .items()
+        .iter()
+        .filter(|x| !is_gvcf_header_line(x))
+        .cloned()
+        .collect();
+    let new_header = VCFHeader::new(header_items, reader.header().samples().to_vec());
+    let mut vcf_writer = VCFWriter::new(writer, &new_header)?;
+
+    // Records are converted in parallel chunk by chunk, and written in the original order.
+    let mut written_count = 0;
+    let chunk_size = record_chunk_size(reader.header().samples().len());
+    let mut chunk = Vec::new();
+    loop {
+        let count = read_record_chunk(reader, &mut chunk, chunk_size)?;
+        let is_variant: Vec<_> = chunk[..count]
+            .par_iter_mut()
+            .map(remove_non_ref_alleles)
+            .collect();
+        for (record, _) in chunk[..count]
+            .iter()
+            .zip(is_variant.iter())
+            .filter(|(_, x)| **x)
+        {
+            vcf_writer.write_record(record)?;
+            written_count += 1;
+        }
+        if count < chunk_size {
+            break;
+        }
+    }
+    Ok(written_count)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::fs::File;
+    use std::io::BufReader;
+    use vcf::VCFRecord;
+
+    #[test]
+    fn test_gvcf_to_vcf() -> Result<(), VCFUtilsError> {
+        let mut reader = VCFReader::new(BufReader::new(File::open("testfiles/gvcf.vcf")?))?;
+        let mut output = Vec::new();
+        assert_eq!(gvcf_to_vcf(&mut reader, &mut output)?, 2);
+
+        let mut reader = VCFReader::new(&output[..])?;
+        assert!(reader.header().alt(b"NON_REF").is_none());
+        assert!(!reader
+            .header()
+            .items()
+            .iter()
+            .any(|x| x.line().starts_with(b"##GVCFBlock")));
+
+        let mut record = VCFRecord::new(reader.header().clone());
+        assert!(reader.next_record(&mut record)?);
+        assert_eq!(record.position, 10108);
+        assert_eq!(record.alternative, vec![b"T".to_vec()]);
+        assert_eq!(record.info(b"MLEAC"), Some(&vec![b"1".to_vec()]));
+        assert_eq!(
+            record.genotype(b"NA12878", b"AD"),
+            Some(&vec![b"9".to_vec(), b"5".to_vec()])
+        );
+        assert_eq!(
+            record.genotype(b"NA12878", b"PL"),
+            Some(&vec![b"73".to_vec(), b"0".to_vec(), b"42".to_vec()])
+        );
+
+        assert!(reader.next_record(&mut record)?);
+        assert_eq!(record.position, 10144);
+        assert_eq!(record.alternative, vec![b"T".to_vec(), b"TAA".to_vec()]);
+        assert_eq!(
+            record.info(b"MLEAC"),
+            Some(&vec![b"1".to_vec(), b"1".to_vec()])
+        );
+        assert_eq!(
+            record.genotype(b"NA12878", b"GT"),
+            Some(&vec![b"1/2".to_vec()])
+        );
+        assert_eq!(
+            record.genotype(b"NA12878", b"PL"),
+            Some(
+                &[&b"300"[..], b"90", b"200", b"80", b"0", b"210"]
+                    .iter()
+                    .map(|x| x.to_vec())
+                    .collect::<Vec<_>>()
+            )
+        );
+
+        assert!(!reader.next_record(&mut record)?);
+        Ok(())
+    }
+}
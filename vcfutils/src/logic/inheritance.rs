@@ -0,0 +1,614 @@
+use super::add_af::in_pseudo_autosomal_region;
+use crate::error::VCFUtilsError;
+use crate::utils::annotation::AnnotationFormat;
+use crate::utils::genotype::{is_chrx, parse_format_integer, sample_genotype};
+use crate::utils::tablewriter::{ColumnType, TableWriter};
+use std::collections::{BTreeMap, HashSet};
+use std::io::BufRead;
+use std::str;
+use vcf::{U8Vec, VCFReader, VCFRecord};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Sex {
+    Male,
+    Female,
+    Unknown,
+}
+
+/// One line of PED file
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PedigreeMember {
+    pub family: U8Vec,
+    pub individual: U8Vec,
+    pub father: Option<U8Vec>,
+    pub mother: Option<U8Vec>,
+    pub sex: Sex,
+    /// `None` if phenotype is missing
+    pub affected: Option<bool>,
+}
+
+/// Load PED file. Columns are family ID, individual ID, father ID, mother ID, sex and
+/// phenotype, and they are separated by tab or space.
+pub fn load_ped<R: BufRead>(reader: R) -> Result<Vec<PedigreeMember>, VCFUtilsError> {
+    let mut members = Vec::new();
+    for line in reader.split(b'\n') {
+        let line = line?;
+        if line.starts_with(b"#") {
+            continue;
+        }
+        let elements: Vec<_> = line
+            .split(|x| x.is_ascii_whitespace())
+            .filter(|x| !x.is_empty())
+            .collect();
+        if elements.is_empty() {
+            continue;
+        }
+        if elements.len() < 6 {
+            return Err(VCFUtilsError::OtherError("PED file requires 6 columns"));
+        }
+        let parent = |x: &[u8]| {
+            if x == b"0" {
+                None
+            } else {
+                Some(x.to_vec())
+            }
+        };
+        members.push(PedigreeMember {
+            family: elements[0].to_vec(),
+            individual: elements[1].to_vec(),
+            father: parent(elements[2]),
+            mother: parent(elements[3]),
+            sex: match elements[4] {
+                b"1" => Sex::Male,
+                b"2" => Sex::Female,
+                _ => Sex::Unknown,
+            },
+            affected: match elements[5] {
+                b"1" => Some(false),
+                b"2" => Some(true),
+                _ => None,
+            },
+        });
+    }
+    Ok(members)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Trio {
+    pub family: U8Vec,
+    pub proband: U8Vec,
+    pub father: U8Vec,
+    pub mother: U8Vec,
+    pub proband_sex: Sex,
+}
+
+/// Find trios whose members are all included in the samples. Individuals marked as unaffected
+/// are not used as probands.
+pub fn find_trios(pedigree: &[PedigreeMember], samples: &[U8Vec]) -> Vec<Trio> {
+    let samples: HashSet<_> = samples.iter().collect();
+    pedigree
+        .iter()
+        .filter(|x| x.affected != Some(false) && samples.contains(&x.individual))
+        .filter_map(|x| {
+            let father = x.father.as_ref().filter(|y| samples.contains(y))?;
+            let mother = x.mother.as_ref().filter(|y| samples.contains(y))?;
+            Some(Trio {
+                family: x.family.clone(),
+                proband: x.individual.clone(),
+                father: father.clone(),
+                mother: mother.clone(),
+                proband_sex: x.sex,
+            })
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum InheritanceMode {
+    DeNovo,
+    HomozygousRecessive,
+    CompoundHeterozygous,
+    XLinked,
+}
+
+impl InheritanceMode {
+    pub fn to_str(&self) -> &str {
+        match self {
+            InheritanceMode::DeNovo => "de_novo",
+            InheritanceMode::HomozygousRecessive => "hom_recessive",
+            InheritanceMode::CompoundHeterozygous => "compound_het",
+            InheritanceMode::XLinked => "x_linked",
+        }
+    }
+}
+
+/// Genotype quality thresholds. Genotypes without GQ or DP fail the check when the threshold
+/// is larger than 0.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub struct InheritanceConfig {
+    pub min_gq: u64,
+    pub min_dp: u64,
+    /// 0-based half-open regions where chromosome X is inherited like autosomes
+    pub pseudo_autosomal_regions: Vec<(U8Vec, u64, u64)>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct InheritanceCandidate {
+    pub record_index: usize,
+    pub chromosome: U8Vec,
+    pub position: u64,
+    pub id: U8Vec,
+    pub reference: U8Vec,
+    pub alternative: U8Vec,
+    pub genes: Vec<U8Vec>,
+    pub family: U8Vec,
+    pub proband: U8Vec,
+    pub mode: InheritanceMode,
+    /// GT of proband, father and mother
+    pub genotypes: [U8Vec; 3],
+    /// Other variants of compound heterozygous pair (CHROM:POS:REF:ALT)
+    pub partners: Vec<String>,
+}
+
+impl InheritanceCandidate {
+    fn variant_name(&self) -> String {
+        format!(
+            "{}:{}:{}:{}",
+            String::from_utf8_lossy(&self.chromosome),
+            self.position,
+            String::from_utf8_lossy(&self.reference),
+            String::from_utf8_lossy(&self.alternative)
+        )
+    }
+}
+
+/// Called genotype of one sample. `None` if GT is missing or fails the quality check.
+fn called_genotype(
+    record: &VCFRecord,
+    sample: &[u8],
+    config: &InheritanceConfig,
+) -> Result<Option<(Vec<usize>, U8Vec)>, VCFUtilsError> {
+    let gt = if let Some(gt) = record.genotype(sample, b"GT").and_then(|x| x.first()) {
+        gt
+    } else {
+        return Ok(None);
+    };
+    let alleles = sample_genotype(record, sample);
+    if alleles.is_empty() || alleles.iter().any(|x| x.is_none()) {
+        return Ok(None);
+    }
+    let passed = |key: &[u8], threshold: u64| {
+        threshold == 0
+            || parse_format_integer(record, sample, key)
+                .map(|x| x >= threshold)
+                .unwrap_or(false)
+    };
+    if !passed(b"GQ", config.min_gq) || !passed(b"DP", config.min_dp) {
+        return Ok(None);
+    }
+    Ok(Some((alleles.into_iter().flatten().collect(), gt.clone())))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Origin {
+    Paternal,
+    Maternal,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Classification {
+    Mode(InheritanceMode),
+    /// Heterozygous variant inherited from one parent
+    CompoundHetComponent(Origin),
+}
+
+/// Classify one allele of a trio from allele indexes of proband, father and mother.
+fn classify(
+    allele: usize,
+    alleles: &[&[usize]; 3],
+    chrx: bool,
+    proband_sex: Sex,
+) -> Option<Classification> {
+    let count = |x: &[usize]| x.iter().filter(|y| **y == allele).count();
+    let (proband, father, mother) = (count(alleles[0]), count(alleles[1]), count(alleles[2]));
+    let is_hom_alt = |x: &[usize], c: usize| c > 0 && c == x.len();
+    let is_het = |x: &[usize], c: usize| c == 1 && x.len() == 2;
+
+    if proband == 0 {
+        return None;
+    }
+    if father == 0 && mother == 0 {
+        return Some(Classification::Mode(InheritanceMode::DeNovo));
+    }
+    if chrx && proband_sex == Sex::Male {
+        if is_hom_alt(alleles[0], proband) && father == 0 && is_het(alleles[2], mother) {
+            return Some(Classification::Mode(InheritanceMode::XLinked));
+        }
+        return None;
+    }
+    if is_hom_alt(alleles[0], proband) {
+        if chrx && is_hom_alt(alleles[1], father) && alleles[1].len() == 1 && mother > 0 {
+            return Some(Classification::Mode(InheritanceMode::XLinked));
+        }
+        if is_het(alleles[1], father) && is_het(alleles[2], mother) {
+            return Some(Classification::Mode(InheritanceMode::HomozygousRecessive));
+        }
+        return None;
+    }
+    if is_het(alleles[0], proband) {
+        if father > 0 && mother == 0 {
+            return Some(Classification::CompoundHetComponent(Origin::Paternal));
+        }
+        if mother > 0 && father == 0 {
+            return Some(Classification::CompoundHetComponent(Origin::Maternal));
+        }
+    }
+    None
+}
+
+/// Genes of annotation entries for the allele. Gene ID is used when gene symbol is not available.
+fn allele_genes(
+    record: &VCFRecord,
+    annotation: Option<&AnnotationFormat>,
+    alt_index: usize,
+) -> Vec<U8Vec> {
+    let annotation = if let Some(annotation) = annotation {
+        annotation
+    } else {
+        return Vec::new();
+    };
+    let mut genes: Vec<U8Vec> = Vec::new();
+    for entry in annotation.entries(record, Some(alt_index)) {
+        let gene = [annotation.gene, annotation.gene_id]
+            .iter()
+            .filter_map(|x| x.and_then(|y| entry.get(y)))
+            .find(|x| !x.is_empty());
+        if let Some(gene) = gene {
+            if !genes.iter().any(|x| x == gene) {
+                genes.push(gene.to_vec());
+            }
+        }
+    }
+    genes
+}
+
+/// Find de novo, homozygous recessive, compound heterozygous and X-linked candidates of trios.
+/// Compound heterozygous candidates are paired within genes of the annotation. Candidates are
+/// returned in order of records.
+pub fn find_inheritance_candidates<R: BufRead>(
+    reader: &mut VCFReader<R>,
+    trios: &[Trio],
+    config: &InheritanceConfig,
+    annotation_key: Option<&[u8]>,
+) -> Result<Vec<InheritanceCandidate>, VCFUtilsError> {
+    let annotation = AnnotationFormat::detect(reader.header(), annotation_key);
+    if annotation.is_none() {
+        log::warn!(
+            "No annotation INFO tag is found. Compound heterozygous candidates are not searched."
+        );
+    }
+
+    let mut candidates = Vec::new();
+    // (trio index, gene) -> (candidate, origin)
+    let mut compound_het: BTreeMap<(usize, U8Vec), Vec<(InheritanceCandidate, Origin)>> =
+        BTreeMap::new();
+    let mut record = VCFRecord::new(reader.header().clone());
+    let mut record_index = 0;
+
+    while reader.next_record(&mut record)? {
+        let chrx = is_chrx(&record.chromosome)
+            && !in_pseudo_autosomal_region(
+                &config.pseudo_autosomal_regions,
+                &record.chromosome,
+                record.position,
+            );
+        for (trio_index, trio) in trios.iter().enumerate() {
+            let mut genotypes = Vec::with_capacity(3);
+            for one in [&trio.proband, &trio.father, &trio.mother] {
+                if let Some(genotype) = called_genotype(&record, one, config)? {
+                    genotypes.push(genotype);
+                }
+            }
+            if genotypes.len() != 3 {
+                continue;
+            }
+            let alleles = [
+                &genotypes[0].0[..],
+                &genotypes[1].0[..],
+                &genotypes[2].0[..],
+            ];
+
+            for (alt_index, alt) in record.alternative.iter().enumerate() {
+                let classification =
+                    if let Some(x) = classify(alt_index + 1, &alleles, chrx, trio.proband_sex) {
+                        x
+                    } else {
+                        continue;
+                    };
+                let genes = allele_genes(&record, annotation.as_ref(), alt_index);
+                let candidate = InheritanceCandidate {
+                    record_index,
+                    chromosome: record.chromosome.clone(),
+                    position: record.position,
+                    id: record.id.join(&b','),
+                    reference: record.reference.clone(),
+                    alternative: alt.clone(),
+                    genes: genes.clone(),
+                    family: trio.family.clone(),
+                    proband: trio.proband.clone(),
+                    mode: match classification {
+                        Classification::Mode(x) => x,
+                        Classification::CompoundHetComponent(_) => {
+                            InheritanceMode::CompoundHeterozygous
+                        }
+                    },
+                    genotypes: [
+                        genotypes[0].1.clone(),
+                        genotypes[1].1.clone(),
+                        genotypes[2].1.clone(),
+                    ],
+                    partners: Vec::new(),
+                };
+                match classification {
+                    Classification::CompoundHetComponent(origin) => {
+                        for one_gene in genes {
+                            let mut one = candidate.clone();
+                            one.genes = vec![one_gene.clone()];
+                            compound_het
+                                .entry((trio_index, one_gene))
+                                .or_default()
+                                .push((one, origin));
+                        }
+                    }
+                    Classification::Mode(_) => candidates.push(candidate),
+                }
+            }
+        }
+        record_index += 1;
+    }
+
+    for (_, variants) in compound_het {
+        let names: Vec<_> = variants
+            .iter()
+            .map(|(x, origin)| (x.variant_name(), *origin))
+            .collect();
+        for (mut one, origin) in variants {
+            one.partners = names
+                .iter()
+                .filter(|(_, x)| *x != origin)
+                .map(|(x, _)| x.clone())
+                .collect();
+            if !one.partners.is_empty() {
+                candidates.push(one);
+            }
+        }
+    }
+
+    candidates.sort_by_key(|x| x.record_index);
+    Ok(candidates)
+}
+
+pub fn inheritance_header() -> Vec<String> {
+    [
+        "CHROM",
+        "POS",
+        "ID",
+        "REF",
+        "ALT",
+        "Gene",
+        "Family",
+        "Proband",
+        "Mode",
+        "ProbandGT",
+        "FatherGT",
+        "MotherGT",
+        "CompHetPartners",
+    ]
+    .iter()
+    .map(|x| x.to_string())
+    .collect()
+}
+
+pub fn inheritance_column_types() -> Vec<ColumnType> {
+    inheritance_header()
+        .iter()
+        .map(|x| match x.as_str() {
+            "POS" => ColumnType::Integer,
+            _ => ColumnType::String,
+        })
+        .collect()
+}
+
+pub fn write_inheritance_candidates(
+    candidates: &[InheritanceCandidate],
+    writer: &mut impl TableWriter,
+) -> Result<(), VCFUtilsError> {
+    writer.set_header(&inheritance_header());
+    writer.write_header()?;
+    for one in candidates {
+        let genes: Vec<_> = one.genes.iter().map(|x| &x[..]).collect();
+        let position = one.position.to_string();
+        let partners = one.partners.join(",");
+        writer.write_row_bytes(&[
+            &one.chromosome,
+            position.as_bytes(),
+            &one.id,
+            &one.reference,
+            &one.alternative,
+            &genes.join(&b","[..]),
+            &one.family,
+            &one.proband,
+            one.mode.to_str().as_bytes(),
+            &one.genotypes[0],
+            &one.genotypes[1],
+            &one.genotypes[2],
+            partners.as_bytes(),
+        ])?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::utils::tablewriter::TSVWriter;
+
+    fn load_trios(vcf_reader: &VCFReader<&[u8]>) -> Result<Vec<Trio>, VCFUtilsError> {
+        let pedigree = load_ped(&include_bytes!("../../testfiles/trio.ped")[..])?;
+        assert_eq!(pedigree.len(), 3);
+        Ok(find_trios(&pedigree, vcf_reader.header().samples()))
+    }
+
+    #[test]
+    fn test_load_ped() -> Result<(), VCFUtilsError> {
+        let pedigree = load_ped(&b"FAM1 child father mother 1 2\nFAM1 father 0 0 1 1\n"[..])?;
+        assert_eq!(
+            pedigree[0],
+            PedigreeMember {
+                family: b"FAM1".to_vec(),
+                individual: b"child".to_vec(),
+                father: Some(b"father".to_vec()),
+                mother: Some(b"mother".to_vec()),
+                sex: Sex::Male,
+                affected: Some(true),
+            }
+        );
+        assert_eq!(pedigree[1].father, None);
+        assert_eq!(pedigree[1].affected, Some(false));
+        assert!(load_ped(&b"FAM1 child father\n"[..]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_find_inheritance_candidates() -> Result<(), VCFUtilsError> {
+        let vcf_data = include_bytes!("../../testfiles/trio.vcf");
+        let mut vcf_reader = VCFReader::new(&vcf_data[..])?;
+        let trios = load_trios(&vcf_reader)?;
+        assert_eq!(trios.len(), 1);
+        assert_eq!(trios[0].proband, b"child");
+
+        let candidates = find_inheritance_candidates(
+            &mut vcf_reader,
+            &trios,
+            &InheritanceConfig::default(),
+            None,
+        )?;
+        let summary: Vec<_> = candidates
+            .iter()
+            .map(|x| (x.position, x.alternative.as_slice(), x.mode))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (1000, &b"G"[..], InheritanceMode::DeNovo),
+                (2000, &b"T"[..], InheritanceMode::DeNovo),
+                (3000, &b"A"[..], InheritanceMode::HomozygousRecessive),
+                (4000, &b"C"[..], InheritanceMode::CompoundHeterozygous),
+                (5000, &b"T"[..], InheritanceMode::CompoundHeterozygous),
+                (8000, &b"T"[..], InheritanceMode::DeNovo),
+                (7000, &b"G"[..], InheritanceMode::XLinked),
+            ]
+        );
+        assert_eq!(candidates[3].partners, vec!["1:5000:A:T".to_string()]);
+        assert_eq!(candidates[3].genes, vec![b"GENE3".to_vec()]);
+
+        let mut vcf_reader = VCFReader::new(&vcf_data[..])?;
+        let candidates = find_inheritance_candidates(
+            &mut vcf_reader,
+            &trios,
+            &InheritanceConfig {
+                min_gq: 20,
+                min_dp: 10,
+                ..Default::default()
+            },
+            None,
+        )?;
+        assert_eq!(candidates.len(), 6);
+        assert!(candidates.iter().all(|x| x.position != 2000));
+
+        let mut write_bytes = Vec::new();
+        write_inheritance_candidates(&candidates, &mut TSVWriter::new(&mut write_bytes))?;
+        let lines: Vec<_> = write_bytes.split(|x| *x == b'\n').collect();
+        assert_eq!(
+            lines[1],
+            b"1\t1000\t\tA\tG\tGENE1\tFAM1\tchild\tde_novo\t0/1\t0/0\t0/0\t"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_find_inheritance_candidates_pseudo_autosomal() -> Result<(), VCFUtilsError> {
+        let vcf_data = include_bytes!("../../testfiles/trio.vcf");
+        let mut vcf_reader = VCFReader::new(&vcf_data[..])?;
+        let trios = load_trios(&vcf_reader)?;
+        let candidates = find_inheritance_candidates(
+            &mut vcf_reader,
+            &trios,
+            &InheritanceConfig {
+                pseudo_autosomal_regions: crate::logic::add_af::GRCH38_PSEUDO_AUTOSOMAL_REGIONS
+                    .iter()
+                    .map(|(x, start, end)| (x.as_bytes().to_vec(), *start, *end))
+                    .collect(),
+                ..Default::default()
+            },
+            None,
+        )?;
+        let summary: Vec<_> = candidates
+            .iter()
+            .filter(|x| x.chromosome == b"X")
+            .map(|x| (x.position, x.mode))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (7000, InheritanceMode::XLinked),
+                (100000, InheritanceMode::HomozygousRecessive),
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_find_compound_heterozygous_and_de_novo() -> Result<(), VCFUtilsError> {
+        let header = include_str!("../../testfiles/trio.vcf")
+            .lines()
+            .take_while(|x| x.starts_with('#'))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let records = [
+            // two paternal variants in GENE1 are not compound heterozygous
+            "1\t1000\t.\tA\tG\t100\tPASS\tANN=G|missense_variant|MODERATE|GENE1|ENSG01\tGT\t0/1\t0/1\t0/0",
+            "1\t1100\t.\tA\tG\t100\tPASS\tANN=G|missense_variant|MODERATE|GENE1|ENSG01\tGT\t0/1\t0/1\t0/0",
+            // paternal and maternal variants in GENE2; heterozygous in both parents is not a component
+            "1\t2000\t.\tC\tT\t100\tPASS\tANN=T|missense_variant|MODERATE|GENE2|ENSG02\tGT\t0/1\t0/1\t0/0",
+            "1\t2100\t.\tC\tT\t100\tPASS\tANN=T|missense_variant|MODERATE|GENE2|ENSG02\tGT\t0/1\t0/1\t0/1",
+            "1\t2200\t.\tC\tT\t100\tPASS\tANN=T|missense_variant|MODERATE|GENE2|ENSG02\tGT\t0/1\t0/0\t0/1",
+            // a parent carries the allele
+            "1\t3000\t.\tG\tA\t100\tPASS\tANN=A|missense_variant|MODERATE|GENE3|ENSG03\tGT\t0/1\t0/0\t1/1",
+            // a parent genotype is missing
+            "1\t4000\t.\tG\tA\t100\tPASS\tANN=A|missense_variant|MODERATE|GENE4|ENSG04\tGT\t0/1\t./.\t0/0",
+            // the second alternative allele is de novo
+            "1\t5000\t.\tG\tA,T\t100\tPASS\tANN=A|missense_variant|MODERATE|GENE5|ENSG05,T|missense_variant|MODERATE|GENE5|ENSG05\tGT\t1/2\t0/1\t0/0",
+        ];
+        let vcf_data = format!("{}\n{}\n", header, records.join("\n"));
+        let mut vcf_reader = VCFReader::new(vcf_data.as_bytes())?;
+        let trios = load_trios(&vcf_reader)?;
+        let candidates =
+            find_inheritance_candidates(&mut vcf_reader, &trios, &Default::default(), None)?;
+        let summary: Vec<_> = candidates
+            .iter()
+            .map(|x| (x.position, x.alternative.as_slice(), x.mode))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (2000, &b"T"[..], InheritanceMode::CompoundHeterozygous),
+                (2200, &b"T"[..], InheritanceMode::CompoundHeterozygous),
+                (5000, &b"T"[..], InheritanceMode::DeNovo),
+            ]
+        );
+        assert_eq!(candidates[0].partners, vec!["1:2200:C:T".to_string()]);
+        assert_eq!(candidates[1].partners, vec!["1:2000:C:T".to_string()]);
+        Ok(())
+    }
+}
@@ -1,8 +1,22 @@
 pub mod add_af;
 pub mod add_contig;
+pub mod annotate;
+pub mod anonymize;
+pub mod compare;
+pub mod filter;
+pub mod gene_burden;
 pub mod generate_sql;
+pub mod gvcf_to_vcf;
+pub mod inheritance;
+pub mod reheader;
+pub mod relatedness;
 pub mod replace_contig;
 pub mod replace_sample;
 pub mod rewrite_format;
 pub mod rewrite_info;
+pub mod sample_stats;
+pub mod sort;
+pub mod subset_samples;
+pub mod validate;
+pub mod vcf2sqlite;
 pub mod vcf2table;
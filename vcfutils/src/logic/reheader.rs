@@ -0,0 +1,381 @@
+use super::validate::RecordValidator;
+use crate::error::VCFUtilsError;
+use std::collections::HashSet;
+use std::io::{BufRead, Write};
+use std::str;
+use vcf::{U8Vec, VCFHeader, VCFHeaderContent, VCFHeaderLine, VCFReader, VCFRecord};
+
+/// Kind of structured meta line
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum HeaderKind {
+    Info,
+    Format,
+    Filter,
+    Alt,
+    Contig,
+}
+
+impl HeaderKind {
+    pub fn to_str(&self) -> &str {
+        match self {
+            HeaderKind::Info => "INFO",
+            HeaderKind::Format => "FORMAT",
+            HeaderKind::Filter => "FILTER",
+            HeaderKind::Alt => "ALT",
+            HeaderKind::Contig => "contig",
+        }
+    }
+
+    /// Return kind and ID of the header line if it is a structured line.
+    pub fn of_line(line: &VCFHeaderLine) -> Option<(HeaderKind, &[u8])> {
+        match line.contents() {
+            VCFHeaderContent::INFO { id, .. } => Some((HeaderKind::Info, id)),
+            VCFHeaderContent::FORMAT { id, .. } => Some((HeaderKind::Format, id)),
+            VCFHeaderContent::FILTER { id, .. } => Some((HeaderKind::Filter, id)),
+            VCFHeaderContent::ALT { id, .. } => Some((HeaderKind::Alt, id)),
+            VCFHeaderContent::Contig { id, .. } => Some((HeaderKind::Contig, id)),
+            _ => None,
+        }
+    }
+
+    /// Parse `KIND/ID` (e.g. `INFO/DP`, `contig/chr1`).
+    pub fn parse_with_id(spec: &str) -> Result<(HeaderKind, U8Vec), VCFUtilsError> {
+        let (kind, id) = spec
+            .split_once('/')
+            .ok_or_else(|| VCFUtilsError::InvalidHeaderEdit(spec.to_string()))?;
+        let kind = match kind.to_ascii_uppercase().as_str() {
+            "INFO" => HeaderKind::Info,
+            "FORMAT" => HeaderKind::Format,
+            "FILTER" => HeaderKind::Filter,
+            "ALT" => HeaderKind::Alt,
+            "CONTIG" => HeaderKind::Contig,
+            _ => return Err(VCFUtilsError::InvalidHeaderEdit(spec.to_string())),
+        };
+        if id.is_empty() {
+            return Err(VCFUtilsError::InvalidHeaderEdit(spec.to_string()));
+        }
+        Ok((kind, id.as_bytes().to_vec()))
+    }
+}
+
+/// An edit of VCF header
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HeaderEdit {
+    /// Add a meta line. A structured line with the same kind and ID is replaced.
+    Add(VCFHeaderLine),
+    /// Remove a structured line. INFO and FORMAT values are also removed from records.
+    Remove(HeaderKind, U8Vec),
+    /// Set an attribute (e.g. `Number`, `Type`, `Description`, `length`) of a structured line.
+    Set(HeaderKind, U8Vec, String, String),
+}
+
+impl HeaderEdit {
+    /// Parse a meta line such as `##INFO=<ID=DP,Number=1,Type=Integer,Description="Depth">`.
+    pub fn parse_add(line: &str) -> Result<Self, VCFUtilsError> {
+        let mut line = line.trim_end().as_bytes().to_vec();
+        line.push(b'\n');
+        Ok(HeaderEdit::Add(VCFHeaderLine::from_bytes(&line, 0)?))
+    }
+
+    /// Parse `KIND/ID` (e.g. `INFO/DP`).
+    pub fn parse_remove(spec: &str) -> Result<Self, VCFUtilsError> {
+        let (kind, id) = HeaderKind::parse_with_id(spec)?;
+        Ok(HeaderEdit::Remove(kind, id))
+    }
+
+    /// Parse `KIND/ID:KEY=VALUE` (e.g. `INFO/AF:Number=A`).
+    pub fn parse_set(spec: &str) -> Result<Self, VCFUtilsError> {
+        let (target, attribute) = spec
+            .split_once(':')
+            .ok_or_else(|| VCFUtilsError::InvalidHeaderEdit(spec.to_string()))?;
+        let (key, value) = attribute
+            .split_once('=')
+            .ok_or_else(|| VCFUtilsError::InvalidHeaderEdit(spec.to_string()))?;
+        if key.is_empty() || key == "ID" || value.contains('"') {
+            return Err(VCFUtilsError::InvalidHeaderEdit(spec.to_string()));
+        }
+        let (kind, id) = HeaderKind::parse_with_id(target)?;
+        Ok(HeaderEdit::Set(
+            kind,
+            id,
+            key.to_string(),
+            value.to_string(),
+        ))
+    }
+}
+
+/// Key and value pairs in a structured meta line
+type Attributes<'a> = Vec<(&'a [u8], &'a [u8])>;
+
+/// Split structured meta line into prefix (e.g. `##INFO`) and attributes.
+fn split_attributes(line: &[u8]) -> Option<(&[u8], Attributes<'_>)> {
+    let start = line.iter().position(|x| *x == b'<')?;
+    let end = line.iter().rposition(|x| *x == b'>')?;
+    if end <= start || start == 0 {
+        return None;
+    }
+    let mut attributes = Vec::new();
+    let mut in_quote = false;
+    let mut item_start = start + 1;
+    for i in (start + 1)..=end {
+        match line[i] {
+            b'"' => in_quote = !in_quote,
+            b',' | b'>' if !in_quote => {
+                let item = &line[item_start..i];
+                let (key, value) = match item.iter().position(|x| *x == b'=') {
+                    Some(pos) => (&item[..pos], &item[pos + 1..]),
+                    None => (item, &b""[..]),
+                };
+                attributes.push((key, value));
+                item_start = i + 1;
+            }
+            _ => (),
+        }
+    }
+    Some((&line[..start - 1], attributes))
+}
+
+fn set_attribute(
+    line: &VCFHeaderLine,
+    key: &str,
+    value: &str,
+) -> Result<VCFHeaderLine, VCFUtilsError> {
+    let (prefix, mut attributes) = split_attributes(line.line()).ok_or_else(|| {
+        VCFUtilsError::InvalidHeaderEdit(String::from_utf8_lossy(line.line()).to_string())
+    })?;
+    let quoted = if key == "Description" || key == "Source" || key == "Version" {
+        format!("\"{}\"", value)
+    } else {
+        value.to_string()
+    };
+    if let Some(one) = attributes.iter_mut().find(|x| x.0 == key.as_bytes()) {
+        one.1 = quoted.as_bytes();
+    } else {
+        attributes.push((key.as_bytes(), quoted.as_bytes()));
+    }
+
+    let mut new_line = prefix.to_vec();
+    new_line.extend_from_slice(b"=<");
+    for (i, (key, value)) in attributes.iter().enumerate() {
+        if i != 0 {
+            new_line.push(b',');
+        }
+        new_line.extend_from_slice(key);
+        new_line.push(b'=');
+        new_line.extend_from_slice(value);
+    }
+    new_line.extend_from_slice(b">\n");
+    Ok(VCFHeaderLine::from_bytes(&new_line, 0)?)
+}
+
+/// Apply edits to header lines in order. If a template is given, its header lines replace the
+/// original header lines before edits. Samples are not changed.
+pub fn edit_header(
+    header: &VCFHeader,
+    template: Option<&VCFHeader>,
+    edits: &[HeaderEdit],
+) -> Result<VCFHeader, VCFUtilsError> {
+    let mut items = template.unwrap_or(header).items().to_vec();
+    for edit in edits {
+        match edit {
+            HeaderEdit::Add(line) => {
+                let target = HeaderKind::of_line(line).map(|(k, id)| (k, id.to_vec()));
+                if let Some(pos) = target.as_ref().and_then(|(kind, id)| {
+                    items
+                        .iter()
+                        .position(|x| HeaderKind::of_line(x) == Some((*kind, &id[..])))
+                }) {
+                    items[pos] = line.clone();
+                } else {
+                    items.push(line.clone());
+                }
+            }
+            HeaderEdit::Remove(kind, id) => {
+                items.retain(|x| HeaderKind::of_line(x) != Some((*kind, &id[..])));
+            }
+            HeaderEdit::Set(kind, id, key, value) => {
+                let pos = items
+                    .iter()
+                    .position(|x| HeaderKind::of_line(x) == Some((*kind, &id[..])))
+                    .ok_or_else(|| {
+                        VCFUtilsError::InvalidHeaderEdit(format!(
+                            "{}/{} is not found in header",
+                            kind.to_str(),
+                            String::from_utf8_lossy(id)
+                        ))
+                    })?;
+                items[pos] = set_attribute(&items[pos], key, value)?;
+            }
+        }
+    }
+    Ok(VCFHeader::new(items, header.samples().to_vec()))
+}
+
+/// Write records with the edited header. Values of removed INFO and FORMAT fields are also
+/// removed from records. If `validate` is true, records are checked against the new header.
+pub fn reheader<R: BufRead, W: Write>(
+    reader: &mut VCFReader<R>,
+    writer: W,
+    template: Option<&VCFHeader>,
+    edits: &[HeaderEdit],
+    validate: bool,
+) -> Result<u64, VCFUtilsError> {
+    let new_header = edit_header(reader.header(), template, edits)?;
+    let removed_info: HashSet<_> = edits
+        .iter()
+        .filter_map(|x| match x {
+            HeaderEdit::Remove(HeaderKind::Info, id) => Some(id.clone()),
+            _ => None,
+        })
+        .collect();
+    let removed_format: HashSet<_> = edits
+        .iter()
+        .filter_map(|x| match x {
+            HeaderEdit::Remove(HeaderKind::Format, id) => Some(id.clone()),
+            _ => None,
+        })
+        .collect();
+
+    let validator = RecordValidator::new(&new_header);
+    let mut vcf_writer = vcf::VCFWriter::new(writer, &new_header)?;
+    let mut record = VCFRecord::new(new_header.clone());
+    let mut count = 0;
+    while reader.next_record(&mut record)? {
+        count += 1;
+        if !removed_info.is_empty() {
+            record.info.retain(|x| !removed_info.contains(&x.0));
+            record.recreate_info_and_genotype_index();
+        }
+        if !removed_format.is_empty() {
+            let keep: Vec<_> = record
+                .format
+                .iter()
+                .map(|x| !removed_format.contains(x))
+                .collect();
+            record.format.retain(|x| !removed_format.contains(x));
+            for one in record.genotype.iter_mut() {
+                let mut index = 0;
+                one.retain(|_| {
+                    index += 1;
+                    keep.get(index - 1).copied().unwrap_or(false)
+                });
+            }
+            record.recreate_info_and_genotype_index();
+        }
+        if validate {
+            validator
+                .check(&record)
+                .map_err(|e| VCFUtilsError::InconsistentRecord(count, e))?;
+        }
+        vcf_writer.write_record(&record)?;
+    }
+    Ok(count)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use vcf::{Number, ValueType};
+
+    fn reheader_simple1(
+        template: Option<&VCFHeader>,
+        edits: &[HeaderEdit],
+    ) -> Result<Vec<u8>, VCFUtilsError> {
+        let vcf_data = include_bytes!("../../testfiles/simple1.vcf");
+        let mut vcf_reader = VCFReader::new(&vcf_data[..])?;
+        let mut write_bytes = Vec::new();
+        reheader(&mut vcf_reader, &mut write_bytes, template, edits, true)?;
+        Ok(write_bytes)
+    }
+
+    #[test]
+    fn test_parse_edit() -> Result<(), VCFUtilsError> {
+        assert_eq!(
+            HeaderEdit::parse_remove("INFO/DP")?,
+            HeaderEdit::Remove(HeaderKind::Info, b"DP".to_vec())
+        );
+        assert_eq!(
+            HeaderEdit::parse_set("contig/1:length=1000")?,
+            HeaderEdit::Set(
+                HeaderKind::Contig,
+                b"1".to_vec(),
+                "length".to_string(),
+                "1000".to_string()
+            )
+        );
+        assert!(HeaderEdit::parse_set("INFO/DP").is_err());
+        assert!(HeaderEdit::parse_remove("FOO/DP").is_err());
+        assert!(HeaderEdit::parse_set("INFO/DP:Description=\"x\"").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_edit_header() -> Result<(), VCFUtilsError> {
+        let vcf_data = include_bytes!("../../testfiles/simple1.vcf");
+        let vcf_reader = VCFReader::new(&vcf_data[..])?;
+        let header = edit_header(
+            vcf_reader.header(),
+            None,
+            &[
+                HeaderEdit::parse_set("INFO/AF:Number=.")?,
+                HeaderEdit::parse_set("INFO/AF:Description=Allele frequency, recalculated")?,
+                HeaderEdit::parse_add("##FILTER=<ID=LowDP,Description=\"Low depth\">")?,
+                HeaderEdit::parse_remove("INFO/AN")?,
+            ],
+        )?;
+        let af = header.info(b"AF").unwrap();
+        assert_eq!(af.number, &Number::Unknown);
+        assert_eq!(af.value_type, &ValueType::Float);
+        assert_eq!(af.description, b"Allele frequency, recalculated");
+        assert!(header.filter(b"LowDP").is_some());
+        assert!(header.info(b"AN").is_none());
+        assert_eq!(header.samples(), vcf_reader.header().samples());
+        Ok(())
+    }
+
+    #[test]
+    fn test_reheader() -> Result<(), VCFUtilsError> {
+        let result = reheader_simple1(
+            None,
+            &[
+                HeaderEdit::parse_remove("INFO/AN")?,
+                HeaderEdit::parse_remove("FORMAT/AD")?,
+            ],
+        )?;
+        let mut result_reader = VCFReader::new(&result[..])?;
+        assert!(result_reader.header().info(b"AN").is_none());
+        let mut record = result_reader.empty_record();
+        while result_reader.next_record(&mut record)? {
+            assert!(record.info(b"AN").is_none());
+            assert!(!record.format.contains(&b"AD".to_vec()));
+            assert_eq!(record.genotype[0].len(), record.format.len());
+            assert!(record.info(b"DP").is_some());
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_reheader_validation() -> Result<(), VCFUtilsError> {
+        // Wrong Number declaration
+        let result = reheader_simple1(None, &[HeaderEdit::parse_set("INFO/AF:Number=1")?]);
+        assert!(matches!(
+            result,
+            Err(VCFUtilsError::InconsistentRecord(_, _))
+        ));
+
+        // Template without INFO/DP
+        let template_data = include_bytes!("../../testfiles/simple1.vcf");
+        let template_reader = VCFReader::new(&template_data[..])?;
+        let template = edit_header(
+            template_reader.header(),
+            None,
+            &[HeaderEdit::parse_remove("INFO/DP")?],
+        )?;
+        let result = reheader_simple1(Some(&template), &[]);
+        assert!(matches!(
+            result,
+            Err(VCFUtilsError::InconsistentRecord(1, ref x)) if x == "INFO/DP is not declared"
+        ));
+        Ok(())
+    }
+}
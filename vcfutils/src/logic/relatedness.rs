@@ -0,0 +1,761 @@
+use super::add_af::{in_pseudo_autosomal_region, parse_sex};
+use super::inheritance::Sex;
+use super::sample_stats::{SampleStats, SexInferenceConfig};
+use crate::error::VCFUtilsError;
+use crate::utils::genotype::{is_chrx, sample_genotype};
+use crate::utils::tablewriter::{ColumnType, TableWriter};
+use rayon::prelude::*;
+use std::collections::{HashMap, VecDeque};
+use std::io::{BufRead, Read};
+use vcf::{U8Vec, VCFReader, VCFRecord};
+
+/// Minimum kinship coefficients of duplicates or monozygotic twins, first, second and third
+/// degree relatives (Manichaikul et al. 2010)
+const KINSHIP_THRESHOLDS: [(f64, &str); 4] = [
+    (0.354, "duplicate"),
+    (0.177, "first-degree"),
+    (0.0884, "second-degree"),
+    (0.0442, "third-degree"),
+];
+
+/// Maximum IBS0 ratio of parent-offspring pairs. Full siblings share no allele at about a quarter
+/// of opposite homozygous sites of unrelated pairs.
+const PARENT_OFFSPRING_MAX_IBS0: f64 = 0.005;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RelatednessConfig {
+    pub min_maf: f64,
+    pub min_call_rate: f64,
+    /// Window size in bp to prune SNVs in linkage disequilibrium. 0 disables pruning.
+    pub prune_window: u64,
+    pub max_r2: f64,
+    /// 0-based half-open regions excluded from sex check
+    pub pseudo_autosomal_regions: Vec<(U8Vec, u64, u64)>,
+}
+
+impl Default for RelatednessConfig {
+    fn default() -> Self {
+        RelatednessConfig {
+            min_maf: 0.05,
+            min_call_rate: 0.95,
+            prune_window: 500_000,
+            max_r2: 0.2,
+            pseudo_autosomal_regions: Vec::new(),
+        }
+    }
+}
+
+/// Genotype counts of a sample pair at SNVs called in both samples
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub struct PairRelatedness {
+    pub sample1: U8Vec,
+    pub sample2: U8Vec,
+    pub snps: u64,
+    pub het_het: u64,
+    /// Opposite homozygous genotypes
+    pub ibs0: u64,
+    pub het1: u64,
+    pub het2: u64,
+    /// Discrepancy between kinship and families, or empty
+    pub flag: &'static str,
+}
+
+impl PairRelatedness {
+    /// KING-robust kinship coefficient for pairs in different families
+    pub fn kinship(&self) -> Option<f64> {
+        let min_het = self.het1.min(self.het2);
+        if min_het == 0 {
+            return None;
+        }
+        Some(
+            (self.het_het as f64 - 2. * self.ibs0 as f64) / (2. * min_het as f64) + 0.5
+                - (self.het1 + self.het2) as f64 / (4. * min_het as f64),
+        )
+    }
+
+    pub fn ibs0_ratio(&self) -> Option<f64> {
+        if self.snps == 0 {
+            None
+        } else {
+            Some(self.ibs0 as f64 / self.snps as f64)
+        }
+    }
+
+    pub fn relationship(&self) -> &'static str {
+        let kinship = match self.kinship() {
+            Some(x) => x,
+            None => return "unknown",
+        };
+        match KINSHIP_THRESHOLDS.iter().find(|(x, _)| kinship >= *x) {
+            Some((_, "first-degree")) => {
+                if self.ibs0_ratio().unwrap_or(1.) <= PARENT_OFFSPRING_MAX_IBS0 {
+                    "parent-offspring"
+                } else {
+                    "full-sibling"
+                }
+            }
+            Some((_, relationship)) => relationship,
+            None => "unrelated",
+        }
+    }
+
+    /// Flag duplicates, unrelated pairs in a family and related pairs across families
+    fn discrepancy(&self, family: &HashMap<U8Vec, U8Vec>) -> &'static str {
+        let relationship = self.relationship();
+        match (family.get(&self.sample1), family.get(&self.sample2)) {
+            _ if relationship == "duplicate" => "duplicate",
+            (Some(x), Some(y)) if x == y && relationship == "unrelated" => "unrelated-in-family",
+            (Some(x), Some(y))
+                if x != y && self.kinship().unwrap_or(0.) >= KINSHIP_THRESHOLDS[2].0 =>
+            {
+                "related-across-families"
+            }
+            _ => "",
+        }
+    }
+}
+
+/// Genotypes of a sample in bit sets of SNVs
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+struct GenotypeBits {
+    hom_ref: Vec<u64>,
+    het: Vec<u64>,
+    hom_alt: Vec<u64>,
+}
+
+impl GenotypeBits {
+    fn push(&mut self, index: usize, dosage: Option<u8>) {
+        if index / 64 >= self.het.len() {
+            self.hom_ref.push(0);
+            self.het.push(0);
+            self.hom_alt.push(0);
+        }
+        let bit = 1 << (index % 64);
+        let bits = match dosage {
+            Some(0) => &mut self.hom_ref,
+            Some(1) => &mut self.het,
+            Some(_) => &mut self.hom_alt,
+            None => return,
+        };
+        *bits.last_mut().unwrap() |= bit;
+    }
+
+    fn compare(&self, other: &GenotypeBits) -> PairRelatedness {
+        let mut result = PairRelatedness::default();
+        for i in 0..self.het.len() {
+            let called1 = self.hom_ref[i] | self.het[i] | self.hom_alt[i];
+            let called2 = other.hom_ref[i] | other.het[i] | other.hom_alt[i];
+            result.snps += (called1 & called2).count_ones() as u64;
+            result.het_het += (self.het[i] & other.het[i]).count_ones() as u64;
+            result.ibs0 += ((self.hom_ref[i] & other.hom_alt[i])
+                | (self.hom_alt[i] & other.hom_ref[i]))
+                .count_ones() as u64;
+            result.het1 += (self.het[i] & called2).count_ones() as u64;
+            result.het2 += (other.het[i] & called1).count_ones() as u64;
+        }
+        result
+    }
+}
+
+/// Alternative allele dosages of a biallelic SNV. Haploid calls are treated as homozygous, and
+/// malformed genotypes as missing. `None` is returned for other variants.
+fn snv_dosages(record: &VCFRecord, samples: &[U8Vec]) -> Option<Vec<Option<u8>>> {
+    if record.reference.len() != 1
+        || record.alternative.len() != 1
+        || record.alternative[0].len() != 1
+        || !b"ACGT".contains(&record.reference[0].to_ascii_uppercase())
+        || !b"ACGT".contains(&record.alternative[0][0].to_ascii_uppercase())
+    {
+        return None;
+    }
+
+    let mut dosages = Vec::with_capacity(samples.len());
+    for one in samples {
+        let alleles = sample_genotype(record, one);
+        let dosage = match alleles.as_slice() {
+            [Some(x)] if *x <= 1 => Some(*x as u8 * 2),
+            [Some(x), Some(y)] if *x <= 1 && *y <= 1 => Some((*x + *y) as u8),
+            _ => None,
+        };
+        dosages.push(dosage);
+    }
+    Some(dosages)
+}
+
+fn passes_frequency_filter(dosages: &[Option<u8>], config: &RelatednessConfig) -> bool {
+    let called: Vec<_> = dosages.iter().flatten().collect();
+    if called.is_empty() || (called.len() as f64) < config.min_call_rate * dosages.len() as f64 {
+        return false;
+    }
+    let af = called.iter().map(|x| **x as f64).sum::<f64>() / (2. * called.len() as f64);
+    af.min(1. - af) >= config.min_maf
+}
+
+/// Squared correlation of dosages in samples called in both SNVs
+fn dosage_r2(dosages1: &[Option<u8>], dosages2: &[Option<u8>]) -> Option<f64> {
+    let pairs: Vec<(f64, f64)> = dosages1
+        .iter()
+        .zip(dosages2.iter())
+        .filter_map(|(x, y)| Some(((*x)? as f64, (*y)? as f64)))
+        .collect();
+    if pairs.is_empty() {
+        return None;
+    }
+    let n = pairs.len() as f64;
+    let mean1 = pairs.iter().map(|x| x.0).sum::<f64>() / n;
+    let mean2 = pairs.iter().map(|x| x.1).sum::<f64>() / n;
+    let covariance: f64 = pairs.iter().map(|x| (x.0 - mean1) * (x.1 - mean2)).sum();
+    let variance1: f64 = pairs.iter().map(|x| (x.0 - mean1).powi(2)).sum();
+    let variance2: f64 = pairs.iter().map(|x| (x.1 - mean2).powi(2)).sum();
+    if variance1 == 0. || variance2 == 0. {
+        None
+    } else {
+        Some(covariance * covariance / (variance1 * variance2))
+    }
+}
+
+/// Compute pairwise relatedness from common biallelic autosomal SNVs pruned for linkage
+/// disequilibrium, and count chromosome X genotypes outside of pseudo-autosomal regions for sex
+/// check. Only pairs with kinship coefficient of at least `min_kinship` and pairs flagged by
+/// discrepancy with `family` are kept, to avoid holding all pairs of large cohorts.
+pub fn relatedness<R: BufRead>(
+    reader: &mut VCFReader<R>,
+    config: &RelatednessConfig,
+    family: &HashMap<U8Vec, U8Vec>,
+    min_kinship: f64,
+) -> Result<(Vec<PairRelatedness>, Vec<SampleStats>), VCFUtilsError> {
+    let samples = reader.header().samples().to_vec();
+    let mut sex_check: Vec<_> = samples.iter().map(|x| SampleStats::new(x)).collect();
+    let mut genotypes = vec![GenotypeBits::default(); samples.len()];
+    let mut snp_count = 0;
+    let mut window: VecDeque<(U8Vec, u64, Vec<Option<u8>>)> = VecDeque::new();
+    let mut record = VCFRecord::new(reader.header().clone());
+
+    while reader.next_record(&mut record)? {
+        let dosages = match snv_dosages(&record, &samples) {
+            Some(x) if passes_frequency_filter(&x, config) => x,
+            _ => continue,
+        };
+
+        let chromosome = record
+            .chromosome
+            .strip_prefix(b"chr")
+            .unwrap_or(&record.chromosome);
+        if is_chrx(&record.chromosome) {
+            if in_pseudo_autosomal_region(
+                &config.pseudo_autosomal_regions,
+                &record.chromosome,
+                record.position,
+            ) {
+                continue;
+            }
+            for (stats, dosage) in sex_check.iter_mut().zip(dosages.iter()) {
+                match dosage {
+                    Some(1) => stats.chrx_het += 1,
+                    Some(2) => stats.chrx_hom_alt += 1,
+                    _ => (),
+                }
+            }
+            continue;
+        }
+        if [&b"Y"[..], b"M", b"MT"].contains(&chromosome) {
+            continue;
+        }
+
+        while let Some((window_chromosome, window_position, _)) = window.front() {
+            if window_chromosome != &record.chromosome
+                || window_position + config.prune_window < record.position
+            {
+                window.pop_front();
+            } else {
+                break;
+            }
+        }
+        if window
+            .iter()
+            .any(|(_, _, x)| dosage_r2(x, &dosages).unwrap_or(0.) > config.max_r2)
+        {
+            continue;
+        }
+
+        for (bits, dosage) in genotypes.iter_mut().zip(dosages.iter()) {
+            bits.push(snp_count, *dosage);
+        }
+        snp_count += 1;
+        if config.prune_window > 0 {
+            window.push_back((record.chromosome.clone(), record.position, dosages));
+        }
+    }
+    log::info!("{} SNVs are used to compute relatedness", snp_count);
+
+    let pairs = (0..samples.len())
+        .into_par_iter()
+        .flat_map_iter(|i| {
+            let samples = &samples;
+            let genotypes = &genotypes;
+            ((i + 1)..samples.len()).filter_map(move |j| {
+                let mut pair = genotypes[i].compare(&genotypes[j]);
+                pair.sample1 = samples[i].clone();
+                pair.sample2 = samples[j].clone();
+                pair.flag = pair.discrepancy(family);
+                if pair.kinship().unwrap_or(f64::NEG_INFINITY) < min_kinship && pair.flag.is_empty()
+                {
+                    None
+                } else {
+                    Some(pair)
+                }
+            })
+        })
+        .collect();
+
+    Ok((pairs, sex_check))
+}
+
+/// Load a column of a table with header indexed by sample ID column.
+pub fn load_sample_column<R: Read>(
+    reader: &mut csv::Reader<R>,
+    id_column: &str,
+    value_column: &str,
+) -> Result<HashMap<U8Vec, U8Vec>, VCFUtilsError> {
+    let header = reader.byte_headers()?.clone();
+    let find_column = |name: &str| {
+        header
+            .iter()
+            .position(|x| x == name.as_bytes())
+            .ok_or_else(|| VCFUtilsError::ColumnNotFound(name.to_string()))
+    };
+    let id_index = find_column(id_column)?;
+    let value_index = find_column(value_column)?;
+
+    let mut result = HashMap::new();
+    let mut record = csv::ByteRecord::new();
+    while reader.read_byte_record(&mut record)? {
+        if let (Some(id), Some(value)) = (record.get(id_index), record.get(value_index)) {
+            result.insert(id.to_vec(), value.to_vec());
+        }
+    }
+    Ok(result)
+}
+
+pub fn relatedness_header() -> Vec<String> {
+    [
+        "Sample1",
+        "Sample2",
+        "SNPs",
+        "HetHet",
+        "IBS0",
+        "Het1",
+        "Het2",
+        "Kinship",
+        "IBS0Ratio",
+        "Relationship",
+        "Flag",
+    ]
+    .iter()
+    .map(|x| x.to_string())
+    .collect()
+}
+
+pub fn relatedness_column_types() -> Vec<ColumnType> {
+    relatedness_header()
+        .iter()
+        .map(|x| match x.as_str() {
+            "Sample1" | "Sample2" | "Relationship" | "Flag" => ColumnType::String,
+            "Kinship" | "IBS0Ratio" => ColumnType::Float,
+            _ => ColumnType::Integer,
+        })
+        .collect()
+}
+
+/// Write pairs, and warn pairs with discrepancy between kinship and families.
+pub fn write_relatedness(
+    pairs: &[PairRelatedness],
+    float_precision: usize,
+    writer: &mut impl TableWriter,
+) -> Result<(), VCFUtilsError> {
+    let format_float = |x: Option<f64>| {
+        x.map(|x| format!("{:.prec$}", x, prec = float_precision))
+            .unwrap_or_default()
+    };
+
+    writer.set_header(&relatedness_header());
+    writer.write_header()?;
+    for one in pairs {
+        if !one.flag.is_empty() {
+            log::warn!(
+                "{} and {} are flagged as {}",
+                String::from_utf8_lossy(&one.sample1),
+                String::from_utf8_lossy(&one.sample2),
+                one.flag
+            );
+        }
+
+        let row = [
+            String::from_utf8_lossy(&one.sample1).to_string(),
+            String::from_utf8_lossy(&one.sample2).to_string(),
+            one.snps.to_string(),
+            one.het_het.to_string(),
+            one.ibs0.to_string(),
+            one.het1.to_string(),
+            one.het2.to_string(),
+            format_float(one.kinship()),
+            format_float(one.ibs0_ratio()),
+            one.relationship().to_string(),
+            one.flag.to_string(),
+        ];
+        writer.write_row(&row.iter().map(|x| x.as_str()).collect::<Vec<_>>())?;
+    }
+    Ok(())
+}
+
+pub fn sex_check_header() -> Vec<String> {
+    [
+        "Sample",
+        "ChrXHet",
+        "ChrXHomAlt",
+        "ChrXHetRatio",
+        "InferredSex",
+        "ExpectedSex",
+        "Flag",
+    ]
+    .iter()
+    .map(|x| x.to_string())
+    .collect()
+}
+
+pub fn sex_check_column_types() -> Vec<ColumnType> {
+    sex_check_header()
+        .iter()
+        .map(|x| match x.as_str() {
+            "ChrXHet" | "ChrXHomAlt" => ColumnType::Integer,
+            "ChrXHetRatio" => ColumnType::Float,
+            _ => ColumnType::String,
+        })
+        .collect()
+}
+
+/// Write inferred sex of samples and flag samples whose inferred sex differs from expected sex.
+pub fn write_sex_check(
+    sex_check: &[SampleStats],
+    expected_sex: &HashMap<U8Vec, U8Vec>,
+    config: &SexInferenceConfig,
+    float_precision: usize,
+    writer: &mut impl TableWriter,
+) -> Result<(), VCFUtilsError> {
+    writer.set_header(&sex_check_header());
+    writer.write_header()?;
+    for one in sex_check {
+        let inferred = one.inferred_sex(config);
+        let expected = match expected_sex.get(&one.sample).map(|x| parse_sex(x)) {
+            Some(Sex::Male) => "male",
+            Some(Sex::Female) => "female",
+            _ => "unknown",
+        };
+        let flag = if inferred != "unknown" && expected != "unknown" && inferred != expected {
+            log::warn!(
+                "Inferred sex of {} is {}, but expected sex is {}",
+                String::from_utf8_lossy(&one.sample),
+                inferred,
+                expected
+            );
+            "sex-mismatch"
+        } else {
+            ""
+        };
+
+        let row = [
+            String::from_utf8_lossy(&one.sample).to_string(),
+            one.chrx_het.to_string(),
+            one.chrx_hom_alt.to_string(),
+            one.chrx_het_ratio()
+                .map(|x| format!("{:.prec$}", x, prec = float_precision))
+                .unwrap_or_default(),
+            inferred.to_string(),
+            expected.to_string(),
+            flag.to_string(),
+        ];
+        writer.write_row(&row.iter().map(|x| x.as_str()).collect::<Vec<_>>())?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::utils::tablewriter::TSVWriter;
+
+    /// Generate a VCF file of unrelated samples A, C and D, and B which is a duplicate of A.
+    /// A and B are males, and C and D are females.
+    fn relatedness_vcf() -> Vec<u8> {
+        let mut vcf = b"##fileformat=VCFv4.2
+##FORMAT=<ID=GT,Number=1,Type=String,Description=\"Genotype\">
+#CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO\tFORMAT\tA\tB\tC\tD
+"
+        .to_vec();
+        let mut seed: u64 = 1;
+        let mut random_genotype = || {
+            seed = seed
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            match (seed >> 33) % 4 {
+                0 => "0/0",
+                1 | 2 => "0/1",
+                _ => "1/1",
+            }
+        };
+        for i in 0..5000 {
+            let a = random_genotype();
+            let c = random_genotype();
+            let d = random_genotype();
+            vcf.extend_from_slice(
+                format!(
+                    "1\t{}\t.\tA\tG\t.\t.\t.\tGT\t{}\t{}\t{}\t{}\n",
+                    i * 1000 + 1,
+                    a,
+                    a,
+                    c,
+                    d
+                )
+                .as_bytes(),
+            );
+        }
+        for i in 0..100 {
+            let c = random_genotype();
+            let d = random_genotype();
+            vcf.extend_from_slice(
+                format!(
+                    "X\t{}\t.\tC\tT\t.\t.\t.\tGT\t1\t1/1\t{}\t{}\n",
+                    i * 1000 + 5_000_000,
+                    c,
+                    d
+                )
+                .as_bytes(),
+            );
+        }
+        vcf
+    }
+
+    #[test]
+    fn test_relatedness() -> Result<(), VCFUtilsError> {
+        let vcf = relatedness_vcf();
+        let mut vcf_reader = VCFReader::new(&vcf[..])?;
+        let config = RelatednessConfig {
+            prune_window: 0,
+            ..Default::default()
+        };
+        let family: HashMap<_, _> = [
+            (b"A".to_vec(), b"F1".to_vec()),
+            (b"C".to_vec(), b"F1".to_vec()),
+        ]
+        .into_iter()
+        .collect();
+        let (pairs, sex_check) = relatedness(&mut vcf_reader, &config, &family, f64::NEG_INFINITY)?;
+        assert_eq!(pairs.len(), 6);
+        assert_eq!(pairs[0].sample1, b"A");
+        assert_eq!(pairs[0].sample2, b"B");
+        assert!(pairs[0].snps > 4000);
+        assert_eq!(pairs[0].het_het, pairs[0].het1);
+        assert_eq!(pairs[0].ibs0, 0);
+        assert_eq!(pairs[0].kinship(), Some(0.5));
+        assert_eq!(pairs[0].relationship(), "duplicate");
+        assert_eq!(pairs[0].flag, "duplicate");
+        for one in &pairs[1..] {
+            assert!(one.kinship().unwrap().abs() < 0.0442, "{:?}", one);
+            assert_eq!(one.relationship(), "unrelated");
+        }
+        assert_eq!(pairs[1].flag, "unrelated-in-family");
+        assert!(pairs[2..].iter().all(|x| x.flag.is_empty()));
+
+        let sex_config = SexInferenceConfig::default();
+        assert_eq!(sex_check[0].chrx_het, 0);
+        assert!(sex_check[0].chrx_hom_alt > 50);
+        assert_eq!(sex_check[0].inferred_sex(&sex_config), "male");
+        assert_eq!(sex_check[2].inferred_sex(&sex_config), "female");
+
+        let mut vcf_reader = VCFReader::new(&vcf[..])?;
+        let (pairs, _) = relatedness(&mut vcf_reader, &config, &family, 0.0442)?;
+        assert_eq!(pairs.len(), 2);
+        let mut output = Vec::new();
+        write_relatedness(&pairs, 3, &mut TSVWriter::new(&mut output))?;
+        let output = String::from_utf8(output).unwrap();
+        let lines: Vec<_> = output.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[1].starts_with("A\tB\t"));
+        assert!(lines[1].ends_with("\t0.500\t0.000\tduplicate\tduplicate"));
+        assert!(lines[2].starts_with("A\tC\t"));
+        assert!(lines[2].ends_with("\tunrelated\tunrelated-in-family"));
+
+        let expected_sex: HashMap<_, _> = [
+            (b"A".to_vec(), b"M".to_vec()),
+            (b"C".to_vec(), b"M".to_vec()),
+        ]
+        .into_iter()
+        .collect();
+        let mut output = Vec::new();
+        write_sex_check(
+            &sex_check,
+            &expected_sex,
+            &sex_config,
+            2,
+            &mut TSVWriter::new(&mut output),
+        )?;
+        let output = String::from_utf8(output).unwrap();
+        let lines: Vec<_> = output.lines().collect();
+        assert!(lines[1].starts_with("A\t0\t"));
+        assert!(lines[1].ends_with("\t0.00\tmale\tmale\t"));
+        assert!(lines[3].starts_with("C\t"));
+        assert!(lines[3].ends_with("\tfemale\tmale\tsex-mismatch"));
+        assert!(lines[4].ends_with("\tfemale\tunknown\t"));
+
+        Ok(())
+    }
+
+    /// Simulate a trio of parents P1 and P2 and their child C, and an unrelated sample U.
+    #[test]
+    fn test_relatedness_trio() -> Result<(), VCFUtilsError> {
+        let mut vcf = b"##fileformat=VCFv4.2
+##FORMAT=<ID=GT,Number=1,Type=String,Description=\"Genotype\">
+#CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO\tFORMAT\tP1\tP2\tC\tU
+"
+        .to_vec();
+        let mut seed: u64 = 7;
+        let mut random_allele = || {
+            seed = seed
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            ((seed >> 33) % 2) as usize
+        };
+        for i in 0..5000 {
+            let mut alleles = [0; 6];
+            for one in alleles.iter_mut() {
+                *one = random_allele();
+            }
+            let child = [alleles[random_allele()], alleles[2 + random_allele()]];
+            let unrelated = [random_allele(), random_allele()];
+            vcf.extend_from_slice(
+                format!(
+                    "1\t{}\t.\tA\tG\t.\t.\t.\tGT\t{}/{}\t{}/{}\t{}/{}\t{}/{}\n",
+                    i * 1000 + 1,
+                    alleles[0],
+                    alleles[1],
+                    alleles[2],
+                    alleles[3],
+                    child[0],
+                    child[1],
+                    unrelated[0],
+                    unrelated[1],
+                )
+                .as_bytes(),
+            );
+        }
+
+        let mut vcf_reader = VCFReader::new(&vcf[..])?;
+        let config = RelatednessConfig {
+            prune_window: 0,
+            ..Default::default()
+        };
+        let family: HashMap<_, _> = [
+            (b"P1".to_vec(), b"F1".to_vec()),
+            (b"P2".to_vec(), b"F1".to_vec()),
+            (b"C".to_vec(), b"F1".to_vec()),
+        ]
+        .into_iter()
+        .collect();
+        let (pairs, _) = relatedness(&mut vcf_reader, &config, &family, 0.0442)?;
+        let pair_names: Vec<_> = pairs
+            .iter()
+            .map(|x| (x.sample1.as_slice(), x.sample2.as_slice()))
+            .collect();
+        // Unrelated parents are flagged as they are in the same family
+        assert_eq!(
+            pair_names,
+            vec![(&b"P1"[..], &b"P2"[..]), (b"P1", b"C"), (b"P2", b"C")]
+        );
+        assert_eq!(pairs[0].relationship(), "unrelated");
+        assert_eq!(pairs[0].flag, "unrelated-in-family");
+        for one in &pairs[1..] {
+            assert_eq!(one.ibs0, 0);
+            assert!((one.kinship().unwrap() - 0.25).abs() < 0.03, "{:?}", one);
+            assert_eq!(one.relationship(), "parent-offspring");
+            assert_eq!(one.flag, "");
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_malformed_genotype() -> Result<(), VCFUtilsError> {
+        let vcf = b"##fileformat=VCFv4.2
+##FORMAT=<ID=GT,Number=1,Type=String,Description=\"Genotype\">
+#CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO\tFORMAT\tA\tB
+1\t100\t.\tA\tG\t.\t.\t.\tGT\t0/x\t0/1
+";
+        let mut vcf_reader = VCFReader::new(&vcf[..])?;
+        let mut record = VCFRecord::new(vcf_reader.header().clone());
+        assert!(vcf_reader.next_record(&mut record)?);
+        assert_eq!(
+            snv_dosages(&record, vcf_reader.header().samples()),
+            Some(vec![None, Some(1)])
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_ld_pruning() -> Result<(), VCFUtilsError> {
+        let vcf = b"##fileformat=VCFv4.2
+##FORMAT=<ID=GT,Number=1,Type=String,Description=\"Genotype\">
+#CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO\tFORMAT\tA\tB\tC\tD
+1\t100\t.\tA\tG\t.\t.\t.\tGT\t0/0\t0/1\t1/1\t0/1
+1\t200\t.\tA\tG\t.\t.\t.\tGT\t0/0\t0/1\t1/1\t0/1
+1\t300\t.\tA\tG\t.\t.\t.\tGT\t0/1\t0/0\t0/1\t1/1
+1\t400\t.\tA\tGT\t.\t.\t.\tGT\t0/1\t0/0\t0/1\t1/1
+1\t500\t.\tA\tG\t.\t.\t.\tGT\t0/0\t0/0\t0/0\t0/0
+1\t600\t.\tA\tG\t.\t.\t.\tGT\t0/1\t./.\t0/0\t0/1
+1\t1000000\t.\tA\tG\t.\t.\t.\tGT\t0/0\t0/1\t1/1\t0/1
+";
+        let mut vcf_reader = VCFReader::new(&vcf[..])?;
+        let (pairs, _) = relatedness(
+            &mut vcf_reader,
+            &RelatednessConfig::default(),
+            &HashMap::new(),
+            f64::NEG_INFINITY,
+        )?;
+        // SNVs at 100, 300 and 1000000 are used. 200 is in LD with 100, 400 is not SNV, 500 is
+        // monomorphic and 600 has low call rate.
+        assert_eq!(pairs[0].snps, 3);
+        assert_eq!(pairs[0].het_het, 0);
+        assert_eq!(pairs[0].ibs0, 0);
+        assert_eq!(pairs[0].het1, 1);
+        assert_eq!(pairs[0].het2, 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_relationship() {
+        let pair = PairRelatedness {
+            snps: 10000,
+            het_het: 1500,
+            ibs0: 0,
+            het1: 3000,
+            het2: 3000,
+            ..Default::default()
+        };
+        assert_eq!(pair.kinship(), Some(0.25));
+        assert_eq!(pair.relationship(), "parent-offspring");
+        let pair = PairRelatedness {
+            ibs0: 300,
+            het_het: 2100,
+            ..pair
+        };
+        assert_eq!(pair.relationship(), "full-sibling");
+        let pair = PairRelatedness {
+            ibs0: 0,
+            het_het: 1000,
+            ..pair
+        };
+        assert!((pair.kinship().unwrap() - 1. / 6.).abs() < 1e-9);
+        assert_eq!(pair.relationship(), "second-degree");
+        let pair = PairRelatedness { het1: 0, ..pair };
+        assert_eq!(pair.relationship(), "unknown");
+    }
+}
@@ -1,4 +1,8 @@
 use crate::error::VCFUtilsError;
+use crate::utils::contig_alias::{
+    contig_name_mapping, detect_contig_naming, detect_genome_build, ContigNaming, GenomeBuild,
+    HG19_CHRM,
+};
 use std::collections::HashMap;
 use std::hash::BuildHasher;
 use std::io::prelude::*;
@@ -43,6 +47,72 @@ pub fn replace_contig<W: Write, R: BufRead, S: BuildHasher>(
     Ok(())
 }
 
+/// Replace contig names with built-in contig name dictionary. Source naming convention and genome
+/// build are detected from header contigs if not specified. An error is returned if the genome
+/// build cannot be detected.
+pub fn replace_contig_with_alias<W: Write, R: BufRead>(
+    mut reader: R,
+    writer: W,
+    build: Option<GenomeBuild>,
+    from: Option<ContigNaming>,
+    to: ContigNaming,
+) -> Result<(), VCFUtilsError> {
+    let mut header = Vec::new();
+    let mut contigs = Vec::new();
+    let mut line = Vec::new();
+    let mut count = 0;
+    while reader.read_until(b'\n', &mut line)? > 0 {
+        count += 1;
+        header.extend_from_slice(&line);
+        if line.starts_with(b"##contig") {
+            let parsed_header = vcf::VCFHeaderLine::from_bytes(&line, count)?;
+            if let vcf::VCFHeaderContent::Contig { id, length } = parsed_header.contents() {
+                contigs.push((id.clone(), *length));
+            }
+        } else if !line.starts_with(b"##") {
+            break;
+        }
+        line.clear();
+    }
+
+    let from = from
+        .or_else(|| detect_contig_naming(&contigs.iter().map(|x| x.0.clone()).collect::<Vec<_>>()))
+        .ok_or(VCFUtilsError::ContigAliasDetectionFailed(
+            "no known contig in header",
+        ))?;
+    // Mitochondrial genome and non-primary contigs differ between builds, so the build is never
+    // guessed
+    let build = build.or_else(|| detect_genome_build(&contigs)).ok_or(
+        VCFUtilsError::ContigAliasDetectionFailed(
+            "genome build is unknown; specify it with --genome-build",
+        ),
+    )?;
+    log::info!(
+        "Replace contig names from {:?} to {:?} ({:?})",
+        from,
+        to,
+        build
+    );
+
+    let names: Vec<_> = contigs.into_iter().map(|x| x.0).collect();
+    let mapping = contig_name_mapping(build, from, to, &names);
+    if build == GenomeBuild::GRCh37
+        && from == ContigNaming::Ucsc
+        && names.iter().any(|x| x == HG19_CHRM.as_bytes())
+    {
+        log::warn!("chrM of hg19 is NC_001807 (16571 bp), not MT (rCRS, NC_012920.1) of GRCh37");
+    }
+    for one in names.iter().filter(|x| !mapping.contains_key(*x)) {
+        log::warn!(
+            "No alias of contig {} in {:?} naming",
+            String::from_utf8_lossy(one),
+            to
+        );
+    }
+
+    replace_contig(header[..].chain(reader), writer, &mapping)
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -88,4 +158,89 @@ mod test {
 
         Ok(())
     }
+
+    #[test]
+    fn test_replace_contig_with_alias() -> Result<(), VCFUtilsError> {
+        let input_vcf = b"##fileformat=VCFv4.2
+##contig=<ID=NC_000001.11>
+##contig=<ID=NC_000002.12>
+##contig=<ID=NT_187361.1>
+#CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO
+NC_000001.11\t100\t.\tA\tG\t.\t.\t.
+NC_000002.12\t200\t.\tC\tT\t.\t.\t.
+NT_187361.1\t300\t.\tG\tA\t.\t.\t.
+";
+        let mut result = Vec::new();
+        replace_contig_with_alias(&input_vcf[..], &mut result, None, None, ContigNaming::Ucsc)?;
+        assert_eq!(
+            String::from_utf8_lossy(&result),
+            "##fileformat=VCFv4.2
+##contig=<ID=chr1>
+##contig=<ID=chr2>
+##contig=<ID=NT_187361.1>
+#CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO
+chr1\t100\t.\tA\tG\t.\t.\t.
+chr2\t200\t.\tC\tT\t.\t.\t.
+NT_187361.1\t300\t.\tG\tA\t.\t.\t.
+"
+        );
+
+        let input_vcf = b"##fileformat=VCFv4.2
+##contig=<ID=chrM>
+##contig=<ID=chr1_KI270706v1_random>
+#CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO
+chrM\t100\t.\tA\tG\t.\t.\t.
+";
+        let mut result = Vec::new();
+        assert!(replace_contig_with_alias(
+            &input_vcf[..],
+            &mut result,
+            None,
+            None,
+            ContigNaming::Ensembl
+        )
+        .is_err());
+        let mut result = Vec::new();
+        replace_contig_with_alias(
+            &input_vcf[..],
+            &mut result,
+            Some(GenomeBuild::GRCh38),
+            None,
+            ContigNaming::Ensembl,
+        )?;
+        assert_eq!(
+            String::from_utf8_lossy(&result),
+            "##fileformat=VCFv4.2
+##contig=<ID=MT>
+##contig=<ID=KI270706.1>
+#CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO
+MT\t100\t.\tA\tG\t.\t.\t.
+"
+        );
+
+        let input_vcf = include_bytes!("../../testfiles/simple1.vcf");
+        let mut result = Vec::new();
+        assert!(replace_contig_with_alias(
+            &input_vcf[..],
+            &mut result,
+            None,
+            None,
+            ContigNaming::RefSeq
+        )
+        .is_err());
+        let mut result = Vec::new();
+        replace_contig_with_alias(
+            &input_vcf[..],
+            &mut result,
+            Some(GenomeBuild::GRCh37),
+            None,
+            ContigNaming::RefSeq,
+        )?;
+        let mut vcf_reader = VCFReader::new(&result[..])?;
+        let mut record = VCFRecord::new(vcf_reader.header().clone());
+        assert!(vcf_reader.next_record(&mut record)?);
+        assert_eq!(record.chromosome, b"NC_000013.10");
+
+        Ok(())
+    }
 }
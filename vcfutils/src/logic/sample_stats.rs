@@ -0,0 +1,364 @@
+use super::add_af::in_pseudo_autosomal_region;
+use crate::error::VCFUtilsError;
+use crate::utils::genotype::{is_chrx, parse_format_integer, sample_genotype};
+use crate::utils::ratio;
+use crate::utils::recalc_af::AlleleCount;
+use crate::utils::tablewriter::{ColumnType, TableWriter};
+use std::io::BufRead;
+use vcf::{U8Vec, VCFReader, VCFRecord};
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub struct SampleStats {
+    pub sample: U8Vec,
+    pub hom_ref: u64,
+    pub het: u64,
+    pub hom_alt: u64,
+    pub missing: u64,
+    pub transitions: u64,
+    pub transversions: u64,
+    pub singletons: u64,
+    pub dp_sum: u64,
+    pub dp_count: u64,
+    pub gq_sum: u64,
+    pub gq_count: u64,
+    pub chrx_het: u64,
+    pub chrx_hom_alt: u64,
+}
+
+impl SampleStats {
+    pub fn new(sample: &[u8]) -> Self {
+        SampleStats {
+            sample: sample.to_vec(),
+            ..Default::default()
+        }
+    }
+
+    pub fn ti_tv_ratio(&self) -> Option<f64> {
+        ratio(self.transitions, self.transversions)
+    }
+
+    pub fn het_hom_ratio(&self) -> Option<f64> {
+        ratio(self.het, self.hom_alt)
+    }
+
+    pub fn mean_dp(&self) -> Option<f64> {
+        ratio(self.dp_sum, self.dp_count)
+    }
+
+    pub fn mean_gq(&self) -> Option<f64> {
+        ratio(self.gq_sum, self.gq_count)
+    }
+
+    /// Fraction of heterozygous calls in non-reference calls on chromosome X outside of
+    /// pseudo-autosomal regions
+    pub fn chrx_het_ratio(&self) -> Option<f64> {
+        ratio(self.chrx_het, self.chrx_het + self.chrx_hom_alt)
+    }
+
+    pub fn inferred_sex(&self, config: &SexInferenceConfig) -> &'static str {
+        match self.chrx_het_ratio() {
+            Some(x) if x <= config.male_max_het_ratio => "male",
+            Some(x) if x >= config.female_min_het_ratio => "female",
+            _ => "unknown",
+        }
+    }
+}
+
+/// Thresholds of heterozygous call fraction on chromosome X to infer sex
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+pub struct SexInferenceConfig {
+    pub male_max_het_ratio: f64,
+    pub female_min_het_ratio: f64,
+}
+
+impl Default for SexInferenceConfig {
+    fn default() -> Self {
+        SexInferenceConfig {
+            male_max_het_ratio: 0.1,
+            female_min_het_ratio: 0.3,
+        }
+    }
+}
+
+/// Returns `Some(true)` for transition, `Some(false)` for transversion and `None` for non-SNV.
+fn is_transition(reference: &[u8], alternative: &[u8]) -> Option<bool> {
+    if reference.len() != 1 || alternative.len() != 1 {
+        return None;
+    }
+    let r = reference[0].to_ascii_uppercase();
+    let a = alternative[0].to_ascii_uppercase();
+    if !b"ACGT".contains(&r) || !b"ACGT".contains(&a) || r == a {
+        return None;
+    }
+    Some(matches!(
+        (r, a),
+        (b'A', b'G') | (b'G', b'A') | (b'C', b'T') | (b'T', b'C')
+    ))
+}
+
+/// Count genotypes of each sample. Calls on chromosome X in `pseudo_autosomal_regions` are not
+/// counted for sex inference, because males are diploid there.
+pub fn sample_stats<R: BufRead>(
+    reader: &mut VCFReader<R>,
+    pseudo_autosomal_regions: &[(U8Vec, u64, u64)],
+) -> Result<Vec<SampleStats>, VCFUtilsError> {
+    let samples = reader.header().samples().to_vec();
+    let mut stats: Vec<_> = samples.iter().map(|x| SampleStats::new(x)).collect();
+    let mut record = VCFRecord::new(reader.header().clone());
+    let mut allele_count = AlleleCount::new(1, 2);
+
+    while reader.next_record(&mut record)? {
+        allele_count.add_record(&record, 2, samples.iter())?;
+        let transition: Vec<_> = record
+            .alternative
+            .iter()
+            .map(|x| is_transition(&record.reference, x))
+            .collect();
+        let chrx = is_chrx(&record.chromosome)
+            && !in_pseudo_autosomal_region(
+                pseudo_autosomal_regions,
+                &record.chromosome,
+                record.position,
+            );
+
+        for (one_sample, one_stats) in samples.iter().zip(stats.iter_mut()) {
+            if let Some(dp) = parse_format_integer(&record, one_sample, b"DP") {
+                one_stats.dp_sum += dp;
+                one_stats.dp_count += 1;
+            }
+            if let Some(gq) = parse_format_integer(&record, one_sample, b"GQ") {
+                one_stats.gq_sum += gq;
+                one_stats.gq_count += 1;
+            }
+
+            let alleles = sample_genotype(&record, one_sample);
+            if alleles.is_empty() || alleles.iter().any(|x| x.is_none()) {
+                one_stats.missing += 1;
+                continue;
+            }
+            let mut alleles: Vec<usize> = alleles.into_iter().flatten().collect();
+            alleles.sort_unstable();
+            let is_hom = alleles.iter().all(|x| *x == alleles[0]);
+            if is_hom && alleles[0] == 0 {
+                one_stats.hom_ref += 1;
+                continue;
+            }
+            if is_hom {
+                one_stats.hom_alt += 1;
+                if chrx {
+                    one_stats.chrx_hom_alt += 1;
+                }
+            } else {
+                one_stats.het += 1;
+                if chrx {
+                    one_stats.chrx_het += 1;
+                }
+            }
+
+            alleles.dedup();
+            for one_allele in alleles.iter().filter(|x| **x > 0) {
+                match transition.get(*one_allele - 1) {
+                    Some(Some(true)) => one_stats.transitions += 1,
+                    Some(Some(false)) => one_stats.transversions += 1,
+                    _ => (),
+                }
+                if allele_count.allele_count.get(*one_allele) == Some(&1) {
+                    one_stats.singletons += 1;
+                }
+            }
+        }
+    }
+
+    Ok(stats)
+}
+
+pub fn sample_stats_header(infer_sex: bool) -> Vec<String> {
+    let mut header: Vec<String> = vec![
+        "Sample",
+        "HomRef",
+        "Het",
+        "HomAlt",
+        "Missing",
+        "Transitions",
+        "Transversions",
+        "TiTv",
+        "HetHomRatio",
+        "MeanDP",
+        "MeanGQ",
+        "Singletons",
+    ]
+    .into_iter()
+    .map(|x| x.to_string())
+    .collect();
+    if infer_sex {
+        header.extend(
+            ["ChrXHet", "ChrXHomAlt", "ChrXHetRatio", "InferredSex"]
+                .iter()
+                .map(|x| x.to_string()),
+        );
+    }
+    header
+}
+
+pub fn sample_stats_column_types(infer_sex: bool) -> Vec<ColumnType> {
+    sample_stats_header(infer_sex)
+        .iter()
+        .map(|x| match x.as_str() {
+            "Sample" | "InferredSex" => ColumnType::String,
+            "TiTv" | "HetHomRatio" | "MeanDP" | "MeanGQ" | "ChrXHetRatio" => ColumnType::Float,
+            _ => ColumnType::Integer,
+        })
+        .collect()
+}
+
+pub fn write_sample_stats(
+    stats: &[SampleStats],
+    sex_inference: Option<&SexInferenceConfig>,
+    float_precision: usize,
+    writer: &mut impl TableWriter,
+) -> Result<(), VCFUtilsError> {
+    let format_float = |x: Option<f64>| {
+        x.map(|x| format!("{:.prec$}", x, prec = float_precision))
+            .unwrap_or_default()
+    };
+
+    writer.set_header(&sample_stats_header(sex_inference.is_some()));
+    writer.write_header()?;
+    for one in stats {
+        let mut row = vec![
+            String::from_utf8_lossy(&one.sample).to_string(),
+            one.hom_ref.to_string(),
+            one.het.to_string(),
+            one.hom_alt.to_string(),
+            one.missing.to_string(),
+            one.transitions.to_string(),
+            one.transversions.to_string(),
+            format_float(one.ti_tv_ratio()),
+            format_float(one.het_hom_ratio()),
+            format_float(one.mean_dp()),
+            format_float(one.mean_gq()),
+            one.singletons.to_string(),
+        ];
+        if let Some(config) = sex_inference {
+            row.push(one.chrx_het.to_string());
+            row.push(one.chrx_hom_alt.to_string());
+            row.push(format_float(one.chrx_het_ratio()));
+            row.push(one.inferred_sex(config).to_string());
+        }
+        writer.write_row(&row.iter().map(|x| x.as_str()).collect::<Vec<_>>())?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::utils::tablewriter::TSVWriter;
+
+    #[test]
+    fn test_is_transition() {
+        assert_eq!(is_transition(b"A", b"G"), Some(true));
+        assert_eq!(is_transition(b"c", b"T"), Some(true));
+        assert_eq!(is_transition(b"A", b"C"), Some(false));
+        assert_eq!(is_transition(b"G", b"T"), Some(false));
+        assert_eq!(is_transition(b"A", b"AT"), None);
+        assert_eq!(is_transition(b"A", b"*"), None);
+    }
+
+    #[test]
+    fn test_sample_stats_malformed_genotype() -> Result<(), VCFUtilsError> {
+        let vcf_data = b"##fileformat=VCFv4.2
+##FORMAT=<ID=GT,Number=1,Type=String,Description=\"Genotype\">
+#CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO\tFORMAT\tS1\tS2
+1\t100\t.\tA\tG\t.\t.\t.\tGT\t0/x\t0/1
+";
+        let mut vcf_reader = VCFReader::new(&vcf_data[..])?;
+        let stats = sample_stats(&mut vcf_reader, &[])?;
+        assert_eq!(stats[0].missing, 1);
+        assert_eq!(stats[1].het, 1);
+        assert_eq!(stats[1].singletons, 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_sample_stats() -> Result<(), VCFUtilsError> {
+        let vcf_data = include_bytes!("../../testfiles/simple1.vcf");
+        let mut vcf_reader = VCFReader::new(&vcf_data[..])?;
+        let stats = sample_stats(&mut vcf_reader, &[])?;
+        assert_eq!(stats.len(), 2);
+        // 1/1, 0/0, 1/2
+        assert_eq!(stats[0].hom_ref, 1);
+        assert_eq!(stats[0].het, 1);
+        assert_eq!(stats[0].hom_alt, 1);
+        assert_eq!(stats[0].missing, 0);
+        assert_eq!(stats[0].transitions, 1);
+        assert_eq!(stats[0].transversions, 0);
+        assert_eq!(stats[0].mean_dp(), Some(56. / 3.));
+        // 1/1, 0/1, 1/2
+        assert_eq!(stats[1].hom_ref, 0);
+        assert_eq!(stats[1].het, 2);
+        assert_eq!(stats[1].hom_alt, 1);
+        assert_eq!(stats[1].singletons, 1);
+
+        let mut write_data: Vec<u8> = Vec::new();
+        write_sample_stats(
+            &stats,
+            Some(&SexInferenceConfig::default()),
+            2,
+            &mut TSVWriter::new(&mut write_data),
+        )?;
+        let written = std::str::from_utf8(&write_data)?;
+        let mut lines = written.lines();
+        assert_eq!(
+            lines.next(),
+            Some(sample_stats_header(true).join("\t").as_str())
+        );
+        assert_eq!(
+            lines.next(),
+            Some("ERP001775_HiSeq2000_SAMEA1531955-1\t1\t1\t1\t0\t1\t0\t\t1.00\t18.67\t\t0\t0\t0\t\tunknown")
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_sample_stats_1kgp() -> Result<(), VCFUtilsError> {
+        let vcf_data = include_bytes!("../../testfiles/1kGP-subset.vcf");
+        let mut vcf_reader = VCFReader::new(&vcf_data[..])?;
+        let stats = sample_stats(&mut vcf_reader, &[])?;
+        assert_eq!(stats.len(), 5);
+        for one in stats.iter() {
+            assert_eq!(
+                one.hom_ref + one.het + one.hom_alt + one.missing,
+                306,
+                "{}",
+                String::from_utf8_lossy(&one.sample)
+            );
+            assert!(one.dp_count > 0);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_sample_stats_pseudo_autosomal() -> Result<(), VCFUtilsError> {
+        let vcf_data = include_bytes!("../../testfiles/sex-chromosome.vcf");
+        let mut vcf_reader = VCFReader::new(&vcf_data[..])?;
+        let stats = sample_stats(&mut vcf_reader, &[])?;
+        assert_eq!((stats[0].chrx_het, stats[0].chrx_hom_alt), (1, 1));
+
+        let pseudo_autosomal_regions: Vec<_> =
+            crate::logic::add_af::GRCH38_PSEUDO_AUTOSOMAL_REGIONS
+                .iter()
+                .map(|(chromosome, start, end)| (chromosome.as_bytes().to_vec(), *start, *end))
+                .collect();
+        let mut vcf_reader = VCFReader::new(&vcf_data[..])?;
+        let stats = sample_stats(&mut vcf_reader, &pseudo_autosomal_regions)?;
+        // het call of M1 in PAR1 is not counted
+        assert_eq!((stats[0].chrx_het, stats[0].chrx_hom_alt), (0, 1));
+        assert_eq!(
+            stats[0].inferred_sex(&SexInferenceConfig::default()),
+            "male"
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,614 @@
+use crate::error::VCFUtilsError;
+use crate::utils::tabix::TabixIndexBuilder;
+use bgzip::{BGZFReader, BGZFWriter, Compression};
+use rayon::prelude::*;
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+use std::fs::File;
+use std::io::{prelude::*, BufReader, BufWriter};
+use std::path::{Path, PathBuf};
+use std::str;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use vcf::U8Vec;
+
+/// Approximate memory usage of one record other than the line itself
+const ENTRY_OVERHEAD: usize = 64;
+
+/// Maximum number of sorted chunks opened at once while merging. Temporary files are merged in
+/// multiple passes if more chunks are spilled.
+pub const MAX_MERGE_CHUNKS: usize = 64;
+
+static TEMPORARY_FILE_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct SortEntry {
+    contig: usize,
+    position: u64,
+    line: U8Vec,
+}
+
+/// Order of contigs. Contigs not found in the initial order are appended in order of appearance.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct ContigOrder {
+    contigs: Vec<U8Vec>,
+    index: HashMap<U8Vec, usize>,
+}
+
+impl ContigOrder {
+    pub fn new(contigs: &[U8Vec]) -> Self {
+        let mut order = ContigOrder::default();
+        for one in contigs {
+            order.get_or_insert(one);
+        }
+        order
+    }
+
+    /// Load contig order from FASTA index (`.fai`)
+    pub fn from_fai(reader: impl BufRead) -> Result<Self, VCFUtilsError> {
+        let mut contigs = Vec::new();
+        for line in reader.split(b'\n') {
+            let line = line?;
+            if let Some(name) = line.split(|x| *x == b'\t').next() {
+                if !name.is_empty() {
+                    contigs.push(name.to_vec());
+                }
+            }
+        }
+        Ok(ContigOrder::new(&contigs))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.contigs.is_empty()
+    }
+
+    fn get_or_insert(&mut self, contig: &[u8]) -> usize {
+        if let Some(index) = self.index.get(contig) {
+            return *index;
+        }
+        let index = self.contigs.len();
+        self.contigs.push(contig.to_vec());
+        self.index.insert(contig.to_vec(), index);
+        index
+    }
+}
+
+/// Return CHROM and POS of a record line.
+fn parse_record_key(line: &[u8]) -> Result<(&[u8], u64), VCFUtilsError> {
+    let mut columns = line.splitn(3, |x| *x == b'\t');
+    let contig = columns.next().unwrap_or_default();
+    let position = columns
+        .next()
+        .ok_or(VCFUtilsError::OtherError("POS column is not found"))?;
+    let position = str::from_utf8(position)?.trim_end().parse::<u64>()?;
+    Ok((contig, position))
+}
+
+/// Return CHROM and zero-based half-open region of a record line. END in INFO column is
+/// considered.
+pub fn record_region(line: &[u8]) -> Result<(&[u8], u64, u64), VCFUtilsError> {
+    let columns: Vec<&[u8]> = line
+        .trim_ascii_end()
+        .splitn(9, |x| *x == b'\t')
+        .take(8)
+        .collect();
+    if columns.len() < 8 {
+        return Err(VCFUtilsError::OtherError("Too few columns in a record"));
+    }
+    let position = str::from_utf8(columns[1])?.parse::<u64>()?;
+    let begin = position.saturating_sub(1);
+    let mut end = begin + (columns[3].len() as u64).max(1);
+    for one in columns[7].split(|x| *x == b';') {
+        if let Some(value) = one.strip_prefix(b"END=") {
+            end = end.max(str::from_utf8(value)?.parse::<u64>()?);
+        }
+    }
+    Ok((columns[0], begin, end))
+}
+
+/// Remove temporary files when dropped
+#[derive(Debug, Default)]
+struct TemporaryFiles {
+    paths: Vec<PathBuf>,
+}
+
+impl Drop for TemporaryFiles {
+    fn drop(&mut self) {
+        for one in self.paths.iter() {
+            if let Err(e) = std::fs::remove_file(one) {
+                log::warn!("Failed to remove temporary file {}: {}", one.display(), e);
+            }
+        }
+    }
+}
+
+fn temporary_path(temp_dir: &Path) -> PathBuf {
+    temp_dir.join(format!(
+        "vcfutils-sort-{}-{}.vcf.gz",
+        std::process::id(),
+        TEMPORARY_FILE_COUNT.fetch_add(1, Ordering::SeqCst)
+    ))
+}
+
+/// Write sorted lines to a BGZF compressed temporary file.
+fn write_temporary_file(
+    path: &Path,
+    mut write_lines: impl FnMut(&mut dyn Write) -> Result<(), VCFUtilsError>,
+) -> Result<(), VCFUtilsError> {
+    let mut file = BufWriter::new(File::create(path)?);
+    let mut writer = BGZFWriter::new(&mut file, Compression::fast());
+    write_lines(&mut writer)?;
+    writer.close()?;
+    file.flush()?;
+    Ok(())
+}
+
+fn spill_chunk(
+    chunk: &mut Vec<SortEntry>,
+    temp_dir: &Path,
+    temporary_files: &mut TemporaryFiles,
+) -> Result<(), VCFUtilsError> {
+    chunk.par_sort_by_key(|x| (x.contig, x.position));
+    let path = temporary_path(temp_dir);
+    temporary_files.paths.push(path.clone());
+    write_temporary_file(&path, |writer| {
+        for one in chunk.drain(..) {
+            writer.write_all(&one.line)?;
+        }
+        Ok(())
+    })?;
+    log::info!("Spilled sorted records to {}", path.display());
+    Ok(())
+}
+
+enum SortedChunk {
+    Memory(std::vec::IntoIter<SortEntry>),
+    File(BufReader<BGZFReader<File>>),
+}
+
+impl SortedChunk {
+    fn next_entry(
+        &mut self,
+        contig_order: &ContigOrder,
+    ) -> Result<Option<SortEntry>, VCFUtilsError> {
+        match self {
+            SortedChunk::Memory(entries) => Ok(entries.next()),
+            SortedChunk::File(reader) => {
+                let mut line = Vec::new();
+                if reader.read_until(b'\n', &mut line)? == 0 {
+                    return Ok(None);
+                }
+                let (contig, position) = parse_record_key(&line)?;
+                let contig = *contig_order
+                    .index
+                    .get(contig)
+                    .ok_or(VCFUtilsError::OtherError(
+                        "Unknown contig in temporary file",
+                    ))?;
+                Ok(Some(SortEntry {
+                    contig,
+                    position,
+                    line,
+                }))
+            }
+        }
+    }
+}
+
+fn open_temporary_file(path: &Path) -> Result<SortedChunk, VCFUtilsError> {
+    Ok(SortedChunk::File(BufReader::new(BGZFReader::new(
+        File::open(path)?,
+    )?)))
+}
+
+/// k-way merge of sorted chunks. Ties are broken by chunk index to keep input order of records.
+fn merge_chunks(
+    chunks: &mut [SortedChunk],
+    contig_order: &ContigOrder,
+    mut write_entry: impl FnMut(&SortEntry) -> Result<(), VCFUtilsError>,
+) -> Result<(), VCFUtilsError> {
+    let mut heads: Vec<Option<SortEntry>> = Vec::new();
+    let mut heap = BinaryHeap::new();
+    for (i, one) in chunks.iter_mut().enumerate() {
+        let entry = one.next_entry(contig_order)?;
+        if let Some(entry) = entry.as_ref() {
+            heap.push(Reverse((entry.contig, entry.position, i)));
+        }
+        heads.push(entry);
+    }
+
+    while let Some(Reverse((_, _, i))) = heap.pop() {
+        let entry = heads[i].take().expect("head of chunk exists");
+        write_entry(&entry)?;
+        let next = chunks[i].next_entry(contig_order)?;
+        if let Some(next) = next.as_ref() {
+            heap.push(Reverse((next.contig, next.position, i)));
+        }
+        heads[i] = next;
+    }
+    Ok(())
+}
+
+/// Merge the first temporary files into one file until at most `max_merge_chunks - 1` files
+/// remain, so that the last merge with records in memory opens at most `max_merge_chunks`
+/// chunks. Merged files replace the first files to keep input order of records.
+fn merge_temporary_files(
+    temporary_files: &mut TemporaryFiles,
+    temp_dir: &Path,
+    contig_order: &ContigOrder,
+    max_merge_chunks: usize,
+) -> Result<(), VCFUtilsError> {
+    let max_merge_chunks = max_merge_chunks.max(2);
+    while temporary_files.paths.len() >= max_merge_chunks {
+        let inputs = temporary_files.paths[..max_merge_chunks].to_vec();
+        let path = temporary_path(temp_dir);
+        temporary_files.paths.push(path.clone());
+        let mut chunks = inputs
+            .iter()
+            .map(|x| open_temporary_file(x))
+            .collect::<Result<Vec<_>, _>>()?;
+        write_temporary_file(&path, |writer| {
+            merge_chunks(&mut chunks, contig_order, |entry| {
+                writer.write_all(&entry.line)?;
+                Ok(())
+            })
+        })?;
+        log::info!(
+            "Merged {} temporary files into {}",
+            inputs.len(),
+            path.display()
+        );
+
+        temporary_files.paths.pop();
+        temporary_files.paths.splice(..max_merge_chunks, [path]);
+        for one in inputs.iter() {
+            std::fs::remove_file(one)?;
+        }
+    }
+    Ok(())
+}
+
+/// Sort VCF records by contig order and position, and write BGZF compressed VCF.
+///
+/// Records are sorted by order of contigs in `contig_order`, or in VCF header if `contig_order`
+/// is empty. Records on unknown contigs are placed after known contigs. When records exceed
+/// `max_memory` bytes, sorted chunks are written to `temp_dir` and merged. Index of the output
+/// is built at the same time, and returned.
+pub fn sort_vcf<R: BufRead, W: Write>(
+    reader: R,
+    writer: W,
+    contig_order: ContigOrder,
+    max_memory: usize,
+    temp_dir: &Path,
+) -> Result<TabixIndexBuilder, VCFUtilsError> {
+    sort_vcf_with_merge_chunks(
+        reader,
+        writer,
+        contig_order,
+        max_memory,
+        temp_dir,
+        MAX_MERGE_CHUNKS,
+    )
+}
+
+fn sort_vcf_with_merge_chunks<R: BufRead, W: Write>(
+    mut reader: R,
+    mut output: W,
+    contig_order: ContigOrder,
+    max_memory: usize,
+    temp_dir: &Path,
+    max_merge_chunks: usize,
+) -> Result<TabixIndexBuilder, VCFUtilsError> {
+    let mut contig_order = contig_order;
+    let use_header_contig = contig_order.is_empty();
+    let mut writer = BGZFWriter::new(&mut output, Compression::default());
+
+    let mut line = Vec::new();
+    let mut line_count = 0;
+    while reader.read_until(b'\n', &mut line)? > 0 {
+        line_count += 1;
+        if !line.starts_with(b"#") {
+            break;
+        }
+        if use_header_contig && line.starts_with(b"##contig") {
+            let parsed_header = vcf::VCFHeaderLine::from_bytes(&line, line_count)?;
+            if let vcf::VCFHeaderContent::Contig { id, .. } = parsed_header.contents() {
+                contig_order.get_or_insert(id);
+            }
+        }
+        writer.write_all(&line)?;
+        line.clear();
+    }
+
+    let mut temporary_files = TemporaryFiles::default();
+    let mut chunk = Vec::new();
+    let mut chunk_memory = 0;
+    while !line.is_empty() {
+        if !line.ends_with(b"\n") {
+            line.push(b'\n');
+        }
+        let (contig, position) = parse_record_key(&line)?;
+        let contig_count = contig_order.contigs.len();
+        let contig_index = contig_order.get_or_insert(contig);
+        if contig_order.contigs.len() > contig_count {
+            log::warn!(
+                "Contig {} is not found in contig order. Placed after known contigs.",
+                String::from_utf8_lossy(contig)
+            );
+        }
+        chunk_memory += line.len() + ENTRY_OVERHEAD;
+        chunk.push(SortEntry {
+            contig: contig_index,
+            position,
+            line: std::mem::take(&mut line),
+        });
+        if chunk_memory >= max_memory {
+            spill_chunk(&mut chunk, temp_dir, &mut temporary_files)?;
+            chunk_memory = 0;
+        }
+        reader.read_until(b'\n', &mut line)?;
+    }
+
+    chunk.par_sort_by_key(|x| (x.contig, x.position));
+    merge_temporary_files(
+        &mut temporary_files,
+        temp_dir,
+        &contig_order,
+        max_merge_chunks,
+    )?;
+    let mut chunks = Vec::new();
+    for one in temporary_files.paths.iter() {
+        chunks.push(open_temporary_file(one)?);
+    }
+    chunks.push(SortedChunk::Memory(chunk.into_iter()));
+
+    let mut index_builder = TabixIndexBuilder::new();
+    merge_chunks(&mut chunks, &contig_order, |entry| {
+        let start_offset = writer.bgzf_pos();
+        writer.write_all(&entry.line)?;
+        let (contig, begin, end) = record_region(&entry.line)?;
+        index_builder.add_record(contig, begin, end, start_offset, writer.bgzf_pos())?;
+        Ok(())
+    })?;
+    writer.close()?;
+    output.flush()?;
+
+    Ok(index_builder)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::utils::tabix::IndexFormat;
+    use bgzip::tabix::Tabix;
+    use std::collections::BTreeMap;
+
+    const UNSORTED_VCF: &[u8] = b"##fileformat=VCFv4.2
+##INFO=<ID=END,Number=1,Type=Integer,Description=\"End position\">
+##contig=<ID=chr2,length=1000000>
+##contig=<ID=chr1,length=1000000>
+#CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO
+chr1\t300\t.\tA\tG\t.\t.\t.
+chr3\t10\t.\tC\tT\t.\t.\t.
+chr2\t500000\t.\tG\tA\t.\t.\t.
+chr1\t100\t.\tT\tC\t.\t.\t.
+chr2\t20\tfirst\tN\t<DEL>\t.\t.\tEND=40000
+chr1\t200\t.\tA\tG\t.\t.\t.
+chr2\t20\tsecond\tA\tG\t.\t.\t.
+";
+
+    fn records(data: &[u8]) -> Vec<String> {
+        let mut reader = BGZFReader::new(data).unwrap();
+        let mut text = String::new();
+        reader.read_to_string(&mut text).unwrap();
+        text.lines()
+            .filter(|x| !x.starts_with('#'))
+            .map(|x| {
+                let columns: Vec<_> = x.split('\t').collect();
+                format!("{}:{}:{}", columns[0], columns[1], columns[2])
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_sort_vcf() -> Result<(), VCFUtilsError> {
+        let expected = vec![
+            "chr2:20:first",
+            "chr2:20:second",
+            "chr2:500000:.",
+            "chr1:100:.",
+            "chr1:200:.",
+            "chr1:300:.",
+            "chr3:10:.",
+        ];
+
+        for max_memory in [1, 200, 1 << 20] {
+            let mut output = Vec::new();
+            let index_builder = sort_vcf(
+                UNSORTED_VCF,
+                &mut output,
+                ContigOrder::default(),
+                max_memory,
+                &std::env::temp_dir(),
+            )?;
+            assert_eq!(records(&output), expected);
+
+            let mut index_data = Vec::new();
+            index_builder.write(&mut index_data, IndexFormat::Tbi)?;
+            let index = bgzip::tabix::Tabix::from_reader(&index_data[..]).unwrap();
+            assert_eq!(
+                index.names,
+                vec![b"chr2\0".to_vec(), b"chr1\0".to_vec(), b"chr3\0".to_vec()]
+            );
+            assert_eq!(index.column_for_sequence, 1);
+            assert_eq!(index.column_for_begin, 2);
+            assert_eq!(index.sequences.len(), 3);
+            // pseudo bin holds number of records
+            assert_eq!(index.sequences[0].bins[&37450].chunks[1].begin, 3);
+            assert_eq!(index.sequences[1].bins[&37450].chunks[1].begin, 3);
+            // deletion spans 3 linear index windows
+            assert!(index.sequences[0].intervals.len() >= 3);
+            assert!(index.sequences[0]
+                .bins
+                .contains_key(&bgzip::tabix::reg2bin(19, 40000)));
+        }
+
+        // every record is spilled, and temporary files are merged in multiple passes
+        for max_merge_chunks in [2, 3] {
+            let mut output = Vec::new();
+            sort_vcf_with_merge_chunks(
+                UNSORTED_VCF,
+                &mut output,
+                ContigOrder::default(),
+                1,
+                &std::env::temp_dir(),
+                max_merge_chunks,
+            )?;
+            assert_eq!(records(&output), expected);
+        }
+
+        let mut output = Vec::new();
+        sort_vcf(
+            UNSORTED_VCF,
+            &mut output,
+            ContigOrder::from_fai(&b"chr1\t1000000\t6\t60\t61\nchr3\t100\t0\t60\t61\n"[..])?,
+            1 << 20,
+            &std::env::temp_dir(),
+        )?;
+        assert_eq!(
+            records(&output),
+            vec![
+                "chr1:100:.",
+                "chr1:200:.",
+                "chr1:300:.",
+                "chr3:10:.",
+                "chr2:20:first",
+                "chr2:20:second",
+                "chr2:500000:.",
+            ]
+        );
+        Ok(())
+    }
+
+    fn read_u32(data: &mut &[u8]) -> u32 {
+        let mut buffer = [0; 4];
+        data.read_exact(&mut buffer).unwrap();
+        u32::from_le_bytes(buffer)
+    }
+
+    fn read_u64(data: &mut &[u8]) -> u64 {
+        let mut buffer = [0; 8];
+        data.read_exact(&mut buffer).unwrap();
+        u64::from_le_bytes(buffer)
+    }
+
+    /// Bins and chunks of each contig in CSI index
+    fn parse_csi(data: &[u8]) -> Vec<BTreeMap<u32, Vec<(u64, u64)>>> {
+        let mut decompressed = Vec::new();
+        BGZFReader::new(data)
+            .unwrap()
+            .read_to_end(&mut decompressed)
+            .unwrap();
+        let mut data = &decompressed[..];
+        assert_eq!(read_u32(&mut data).to_le_bytes(), *b"CSI\x01");
+        let _min_shift = read_u32(&mut data);
+        let _depth = read_u32(&mut data);
+        let aux_length = read_u32(&mut data) as usize;
+        data = &data[aux_length..];
+        let mut references = Vec::new();
+        for _ in 0..read_u32(&mut data) {
+            let mut bins = BTreeMap::new();
+            for _ in 0..read_u32(&mut data) {
+                let bin = read_u32(&mut data);
+                let _loffset = read_u64(&mut data);
+                let chunks = (0..read_u32(&mut data))
+                    .map(|_| (read_u64(&mut data), read_u64(&mut data)))
+                    .collect();
+                bins.insert(bin, chunks);
+            }
+            references.push(bins);
+        }
+        references
+    }
+
+    #[test]
+    fn test_index_matches_tabix() -> Result<(), VCFUtilsError> {
+        // index created by tabix
+        let expected =
+            Tabix::from_reader(&include_bytes!("../../testfiles/1kGP-subset.vcf.gz.tbi")[..])
+                .unwrap();
+
+        let compressed = include_bytes!("../../testfiles/1kGP-subset.vcf.gz");
+        // (compressed offset, uncompressed offset, uncompressed size) of BGZF blocks
+        let mut blocks = Vec::new();
+        let (mut compressed_offset, mut uncompressed_offset) = (0, 0);
+        while compressed_offset < compressed.len() {
+            let mut data = &compressed[compressed_offset + 16..];
+            let block_size = (read_u32(&mut data) & 0xffff) as usize + 1;
+            let mut data = &compressed[compressed_offset + block_size - 4..];
+            let uncompressed_size = read_u32(&mut data) as usize;
+            blocks.push((compressed_offset, uncompressed_offset, uncompressed_size));
+            compressed_offset += block_size;
+            uncompressed_offset += uncompressed_size;
+        }
+        // Offsets at the end of a block point to the next block as htslib does
+        let virtual_offset = |position: usize| {
+            let (compressed, uncompressed, _) = blocks
+                .iter()
+                .find(|(_, start, size)| position < start + size)
+                .unwrap_or(blocks.last().unwrap());
+            ((*compressed as u64) << 16) | (position - uncompressed) as u64
+        };
+
+        let mut text = Vec::new();
+        BGZFReader::new(&compressed[..])?.read_to_end(&mut text)?;
+        let mut index_builder = TabixIndexBuilder::new();
+        let mut start_offset = 0;
+        for line in text.split_inclusive(|x| *x == b'\n') {
+            let end_offset = start_offset + line.len();
+            if !line.starts_with(b"#") {
+                let (contig, begin, end) = record_region(line)?;
+                index_builder.add_record(
+                    contig,
+                    begin,
+                    end,
+                    virtual_offset(start_offset),
+                    virtual_offset(end_offset),
+                )?;
+            }
+            start_offset = end_offset;
+        }
+
+        let mut index_data = Vec::new();
+        index_builder.write(&mut index_data, IndexFormat::Tbi)?;
+        assert_eq!(Tabix::from_reader(&index_data[..]).unwrap(), expected);
+
+        // CSI with the default depth has the same bins and chunks as tabix
+        let mut index_data = Vec::new();
+        index_builder.write(&mut index_data, IndexFormat::Csi)?;
+        let expected_bins: Vec<BTreeMap<_, _>> = expected
+            .sequences
+            .iter()
+            .map(|x| {
+                x.bins
+                    .values()
+                    .map(|y| (y.bin, y.chunks.iter().map(|z| (z.begin, z.end)).collect()))
+                    .collect()
+            })
+            .collect();
+        assert_eq!(parse_csi(&index_data), expected_bins);
+        Ok(())
+    }
+
+    #[test]
+    fn test_record_region() -> Result<(), VCFUtilsError> {
+        assert_eq!(
+            record_region(b"chr1\t100\t.\tACG\tA\t.\t.\t.\n")?,
+            (&b"chr1"[..], 99, 102)
+        );
+        assert_eq!(
+            record_region(b"chr1\t100\t.\tA\t<DEL>\t.\t.\tSVTYPE=DEL;END=200\tGT\t0/1\n")?,
+            (&b"chr1"[..], 99, 200)
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,294 @@
+use crate::error::VCFUtilsError;
+use crate::logic::add_af::{
+    add_af_to_record, add_allele_count_header, add_hemizygous_count_header, add_site_qc_header,
+    AlleleCountInfoKeys, SexChromosomePloidy,
+};
+use crate::utils::recalc_af::AlleleCount;
+use std::collections::HashSet;
+use std::io::{BufRead, Write};
+use vcf::{U8Vec, VCFHeader, VCFReader, VCFRecord, VCFWriter};
+
+/// Resolve output sample order.
+///
+/// If `exclude` is false, samples are kept in the order of `sample_list`.
+/// If `exclude` is true, samples in `sample_list` are removed and the
+/// original order is kept.
+pub fn select_samples(
+    vcf_samples: &[U8Vec],
+    sample_list: &[U8Vec],
+    exclude: bool,
+) -> Result<Vec<U8Vec>, VCFUtilsError> {
+    let vcf_sample_set: HashSet<_> = vcf_samples.iter().collect();
+    if exclude {
+        for one in sample_list {
+            if !vcf_sample_set.contains(one) {
+                log::warn!(
+                    "Sample to exclude is not found in VCF: {}",
+                    String::from_utf8_lossy(one)
+                );
+            }
+        }
+        let exclude_set: HashSet<_> = sample_list.iter().collect();
+        Ok(vcf_samples
+            .iter()
+            .filter(|x| !exclude_set.contains(x))
+            .cloned()
+            .collect())
+    } else {
+        let mut selected = Vec::new();
+        let mut selected_set = HashSet::new();
+        for one in sample_list {
+            if !vcf_sample_set.contains(one) {
+                return Err(VCFUtilsError::SampleNotFound(
+                    String::from_utf8_lossy(one).to_string(),
+                ));
+            }
+            if selected_set.insert(one.clone()) {
+                selected.push(one.clone());
+            } else {
+                log::warn!(
+                    "Sample is listed more than once: {}",
+                    String::from_utf8_lossy(one)
+                );
+            }
+        }
+        Ok(selected)
+    }
+}
+
+/// Write VCF with `samples` in the given order. If `recalc_af` is true, allele counts of
+/// the samples are recalculated, and males are counted as haploid on sex chromosomes if
+/// `sex_chromosome_ploidy` is given.
+#[allow(clippy::too_many_arguments)]
+pub fn subset_samples<R: BufRead, W: Write>(
+    reader: &mut VCFReader<R>,
+    writer: W,
+    samples: &[U8Vec],
+    drop_monomorphic: bool,
+    recalc_af: bool,
+    sex_chromosome_ploidy: Option<&SexChromosomePloidy>,
+    site_qc: bool,
+    af_precision: usize,
+) -> Result<(), VCFUtilsError> {
+    let sample_indexes = samples
+        .iter()
+        .map(|x| {
+            reader.header().sample_index(x).ok_or_else(|| {
+                VCFUtilsError::SampleNotFound(String::from_utf8_lossy(x).to_string())
+            })
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let sample_set: HashSet<U8Vec> = samples.iter().cloned().collect();
+
+    let info_keys = AlleleCountInfoKeys::new(None);
+    let mut header_items = reader.header().items().to_vec();
+    if recalc_af {
+        add_allele_count_header(reader.header(), &mut header_items, &info_keys);
+        if sex_chromosome_ploidy.is_some() {
+            add_hemizygous_count_header(reader.header(), &mut header_items, &info_keys);
+        }
+        if site_qc {
+            add_site_qc_header(reader.header(), &mut header_items, &info_keys);
+        }
+    }
+    let new_header = VCFHeader::new(header_items, samples.to_vec());
+
+    let mut vcf_writer = VCFWriter::new(writer, &new_header)?;
+    let mut record = VCFRecord::new(reader.header().clone());
+    let mut allele_count = AlleleCount::new(1, 2);
+
+    while reader.next_record(&mut record)? {
+        // Allele counts are calculated before genotype columns are rearranged,
+        // because sample lookup depends on the original header.
+        if recalc_af {
+            add_af_to_record(
+                &mut record,
+                &info_keys,
+                &sample_set,
+                &mut allele_count,
+                af_precision,
+                sex_chromosome_ploidy,
+                site_qc,
+            )?;
+        } else if drop_monomorphic {
+            allele_count.add_record(&record, 2, samples.iter())?;
+        }
+
+        if drop_monomorphic && allele_count.allele_count.iter().skip(1).all(|x| *x == 0) {
+            continue;
+        }
+
+        if samples.is_empty() {
+            record.format.clear();
+            record.genotype.clear();
+        } else {
+            let new_genotype: Vec<_> = sample_indexes
+                .iter()
+                .map(|x| record.genotype.get(*x).cloned().unwrap_or_default())
+                .collect();
+            record.genotype = new_genotype;
+        }
+        vcf_writer.write_record(&record)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_select_samples() -> Result<(), VCFUtilsError> {
+        let vcf_samples = vec![b"A".to_vec(), b"B".to_vec(), b"C".to_vec()];
+        assert_eq!(
+            select_samples(&vcf_samples, &[b"C".to_vec(), b"A".to_vec()], false)?,
+            vec![b"C".to_vec(), b"A".to_vec()]
+        );
+        assert_eq!(
+            select_samples(
+                &vcf_samples,
+                &[b"C".to_vec(), b"A".to_vec(), b"C".to_vec()],
+                false
+            )?,
+            vec![b"C".to_vec(), b"A".to_vec()]
+        );
+        assert_eq!(
+            select_samples(&vcf_samples, &[b"C".to_vec(), b"X".to_vec()], true)?,
+            vec![b"A".to_vec(), b"B".to_vec()]
+        );
+        assert!(select_samples(&vcf_samples, &[b"X".to_vec()], false).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_subset_samples() -> Result<(), VCFUtilsError> {
+        let vcf_data = include_bytes!("../../testfiles/1kGP-subset.vcf");
+        let mut vcf_reader = VCFReader::new(&vcf_data[..])?;
+        let mut write_data: Vec<u8> = Vec::new();
+        subset_samples(
+            &mut vcf_reader,
+            &mut write_data,
+            &[
+                b"SRP150637__HG00102".to_vec(),
+                b"SRP150637__HG00099".to_vec(),
+            ],
+            false,
+            false,
+            None,
+            false,
+            4,
+        )?;
+
+        let mut vcf_reader2 = VCFReader::new(&write_data[..])?;
+        assert_eq!(
+            vcf_reader2.header().samples(),
+            &[
+                b"SRP150637__HG00102".to_vec(),
+                b"SRP150637__HG00099".to_vec()
+            ]
+        );
+        let mut record = VCFRecord::new(vcf_reader2.header().clone());
+        assert!(vcf_reader2.next_record(&mut record)?);
+        assert_eq!(record.position, 32872836);
+        assert_eq!(
+            record.genotype(b"SRP150637__HG00099", b"DP"),
+            Some(&vec![b"31".to_vec()])
+        );
+        assert_eq!(
+            record.genotype(b"SRP150637__HG00102", b"DP"),
+            Some(&vec![b"28".to_vec()])
+        );
+        // INFO is not modified without recalculation
+        assert_eq!(record.info(b"AC"), Some(&vec![b"1".to_vec()]));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_subset_samples_drop_monomorphic() -> Result<(), VCFUtilsError> {
+        let vcf_data = include_bytes!("../../testfiles/1kGP-subset.vcf");
+        let mut vcf_reader = VCFReader::new(&vcf_data[..])?;
+        let mut write_data: Vec<u8> = Vec::new();
+        subset_samples(
+            &mut vcf_reader,
+            &mut write_data,
+            &[
+                b"SRP150637__HG00099".to_vec(),
+                b"SRP150637__HG00102".to_vec(),
+            ],
+            true,
+            true,
+            None,
+            false,
+            4,
+        )?;
+
+        let mut vcf_reader2 = VCFReader::new(&write_data[..])?;
+        let mut record = VCFRecord::new(vcf_reader2.header().clone());
+        assert!(vcf_reader2.next_record(&mut record)?);
+        // 32872836 is not variable in HG00099 and HG00102
+        assert_eq!(record.position, 32872987);
+        assert_eq!(record.info(b"AC"), Some(&vec![b"3".to_vec()]));
+        assert_eq!(record.info(b"AN"), Some(&vec![b"4".to_vec()]));
+        assert_eq!(record.info(b"AF"), Some(&vec![b"0.7500".to_vec()]));
+        assert_eq!(
+            record.info(b"GenotypeCount"),
+            Some(&vec![b"0".to_vec(), b"1".to_vec(), b"1".to_vec()])
+        );
+
+        while vcf_reader2.next_record(&mut record)? {
+            assert!(record
+                .info(b"AC")
+                .unwrap()
+                .iter()
+                .any(|x| x.as_slice() != b"0"));
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_subset_samples_sex_chromosome() -> Result<(), VCFUtilsError> {
+        let vcf_data = include_bytes!("../../testfiles/sex-chromosome.vcf");
+        let mut vcf_reader = VCFReader::new(&vcf_data[..])?;
+        let sample_sex = crate::logic::add_af::load_sex_table(csv::Reader::from_reader(
+            &include_bytes!("../../testfiles/sex.csv")[..],
+        ))?;
+        let pseudo_autosomal_regions: Vec<_> =
+            crate::logic::add_af::GRCH38_PSEUDO_AUTOSOMAL_REGIONS
+                .iter()
+                .map(|(chromosome, start, end)| (chromosome.as_bytes().to_vec(), *start, *end))
+                .collect();
+        let ploidy = SexChromosomePloidy::new(sample_sex, &pseudo_autosomal_regions);
+        let mut write_data: Vec<u8> = Vec::new();
+        subset_samples(
+            &mut vcf_reader,
+            &mut write_data,
+            &[b"M1".to_vec(), b"F1".to_vec()],
+            false,
+            true,
+            Some(&ploidy),
+            false,
+            4,
+        )?;
+
+        let mut vcf_reader2 = VCFReader::new(&write_data[..])?;
+        assert!(vcf_reader2.header().info(b"nhemialt").is_some());
+        let mut record = VCFRecord::new(vcf_reader2.header().clone());
+        // AC, AN and nhemialt
+        let expected = [
+            ["1", "4", "0"],
+            ["2", "4", "0"],
+            ["2", "3", "1"],
+            ["1", "1", "1"],
+        ];
+        for values in expected {
+            assert!(vcf_reader2.next_record(&mut record)?);
+            for (key, value) in [&b"AC"[..], b"AN", b"nhemialt"].iter().zip(values) {
+                assert_eq!(record.info(key), Some(&vec![value.as_bytes().to_vec()]));
+            }
+        }
+        Ok(())
+    }
+}
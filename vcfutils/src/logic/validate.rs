@@ -0,0 +1,723 @@
+use crate::error::VCFUtilsError;
+use crate::utils::genotype::parse_genotype;
+use crate::utils::recalc_af::number_of_genotype;
+use crate::utils::tablewriter::{ColumnType, TableWriter};
+use bio::io::fasta::IndexedReader;
+use std::collections::{HashMap, HashSet};
+use std::io::{BufRead, Read, Seek};
+use std::str;
+use vcf::{Number, U8Vec, VCFHeader, VCFHeaderContent, VCFHeaderLine, VCFRecord, ValueType};
+
+/// Check consistency between records and header declarations.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecordValidator {
+    header: VCFHeader,
+    contigs: Option<HashSet<U8Vec>>,
+}
+
+fn expected_count(number: &Number, alt_count: usize, ploidy: Option<usize>) -> Option<usize> {
+    match number {
+        Number::Number(x) => Some(*x as usize),
+        Number::Zero => Some(0),
+        Number::Allele => Some(alt_count),
+        Number::Reference => Some(alt_count + 1),
+        Number::Genotype => ploidy.map(|x| number_of_genotype(alt_count, x)),
+        Number::Unknown | Number::Other(_) => None,
+    }
+}
+
+fn is_valid_value(value_type: &ValueType, value: &[u8]) -> bool {
+    if value == b"." {
+        return true;
+    }
+    match value_type {
+        ValueType::Integer => str::from_utf8(value)
+            .map(|x| x.parse::<i64>().is_ok())
+            .unwrap_or(false),
+        ValueType::Float => str::from_utf8(value)
+            .map(|x| x.parse::<f64>().is_ok())
+            .unwrap_or(false),
+        ValueType::Character => value.len() == 1,
+        _ => true,
+    }
+}
+
+fn check_values(
+    field: &str,
+    id: &[u8],
+    number: &Number,
+    value_type: &ValueType,
+    values: &[U8Vec],
+    alt_count: usize,
+    ploidy: Option<usize>,
+) -> Result<(), String> {
+    let is_missing = values.len() == 1 && values[0] == b".";
+    if let Some(count) = expected_count(number, alt_count, ploidy) {
+        if !is_missing && values.len() != count {
+            return Err(format!(
+                "{}/{} has {} values, but {} values are expected",
+                field,
+                String::from_utf8_lossy(id),
+                values.len(),
+                count
+            ));
+        }
+    }
+    if let Some(value) = values.iter().find(|x| !is_valid_value(value_type, x)) {
+        return Err(format!(
+            "{}/{} has value \"{}\" which is not {:?}",
+            field,
+            String::from_utf8_lossy(id),
+            String::from_utf8_lossy(value),
+            value_type
+        ));
+    }
+    Ok(())
+}
+
+impl RecordValidator {
+    /// Contigs are checked only if the header has contig lines.
+    pub fn new(header: &VCFHeader) -> Self {
+        let contigs: HashSet<_> = header
+            .items()
+            .iter()
+            .filter_map(|x| match x.contents() {
+                VCFHeaderContent::Contig { id, .. } => Some(id.clone()),
+                _ => None,
+            })
+            .collect();
+        RecordValidator {
+            header: header.clone(),
+            contigs: if contigs.is_empty() {
+                None
+            } else {
+                Some(contigs)
+            },
+        }
+    }
+
+    /// Return descriptions of all problems in the record. Each INFO and FORMAT key is
+    /// reported at most once.
+    pub fn problems(&self, record: &VCFRecord) -> Vec<String> {
+        let mut problems = Vec::new();
+        let alt_count = record.alternative.len();
+        if let Some(contigs) = self.contigs.as_ref() {
+            if !contigs.contains(&record.chromosome) {
+                problems.push(format!(
+                    "contig {} is not declared",
+                    String::from_utf8_lossy(&record.chromosome)
+                ));
+            }
+        }
+        for one in record.filter.iter() {
+            if one != b"PASS" && one != b"." && self.header.filter(one).is_none() {
+                problems.push(format!(
+                    "FILTER/{} is not declared",
+                    String::from_utf8_lossy(one)
+                ));
+            }
+        }
+        for (key, values) in record.info.iter().filter(|x| x.0 != b".") {
+            let definition = match self.header.info(key) {
+                Some(x) => x,
+                None => {
+                    problems.push(format!(
+                        "INFO/{} is not declared",
+                        String::from_utf8_lossy(key)
+                    ));
+                    continue;
+                }
+            };
+            if definition.value_type == &ValueType::Flag {
+                if !values.is_empty() {
+                    problems.push(format!(
+                        "INFO/{} is declared as Flag, but has values",
+                        String::from_utf8_lossy(key)
+                    ));
+                }
+                continue;
+            }
+            if let Err(e) = check_values(
+                "INFO",
+                key,
+                definition.number,
+                definition.value_type,
+                values,
+                alt_count,
+                None,
+            ) {
+                problems.push(e);
+            }
+        }
+        let gt_index = record.format.iter().position(|x| x == b"GT");
+        for (format_index, key) in record.format.iter().enumerate() {
+            let definition = match self.header.format(key) {
+                Some(x) => x,
+                None => {
+                    problems.push(format!(
+                        "FORMAT/{} is not declared",
+                        String::from_utf8_lossy(key)
+                    ));
+                    continue;
+                }
+            };
+            for sample in record.genotype.iter() {
+                let values = match sample.get(format_index) {
+                    Some(x) => x,
+                    None => continue,
+                };
+                let ploidy = gt_index
+                    .and_then(|x| sample.get(x))
+                    .and_then(|x| x.first())
+                    .filter(|x| x.as_slice() != b".")
+                    .map(|x| x.iter().filter(|y| **y == b'/' || **y == b'|').count() + 1);
+                if let Err(e) = check_values(
+                    "FORMAT",
+                    key,
+                    definition.number,
+                    definition.value_type,
+                    values,
+                    alt_count,
+                    ploidy,
+                ) {
+                    problems.push(e);
+                    break;
+                }
+            }
+        }
+        problems
+    }
+
+    /// Return the description of the first problem in the record.
+    pub fn check(&self, record: &VCFRecord) -> Result<(), String> {
+        match self.problems(record).into_iter().next() {
+            Some(x) => Err(x),
+            None => Ok(()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+impl Severity {
+    pub fn to_str(&self) -> &str {
+        match self {
+            Severity::Error => "ERROR",
+            Severity::Warning => "WARNING",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ValidationIssue {
+    /// 1-based line number in the VCF file
+    pub line: u64,
+    pub severity: Severity,
+    pub chromosome: U8Vec,
+    pub position: Option<u64>,
+    pub message: String,
+}
+
+struct IssueCollector {
+    issues: Vec<ValidationIssue>,
+}
+
+impl IssueCollector {
+    fn line(&mut self, line: u64, severity: Severity, message: String) {
+        self.issues.push(ValidationIssue {
+            line,
+            severity,
+            chromosome: Vec::new(),
+            position: None,
+            message,
+        });
+    }
+
+    fn record(&mut self, line: u64, record: &VCFRecord, severity: Severity, message: String) {
+        self.issues.push(ValidationIssue {
+            line,
+            severity,
+            chromosome: record.chromosome.clone(),
+            position: Some(record.position),
+            message,
+        });
+    }
+}
+
+fn is_valid_reference(reference: &[u8]) -> bool {
+    !reference.is_empty()
+        && reference
+            .iter()
+            .all(|x| matches!(x.to_ascii_uppercase(), b'A' | b'C' | b'G' | b'T' | b'N'))
+}
+
+/// Check header lines. Return the header if `#CHROM` line is found.
+fn validate_header<R: BufRead>(
+    reader: &mut R,
+    line_number: &mut u64,
+    collector: &mut IssueCollector,
+) -> Result<Option<VCFHeader>, VCFUtilsError> {
+    let mut items = Vec::new();
+    let mut ids = HashSet::new();
+    let mut line = Vec::new();
+    loop {
+        line.clear();
+        if reader.read_until(b'\n', &mut line)? == 0 {
+            collector.line(
+                *line_number,
+                Severity::Error,
+                "#CHROM header line is not found".to_string(),
+            );
+            return Ok(None);
+        }
+        *line_number += 1;
+
+        if *line_number == 1 {
+            match VCFHeaderLine::from_bytes(&line, *line_number).map(|x| x.contents().clone()) {
+                Ok(VCFHeaderContent::FileFormat(version)) => {
+                    if version != vcf::VCFVersion::Vcf4_2 && version != vcf::VCFVersion::Vcf4_3 {
+                        collector.line(
+                            *line_number,
+                            Severity::Warning,
+                            "fileformat is not VCFv4.2 or VCFv4.3".to_string(),
+                        );
+                    }
+                }
+                _ => collector.line(
+                    *line_number,
+                    Severity::Error,
+                    "First line is not ##fileformat".to_string(),
+                ),
+            }
+        }
+
+        if line.starts_with(b"##") {
+            match VCFHeaderLine::from_bytes(&line, *line_number) {
+                Ok(item) => {
+                    let id = match item.contents() {
+                        VCFHeaderContent::INFO { id, .. } => Some(("INFO", id.clone())),
+                        VCFHeaderContent::FORMAT { id, .. } => Some(("FORMAT", id.clone())),
+                        VCFHeaderContent::FILTER { id, .. } => Some(("FILTER", id.clone())),
+                        VCFHeaderContent::Contig { id, .. } => Some(("contig", id.clone())),
+                        _ => None,
+                    };
+                    if let Some((kind, id)) = id {
+                        if !ids.insert((kind, id.clone())) {
+                            collector.line(
+                                *line_number,
+                                Severity::Warning,
+                                format!(
+                                    "{}/{} is declared more than once",
+                                    kind,
+                                    String::from_utf8_lossy(&id)
+                                ),
+                            );
+                        }
+                    }
+                    items.push(item);
+                }
+                Err(_) => collector.line(
+                    *line_number,
+                    Severity::Error,
+                    "Malformed meta information line".to_string(),
+                ),
+            }
+        } else if line.starts_with(b"#CHROM") {
+            while line.ends_with(b"\n") || line.ends_with(b"\r") {
+                line.pop();
+            }
+            let columns: Vec<_> = line.split(|x| *x == b'\t').collect();
+            let expected: &[&[u8]] = &[
+                b"#CHROM", b"POS", b"ID", b"REF", b"ALT", b"QUAL", b"FILTER", b"INFO", b"FORMAT",
+            ];
+            let samples: Vec<_> = columns.iter().skip(9).map(|x| x.to_vec()).collect();
+            let expected_len = if columns.len() > 8 { 9 } else { 8 };
+            if columns[..columns.len().min(9)] != expected[..expected_len] {
+                collector.line(
+                    *line_number,
+                    Severity::Error,
+                    "#CHROM header line does not have the fixed columns".to_string(),
+                );
+            }
+            if !items
+                .iter()
+                .any(|x| matches!(x.contents(), VCFHeaderContent::Contig { .. }))
+            {
+                collector.line(
+                    *line_number,
+                    Severity::Warning,
+                    "No contig is declared in header".to_string(),
+                );
+            }
+            return Ok(Some(VCFHeader::new(items, samples)));
+        } else {
+            collector.line(
+                *line_number,
+                Severity::Error,
+                "Record is found before #CHROM header line".to_string(),
+            );
+            return Ok(None);
+        }
+    }
+}
+
+/// Check a VCF file, and return issues found. REF alleles are compared with the reference
+/// sequence if given.
+pub fn validate_vcf<R: BufRead, F: Read + Seek>(
+    mut reader: R,
+    mut reference: Option<&mut IndexedReader<F>>,
+) -> Result<Vec<ValidationIssue>, VCFUtilsError> {
+    let mut collector = IssueCollector { issues: Vec::new() };
+    let mut line_number = 0;
+    let header = match validate_header(&mut reader, &mut line_number, &mut collector)? {
+        Some(x) => x,
+        None => return Ok(collector.issues),
+    };
+    let column_count = if header.samples().is_empty() {
+        8
+    } else {
+        9 + header.samples().len()
+    };
+    let validator = RecordValidator::new(&header);
+    let reference_contigs: HashMap<_, _> = reference
+        .as_ref()
+        .map(|x| {
+            x.index
+                .sequences()
+                .into_iter()
+                .map(|x| (x.name.into_bytes(), x.len))
+                .collect()
+        })
+        .unwrap_or_default();
+    let mut reported_reference_contigs = HashSet::new();
+    let mut reference_sequence = Vec::new();
+
+    let mut finished_contigs = HashSet::new();
+    let mut last_position: HashMap<U8Vec, u64> = HashMap::new();
+    let mut last_contig: Option<U8Vec> = None;
+
+    let mut record = VCFRecord::new(header.clone());
+    let mut line = Vec::new();
+    loop {
+        line.clear();
+        if reader.read_until(b'\n', &mut line)? == 0 {
+            break;
+        }
+        line_number += 1;
+        let trimmed = line
+            .iter()
+            .rposition(|x| *x != b'\n' && *x != b'\r')
+            .map(|x| &line[..=x])
+            .unwrap_or(&[]);
+        if trimmed.is_empty() {
+            continue;
+        }
+
+        let columns = trimmed.split(|x| *x == b'\t').count();
+        if columns != column_count {
+            collector.line(
+                line_number,
+                Severity::Error,
+                format!(
+                    "{} columns are found, but {} are expected",
+                    columns, column_count
+                ),
+            );
+            continue;
+        }
+        if record.parse_bytes(&line, line_number).is_err() {
+            collector.line(line_number, Severity::Error, "Malformed record".to_string());
+            continue;
+        }
+
+        // Sort order
+        if last_contig.as_ref() != Some(&record.chromosome) {
+            if let Some(x) = last_contig.take() {
+                finished_contigs.insert(x);
+            }
+            if finished_contigs.contains(&record.chromosome) {
+                collector.record(
+                    line_number,
+                    &record,
+                    Severity::Error,
+                    "Records are not sorted: contig appears again".to_string(),
+                );
+            }
+            last_contig = Some(record.chromosome.clone());
+        }
+        let last = last_position
+            .entry(record.chromosome.clone())
+            .or_insert(record.position);
+        if record.position < *last {
+            collector.record(
+                line_number,
+                &record,
+                Severity::Error,
+                format!("Records are not sorted: previous position is {}", *last),
+            );
+        }
+        *last = (*last).max(record.position);
+
+        if !is_valid_reference(&record.reference) {
+            collector.record(
+                line_number,
+                &record,
+                Severity::Error,
+                format!(
+                    "REF {} contains invalid base",
+                    String::from_utf8_lossy(&record.reference)
+                ),
+            );
+        }
+
+        for problem in validator.problems(&record) {
+            collector.record(line_number, &record, Severity::Error, problem);
+        }
+
+        // Genotypes
+        if let Some(gt_index) = record.format.iter().position(|x| x == b"GT") {
+            for (sample, values) in header.samples().iter().zip(record.genotype.iter()) {
+                let gt = match values.get(gt_index).and_then(|x| x.first()) {
+                    Some(x) => x,
+                    None => continue,
+                };
+                let message = match parse_genotype(gt) {
+                    Ok(alleles) => alleles
+                        .into_iter()
+                        .flatten()
+                        .find(|x| *x > record.alternative.len())
+                        .map(|x| format!("GT allele index {} exceeds number of ALT alleles", x)),
+                    Err(_) => Some("Malformed GT".to_string()),
+                };
+                if let Some(message) = message {
+                    collector.record(
+                        line_number,
+                        &record,
+                        Severity::Error,
+                        format!(
+                            "{} in sample {}: {}",
+                            message,
+                            String::from_utf8_lossy(sample),
+                            String::from_utf8_lossy(gt)
+                        ),
+                    );
+                }
+            }
+        }
+
+        // Reference sequence
+        if let Some(reference) = reference.as_mut() {
+            let contig_length = if let Some(x) = reference_contigs.get(&record.chromosome) {
+                *x
+            } else {
+                if reported_reference_contigs.insert(record.chromosome.clone()) {
+                    collector.record(
+                        line_number,
+                        &record,
+                        Severity::Warning,
+                        "contig is not found in reference FASTA".to_string(),
+                    );
+                }
+                continue;
+            };
+            if record.position == 0 || !is_valid_reference(&record.reference) {
+                continue;
+            }
+            let start = record.position - 1;
+            let end = start + record.reference.len() as u64;
+            if end > contig_length {
+                collector.record(
+                    line_number,
+                    &record,
+                    Severity::Error,
+                    format!(
+                        "REF ends at {}, beyond the end of reference contig (length {})",
+                        end, contig_length
+                    ),
+                );
+                continue;
+            }
+            let fetched = reference
+                .fetch(str::from_utf8(&record.chromosome)?, start, end)
+                .and_then(|_| reference.read(&mut reference_sequence));
+            if let Err(e) = fetched {
+                collector.record(
+                    line_number,
+                    &record,
+                    Severity::Error,
+                    format!("Failed to read reference sequence: {}", e),
+                );
+            } else if !reference_sequence.eq_ignore_ascii_case(&record.reference) {
+                collector.record(
+                    line_number,
+                    &record,
+                    Severity::Error,
+                    format!(
+                        "REF {} does not match reference sequence {}",
+                        String::from_utf8_lossy(&record.reference),
+                        String::from_utf8_lossy(&reference_sequence)
+                    ),
+                );
+            }
+        }
+    }
+
+    Ok(collector.issues)
+}
+
+pub fn validation_header() -> Vec<String> {
+    ["Line", "Severity", "CHROM", "POS", "Message"]
+        .iter()
+        .map(|x| x.to_string())
+        .collect()
+}
+
+pub fn validation_column_types() -> Vec<ColumnType> {
+    validation_header()
+        .iter()
+        .map(|x| match x.as_str() {
+            "Line" | "POS" => ColumnType::Integer,
+            _ => ColumnType::String,
+        })
+        .collect()
+}
+
+pub fn write_validation_issues(
+    issues: &[ValidationIssue],
+    writer: &mut impl TableWriter,
+) -> Result<(), VCFUtilsError> {
+    writer.set_header(&validation_header());
+    writer.write_header()?;
+    for one in issues {
+        let row = [
+            one.line.to_string(),
+            one.severity.to_str().to_string(),
+            String::from_utf8_lossy(&one.chromosome).to_string(),
+            one.position.map(|x| x.to_string()).unwrap_or_default(),
+            one.message.clone(),
+        ];
+        writer.write_row(&row.iter().map(|x| x.as_str()).collect::<Vec<_>>())?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_validate_vcf() -> Result<(), VCFUtilsError> {
+        let vcf_data = include_bytes!("../../testfiles/validate.vcf");
+        let mut reference = IndexedReader::new(
+            Cursor::new(&include_bytes!("../../testfiles/validate-ref.fa")[..]),
+            &include_bytes!("../../testfiles/validate-ref.fa.fai")[..],
+        )?;
+        let issues = validate_vcf(&vcf_data[..], Some(&mut reference))?;
+        let summary: Vec<_> = issues
+            .iter()
+            .map(|x| (x.line, x.severity, x.message.as_str()))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (
+                    10,
+                    Severity::Error,
+                    "REF T does not match reference sequence A"
+                ),
+                (11, Severity::Error, "INFO/XX is not declared"),
+                (
+                    12,
+                    Severity::Error,
+                    "INFO/AC has 1 values, but 2 values are expected"
+                ),
+                (
+                    13,
+                    Severity::Error,
+                    "FORMAT/DP has value \"abc\" which is not Integer"
+                ),
+                (
+                    14,
+                    Severity::Error,
+                    "Records are not sorted: previous position is 60"
+                ),
+                (15, Severity::Error, "Malformed GT in sample S1: 0/x"),
+                (
+                    16,
+                    Severity::Error,
+                    "GT allele index 2 exceeds number of ALT alleles in sample S1: 1/2"
+                ),
+                (17, Severity::Error, "contig chr3 is not declared"),
+                (
+                    17,
+                    Severity::Warning,
+                    "contig is not found in reference FASTA"
+                ),
+                (
+                    18,
+                    Severity::Error,
+                    "Records are not sorted: contig appears again"
+                ),
+                (
+                    19,
+                    Severity::Error,
+                    "9 columns are found, but 10 are expected"
+                ),
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_validate_reference_length() -> Result<(), VCFUtilsError> {
+        let vcf_data = b"##fileformat=VCFv4.2\n\
+                         ##contig=<ID=chr2,length=100>\n\
+                         #CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO\n\
+                         chr2\t39\t.\tAAA\tG\t50\tPASS\t.\n\
+                         chr2\t40\t.\tA\tG\t50\tPASS\t.\n";
+        let mut reference = IndexedReader::new(
+            Cursor::new(&include_bytes!("../../testfiles/validate-ref.fa")[..]),
+            &include_bytes!("../../testfiles/validate-ref.fa.fai")[..],
+        )?;
+        let issues = validate_vcf(&vcf_data[..], Some(&mut reference))?;
+        let summary: Vec<_> = issues
+            .iter()
+            .map(|x| (x.line, x.severity, x.message.as_str()))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![(
+                4,
+                Severity::Error,
+                "REF ends at 41, beyond the end of reference contig (length 40)"
+            )]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_validate_bad_header() -> Result<(), VCFUtilsError> {
+        let issues = validate_vcf::<_, Cursor<Vec<u8>>>(
+            &include_bytes!("../../testfiles/bad1.vcf")[..],
+            None,
+        )?;
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].line, 13);
+        assert_eq!(
+            issues[0].message,
+            "Record is found before #CHROM header line"
+        );
+
+        let issues = validate_vcf::<_, Cursor<Vec<u8>>>(
+            &include_bytes!("../../testfiles/simple1.vcf")[..],
+            None,
+        )?;
+        assert_eq!(issues, vec![]);
+        Ok(())
+    }
+}
@@ -0,0 +1,351 @@
+use super::generate_sql::{create_table_sql, insert_sql, quote_identifier, sql_columns, SqlColumn};
+use super::vcf2table::{create_header_line, vcf2table, HeaderType, VCF2CSVConfig};
+use crate::error::VCFUtilsError;
+use crate::utils::annotation::GENE_FIELD_NAMES;
+use crate::utils::tablewriter::TableWriter;
+use rusqlite::types::Value;
+use rusqlite::{params_from_iter, Connection, Statement};
+use std::io::BufRead;
+use vcf::VCFReader;
+
+/// Table writer which inserts rows into SQLite table with a prepared statement.
+pub struct SqliteTableWriter<'conn> {
+    statement: Statement<'conn>,
+    columns: Vec<SqlColumn>,
+    header: Vec<String>,
+}
+
+impl<'conn> SqliteTableWriter<'conn> {
+    pub fn new(
+        connection: &'conn Connection,
+        table_name: &str,
+        columns: &[SqlColumn],
+    ) -> Result<Self, VCFUtilsError> {
+        Ok(SqliteTableWriter {
+            statement: connection.prepare(&insert_sql(table_name, columns))?,
+            columns: columns.to_vec(),
+            header: Vec::new(),
+        })
+    }
+}
+
+/// Convert a cell into SQL value. Empty cells are stored as NULL, and values which
+/// cannot be parsed as the column type are stored as text.
+fn sql_value(value: &str, sql_type: &str) -> Value {
+    if value.is_empty() {
+        return Value::Null;
+    }
+    match sql_type {
+        "INTEGER" => value
+            .parse()
+            .map(Value::Integer)
+            .unwrap_or_else(|_| Value::Text(value.to_string())),
+        "REAL" => value
+            .parse()
+            .map(Value::Real)
+            .unwrap_or_else(|_| Value::Text(value.to_string())),
+        _ => Value::Text(value.to_string()),
+    }
+}
+
+impl<'conn> TableWriter for SqliteTableWriter<'conn> {
+    fn set_header(&mut self, items: &[String]) {
+        self.header.clear();
+        self.header.extend_from_slice(items);
+    }
+
+    fn header(&self) -> &[String] {
+        &self.header
+    }
+
+    /// Columns are created with CREATE TABLE statement.
+    fn write_header(&mut self) -> Result<(), VCFUtilsError> {
+        Ok(())
+    }
+
+    fn write_row(&mut self, items: &[&str]) -> Result<(), VCFUtilsError> {
+        let values = self
+            .columns
+            .iter()
+            .map(|x| sql_value(items.get(x.index).copied().unwrap_or_default(), x.sql_type));
+        self.statement.execute(params_from_iter(values))?;
+        Ok(())
+    }
+
+    fn is_formula_compatible(&self) -> bool {
+        false
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct SqliteIndexConfig {
+    /// Create an index on CHROM and POS
+    pub position: bool,
+    /// Create an index on each gene column
+    pub gene: bool,
+}
+
+pub fn create_index_sql(
+    table_name: &str,
+    header_items: &[HeaderType],
+    columns: &[SqlColumn],
+    index_config: &SqliteIndexConfig,
+) -> Vec<String> {
+    let mut sql = Vec::new();
+    if index_config.position {
+        let position_columns: Vec<_> = [HeaderType::CHROM, HeaderType::POS]
+            .iter()
+            .filter_map(|item| columns.iter().find(|x| header_items[x.index] == *item))
+            .map(|x| quote_identifier(&x.name))
+            .collect();
+        sql.push(format!(
+            "CREATE INDEX {} ON {} ({});",
+            quote_identifier(&format!("{}_position", table_name)),
+            quote_identifier(table_name),
+            position_columns.join(", ")
+        ));
+    }
+    if index_config.gene {
+        let gene_columns: Vec<_> = columns
+            .iter()
+            .filter(|x| match &header_items[x.index] {
+                HeaderType::GeneName
+                | HeaderType::GeneChange
+                | HeaderType::CanonicalChange
+                | HeaderType::SvGenes => true,
+                HeaderType::Annotation(_, _, name) => GENE_FIELD_NAMES.contains(&name.as_str()),
+                _ => false,
+            })
+            .collect();
+        if gene_columns.is_empty() {
+            log::warn!("No gene column is found. Gene index is not created.");
+        }
+        for one in gene_columns {
+            sql.push(format!(
+                "CREATE INDEX {} ON {} ({});",
+                quote_identifier(&format!("{}_{}", table_name, one.name)),
+                quote_identifier(table_name),
+                quote_identifier(&one.name)
+            ));
+        }
+    }
+    sql
+}
+
+/// Create a table and insert all records in a transaction. Returns the number of inserted rows.
+pub fn vcf2sqlite<R: BufRead>(
+    vcf_reader: &mut VCFReader<R>,
+    connection: &mut Connection,
+    config: &VCF2CSVConfig,
+    table_name: &str,
+    index_config: &SqliteIndexConfig,
+) -> Result<u32, VCFUtilsError> {
+    let header_items = create_header_line(vcf_reader.header(), config)?;
+    let columns = sql_columns(&header_items);
+
+    let transaction = connection.transaction()?;
+    transaction.execute_batch(&create_table_sql(table_name, &columns))?;
+    let row_count = {
+        let mut writer = SqliteTableWriter::new(&transaction, table_name, &columns)?;
+        vcf2table(vcf_reader, &header_items, config, None, true, &mut writer)
+            .map_err(VCFUtilsError::TableConversionError)?
+    };
+    for one in create_index_sql(table_name, &header_items, &columns, index_config) {
+        transaction.execute_batch(&one)?;
+    }
+    transaction.commit()?;
+    Ok(row_count)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::logic::vcf2table::SnpEffImpact;
+
+    #[test]
+    fn test_sql_value() {
+        assert_eq!(sql_value("", "INTEGER"), Value::Null);
+        assert_eq!(sql_value("12", "INTEGER"), Value::Integer(12));
+        assert_eq!(sql_value("1,2", "INTEGER"), Value::Text("1,2".to_string()));
+        assert_eq!(sql_value("0.5", "REAL"), Value::Real(0.5));
+        assert_eq!(sql_value("12", "TEXT"), Value::Text("12".to_string()));
+    }
+
+    #[test]
+    fn test_vcf2sqlite() -> Result<(), VCFUtilsError> {
+        let vcf_data = include_bytes!("../../testfiles/simple1.vcf");
+        let config = VCF2CSVConfig {
+            split_multi_allelic: true,
+            decoded_genotype: false,
+            canonical_list: None,
+            info_list: vec![b"AC".to_vec(), b"AN".to_vec(), b"AF".to_vec()],
+            format_list: vec![b"GT".to_vec(), b"AD".to_vec(), b"DP".to_vec()],
+            priority_info_list: Vec::new(),
+            priority_format_list: Vec::new(),
+            replace_sample_name: None,
+            group_names: None,
+            annotation_key: None,
+            annotation_fields: Vec::new(),
+            long_format: false,
+            variant_filter: None,
+            structural_variant: false,
+            sv_gene_regions: None,
+        };
+        let mut vcf_reader = VCFReader::new(&vcf_data[..])?;
+        let mut connection = Connection::open_in_memory()?;
+        let row_count = vcf2sqlite(
+            &mut vcf_reader,
+            &mut connection,
+            &config,
+            "vcf_table",
+            &SqliteIndexConfig {
+                position: true,
+                gene: false,
+            },
+        )?;
+        assert_eq!(row_count, 4);
+        let count: i64 =
+            connection.query_row("SELECT COUNT(*) FROM vcf_table", [], |row| row.get(0))?;
+        assert_eq!(count, 4);
+
+        let mut statement = connection
+            .prepare("SELECT CHROM, POS, REF, ALT, AC, AF FROM vcf_table ORDER BY rowid")?;
+        let rows = statement
+            .query_map([], |row| {
+                Ok((
+                    row.get(0)?,
+                    row.get(1)?,
+                    row.get(2)?,
+                    row.get(3)?,
+                    row.get(4)?,
+                    row.get(5)?,
+                ))
+            })?
+            .collect::<Result<Vec<(String, i64, String, String, i64, f64)>, _>>()?;
+        assert_eq!(
+            rows,
+            vec![
+                (
+                    "13".to_string(),
+                    32889968,
+                    "G".to_string(),
+                    "A".to_string(),
+                    54,
+                    1.
+                ),
+                (
+                    "13".to_string(),
+                    32897288,
+                    "ATT".to_string(),
+                    "A".to_string(),
+                    25,
+                    0.463
+                ),
+                (
+                    "13".to_string(),
+                    32918303,
+                    "GAA".to_string(),
+                    "G".to_string(),
+                    27,
+                    0.5
+                ),
+                (
+                    "13".to_string(),
+                    32918303,
+                    "GAA".to_string(),
+                    "GAAA".to_string(),
+                    26,
+                    0.481
+                ),
+            ]
+        );
+
+        let mut statement = connection
+            .prepare("SELECT name FROM sqlite_master WHERE type = 'index' ORDER BY name")?;
+        let indexes = statement
+            .query_map([], |row| row.get(0))?
+            .collect::<Result<Vec<String>, _>>()?;
+        assert_eq!(indexes, vec!["vcf_table_position".to_string()]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_vcf2sqlite_identifier_names() -> Result<(), VCFUtilsError> {
+        let vcf = b"##fileformat=VCFv4.2
+##INFO=<ID=GROUP,Number=1,Type=String,Description=\"Group\">
+##INFO=<ID=AC.1,Number=1,Type=Integer,Description=\"Count 1\">
+##INFO=<ID=AC_1,Number=1,Type=Integer,Description=\"Count 2\">
+##FORMAT=<ID=GT,Number=1,Type=String,Description=\"Genotype\">
+#CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO\tFORMAT\t12345
+1\t100\t.\tA\tG\t.\t.\tGROUP=case;AC.1=1;AC_1=2\tGT\t0/1
+";
+        let config = VCF2CSVConfig {
+            split_multi_allelic: true,
+            decoded_genotype: false,
+            canonical_list: None,
+            info_list: vec![b"GROUP".to_vec(), b"AC.1".to_vec(), b"AC_1".to_vec()],
+            format_list: vec![b"GT".to_vec()],
+            priority_info_list: Vec::new(),
+            priority_format_list: Vec::new(),
+            replace_sample_name: None,
+            group_names: None,
+            annotation_key: None,
+            annotation_fields: Vec::new(),
+            long_format: false,
+            variant_filter: None,
+            structural_variant: false,
+            sv_gene_regions: None,
+        };
+        let mut vcf_reader = VCFReader::new(&vcf[..])?;
+        let mut connection = Connection::open_in_memory()?;
+        let row_count = vcf2sqlite(
+            &mut vcf_reader,
+            &mut connection,
+            &config,
+            "vcf_table",
+            &SqliteIndexConfig {
+                position: true,
+                gene: false,
+            },
+        )?;
+        assert_eq!(row_count, 1);
+
+        let mut statement = connection.prepare(
+            r#"SELECT "GROUP", "AC_1", "AC_1_2", "12345__GT" FROM vcf_table ORDER BY rowid"#,
+        )?;
+        let rows = statement
+            .query_map([], |row| {
+                Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?))
+            })?
+            .collect::<Result<Vec<(String, i64, i64, String)>, _>>()?;
+        assert_eq!(rows, vec![("case".to_string(), 1, 2, "0/1".to_string())]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_create_index_sql() {
+        let header_items = vec![
+            HeaderType::VcfLine,
+            HeaderType::CHROM,
+            HeaderType::POS,
+            HeaderType::GeneName,
+            HeaderType::SnpEffImpact(SnpEffImpact::High),
+        ];
+        let columns = sql_columns(&header_items);
+        assert_eq!(
+            create_index_sql(
+                "vcf_table",
+                &header_items,
+                &columns,
+                &SqliteIndexConfig {
+                    position: true,
+                    gene: true
+                }
+            ),
+            vec![
+                r#"CREATE INDEX "vcf_table_position" ON "vcf_table" ("CHROM", "POS");"#.to_string(),
+                r#"CREATE INDEX "vcf_table_Gene" ON "vcf_table" ("Gene");"#.to_string(),
+            ]
+        );
+    }
+}
@@ -1,21 +1,33 @@
+use super::filter::VariantFilter;
 use crate::error::VCFUtilsError;
-use crate::utils::tablewriter::{TableWriter, XlsxDataType, XlsxSheetWriter};
+use crate::utils::annotation::{AnnotationFormat, GENE_FIELD_NAMES};
+use crate::utils::gvcf::{is_non_ref_allele, remove_non_ref_alleles};
+use crate::utils::structural_variant::{parse_structural_variant, GeneRegions, StructuralVariant};
+use crate::utils::tablewriter::{
+    ColumnType, HyperlinkTemplate, TableWriter, XlsxDataType, XlsxWorkbookWriter,
+};
+use crate::utils::{read_record_chunk, record_chunk_size};
 use anyhow::Context;
 use nom::branch::alt;
 use nom::bytes::complete::{tag, take_while1};
 use nom::character::is_digit;
 use nom::multi::many0;
 use nom::sequence::tuple;
+use rayon::prelude::*;
 use std::collections::HashSet;
 use std::fmt;
 use std::io::{BufRead, Write};
 use std::str;
 use vcf::{self, U8Vec, VCFHeader, VCFReader, VCFRecord};
+use xlsxwriter::format::FormatColor;
+use xlsxwriter::worksheet::conditional_format::{ConditionalFormat, ConditionalFormatRuleTypes};
 
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub struct VCF2CSVConfig {
     pub split_multi_allelic: bool,
     pub decoded_genotype: bool,
+    /// Canonical transcripts. Annotation entries flagged with VEP `CANONICAL=YES` are also
+    /// canonical, so an empty list keeps only flagged entries. Entries are not filtered if `None`.
     pub canonical_list: Option<HashSet<U8Vec>>,
     pub priority_info_list: Vec<U8Vec>,
     pub info_list: Vec<U8Vec>,
@@ -23,6 +35,18 @@ pub struct VCF2CSVConfig {
     pub format_list: Vec<U8Vec>,
     pub replace_sample_name: Option<Vec<U8Vec>>,
     pub group_names: Option<Vec<U8Vec>>,
+    /// Pipe-delimited annotation INFO key. ANN, CSQ and BCSQ are detected when not given.
+    pub annotation_key: Option<U8Vec>,
+    /// Annotation subfields to expand into columns
+    pub annotation_fields: Vec<U8Vec>,
+    /// Write one row per annotation entry, with one column per annotation subfield
+    pub long_format: bool,
+    /// Gene list and panel region filter
+    pub variant_filter: Option<VariantFilter>,
+    /// Add SV type, start, end, length and mate breakend columns
+    pub structural_variant: bool,
+    /// Gene regions to list genes overlapping structural variants
+    pub sv_gene_regions: Option<GeneRegions>,
 }
 
 #[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy)]
@@ -76,6 +100,13 @@ pub enum HeaderType {
     ID,
     REF,
     ALT,
+    SvType,
+    SvStart,
+    SvEnd,
+    SvLength,
+    MateCHROM,
+    MatePOS,
+    SvGenes,
     QUAL,
     FILTER,
     SnpEff,
@@ -94,6 +125,8 @@ pub enum HeaderType {
     ),
     // Variant Allele Frequency (VAF): SampleName, ReplacedSampleName, sample index
     VAF(U8Vec, Option<U8Vec>, usize),
+    // Annotation subfield: INFO key, field index, field name
+    Annotation(U8Vec, usize, String),
     Empty,
 }
 
@@ -135,6 +168,7 @@ impl HeaderType {
             HeaderType::SnpEffHighestImpact => 10.,
             HeaderType::SnpEff => 50.,
             HeaderType::SnpEffImpact(_) => 20.,
+            HeaderType::SvGenes => 20.,
             _ => xlsxwriter::worksheet::LXW_DEF_COL_WIDTH,
         }
     }
@@ -157,6 +191,13 @@ impl ToString for HeaderType {
             HeaderType::ID => "ID".to_string(),
             HeaderType::REF => "REF".to_string(),
             HeaderType::ALT => "ALT".to_string(),
+            HeaderType::SvType => "SV type".to_string(),
+            HeaderType::SvStart => "SV start".to_string(),
+            HeaderType::SvEnd => "SV end".to_string(),
+            HeaderType::SvLength => "SV length".to_string(),
+            HeaderType::MateCHROM => "Mate CHROM".to_string(),
+            HeaderType::MatePOS => "Mate POS".to_string(),
+            HeaderType::SvGenes => "SV genes".to_string(),
             HeaderType::QUAL => "QUAL".to_string(),
             HeaderType::FILTER => "FILTER".to_string(),
             HeaderType::Info(x, num, _, index, _) => {
@@ -188,13 +229,20 @@ impl ToString for HeaderType {
             HeaderType::SnpEffImpact(impact) => format!("GeneImpact__{}", impact.to_str()),
             HeaderType::SnpEffHighestImpact => "SnpEff Impact".to_string(),
             HeaderType::SnpEff => "SnpEff".to_string(),
+            HeaderType::Annotation(key, _, name) => {
+                format!("{}__{}", String::from_utf8_lossy(key), name)
+            }
             HeaderType::Empty => "".to_string(),
         }
     }
 }
 
-pub fn create_header_line(header: &VCFHeader, config: &VCF2CSVConfig) -> Vec<HeaderType> {
+pub fn create_header_line(
+    header: &VCFHeader,
+    config: &VCF2CSVConfig,
+) -> Result<Vec<HeaderType>, VCFUtilsError> {
     let mut header_items = vec![HeaderType::VcfLine];
+    let annotation = AnnotationFormat::detect(header, config.annotation_key.as_deref());
 
     if config.group_names.is_some() {
         header_items.push(HeaderType::GroupName);
@@ -212,11 +260,27 @@ pub fn create_header_line(header: &VCFHeader, config: &VCF2CSVConfig) -> Vec<Hea
         HeaderType::ALT,
     ]);
 
-    if header.info(b"ANN").is_some() {
-        if config.canonical_list.is_some() {
+    if config.structural_variant {
+        header_items.append(&mut vec![
+            HeaderType::SvType,
+            HeaderType::SvStart,
+            HeaderType::SvEnd,
+            HeaderType::SvLength,
+            HeaderType::MateCHROM,
+            HeaderType::MatePOS,
+        ]);
+        if config.sv_gene_regions.is_some() {
+            header_items.push(HeaderType::SvGenes);
+        }
+    }
+
+    if let Some(annotation) = annotation.as_ref().filter(|_| !config.long_format) {
+        if config.canonical_list.is_some() && annotation.transcript.is_some() {
             header_items.append(&mut vec![HeaderType::CanonicalChange]);
         };
-        header_items.append(&mut vec![HeaderType::SnpEffHighestImpact]);
+        if annotation.impact.is_some() {
+            header_items.append(&mut vec![HeaderType::SnpEffHighestImpact]);
+        }
     }
 
     for (sample_index, one_sample) in header.samples().iter().enumerate() {
@@ -237,10 +301,9 @@ pub fn create_header_line(header: &VCFHeader, config: &VCF2CSVConfig) -> Vec<Hea
     }
 
     let add_info = |one_info: &[u8], header_items: &mut Vec<HeaderType>| {
-        let info = header.info(one_info).expect(&format!(
-            "{} is not found in format list",
-            String::from_utf8_lossy(one_info)
-        ));
+        let info = header.info(one_info).ok_or_else(|| {
+            VCFUtilsError::UndefinedHeaderKey(format!("INFO/{}", String::from_utf8_lossy(one_info)))
+        })?;
         match info.number {
             vcf::Number::Number(x) => {
                 for i in 0..*x {
@@ -279,16 +342,19 @@ pub fn create_header_line(header: &VCFHeader, config: &VCF2CSVConfig) -> Vec<Hea
                 ));
             }
         }
+        Ok::<_, VCFUtilsError>(())
     };
 
     let add_format = |sample_index: usize,
                       one_sample: &[u8],
                       one_format: &[u8],
                       header_items: &mut Vec<HeaderType>| {
-        let format = header.format(one_format).expect(&format!(
-            "{} is not found in format list",
-            String::from_utf8_lossy(one_format)
-        ));
+        let format = header.format(one_format).ok_or_else(|| {
+            VCFUtilsError::UndefinedHeaderKey(format!(
+                "FORMAT/{}",
+                String::from_utf8_lossy(one_format)
+            ))
+        })?;
         match format.number {
             vcf::Number::Number(x) => {
                 for i in 0..*x {
@@ -351,26 +417,55 @@ pub fn create_header_line(header: &VCFHeader, config: &VCF2CSVConfig) -> Vec<Hea
                 ));
             }
         }
+        Ok::<_, VCFUtilsError>(())
     };
 
     for one_info in &config.priority_info_list {
-        add_info(one_info, &mut header_items);
+        add_info(one_info, &mut header_items)?;
     }
 
     for (sample_index, one_sample) in header.samples().iter().enumerate() {
         for one_format in &config.priority_format_list {
-            add_format(sample_index, &one_sample, &one_format, &mut header_items);
+            add_format(sample_index, &one_sample, &one_format, &mut header_items)?;
         }
     }
 
-    if header.info(b"ANN").is_some() {
-        header_items.append(&mut vec![
-            HeaderType::SnpEff,
-            HeaderType::SnpEffImpact(SnpEffImpact::High),
-            HeaderType::SnpEffImpact(SnpEffImpact::Moderate),
-            HeaderType::SnpEffImpact(SnpEffImpact::Low),
-            HeaderType::SnpEffImpact(SnpEffImpact::Modifier),
-        ]);
+    if let Some(annotation) = annotation.as_ref() {
+        if config.long_format {
+            if config.annotation_fields.is_empty() {
+                for (index, name) in annotation.fields.iter().enumerate() {
+                    header_items.push(HeaderType::Annotation(
+                        annotation.key.clone(),
+                        index,
+                        name.clone(),
+                    ));
+                }
+            }
+        } else if annotation.transcript.is_some() {
+            header_items.push(HeaderType::SnpEff);
+        }
+        if annotation.impact.is_some() && annotation.gene.is_some() && !config.long_format {
+            header_items.append(&mut vec![
+                HeaderType::SnpEffImpact(SnpEffImpact::High),
+                HeaderType::SnpEffImpact(SnpEffImpact::Moderate),
+                HeaderType::SnpEffImpact(SnpEffImpact::Low),
+                HeaderType::SnpEffImpact(SnpEffImpact::Modifier),
+            ]);
+        }
+        for one_field in &config.annotation_fields {
+            let index = annotation.field_index(one_field).ok_or_else(|| {
+                VCFUtilsError::AnnotationFieldNotFound(format!(
+                    "{} in {}",
+                    String::from_utf8_lossy(one_field),
+                    String::from_utf8_lossy(&annotation.key)
+                ))
+            })?;
+            header_items.push(HeaderType::Annotation(
+                annotation.key.clone(),
+                index,
+                annotation.fields[index].clone(),
+            ));
+        }
 
         // if config.canonical_list.is_some() {
         //     header_items.insert(2, HeaderType::CanonicalChange);
@@ -384,16 +479,16 @@ pub fn create_header_line(header: &VCFHeader, config: &VCF2CSVConfig) -> Vec<Hea
     header_items.append(&mut vec![HeaderType::QUAL, HeaderType::FILTER]);
 
     for one_info in &config.info_list {
-        add_info(one_info, &mut header_items);
+        add_info(one_info, &mut header_items)?;
     }
 
     for (sample_index, one_sample) in header.samples().iter().enumerate() {
         for one_format in &config.format_list {
-            add_format(sample_index, &one_sample, &one_format, &mut header_items);
+            add_format(sample_index, &one_sample, &one_format, &mut header_items)?;
         }
     }
 
-    header_items
+    Ok(header_items)
 }
 
 fn write_comma_separated_values(writer: &mut U8Vec, values: &[U8Vec]) {
@@ -452,16 +547,38 @@ fn write_value_for_alt_index(
     }
 }
 
-fn write_value_for_snpeff_ann(
+/// Field of an annotation entry. Missing fields are treated as empty.
+fn annotation_field<'a>(entry: &[&'a [u8]], index: Option<usize>) -> &'a [u8] {
+    index.and_then(|x| entry.get(x)).copied().unwrap_or(b"")
+}
+
+/// An entry is canonical if its transcript is in `canonical_list` or it is flagged with VEP
+/// `CANONICAL=YES`. All entries are canonical if `canonical_list` is `None`.
+fn is_canonical(
+    annotation: &AnnotationFormat,
+    entry: &[&[u8]],
+    canonical_list: Option<&HashSet<U8Vec>>,
+) -> bool {
+    canonical_list
+        .map(|x| {
+            (annotation.canonical.is_some()
+                && annotation_field(entry, annotation.canonical) == b"YES")
+                || x.contains(annotation_field(entry, annotation.transcript))
+        })
+        .unwrap_or(true)
+}
+
+fn write_value_for_annotation(
     record: &VCFRecord,
     writer: &mut U8Vec,
     alt_index: usize,
+    annotation: &AnnotationFormat,
 ) -> Result<(), VCFUtilsError> {
     let mut first_record = true;
-    if let Some(snpeff_record) = record.info(b"ANN") {
-        for one in snpeff_record {
-            let mut ann = one.splitn(2, |x| *x == b'|');
-            if ann.next() == Some(&record.alternative[alt_index]) {
+    if let Some(annotation_record) = record.info(&annotation.key) {
+        for one in annotation_record {
+            let entry: Vec<_> = one.split(|x| *x == b'|').collect();
+            if annotation.is_allele_matched(record, &entry, Some(alt_index)) {
                 if first_record {
                     first_record = false;
                 } else {
@@ -478,54 +595,64 @@ fn write_canonical_snpeff(
     record: &VCFRecord,
     writer: &mut U8Vec,
     alt_index: Option<usize>,
+    annotation: &AnnotationFormat,
     canonical_list: Option<&HashSet<U8Vec>>,
 ) -> Result<(), VCFUtilsError> {
     if let Some(canonical_list) = canonical_list {
-        if let Some(snpeff_record) = record.info(b"ANN") {
-            let alt_genotype: Option<&[u8]> = alt_index.map(|x| &record.alternative[x][..]);
-            let mut first = true;
-
-            let snpeff_parsed = snpeff_record
-                .iter()
-                .map(|x| x.split(|y| *y == b'|').collect::<Vec<_>>())
-                .filter(|x| alt_genotype.is_none() || alt_genotype == Some(x[0]))
-                .filter(|x| canonical_list.contains(x[6]))
-                .collect::<Vec<_>>();
-
-            let mut highest_impact = SnpEffImpact::Modifier;
+        let mut first = true;
+
+        let snpeff_parsed = annotation
+            .entries(record, alt_index)
+            .into_iter()
+            .filter(|x| is_canonical(annotation, x, Some(canonical_list)))
+            .collect::<Vec<_>>();
+
+        let mut highest_impact = SnpEffImpact::Modifier;
+        if annotation.impact.is_some() {
             for one in snpeff_parsed.iter() {
-                highest_impact = highest_impact.min(one[2].try_into()?);
+                highest_impact =
+                    highest_impact.min(annotation_field(one, annotation.impact).try_into()?);
             }
+        }
 
-            let have_protein_changed = snpeff_parsed.iter().any(|x| !x[10].is_empty());
-
-            for ann in snpeff_parsed {
-                if !have_protein_changed || !ann[10].is_empty() {
-                    let transcript = ann[6];
-                    let gene = ann[3];
-                    let impact: SnpEffImpact = ann[2].try_into()?;
+        let have_protein_changed = snpeff_parsed
+            .iter()
+            .any(|x| !annotation_field(x, annotation.protein_change).is_empty());
+
+        for ann in snpeff_parsed {
+            let protein_change = annotation_field(&ann, annotation.protein_change);
+            if !have_protein_changed || !protein_change.is_empty() {
+                let transcript = annotation_field(&ann, annotation.transcript);
+                let gene = annotation_field(&ann, annotation.gene);
+                if annotation.impact.is_some() {
+                    let impact: SnpEffImpact =
+                        annotation_field(&ann, annotation.impact).try_into()?;
                     if (impact < SnpEffImpact::Moderate && impact < highest_impact)
                         || impact == SnpEffImpact::Modifier
                     {
                         continue;
                     }
+                }
 
-                    if first {
-                        first = false;
-                    } else {
-                        write!(writer, ", ")?;
-                    }
+                if first {
+                    first = false;
+                } else {
+                    write!(writer, ", ")?;
+                }
+                write!(
+                    writer,
+                    "{}({}):",
+                    str::from_utf8(transcript)?,
+                    str::from_utf8(gene)?
+                )?;
+                if protein_change.is_empty() {
                     write!(
                         writer,
-                        "{}({}):",
-                        str::from_utf8(transcript)?,
-                        str::from_utf8(gene)?
+                        "{}",
+                        str::from_utf8(annotation_field(&ann, annotation.cds_change))?
                     )?;
-                    if ann[10].is_empty() {
-                        write!(writer, "{}", str::from_utf8(ann[9])?)?;
-                    } else {
-                        write!(writer, "{}", str::from_utf8(ann[10])?)?
-                    }
+                } else {
+                    write!(writer, "{}", str::from_utf8(protein_change)?)?
                 }
             }
         }
@@ -537,29 +664,24 @@ fn write_snpeff_all(
     record: &VCFRecord,
     writer: &mut U8Vec,
     alt_index: Option<usize>,
+    annotation: &AnnotationFormat,
 ) -> Result<(), VCFUtilsError> {
-    if let Some(snpeff_record) = record.info(b"ANN") {
-        let alt_genotype: Option<&[u8]> = alt_index.map(|x| &record.alternative[x][..]);
-        let mut first = true;
-        for one in snpeff_record {
-            let ann: Vec<_> = one.split(|x| *x == b'|').collect();
-            let genotype = ann[0];
-            if alt_genotype.is_none() || alt_genotype == Some(genotype) {
-                if first {
-                    first = false;
-                } else {
-                    writer.extend_from_slice(b", ");
-                }
-                writer.extend_from_slice(ann[6]);
-                writer.extend_from_slice(b"(");
-                writer.extend_from_slice(ann[3]);
-                writer.extend_from_slice(b"):");
-                if ann[10].is_empty() {
-                    writer.extend_from_slice(ann[9]);
-                } else {
-                    writer.extend_from_slice(ann[10]);
-                }
-            }
+    let mut first = true;
+    for ann in annotation.entries(record, alt_index) {
+        if first {
+            first = false;
+        } else {
+            writer.extend_from_slice(b", ");
+        }
+        writer.extend_from_slice(annotation_field(&ann, annotation.transcript));
+        writer.extend_from_slice(b"(");
+        writer.extend_from_slice(annotation_field(&ann, annotation.gene));
+        writer.extend_from_slice(b"):");
+        let protein_change = annotation_field(&ann, annotation.protein_change);
+        if protein_change.is_empty() {
+            writer.extend_from_slice(annotation_field(&ann, annotation.cds_change));
+        } else {
+            writer.extend_from_slice(protein_change);
         }
     }
 
@@ -571,32 +693,26 @@ fn write_impact_gene(
     writer: &mut U8Vec,
     alt_index: Option<usize>,
     header: &HeaderType,
+    annotation: &AnnotationFormat,
 ) -> Result<(), VCFUtilsError> {
-    if let Some(snpeff_record) = record.info(b"ANN") {
-        let alt_genotype: Option<&[u8]> = alt_index.map(|x| &record.alternative[x][..]);
-        let mut genes: HashSet<U8Vec> = HashSet::new();
-        for one in snpeff_record {
-            let ann: Vec<_> = one.split(|x| *x == b'|').collect();
-            let genotype = ann[0];
-            if alt_genotype.is_none() || alt_genotype == Some(genotype) {
-                match header {
-                    HeaderType::SnpEffImpact(impact) => {
-                        if ann[2] == impact.to_str().as_bytes() {
-                            genes.insert(ann[3].to_vec());
-                        }
-                    }
-                    _ => unreachable!(),
+    let mut genes: HashSet<U8Vec> = HashSet::new();
+    for ann in annotation.entries(record, alt_index) {
+        match header {
+            HeaderType::SnpEffImpact(impact) => {
+                if annotation_field(&ann, annotation.impact) == impact.to_str().as_bytes() {
+                    genes.insert(annotation_field(&ann, annotation.gene).to_vec());
                 }
             }
+            _ => unreachable!(),
         }
-        let mut gene_list: Vec<_> = genes.iter().collect();
-        gene_list.sort();
-        for (i, one) in gene_list.iter().enumerate() {
-            if i != 0 {
-                writer.extend_from_slice(b", ");
-            }
-            writer.extend_from_slice(one);
+    }
+    let mut gene_list: Vec<_> = genes.iter().collect();
+    gene_list.sort();
+    for (i, one) in gene_list.iter().enumerate() {
+        if i != 0 {
+            writer.extend_from_slice(b", ");
         }
+        writer.extend_from_slice(one);
     }
     Ok(())
 }
@@ -605,21 +721,17 @@ fn write_snpeff_impact(
     record: &VCFRecord,
     writer: &mut U8Vec,
     alt_index: Option<usize>,
+    annotation: &AnnotationFormat,
     canonical_list: Option<&HashSet<U8Vec>>,
 ) -> Result<(), VCFUtilsError> {
-    if let Some(snpeff_record) = record.info(b"ANN") {
-        let alt_genotype: Option<&[u8]> = alt_index.map(|x| &record.alternative[x][..]);
-
-        let snpeff_parsed = snpeff_record
-            .iter()
-            .map(|x| x.split(|y| *y == b'|').collect::<Vec<_>>())
-            .filter(|x| alt_genotype.is_none() || alt_genotype == Some(x[0]))
-            .filter(|x| canonical_list.map(|y| y.contains(x[6])).unwrap_or(true))
-            .collect::<Vec<_>>();
-
+    if record.info(&annotation.key).is_some() {
         let mut impact = SnpEffImpact::Modifier;
-        for one in snpeff_parsed.iter() {
-            impact = impact.min(one[2].try_into()?);
+        for one in annotation
+            .entries(record, alt_index)
+            .iter()
+            .filter(|x| is_canonical(annotation, x, canonical_list))
+        {
+            impact = impact.min(annotation_field(one, annotation.impact).try_into()?);
         }
         write!(writer, "{}", impact.to_str())?;
     }
@@ -631,46 +743,70 @@ fn write_canonical_gene(
     writer: &mut U8Vec,
     alt_index: Option<usize>,
     header: &HeaderType,
+    annotation: &AnnotationFormat,
     canonical_list: Option<&HashSet<U8Vec>>,
 ) -> Result<(), VCFUtilsError> {
-    if let Some(snpeff_record) = record.info(b"ANN") {
-        let alt_genotype: Option<&[u8]> = alt_index.map(|x| &record.alternative[x][..]);
-        let mut first = true;
-        for one in snpeff_record {
-            let ann: Vec<_> = one.split(|x| *x == b'|').collect();
-            let genotype = ann[0];
-            let transcript = ann[6];
-            if (alt_genotype.is_none() || alt_genotype == Some(genotype))
-                && canonical_list
-                    .map(|x| x.contains(transcript))
-                    .unwrap_or(true)
-            {
-                if first {
-                    first = false;
+    let mut first = true;
+    for ann in annotation
+        .entries(record, alt_index)
+        .iter()
+        .filter(|x| is_canonical(annotation, x, canonical_list))
+    {
+        if first {
+            first = false;
+        } else {
+            write!(writer, ", ")?;
+        }
+        let gene = annotation_field(ann, annotation.gene);
+        let cds_change = annotation_field(ann, annotation.cds_change);
+        let protein_change = annotation_field(ann, annotation.protein_change);
+        match header {
+            HeaderType::GeneChange => {
+                write!(writer, "{} ", str::from_utf8(gene)?)?;
+                if protein_change.is_empty() {
+                    write!(writer, "{}", str::from_utf8(cds_change)?)?;
                 } else {
-                    write!(writer, ", ")?;
-                }
-                match header {
-                    HeaderType::GeneChange => {
-                        write!(writer, "{} ", str::from_utf8(ann[3])?)?;
-                        if ann[10].is_empty() {
-                            write!(writer, "{}", str::from_utf8(ann[9])?)?;
-                        } else {
-                            write!(writer, "{}", str::from_utf8(ann[10])?)?
-                        }
-                    }
-                    HeaderType::GeneName => write!(writer, "{}", str::from_utf8(ann[3])?)?,
-                    HeaderType::TranscriptName => write!(writer, "{}", str::from_utf8(ann[6])?)?,
-                    HeaderType::AminoChange => write!(writer, "{}", str::from_utf8(ann[10])?)?,
-                    HeaderType::CDSChange => write!(writer, "{}", str::from_utf8(ann[9])?)?,
-                    _ => unreachable!(),
+                    write!(writer, "{}", str::from_utf8(protein_change)?)?
                 }
             }
+            HeaderType::GeneName => write!(writer, "{}", str::from_utf8(gene)?)?,
+            HeaderType::TranscriptName => write!(
+                writer,
+                "{}",
+                str::from_utf8(annotation_field(ann, annotation.transcript))?
+            )?,
+            HeaderType::AminoChange => write!(writer, "{}", str::from_utf8(protein_change)?)?,
+            HeaderType::CDSChange => write!(writer, "{}", str::from_utf8(cds_change)?)?,
+            _ => unreachable!(),
         }
     }
     Ok(())
 }
 
+/// Write one subfield of annotation entries for the allele. Values are listed in the order of
+/// entries, so columns of the same annotation can be matched by position.
+fn write_annotation_field(
+    record: &VCFRecord,
+    writer: &mut U8Vec,
+    alt_index: Option<usize>,
+    annotation: &AnnotationFormat,
+    field_index: usize,
+    canonical_list: Option<&HashSet<U8Vec>>,
+) -> Result<(), VCFUtilsError> {
+    for (i, ann) in annotation
+        .entries(record, alt_index)
+        .iter()
+        .filter(|x| is_canonical(annotation, x, canonical_list))
+        .enumerate()
+    {
+        if i != 0 {
+            writer.extend_from_slice(b", ");
+        }
+        writer.extend_from_slice(annotation_field(ann, Some(field_index)));
+    }
+    Ok(())
+}
+
 fn genotype_for_index(record: &VCFRecord, index: usize) -> &[u8] {
     if index == 0 {
         &record.reference[..]
@@ -721,16 +857,85 @@ fn write_value_for_decoded_genotype(
     Ok(())
 }
 
+/// Write a structural variant column. Values of alleles are separated by comma if `alt_index`
+/// is not given. Columns of records without structural variant alleles are left empty.
+fn write_structural_variant(
+    writer: &mut U8Vec,
+    header: &HeaderType,
+    structural_variants: &[Option<StructuralVariant>],
+    alt_index: Option<usize>,
+) -> Result<(), VCFUtilsError> {
+    let structural_variants = if let Some(alt_index) = alt_index {
+        structural_variants
+            .get(alt_index..=alt_index)
+            .unwrap_or_default()
+    } else {
+        structural_variants
+    };
+    if structural_variants.iter().all(|x| x.is_none()) {
+        return Ok(());
+    }
+    for (i, one) in structural_variants.iter().enumerate() {
+        if i != 0 {
+            writer.push(b',');
+        }
+        let sv = if let Some(sv) = one {
+            sv
+        } else {
+            continue;
+        };
+        match header {
+            HeaderType::SvType => writer.extend_from_slice(&sv.sv_type),
+            HeaderType::SvStart => write!(writer, "{}", sv.start)?,
+            HeaderType::SvEnd => {
+                if let Some(end) = sv.end {
+                    write!(writer, "{}", end)?;
+                }
+            }
+            HeaderType::SvLength => {
+                if let Some(length) = sv.length {
+                    write!(writer, "{}", length)?;
+                }
+            }
+            HeaderType::MateCHROM => {
+                if let Some(mate) = sv.mate.as_ref() {
+                    writer.extend_from_slice(&mate.chromosome);
+                }
+            }
+            HeaderType::MatePOS => {
+                if let Some(mate) = sv.mate.as_ref() {
+                    write!(writer, "{}", mate.position)?;
+                }
+            }
+            HeaderType::SvGenes => {
+                for (j, gene) in sv.genes.iter().enumerate() {
+                    if j != 0 {
+                        writer.extend_from_slice(b", ");
+                    }
+                    writer.extend_from_slice(gene);
+                }
+            }
+            _ => unreachable!(),
+        }
+    }
+    Ok(())
+}
+
 pub fn vcf2table_set_data_type(
     header_contents: &[HeaderType],
-    writer: &mut XlsxSheetWriter,
+    writer: &mut XlsxWorkbookWriter,
 ) -> Result<(), VCFUtilsError> {
     let types: Vec<_> = header_contents
         .iter()
         .map(|x| match x {
-            HeaderType::POS | HeaderType::QUAL | HeaderType::VcfLine | HeaderType::AltIndex => {
-                XlsxDataType::Number
-            }
+            HeaderType::POS
+            | HeaderType::QUAL
+            | HeaderType::VcfLine
+            | HeaderType::AltIndex
+            | HeaderType::SvStart
+            | HeaderType::SvEnd
+            | HeaderType::SvLength
+            | HeaderType::MatePOS => XlsxDataType::Number,
             HeaderType::Info(_, _, t, _, _) => match t {
                 vcf::ValueType::Float | vcf::ValueType::Integer => XlsxDataType::Number,
                 _ => XlsxDataType::String,
@@ -759,8 +964,18 @@ pub fn vcf2table_set_data_type(
                 let name = replaced_sample_name.as_ref().unwrap_or(sample_name);
                 format!("Variant Allele Frequency for {}", String::from_utf8_lossy(name))
             }
+            HeaderType::Annotation(key, _, name) => {
+                format!("{} in {} annotation", name, String::from_utf8_lossy(key))
+            }
             HeaderType::CanonicalChange => "Canonical transcript change".to_string(),
             HeaderType::SnpEffHighestImpact => "Highest impact of SnpEff".to_string(),
+            HeaderType::SvType => "SVTYPE, or type of symbolic or breakend allele".to_string(),
+            HeaderType::SvEnd => "END, or end of reference allele".to_string(),
+            HeaderType::SvLength => "Absolute value of SVLEN, or length of SV".to_string(),
+            HeaderType::MateCHROM | HeaderType::MatePOS => {
+                "Mate breakend from ALT notation, or CHR2 and POS2/END".to_string()
+            }
+            HeaderType::SvGenes => "Genes overlapping SV or breakends".to_string(),
             _ => "".to_string(),
         })
         .collect();
@@ -769,6 +984,155 @@ pub fn vcf2table_set_data_type(
     Ok(())
 }
 
+/// Set hyperlink templates of columns. ID column is linked to dbSNP, CLNVID INFO to ClinVar and
+/// gene columns to gnomAD by default. `custom` templates are specified with column names, and
+/// replace the default templates.
+pub fn vcf2table_set_hyperlinks(
+    header_contents: &[HeaderType],
+    custom: &[(String, HyperlinkTemplate)],
+    use_default: bool,
+    writer: &mut XlsxWorkbookWriter,
+) {
+    let hyperlinks: Vec<_> = header_contents
+        .iter()
+        .map(|x| {
+            let name = x.to_string();
+            if let Some((_, template)) = custom.iter().find(|(column, _)| *column == name) {
+                return Some(template.clone());
+            }
+            if !use_default {
+                return None;
+            }
+            match x {
+                HeaderType::ID => Some(HyperlinkTemplate::DbSnp),
+                HeaderType::Info(id, _, _, _, _) if id == b"CLNVID" => {
+                    Some(HyperlinkTemplate::ClinVar)
+                }
+                HeaderType::GeneName => Some(HyperlinkTemplate::GnomadGene),
+                HeaderType::Annotation(_, _, field)
+                    if GENE_FIELD_NAMES.contains(&field.as_str()) || field == "Gene" =>
+                {
+                    Some(HyperlinkTemplate::GnomadGene)
+                }
+                _ => None,
+            }
+        })
+        .collect();
+    writer.set_hyperlinks(&hyperlinks);
+}
+
+/// Freeze header row and leading columns, enable autofilter, and add conditional formatting to
+/// impact and VAF columns. Leading columns up to ALT are frozen if `frozen_columns` is not given.
+pub fn vcf2table_format_sheet(
+    sheet: &mut xlsxwriter::Worksheet,
+    header_contents: &[HeaderType],
+    row_count: u32,
+    frozen_columns: Option<u16>,
+) -> Result<(), VCFUtilsError> {
+    let frozen_columns = frozen_columns.unwrap_or_else(|| {
+        header_contents
+            .iter()
+            .position(|x| *x == HeaderType::ALT)
+            .map(|x| x as u16 + 1)
+            .unwrap_or(0)
+    });
+    sheet.freeze_panes(1, frozen_columns);
+    let last_column = (header_contents.len() - 1) as u16;
+    sheet.autofilter(0, 0, row_count, last_column)?;
+    if row_count == 0 {
+        return Ok(());
+    }
+
+    let impact_formats: Vec<_> = [
+        (SnpEffImpact::High, 0x9C0006, 0xFFC7CE),
+        (SnpEffImpact::Moderate, 0x9C5700, 0xFFEB9C),
+        (SnpEffImpact::Low, 0x006100, 0xC6EFCE),
+    ]
+    .iter()
+    .map(|(impact, font_color, bg_color)| {
+        let mut format = xlsxwriter::Format::new();
+        format
+            .set_font_color(FormatColor::Custom(*font_color))
+            .set_bg_color(FormatColor::Custom(*bg_color));
+        ConditionalFormat::text_containing(impact.to_str(), &format)
+    })
+    .collect();
+    let vaf_format = ConditionalFormat::two_color_scale(
+        ConditionalFormatRuleTypes::Number,
+        ConditionalFormatRuleTypes::Number,
+        0.,
+        1.,
+        FormatColor::White,
+        FormatColor::Custom(0xF8696B),
+    );
+
+    for (i, one) in header_contents.iter().enumerate() {
+        let column = i as u16;
+        match one {
+            HeaderType::SnpEffHighestImpact => {
+                for format in impact_formats.iter() {
+                    sheet.conditional_format_range(1, column, row_count, column, format)?;
+                }
+            }
+            HeaderType::VAF(_, _, _) => {
+                sheet.conditional_format_range(1, column, row_count, column, &vaf_format)?;
+            }
+            _ => (),
+        }
+    }
+    Ok(())
+}
+
+/// Column types for writers with typed columns. Numeric INFO and FORMAT columns are typed only
+/// when each cell contains a single value, otherwise they are written as string.
+pub fn vcf2table_column_types(
+    header_contents: &[HeaderType],
+    split_multi_allelic: bool,
+) -> Vec<ColumnType> {
+    let value_column_type = |number: &vcf::Number, value_type: &vcf::ValueType, index: i32| {
+        let single_value = match number {
+            vcf::Number::Number(_) => true,
+            vcf::Number::Reference => index == 0 || split_multi_allelic,
+            vcf::Number::Allele => split_multi_allelic,
+            _ => false,
+        };
+        match value_type {
+            vcf::ValueType::Flag => ColumnType::Boolean,
+            vcf::ValueType::Integer if single_value => ColumnType::Integer,
+            vcf::ValueType::Float if single_value => ColumnType::Float,
+            _ => ColumnType::String,
+        }
+    };
+
+    header_contents
+        .iter()
+        .map(|x| match x {
+            HeaderType::POS | HeaderType::VcfLine | HeaderType::AltIndex => ColumnType::Integer,
+            HeaderType::QUAL => ColumnType::Float,
+            HeaderType::Info(_, number, value_type, index, _) => {
+                value_column_type(number, value_type, *index)
+            }
+            HeaderType::Genotype(_, key, number, value_type, index, _, _) => {
+                if key == b"GT" || key == b"PGT" {
+                    ColumnType::String
+                } else {
+                    value_column_type(number, value_type, *index)
+                }
+            }
+            HeaderType::VAF(_, _, _) if split_multi_allelic => ColumnType::Float,
+            HeaderType::SvStart
+            | HeaderType::SvEnd
+            | HeaderType::SvLength
+            | HeaderType::MatePOS
+                if split_multi_allelic =>
+            {
+                ColumnType::Integer
+            }
+            _ => ColumnType::String,
+        })
+        .collect()
+}
+
 fn setup_row(
     group_name: Option<&U8Vec>,
     header_contents: &[HeaderType],
@@ -777,8 +1141,11 @@ fn setup_row(
     index: u32,
     alt_index: Option<usize>,
     translate_genotype: bool,
+    annotation: Option<&AnnotationFormat>,
+    annotation_entry: Option<&[&[u8]]>,
     canonical_list: Option<&HashSet<U8Vec>>,
     is_formula_compatible: bool,
+    structural_variants: &[Option<StructuralVariant>],
 ) -> anyhow::Result<()> {
     for (header, column) in header_contents.iter().zip(row.iter_mut()) {
         column.clear();
@@ -797,22 +1164,25 @@ fn setup_row(
                 }
             }
             HeaderType::CanonicalChange => {
-                write_canonical_snpeff(record, column, alt_index, canonical_list)?
-            }
-            HeaderType::GeneChange => {
-                write_canonical_gene(record, column, alt_index, header, canonical_list)?
-            }
-            HeaderType::GeneName => {
-                write_canonical_gene(record, column, alt_index, header, canonical_list)?
-            }
-            HeaderType::TranscriptName => {
-                write_canonical_gene(record, column, alt_index, header, canonical_list)?
-            }
-            HeaderType::AminoChange => {
-                write_canonical_gene(record, column, alt_index, header, canonical_list)?
+                if let Some(annotation) = annotation {
+                    write_canonical_snpeff(record, column, alt_index, annotation, canonical_list)?
+                }
             }
-            HeaderType::CDSChange => {
-                write_canonical_gene(record, column, alt_index, header, canonical_list)?
+            HeaderType::GeneChange
+            | HeaderType::GeneName
+            | HeaderType::TranscriptName
+            | HeaderType::AminoChange
+            | HeaderType::CDSChange => {
+                if let Some(annotation) = annotation {
+                    write_canonical_gene(
+                        record,
+                        column,
+                        alt_index,
+                        header,
+                        annotation,
+                        canonical_list,
+                    )?
+                }
             }
             HeaderType::CHROM => column.extend_from_slice(&record.chromosome),
             HeaderType::POS => {
@@ -829,6 +1199,15 @@ fn setup_row(
                     write_comma_separated_values(column, &record.alternative);
                 }
             }
+            HeaderType::SvType
+            | HeaderType::SvStart
+            | HeaderType::SvEnd
+            | HeaderType::SvLength
+            | HeaderType::MateCHROM
+            | HeaderType::MatePOS
+            | HeaderType::SvGenes => {
+                write_structural_variant(column, header, structural_variants, alt_index)?;
+            }
             HeaderType::QUAL => {
                 if let Some(q) = record.qual {
                     write!(column, "{}", q)?;
@@ -845,9 +1224,10 @@ fn setup_row(
                         write!(column, "FALSE")?;
                     }
                 } else if let Some(values) = record.info(key) {
-                    if key == b"ANN" && number == &vcf::Number::Unknown {
+                    let annotation = annotation.filter(|x| &x.key == key);
+                    if let (Some(annotation), vcf::Number::Unknown) = (annotation, number) {
                         if let Some(alt_index) = alt_index {
-                            write_value_for_snpeff_ann(record, column, alt_index)?;
+                            write_value_for_annotation(record, column, alt_index, annotation)?;
                         } else {
                             write_value_for_alt_index(column, values, number, *index, alt_index);
                         }
@@ -916,13 +1296,33 @@ fn setup_row(
                 //let dp = record.genotype(sample_name, b"AD").map(|x| x.get(alt_index));
             }
             HeaderType::SnpEffHighestImpact => {
-                write_snpeff_impact(record, column, alt_index, canonical_list)?;
+                if let Some(annotation) = annotation {
+                    write_snpeff_impact(record, column, alt_index, annotation, canonical_list)?;
+                }
             }
             HeaderType::SnpEffImpact(_) => {
-                write_impact_gene(record, column, alt_index, header)?;
+                if let Some(annotation) = annotation {
+                    write_impact_gene(record, column, alt_index, header, annotation)?;
+                }
             }
             HeaderType::SnpEff => {
-                write_snpeff_all(record, column, alt_index)?;
+                if let Some(annotation) = annotation {
+                    write_snpeff_all(record, column, alt_index, annotation)?;
+                }
+            }
+            HeaderType::Annotation(_, field_index, _) => {
+                if let Some(entry) = annotation_entry {
+                    column.extend_from_slice(annotation_field(entry, Some(*field_index)));
+                } else if let Some(annotation) = annotation {
+                    write_annotation_field(
+                        record,
+                        column,
+                        alt_index,
+                        annotation,
+                        *field_index,
+                        canonical_list,
+                    )?;
+                }
             }
             HeaderType::Empty => {}
         }
@@ -956,49 +1356,144 @@ pub fn vcf2table<R: BufRead, W: TableWriter>(
     //     header_contents.len(),
     //     header_contents
     // );
-    let mut row: Vec<U8Vec> = header_contents.iter().map(|_| Vec::new()).collect();
+    let annotation =
+        AnnotationFormat::detect(vcf_reader.header(), config.annotation_key.as_deref());
+    let is_formula_compatible = writer.is_formula_compatible();
 
     let mut index: u32 = 0;
     let mut row_count = 0;
 
-    let mut record = VCFRecord::new(vcf_reader.header().clone());
-    while vcf_reader.next_record(&mut record)? {
-        index += 1;
-
-        if config.split_multi_allelic {
-            for (alt_index, _) in record.alternative.iter().enumerate() {
-                setup_row(
-                    group_name,
+    // Rows are created in parallel chunk by chunk, and written in the original order.
+    let chunk_size = record_chunk_size(vcf_reader.header().samples().len());
+    let mut chunk = Vec::new();
+    loop {
+        let count = read_record_chunk(vcf_reader, &mut chunk, chunk_size)?;
+        let chunk_rows = chunk[..count]
+            .par_iter()
+            .enumerate()
+            .map(|(i, record)| {
+                record_rows(
+                    record,
+                    index + i as u32 + 1,
                     header_contents,
-                    &record,
-                    &mut row,
-                    index,
-                    Some(alt_index),
-                    config.decoded_genotype,
-                    config.canonical_list.as_ref(),
-                    writer.is_formula_compatible(),
-                )?;
-                writer.write_row_bytes(&row.iter().map(|x| -> &[u8] { &x }).collect::<Vec<_>>())?;
-                row_count += 1;
+                    config,
+                    group_name,
+                    annotation.as_ref(),
+                    is_formula_compatible,
+                )
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        index += count as u32;
+
+        for row in chunk_rows.iter().flatten() {
+            writer.write_row_bytes(&row.iter().map(|x| -> &[u8] { x }).collect::<Vec<_>>())?;
+            row_count += 1;
+        }
+        if count < chunk_size {
+            break;
+        }
+    }
+
+    Ok(row_count)
+}
+
+/// Create rows of a record. `index` is 1-based record index in the VCF.
+fn record_rows(
+    record: &VCFRecord,
+    index: u32,
+    header_contents: &[HeaderType],
+    config: &VCF2CSVConfig,
+    group_name: Option<&U8Vec>,
+    annotation: Option<&AnnotationFormat>,
+    is_formula_compatible: bool,
+) -> anyhow::Result<Vec<Vec<U8Vec>>> {
+    // gVCF reference blocks are skipped, and non-reference alleles are not written.
+    let stripped_record;
+    let record = if record.alternative.iter().any(|x| is_non_ref_allele(x)) {
+        let mut one = record.clone();
+        if !remove_non_ref_alleles(&mut one) {
+            return Ok(Vec::new());
+        }
+        stripped_record = one;
+        &stripped_record
+    } else {
+        record
+    };
+
+    let mut rows = Vec::new();
+    let alt_indexes: Vec<_> = if config.split_multi_allelic {
+        (0..record.alternative.len()).map(Some).collect()
+    } else {
+        vec![None]
+    };
+
+    let structural_variants = if config.structural_variant {
+        (0..record.alternative.len())
+            .map(|i| {
+                let mut sv = parse_structural_variant(record, i)?;
+                if let (Some(sv), Some(gene_regions)) =
+                    (sv.as_mut(), config.sv_gene_regions.as_ref())
+                {
+                    gene_regions.annotate(&record.chromosome, sv);
+                }
+                Ok(sv)
+            })
+            .collect::<Result<Vec<_>, VCFUtilsError>>()?
+    } else {
+        Vec::new()
+    };
+
+    for alt_index in alt_indexes {
+        if let Some(variant_filter) = config.variant_filter.as_ref() {
+            if !variant_filter.is_matched(record, annotation, alt_index) {
+                continue;
             }
+        }
+
+        // In long format, a row is written for each annotation entry.
+        // Alleles without annotation are written as a row with empty annotation columns.
+        let entries = match annotation {
+            Some(annotation) if config.long_format => annotation
+                .entries(record, alt_index)
+                .into_iter()
+                .filter(|x| is_canonical(annotation, x, config.canonical_list.as_ref()))
+                .filter(|x| {
+                    config
+                        .variant_filter
+                        .as_ref()
+                        .map(|y| y.is_entry_matched(annotation, x))
+                        .unwrap_or(true)
+                })
+                .collect(),
+            _ => Vec::new(),
+        };
+        let entries: Vec<Option<&[&[u8]]>> = if entries.is_empty() {
+            vec![None]
         } else {
+            entries.iter().map(|x| Some(&x[..])).collect()
+        };
+
+        for entry in entries {
+            let mut row: Vec<U8Vec> = header_contents.iter().map(|_| Vec::new()).collect();
             setup_row(
                 group_name,
                 header_contents,
-                &record,
+                record,
                 &mut row,
                 index,
-                None,
+                alt_index,
                 config.decoded_genotype,
+                annotation,
+                entry,
                 config.canonical_list.as_ref(),
-                writer.is_formula_compatible(),
+                is_formula_compatible,
+                &structural_variants,
             )?;
-            writer.write_row_bytes(&row.iter().map(|x| -> &[u8] { &x }).collect::<Vec<_>>())?;
-            row_count += 1;
+            rows.push(row);
         }
     }
 
-    Ok(row_count)
+    Ok(rows)
 }
 
 pub fn merge_header_contents(original: &[HeaderType], new: &[HeaderType]) -> Vec<HeaderType> {
@@ -1075,6 +1570,7 @@ pub fn merge_header_contents(original: &[HeaderType], new: &[HeaderType]) -> Vec
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::utils::arrowwriter::{ArrowFileFormat, ArrowTableWriter};
     use crate::utils::tablewriter;
     use std::io::BufReader;
 
@@ -1149,11 +1645,17 @@ mod test {
             format_list: vec![b"AD".to_vec(), b"DP".to_vec(), b"GT".to_vec()],
             replace_sample_name: None,
             group_names: None,
+            annotation_key: None,
+            annotation_fields: Vec::new(),
+            long_format: false,
+            variant_filter: None,
+            structural_variant: false,
+            sv_gene_regions: None,
         };
         let mut vcf_data_reader = BufReader::new(&vcf_data[..]);
         let mut vcf_reader = vcf::VCFReader::new(&mut vcf_data_reader)?;
         let mut write_bytes = Vec::<u8>::new();
-        let header_contents = create_header_line(&vcf_reader.header(), &config);
+        let header_contents = create_header_line(&vcf_reader.header(), &config)?;
         vcf2table(
             &mut vcf_reader,
             &header_contents,
@@ -1195,11 +1697,17 @@ mod test {
             format_list: vec![b"AD".to_vec(), b"DP".to_vec(), b"GT".to_vec()],
             replace_sample_name: None,
             group_names: None,
+            annotation_key: None,
+            annotation_fields: Vec::new(),
+            long_format: false,
+            variant_filter: None,
+            structural_variant: false,
+            sv_gene_regions: None,
         };
         let mut vcf_data_reader = BufReader::new(&vcf_data[..]);
         let mut vcf_reader = vcf::VCFReader::new(&mut vcf_data_reader)?;
         let mut write_bytes = Vec::<u8>::new();
-        let header_contents = create_header_line(&vcf_reader.header(), &config);
+        let header_contents = create_header_line(&vcf_reader.header(), &config)?;
         vcf2table(
             &mut vcf_reader,
             &header_contents,
@@ -1240,11 +1748,17 @@ mod test {
             format_list: vec![b"AD".to_vec(), b"DP".to_vec(), b"GT".to_vec()],
             replace_sample_name: None,
             group_names: None,
+            annotation_key: None,
+            annotation_fields: Vec::new(),
+            long_format: false,
+            variant_filter: None,
+            structural_variant: false,
+            sv_gene_regions: None,
         };
         let mut vcf_data_reader = BufReader::new(&vcf_data[..]);
         let mut vcf_reader = vcf::VCFReader::new(&mut vcf_data_reader)?;
         let mut write_bytes = Vec::<u8>::new();
-        let header_contents = create_header_line(&vcf_reader.header(), &config);
+        let header_contents = create_header_line(&vcf_reader.header(), &config)?;
         vcf2table(
             &mut vcf_reader,
             &header_contents,
@@ -1263,6 +1777,241 @@ mod test {
         Ok(())
     }
 
+    #[test]
+    fn test_vcf2table_csv_split_multi_vep() -> anyhow::Result<()> {
+        let vcf_data = include_bytes!("../../testfiles/simple1-vep.vcf");
+        let config = VCF2CSVConfig {
+            split_multi_allelic: true,
+            decoded_genotype: false,
+            canonical_list: Some(HashSet::from([b"ENST00000380152".to_vec()])),
+            priority_info_list: Vec::new(),
+            priority_format_list: Vec::new(),
+            info_list: vec![b"AC".to_vec(), b"CSQ".to_vec()],
+            format_list: vec![b"GT".to_vec()],
+            replace_sample_name: None,
+            group_names: None,
+            annotation_key: None,
+            annotation_fields: vec![
+                b"Consequence".to_vec(),
+                b"SYMBOL".to_vec(),
+                b"HGVSp".to_vec(),
+            ],
+            long_format: false,
+            variant_filter: None,
+            structural_variant: false,
+            sv_gene_regions: None,
+        };
+        let mut vcf_data_reader = BufReader::new(&vcf_data[..]);
+        let mut vcf_reader = vcf::VCFReader::new(&mut vcf_data_reader)?;
+        let mut write_bytes = Vec::<u8>::new();
+        let header_contents = create_header_line(vcf_reader.header(), &config)?;
+        vcf2table(
+            &mut vcf_reader,
+            &header_contents,
+            &config,
+            None,
+            true,
+            &mut tablewriter::CSVWriter::new(&mut write_bytes),
+        )?;
+        std::fs::File::create("../target/split-vep.csv")?.write_all(&write_bytes)?;
+        assert_eq!(
+            &write_bytes[..],
+            &include_bytes!("../../testfiles/simple1-expected-multiallelic-split-vep.csv")[..]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_vcf2table_vep_canonical_flag() -> anyhow::Result<()> {
+        let vcf_data = include_bytes!("../../testfiles/simple1-vep.vcf");
+        let mut config = VCF2CSVConfig {
+            split_multi_allelic: true,
+            decoded_genotype: false,
+            canonical_list: Some(HashSet::new()),
+            priority_info_list: Vec::new(),
+            priority_format_list: Vec::new(),
+            info_list: Vec::new(),
+            format_list: Vec::new(),
+            replace_sample_name: None,
+            group_names: None,
+            annotation_key: Some(b"CSQ".to_vec()),
+            annotation_fields: vec![b"NOT_EXIST".to_vec()],
+            long_format: true,
+            variant_filter: None,
+            structural_variant: false,
+            sv_gene_regions: None,
+        };
+        let mut vcf_reader = vcf::VCFReader::new(&vcf_data[..])?;
+        assert!(matches!(
+            create_header_line(vcf_reader.header(), &config),
+            Err(VCFUtilsError::AnnotationFieldNotFound(_))
+        ));
+
+        config.annotation_fields = vec![b"Feature".to_vec()];
+        let header_contents = create_header_line(vcf_reader.header(), &config)?;
+        let mut write_bytes = Vec::<u8>::new();
+        vcf2table(
+            &mut vcf_reader,
+            &header_contents,
+            &config,
+            None,
+            true,
+            &mut tablewriter::CSVWriter::new(&mut write_bytes),
+        )?;
+        // Only entries flagged with CANONICAL=YES are written
+        let table = String::from_utf8(write_bytes)?;
+        let rows: Vec<_> = table.lines().skip(1).collect();
+        assert_eq!(rows.len(), 4);
+        assert!(rows
+            .iter()
+            .all(|x| x.contains(",ENST00000380152,") && !x.contains("ENST00000544455")));
+        Ok(())
+    }
+
+    #[test]
+    fn test_vcf2table_variant_filter() -> anyhow::Result<()> {
+        let vcf_data = include_bytes!("../../testfiles/simple1-vep.vcf");
+        let mut config = VCF2CSVConfig {
+            split_multi_allelic: true,
+            decoded_genotype: false,
+            canonical_list: None,
+            priority_info_list: Vec::new(),
+            priority_format_list: Vec::new(),
+            info_list: Vec::new(),
+            format_list: Vec::new(),
+            replace_sample_name: None,
+            group_names: None,
+            annotation_key: None,
+            annotation_fields: vec![b"SYMBOL".to_vec()],
+            long_format: true,
+            variant_filter: Some(VariantFilter::new(Some(&[b"ZAR1L".to_vec()]), None, 0)),
+            structural_variant: false,
+            sv_gene_regions: None,
+        };
+        let table = |config: &VCF2CSVConfig| -> anyhow::Result<String> {
+            let mut vcf_reader = vcf::VCFReader::new(&vcf_data[..])?;
+            let header_contents = create_header_line(vcf_reader.header(), config)?;
+            let mut write_bytes = Vec::<u8>::new();
+            vcf2table(
+                &mut vcf_reader,
+                &header_contents,
+                config,
+                None,
+                true,
+                &mut tablewriter::CSVWriter::new(&mut write_bytes),
+            )?;
+            Ok(String::from_utf8(write_bytes)?)
+        };
+
+        // Only annotation entries of genes in the list are written in long format
+        let result = table(&config)?;
+        let rows: Vec<_> = result.lines().skip(1).collect();
+        assert_eq!(rows.len(), 1);
+        assert!(rows[0].contains(",32889968,") && rows[0].contains(",ZAR1L,"));
+
+        config.long_format = false;
+        config.variant_filter = Some(VariantFilter::new(
+            None,
+            Some(&[(b"13".to_vec(), 32897000, 32900000)]),
+            0,
+        ));
+        let result = table(&config)?;
+        let rows: Vec<_> = result.lines().skip(1).collect();
+        assert_eq!(rows.len(), 1);
+        assert!(rows[0].contains(",32897288,"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_vcf2table_csv_long_format_snpeff() -> anyhow::Result<()> {
+        let vcf_data = include_bytes!("../../testfiles/simple1-snpeff.vcf");
+        let config = VCF2CSVConfig {
+            split_multi_allelic: true,
+            decoded_genotype: false,
+            canonical_list: None,
+            priority_info_list: Vec::new(),
+            priority_format_list: Vec::new(),
+            info_list: vec![b"AC".to_vec()],
+            format_list: vec![b"GT".to_vec()],
+            replace_sample_name: None,
+            group_names: None,
+            annotation_key: None,
+            annotation_fields: Vec::new(),
+            long_format: true,
+            variant_filter: None,
+            structural_variant: false,
+            sv_gene_regions: None,
+        };
+        let mut vcf_data_reader = BufReader::new(&vcf_data[..]);
+        let mut vcf_reader = vcf::VCFReader::new(&mut vcf_data_reader)?;
+        let mut write_bytes = Vec::<u8>::new();
+        let header_contents = create_header_line(vcf_reader.header(), &config)?;
+        assert!(!header_contents.contains(&HeaderType::SnpEff));
+        let row_count = vcf2table(
+            &mut vcf_reader,
+            &header_contents,
+            &config,
+            None,
+            true,
+            &mut tablewriter::CSVWriter::new(&mut write_bytes),
+        )?;
+        std::fs::File::create("../target/long-snpeff.csv")?.write_all(&write_bytes)?;
+        assert_eq!(
+            row_count as usize,
+            write_bytes.split(|x| *x == b'\n').count() - 2
+        );
+        assert_eq!(
+            &write_bytes[..],
+            &include_bytes!("../../testfiles/simple1-expected-long-snpeff.csv")[..]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_vcf2table_bcsq() -> anyhow::Result<()> {
+        let vcf_data = include_bytes!("../../testfiles/simple1-vep.vcf");
+        let config = VCF2CSVConfig {
+            split_multi_allelic: false,
+            decoded_genotype: false,
+            canonical_list: None,
+            priority_info_list: Vec::new(),
+            priority_format_list: Vec::new(),
+            info_list: Vec::new(),
+            format_list: Vec::new(),
+            replace_sample_name: None,
+            group_names: None,
+            annotation_key: Some(b"BCSQ".to_vec()),
+            annotation_fields: vec![b"amino_acid_change".to_vec()],
+            long_format: false,
+            variant_filter: None,
+            structural_variant: false,
+            sv_gene_regions: None,
+        };
+        let mut vcf_reader = vcf::VCFReader::new(&vcf_data[..])?;
+        let header_contents = create_header_line(vcf_reader.header(), &config)?;
+        assert!(!header_contents.contains(&HeaderType::SnpEffHighestImpact));
+        let mut write_bytes = Vec::<u8>::new();
+        vcf2table(
+            &mut vcf_reader,
+            &header_contents,
+            &config,
+            None,
+            true,
+            &mut tablewriter::CSVWriter::new(&mut write_bytes),
+        )?;
+        let table = String::from_utf8(write_bytes)?;
+        let mut lines = table.lines();
+        assert!(lines
+            .next()
+            .unwrap()
+            .ends_with("SnpEff,BCSQ__amino_acid_change,QUAL,FILTER"));
+        assert!(lines
+            .next()
+            .unwrap()
+            .ends_with("ENST00000380152(BRCA2):372N>372H,372N>372H,25743.5,"));
+        Ok(())
+    }
+
     #[test]
     fn test_vcf2table_csv_no_split() -> anyhow::Result<()> {
         let vcf_data = include_bytes!("../../testfiles/simple1.vcf");
@@ -1281,11 +2030,17 @@ mod test {
             format_list: vec![b"AD".to_vec(), b"DP".to_vec(), b"GT".to_vec()],
             replace_sample_name: None,
             group_names: None,
+            annotation_key: None,
+            annotation_fields: Vec::new(),
+            long_format: false,
+            variant_filter: None,
+            structural_variant: false,
+            sv_gene_regions: None,
         };
         let mut vcf_data_reader = BufReader::new(&vcf_data[..]);
         let mut vcf_reader = vcf::VCFReader::new(&mut vcf_data_reader)?;
         let mut write_bytes = Vec::<u8>::new();
-        let header_contents = create_header_line(&vcf_reader.header(), &config);
+        let header_contents = create_header_line(&vcf_reader.header(), &config)?;
         vcf2table(
             &mut vcf_reader,
             &header_contents,
@@ -1305,6 +2060,51 @@ mod test {
         Ok(())
     }
 
+    #[test]
+    fn test_vcf2table_csv_structural_variant() -> anyhow::Result<()> {
+        let vcf_data = include_bytes!("../../testfiles/sv.vcf");
+        let config = VCF2CSVConfig {
+            split_multi_allelic: true,
+            decoded_genotype: false,
+            canonical_list: None,
+            priority_info_list: Vec::new(),
+            priority_format_list: Vec::new(),
+            info_list: vec![b"SVTYPE".to_vec()],
+            format_list: vec![b"GT".to_vec()],
+            replace_sample_name: None,
+            group_names: None,
+            annotation_key: None,
+            annotation_fields: Vec::new(),
+            long_format: false,
+            variant_filter: None,
+            structural_variant: true,
+            sv_gene_regions: Some(GeneRegions::new(&crate::logic::filter::load_named_bed(
+                &include_bytes!("../../testfiles/sv-genes.bed")[..],
+            )?)),
+        };
+        let mut vcf_data_reader = BufReader::new(&vcf_data[..]);
+        let mut vcf_reader = vcf::VCFReader::new(&mut vcf_data_reader)?;
+        let mut write_bytes = Vec::<u8>::new();
+        let header_contents = create_header_line(vcf_reader.header(), &config)?;
+        vcf2table(
+            &mut vcf_reader,
+            &header_contents,
+            &config,
+            None,
+            true,
+            &mut tablewriter::CSVWriter::new(&mut write_bytes),
+        )?;
+        std::fs::write(
+            "../target/vcfutils-test_vcf2table_csv_structural_variant.csv",
+            &write_bytes[..],
+        )?;
+        assert_eq!(
+            &write_bytes[..],
+            &include_bytes!("../../testfiles/sv-expected.csv")[..]
+        );
+        Ok(())
+    }
+
     #[test]
     fn test_vcf2table_xlsx_split_multi() -> anyhow::Result<()> {
         let vcf_data = include_bytes!("../../testfiles/simple1.vcf");
@@ -1323,15 +2123,20 @@ mod test {
             format_list: vec![b"GT".to_vec(), b"AD".to_vec(), b"DP".to_vec()],
             replace_sample_name: None,
             group_names: None,
+            annotation_key: None,
+            annotation_fields: Vec::new(),
+            long_format: false,
+            variant_filter: None,
+            structural_variant: false,
+            sv_gene_regions: None,
         };
         let mut vcf_data_reader = BufReader::new(&vcf_data[..]);
         let mut vcf_reader = vcf::VCFReader::new(&mut vcf_data_reader)?;
         let workbook = xlsxwriter::Workbook::new("../target/table-split-multi.xlsx")?;
-        let mut sheet = workbook.add_worksheet(None)?;
-        let mut writer = tablewriter::XlsxSheetWriter::new(&mut sheet);
-        let header_contents = create_header_line(&vcf_reader.header(), &config);
+        let mut writer = tablewriter::XlsxWorkbookWriter::new(&workbook, "Variants");
+        let header_contents = create_header_line(&vcf_reader.header(), &config)?;
         vcf2table_set_data_type(&header_contents, &mut writer)?;
-        let row = vcf2table(
+        vcf2table(
             &mut vcf_reader,
             &header_contents,
             &config,
@@ -1339,7 +2144,10 @@ mod test {
             true,
             &mut writer,
         )?;
-        sheet.autofilter(0, 0, row, (header_contents.len() - 1) as u16)?;
+        for (sheet, row) in writer.worksheets_mut() {
+            vcf2table_format_sheet(sheet, &header_contents, row, None)?;
+        }
+        drop(writer);
         workbook.close()?;
         Ok(())
     }
@@ -1362,15 +2170,20 @@ mod test {
             format_list: vec![b"GT".to_vec(), b"AD".to_vec(), b"DP".to_vec()],
             replace_sample_name: None,
             group_names: None,
+            annotation_key: None,
+            annotation_fields: Vec::new(),
+            long_format: false,
+            variant_filter: None,
+            structural_variant: false,
+            sv_gene_regions: None,
         };
         let mut vcf_data_reader = BufReader::new(&vcf_data[..]);
         let mut vcf_reader = vcf::VCFReader::new(&mut vcf_data_reader)?;
         let workbook = xlsxwriter::Workbook::new("../target/table-no-split.xlsx")?;
-        let mut sheet = workbook.add_worksheet(None)?;
-        let mut writer = tablewriter::XlsxSheetWriter::new(&mut sheet);
-        let header_contents = create_header_line(&vcf_reader.header(), &config);
+        let mut writer = tablewriter::XlsxWorkbookWriter::new(&workbook, "Variants");
+        let header_contents = create_header_line(&vcf_reader.header(), &config)?;
         vcf2table_set_data_type(&header_contents, &mut writer)?;
-        let row = vcf2table(
+        vcf2table(
             &mut vcf_reader,
             &header_contents,
             &config,
@@ -1378,7 +2191,10 @@ mod test {
             true,
             &mut writer,
         )?;
-        sheet.autofilter(0, 0, row, (header_contents.len() - 1) as u16)?;
+        for (sheet, row) in writer.worksheets_mut() {
+            vcf2table_format_sheet(sheet, &header_contents, row, None)?;
+        }
+        drop(writer);
         workbook.close()?;
         Ok(())
     }
@@ -1401,15 +2217,20 @@ mod test {
             format_list: vec![b"GT".to_vec(), b"AD".to_vec(), b"DP".to_vec()],
             replace_sample_name: Some(vec![b"SAMPLE1".to_vec()]),
             group_names: Some(vec![b"GROUP".to_vec()]),
+            annotation_key: None,
+            annotation_fields: Vec::new(),
+            long_format: false,
+            variant_filter: None,
+            structural_variant: false,
+            sv_gene_regions: None,
         };
         let mut vcf_data_reader = BufReader::new(&vcf_data[..]);
         let mut vcf_reader = vcf::VCFReader::new(&mut vcf_data_reader)?;
         let workbook = xlsxwriter::Workbook::new("../target/table-split-multi-with-group.xlsx")?;
-        let mut sheet = workbook.add_worksheet(None)?;
-        let mut writer = tablewriter::XlsxSheetWriter::new(&mut sheet);
-        let header_contents = create_header_line(&vcf_reader.header(), &config);
+        let mut writer = tablewriter::XlsxWorkbookWriter::new(&workbook, "Variants");
+        let header_contents = create_header_line(&vcf_reader.header(), &config)?;
         vcf2table_set_data_type(&header_contents, &mut writer)?;
-        let row = vcf2table(
+        vcf2table(
             &mut vcf_reader,
             &header_contents,
             &config,
@@ -1417,8 +2238,120 @@ mod test {
             true,
             &mut writer,
         )?;
-        sheet.autofilter(0, 0, row, (header_contents.len() - 1) as u16)?;
+        for (sheet, row) in writer.worksheets_mut() {
+            vcf2table_format_sheet(sheet, &header_contents, row, None)?;
+        }
+        drop(writer);
         workbook.close()?;
         Ok(())
     }
+
+    #[test]
+    fn test_vcf2table_xlsx_sheet_rollover() -> anyhow::Result<()> {
+        let vcf_data = include_bytes!("../../testfiles/simple1.vcf");
+        let config = VCF2CSVConfig {
+            split_multi_allelic: true,
+            decoded_genotype: false,
+            canonical_list: None,
+            priority_info_list: Vec::new(),
+            priority_format_list: Vec::new(),
+            info_list: vec![b"AC".to_vec()],
+            format_list: vec![b"GT".to_vec()],
+            replace_sample_name: None,
+            group_names: None,
+            annotation_key: None,
+            annotation_fields: Vec::new(),
+            long_format: false,
+            variant_filter: None,
+            structural_variant: false,
+            sv_gene_regions: None,
+        };
+        let workbook = xlsxwriter::Workbook::new("../target/table-sheet-rollover.xlsx")?;
+        let mut writer = tablewriter::XlsxWorkbookWriter::new(&workbook, "Variants");
+        writer.set_max_rows(3);
+        writer.set_split_column(Some("CHROM"));
+        writer.reserve_sheet_name("13");
+        for write_header in [true, false] {
+            let mut vcf_data_reader = BufReader::new(&vcf_data[..]);
+            let mut vcf_reader = vcf::VCFReader::new(&mut vcf_data_reader)?;
+            let header_contents = create_header_line(vcf_reader.header(), &config)?;
+            vcf2table_set_data_type(&header_contents, &mut writer)?;
+            vcf2table(
+                &mut vcf_reader,
+                &header_contents,
+                &config,
+                None,
+                write_header,
+                &mut writer,
+            )?;
+        }
+        assert_eq!(
+            writer.sheet_rows(),
+            vec![("13_2", 2), ("13 (2)", 2), ("13 (3)", 2), ("13 (4)", 2)]
+        );
+        drop(writer);
+        workbook.close()?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_vcf2table_parquet_split_multi() -> anyhow::Result<()> {
+        let vcf_data = include_bytes!("../../testfiles/simple1.vcf");
+        let config = VCF2CSVConfig {
+            split_multi_allelic: true,
+            decoded_genotype: false,
+            canonical_list: None,
+            priority_info_list: Vec::new(),
+            priority_format_list: Vec::new(),
+            info_list: vec![
+                b"AC".to_vec(),
+                b"AF".to_vec(),
+                b"AN".to_vec(),
+                b"DP".to_vec(),
+            ],
+            format_list: vec![b"AD".to_vec(), b"DP".to_vec(), b"GT".to_vec()],
+            replace_sample_name: None,
+            group_names: None,
+            annotation_key: None,
+            annotation_fields: Vec::new(),
+            long_format: false,
+            variant_filter: None,
+            structural_variant: false,
+            sv_gene_regions: None,
+        };
+        let mut vcf_data_reader = BufReader::new(&vcf_data[..]);
+        let mut vcf_reader = vcf::VCFReader::new(&mut vcf_data_reader)?;
+        let header_contents = create_header_line(vcf_reader.header(), &config)?;
+        let column_types = vcf2table_column_types(&header_contents, config.split_multi_allelic);
+        use ColumnType::*;
+        assert_eq!(
+            column_types,
+            vec![
+                Integer, Integer, String, Integer, String, String, String, Float, Float, Float,
+                String, Integer, Float, Integer, Integer, Integer, Integer, Integer, String,
+                Integer, Integer, Integer, String
+            ]
+        );
+        assert_eq!(
+            vcf2table_column_types(&header_contents, false)[11..14],
+            [String, String, Integer]
+        );
+
+        let mut write_bytes = Vec::<u8>::new();
+        let mut writer = ArrowTableWriter::new(&mut write_bytes, ArrowFileFormat::Parquet);
+        writer.set_column_types(&column_types);
+        vcf2table(
+            &mut vcf_reader,
+            &header_contents,
+            &config,
+            None,
+            true,
+            &mut writer,
+        )?;
+        writer.close()?;
+        std::fs::File::create("../target/simple1-multiallelic-split.parquet")?
+            .write_all(&write_bytes)?;
+        assert!(write_bytes.starts_with(b"PAR1"));
+        Ok(())
+    }
 }
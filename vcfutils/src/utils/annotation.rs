@@ -0,0 +1,252 @@
+// Pipe-delimited functional annotations (snpEff ANN, Ensembl VEP CSQ, bcftools BCSQ and similar)
+
+use std::str;
+use vcf::{U8Vec, VCFHeader, VCFRecord};
+
+/// Annotation keys which are detected automatically, in order of preference.
+pub const KNOWN_ANNOTATION_KEYS: &[&[u8]] = &[b"ANN", b"CSQ", b"BCSQ"];
+
+/// Subfield names for gene symbol used by snpEff, VEP and bcftools/csq.
+pub const GENE_FIELD_NAMES: &[&str] = &["Gene_Name", "SYMBOL", "gene"];
+
+const SNPEFF_FIELDS: &[&str] = &[
+    "Allele",
+    "Annotation",
+    "Annotation_Impact",
+    "Gene_Name",
+    "Gene_ID",
+    "Feature_Type",
+    "Feature_ID",
+    "Transcript_BioType",
+    "Rank",
+    "HGVS.c",
+    "HGVS.p",
+    "cDNA.pos / cDNA.length",
+    "CDS.pos / CDS.length",
+    "AA.pos / AA.length",
+    "Distance",
+    "ERRORS / WARNINGS / INFO",
+];
+
+/// Layout of a pipe-delimited annotation INFO field.
+///
+/// Subfield names are parsed from the header description, and well-known subfields are
+/// resolved for snpEff, VEP and bcftools/csq naming.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct AnnotationFormat {
+    pub key: U8Vec,
+    pub fields: Vec<String>,
+    pub allele: Option<usize>,
+    pub allele_number: Option<usize>,
+    pub consequence: Option<usize>,
+    pub impact: Option<usize>,
+    pub gene: Option<usize>,
+    pub gene_id: Option<usize>,
+    pub transcript: Option<usize>,
+    pub cds_change: Option<usize>,
+    pub protein_change: Option<usize>,
+    /// VEP `CANONICAL` flag subfield
+    pub canonical: Option<usize>,
+}
+
+/// Parse subfield names from INFO description such as
+/// `Consequence annotations from Ensembl VEP. Format: Allele|Consequence|IMPACT` or
+/// `Functional annotations: 'Allele | Annotation | Annotation_Impact'`.
+pub fn parse_annotation_fields(description: &str) -> Vec<String> {
+    let format = if let Some(pos) = description.rfind("Format:") {
+        &description[pos + "Format:".len()..]
+    } else if let Some(pos) = description.find(':') {
+        &description[pos + 1..]
+    } else {
+        description
+    };
+    let format = format.trim().trim_matches(|x| x == '\'' || x == '"').trim();
+    if !format.contains('|') {
+        return Vec::new();
+    }
+    format.split('|').map(|x| x.trim().to_string()).collect()
+}
+
+impl AnnotationFormat {
+    pub fn new(key: &[u8], fields: Vec<String>) -> Self {
+        let find = |names: &[&str]| fields.iter().position(|x| names.contains(&x.as_str()));
+        AnnotationFormat {
+            key: key.to_vec(),
+            allele: find(&["Allele"]),
+            allele_number: find(&["ALLELE_NUM"]),
+            consequence: find(&["Annotation", "Consequence"]),
+            impact: find(&["Annotation_Impact", "IMPACT"]),
+            gene: find(GENE_FIELD_NAMES),
+            gene_id: find(&["Gene_ID", "Gene"]),
+            transcript: find(&["Feature_ID", "Feature", "transcript"]),
+            cds_change: find(&["HGVS.c", "HGVSc", "dna_change"]),
+            protein_change: find(&["HGVS.p", "HGVSp", "amino_acid_change"]),
+            canonical: find(&["CANONICAL"]),
+            fields,
+        }
+    }
+
+    /// Create a layout from the description of INFO `key`. Returns `None` if the key is not
+    /// defined or the description does not contain pipe-delimited subfield names. snpEff `ANN`
+    /// falls back to the standard snpEff layout.
+    pub fn from_header(header: &VCFHeader, key: &[u8]) -> Option<Self> {
+        let info = header.info(key)?;
+        let fields = parse_annotation_fields(&String::from_utf8_lossy(info.description));
+        if fields.is_empty() {
+            if key == b"ANN" {
+                return Some(AnnotationFormat::new(
+                    key,
+                    SNPEFF_FIELDS.iter().map(|x| x.to_string()).collect(),
+                ));
+            }
+            return None;
+        }
+        Some(AnnotationFormat::new(key, fields))
+    }
+
+    /// Find the annotation layout. The first known annotation key in the header is used when
+    /// `key` is not given.
+    pub fn detect(header: &VCFHeader, key: Option<&[u8]>) -> Option<Self> {
+        if let Some(key) = key {
+            AnnotationFormat::from_header(header, key)
+        } else {
+            KNOWN_ANNOTATION_KEYS
+                .iter()
+                .find_map(|x| AnnotationFormat::from_header(header, x))
+        }
+    }
+
+    pub fn field_index(&self, name: &[u8]) -> Option<usize> {
+        self.fields.iter().position(|x| x.as_bytes() == name)
+    }
+
+    /// Test whether an annotation entry describes the alternative allele.
+    /// Entries without allele information match all alleles.
+    pub fn is_allele_matched(
+        &self,
+        record: &VCFRecord,
+        entry: &[&[u8]],
+        alt_index: Option<usize>,
+    ) -> bool {
+        let alt_index = if let Some(alt_index) = alt_index {
+            alt_index
+        } else {
+            return true;
+        };
+        if let Some(allele_number) = self
+            .allele_number
+            .and_then(|x| entry.get(x))
+            .filter(|x| !x.is_empty())
+        {
+            return str::from_utf8(allele_number)
+                .ok()
+                .and_then(|x| x.parse::<usize>().ok())
+                == Some(alt_index + 1);
+        }
+        if let Some(allele) = self.allele.and_then(|x| entry.get(x)) {
+            let alt = if let Some(alt) = record.alternative.get(alt_index) {
+                alt
+            } else {
+                return false;
+            };
+            // Trimmed representation is used only when the allele is not written as in ALT
+            if record.alternative.iter().any(|x| *allele == &x[..]) {
+                return *allele == &alt[..];
+            }
+            return *allele == &trimmed_allele(record, alt)[..];
+        }
+        true
+    }
+
+    /// Split annotation entries of the record into subfields. Only entries for the
+    /// alternative allele are returned when `alt_index` is given.
+    pub fn entries<'a>(
+        &self,
+        record: &'a VCFRecord,
+        alt_index: Option<usize>,
+    ) -> Vec<Vec<&'a [u8]>> {
+        record
+            .info(&self.key)
+            .map(|values| {
+                values
+                    .iter()
+                    .map(|x| x.split(|y| *y == b'|').collect::<Vec<_>>())
+                    .filter(|x| self.is_allele_matched(record, x, alt_index))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+}
+
+/// Allele representation used by VEP. A first base shared by all alleles is removed,
+/// and an empty allele is written as `-`.
+fn trimmed_allele(record: &VCFRecord, allele: &[u8]) -> U8Vec {
+    let first = record.reference.first();
+    if first.is_some() && record.alternative.iter().all(|x| x.first() == first) {
+        if allele.len() > 1 {
+            allele[1..].to_vec()
+        } else {
+            b"-".to_vec()
+        }
+    } else {
+        allele.to_vec()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_annotation_fields() {
+        assert_eq!(
+            parse_annotation_fields(
+                "Functional annotations: 'Allele | Annotation | Annotation_Impact | Gene_Name' "
+            ),
+            vec!["Allele", "Annotation", "Annotation_Impact", "Gene_Name"]
+        );
+        assert_eq!(
+            parse_annotation_fields(
+                "Consequence annotations from Ensembl VEP. Format: Allele|Consequence|IMPACT|SYMBOL"
+            ),
+            vec!["Allele", "Consequence", "IMPACT", "SYMBOL"]
+        );
+        assert_eq!(
+            parse_annotation_fields("Haplotype-aware consequence annotation from BCFtools/csq, see http://samtools.github.io/bcftools/howtos/csq-calling.html for details. Format: Consequence|gene|transcript|biotype|strand|amino_acid_change|dna_change"),
+            vec!["Consequence", "gene", "transcript", "biotype", "strand", "amino_acid_change", "dna_change"]
+        );
+        assert!(parse_annotation_fields("Allele count").is_empty());
+    }
+
+    #[test]
+    fn test_annotation_format() -> Result<(), crate::error::VCFUtilsError> {
+        let vcf_data = include_bytes!("../../testfiles/simple1-vep.vcf");
+        let mut vcf_reader = vcf::VCFReader::new(&vcf_data[..])?;
+        let format = AnnotationFormat::detect(vcf_reader.header(), None).unwrap();
+        assert_eq!(format.key, b"CSQ");
+        assert_eq!(format.allele, Some(0));
+        assert_eq!(format.impact, Some(2));
+        assert_eq!(format.gene, Some(3));
+        assert_eq!(format.transcript, Some(6));
+        assert_eq!(
+            format.field_index(b"CANONICAL"),
+            Some(format.fields.len() - 1)
+        );
+        assert_eq!(format.canonical, Some(format.fields.len() - 1));
+
+        let mut record = VCFRecord::new(vcf_reader.header().clone());
+        let mut deletion_found = false;
+        while vcf_reader.next_record(&mut record)? {
+            for alt_index in 0..record.alternative.len() {
+                let entries = format.entries(&record, Some(alt_index));
+                assert!(!entries.is_empty());
+                if record.reference.len() > record.alternative[alt_index].len() {
+                    deletion_found = true;
+                    assert!(entries.iter().all(|x| x[0] == b"-"));
+                }
+            }
+        }
+        assert!(deletion_found);
+        Ok(())
+    }
+}
@@ -0,0 +1,286 @@
+use crate::error::VCFUtilsError;
+use crate::utils::tablewriter::{ColumnType, TableWriter};
+use arrow::array::{ArrayRef, BooleanBuilder, Float64Builder, Int64Builder, StringBuilder};
+use arrow::datatypes::{DataType, Field, Schema, SchemaRef};
+use arrow::ipc::writer::FileWriter;
+use arrow::record_batch::RecordBatch;
+use parquet::arrow::ArrowWriter;
+use parquet::basic::Compression;
+use parquet::file::properties::WriterProperties;
+use std::io::Write;
+use std::sync::Arc;
+
+pub const DEFAULT_BATCH_SIZE: usize = 65536;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ArrowFileFormat {
+    Parquet,
+    Ipc,
+}
+
+enum ColumnBuilder {
+    String(StringBuilder),
+    Integer(Int64Builder),
+    Float(Float64Builder),
+    Boolean(BooleanBuilder),
+}
+
+impl ColumnBuilder {
+    fn new(column_type: ColumnType) -> Self {
+        match column_type {
+            ColumnType::String => ColumnBuilder::String(StringBuilder::new()),
+            ColumnType::Integer => ColumnBuilder::Integer(Int64Builder::new()),
+            ColumnType::Float => ColumnBuilder::Float(Float64Builder::new()),
+            ColumnType::Boolean => ColumnBuilder::Boolean(BooleanBuilder::new()),
+        }
+    }
+
+    /// Empty values and values which cannot be parsed as the column type are stored as null.
+    fn append(&mut self, value: &str) {
+        match self {
+            ColumnBuilder::String(b) => {
+                if value.is_empty() {
+                    b.append_null();
+                } else {
+                    b.append_value(value);
+                }
+            }
+            ColumnBuilder::Integer(b) => b.append_option(value.parse().ok()),
+            ColumnBuilder::Float(b) => b.append_option(value.parse().ok()),
+            ColumnBuilder::Boolean(b) => b.append_option(match value {
+                "TRUE" | "True" | "true" => Some(true),
+                "FALSE" | "False" | "false" => Some(false),
+                _ => None,
+            }),
+        }
+    }
+
+    fn finish(&mut self) -> ArrayRef {
+        match self {
+            ColumnBuilder::String(b) => Arc::new(b.finish()),
+            ColumnBuilder::Integer(b) => Arc::new(b.finish()),
+            ColumnBuilder::Float(b) => Arc::new(b.finish()),
+            ColumnBuilder::Boolean(b) => Arc::new(b.finish()),
+        }
+    }
+}
+
+enum ArrowFileWriter<W: Write + Send> {
+    Parquet(ArrowWriter<W>),
+    Ipc(FileWriter<W>),
+}
+
+impl<W: Write + Send> ArrowFileWriter<W> {
+    fn write(&mut self, batch: &RecordBatch) -> Result<(), VCFUtilsError> {
+        match self {
+            ArrowFileWriter::Parquet(w) => w.write(batch)?,
+            ArrowFileWriter::Ipc(w) => w.write(batch)?,
+        }
+        Ok(())
+    }
+
+    fn close(self) -> Result<(), VCFUtilsError> {
+        match self {
+            ArrowFileWriter::Parquet(w) => {
+                w.close()?;
+            }
+            ArrowFileWriter::Ipc(mut w) => w.finish()?,
+        }
+        Ok(())
+    }
+}
+
+/// Table writer for Parquet and Arrow IPC file.
+///
+/// Schema is fixed when the first row is written, and rows are written in batches.
+/// [`ArrowTableWriter::close`] must be called to write file footer.
+pub struct ArrowTableWriter<W: Write + Send> {
+    output: Option<W>,
+    writer: Option<ArrowFileWriter<W>>,
+    format: ArrowFileFormat,
+    header: Vec<String>,
+    column_types: Vec<ColumnType>,
+    schema: Option<SchemaRef>,
+    builders: Vec<ColumnBuilder>,
+    batch_size: usize,
+    buffered_rows: usize,
+}
+
+impl<W: Write + Send> ArrowTableWriter<W> {
+    pub fn new(writer: W, format: ArrowFileFormat) -> Self {
+        ArrowTableWriter {
+            output: Some(writer),
+            writer: None,
+            format,
+            header: Vec::new(),
+            column_types: Vec::new(),
+            schema: None,
+            builders: Vec::new(),
+            batch_size: DEFAULT_BATCH_SIZE,
+            buffered_rows: 0,
+        }
+    }
+
+    pub fn set_batch_size(&mut self, batch_size: usize) {
+        self.batch_size = batch_size.max(1);
+    }
+
+    /// Set column types. Columns without type are written as string.
+    pub fn set_column_types(&mut self, column_types: &[ColumnType]) {
+        self.column_types.clear();
+        self.column_types.extend_from_slice(column_types);
+    }
+
+    fn setup_writer(&mut self) -> Result<(), VCFUtilsError> {
+        if self.schema.is_some() {
+            return Ok(());
+        }
+        let column_types: Vec<_> = (0..self.header.len())
+            .map(|i| {
+                self.column_types
+                    .get(i)
+                    .copied()
+                    .unwrap_or(ColumnType::String)
+            })
+            .collect();
+        let schema = Arc::new(Schema::new(
+            self.header
+                .iter()
+                .zip(column_types.iter())
+                .map(|(name, t)| {
+                    Field::new(
+                        name,
+                        match t {
+                            ColumnType::String => DataType::Utf8,
+                            ColumnType::Integer => DataType::Int64,
+                            ColumnType::Float => DataType::Float64,
+                            ColumnType::Boolean => DataType::Boolean,
+                        },
+                        true,
+                    )
+                })
+                .collect::<Vec<_>>(),
+        ));
+        let output = self
+            .output
+            .take()
+            .ok_or(VCFUtilsError::OtherError("Arrow writer is already closed"))?;
+        self.writer = Some(match self.format {
+            ArrowFileFormat::Parquet => ArrowFileWriter::Parquet(ArrowWriter::try_new(
+                output,
+                schema.clone(),
+                Some(
+                    WriterProperties::builder()
+                        .set_compression(Compression::SNAPPY)
+                        .build(),
+                ),
+            )?),
+            ArrowFileFormat::Ipc => ArrowFileWriter::Ipc(FileWriter::try_new(output, &schema)?),
+        });
+        self.builders = column_types.into_iter().map(ColumnBuilder::new).collect();
+        self.schema = Some(schema);
+        Ok(())
+    }
+
+    fn flush_batch(&mut self) -> Result<(), VCFUtilsError> {
+        if self.buffered_rows == 0 {
+            return Ok(());
+        }
+        if let (Some(schema), Some(writer)) = (self.schema.as_ref(), self.writer.as_mut()) {
+            let columns: Vec<_> = self.builders.iter_mut().map(|x| x.finish()).collect();
+            writer.write(&RecordBatch::try_new(schema.clone(), columns)?)?;
+        }
+        self.buffered_rows = 0;
+        Ok(())
+    }
+
+    /// Write remaining rows and file footer.
+    pub fn close(mut self) -> Result<(), VCFUtilsError> {
+        self.setup_writer()?;
+        self.flush_batch()?;
+        if let Some(writer) = self.writer.take() {
+            writer.close()?;
+        }
+        Ok(())
+    }
+}
+
+impl<W: Write + Send> TableWriter for ArrowTableWriter<W> {
+    fn set_header(&mut self, items: &[String]) {
+        self.header.clear();
+        self.header.extend_from_slice(items);
+    }
+
+    fn header(&self) -> &[String] {
+        &self.header
+    }
+
+    /// Column names are written as a part of schema.
+    fn write_header(&mut self) -> Result<(), VCFUtilsError> {
+        Ok(())
+    }
+
+    fn write_row(&mut self, items: &[&str]) -> Result<(), VCFUtilsError> {
+        self.setup_writer()?;
+        for (i, builder) in self.builders.iter_mut().enumerate() {
+            builder.append(items.get(i).copied().unwrap_or(""));
+        }
+        self.buffered_rows += 1;
+        if self.buffered_rows >= self.batch_size {
+            self.flush_batch()?;
+        }
+        Ok(())
+    }
+
+    fn is_formula_compatible(&self) -> bool {
+        false
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_column_builder() {
+        let mut builder = ColumnBuilder::new(ColumnType::Integer);
+        builder.append("1");
+        builder.append("");
+        builder.append(".");
+        let array = builder.finish();
+        assert_eq!(array.len(), 3);
+        assert_eq!(array.null_count(), 2);
+        assert_eq!(array.data_type(), &DataType::Int64);
+
+        let mut builder = ColumnBuilder::new(ColumnType::Boolean);
+        builder.append("TRUE");
+        builder.append("FALSE");
+        let array = builder.finish();
+        assert_eq!(array.null_count(), 0);
+        assert_eq!(array.data_type(), &DataType::Boolean);
+    }
+
+    #[test]
+    fn test_arrow_table_writer() -> Result<(), VCFUtilsError> {
+        for format in [ArrowFileFormat::Parquet, ArrowFileFormat::Ipc] {
+            let mut write_buf: Vec<u8> = Vec::new();
+            let mut writer = ArrowTableWriter::new(&mut write_buf, format);
+            writer.set_batch_size(2);
+            writer.set_header(&["CHROM".to_string(), "POS".to_string(), "AF".to_string()]);
+            writer.set_column_types(&[ColumnType::String, ColumnType::Integer, ColumnType::Float]);
+            writer.write_header()?;
+            writer.write_row(&["1", "100", "0.5"])?;
+            writer.write_row(&["1", "200", ""])?;
+            writer.write_row(&["2", "300", "0.25"])?;
+            writer.close()?;
+
+            let magic: &[u8] = match format {
+                ArrowFileFormat::Parquet => b"PAR1",
+                ArrowFileFormat::Ipc => b"ARROW1",
+            };
+            assert!(write_buf.starts_with(magic));
+            assert!(write_buf.ends_with(magic));
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,288 @@
+//! Convert BCF to VCF text and vice versa with htslib.
+//!
+//! Logic modules only handle VCF text with the `vcf` crate. [`BCFTextReader`] formats BCF
+//! records as VCF lines, and [`BCFTextWriter`] parses VCF lines written by `vcf::VCFWriter`
+//! into BCF records, so that every command can read and write BCF without changes.
+
+use rust_htslib::bcf::{self, Read as _};
+use rust_htslib::htslib;
+use std::io::{self, BufRead, Read, Write};
+use std::path::Path;
+use std::ptr;
+
+/// Magic bytes of uncompressed BCF2 data
+pub const BCF_MAGIC: &[u8] = b"BCF\x02";
+
+/// Return true if `data` starts with BCF magic bytes.
+pub fn is_bcf(data: &[u8]) -> bool {
+    data.starts_with(BCF_MAGIC)
+}
+
+/// Return true if the path should be written as BCF.
+pub fn is_bcf_path(path: &str) -> bool {
+    path.ends_with(".bcf")
+}
+
+fn htslib_error(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+/// Owned htslib string buffer
+struct KString {
+    inner: htslib::kstring_t,
+}
+
+impl KString {
+    fn new() -> Self {
+        KString {
+            inner: htslib::kstring_t {
+                l: 0,
+                m: 0,
+                s: ptr::null_mut(),
+            },
+        }
+    }
+
+    fn as_bytes(&self) -> &[u8] {
+        if self.inner.s.is_null() {
+            &[]
+        } else {
+            unsafe { std::slice::from_raw_parts(self.inner.s as *const u8, self.inner.l as usize) }
+        }
+    }
+}
+
+impl Drop for KString {
+    fn drop(&mut self) {
+        if !self.inner.s.is_null() {
+            unsafe { htslib::free(self.inner.s as *mut std::os::raw::c_void) };
+        }
+    }
+}
+
+unsafe impl Send for KString {}
+
+/// Read BCF file as VCF text.
+pub struct BCFTextReader {
+    reader: bcf::Reader,
+    record: bcf::Record,
+    buffer: KString,
+    consumed: usize,
+}
+
+impl BCFTextReader {
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Self, rust_htslib::errors::Error> {
+        let reader = bcf::Reader::from_path(path)?;
+        let record = reader.empty_record();
+        let mut buffer = KString::new();
+        if unsafe { htslib::bcf_hdr_format(reader.header().inner, 0, &mut buffer.inner) } < 0 {
+            return Err(rust_htslib::errors::Error::BcfInvalidRecord);
+        }
+        Ok(BCFTextReader {
+            reader,
+            record,
+            buffer,
+            consumed: 0,
+        })
+    }
+}
+
+impl Read for BCFTextReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let data = self.fill_buf()?;
+        let length = data.len().min(buf.len());
+        buf[..length].copy_from_slice(&data[..length]);
+        self.consume(length);
+        Ok(length)
+    }
+}
+
+impl BufRead for BCFTextReader {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        if self.consumed >= self.buffer.as_bytes().len() {
+            match self.reader.read(&mut self.record) {
+                Some(Ok(())) => {
+                    self.buffer.inner.l = 0;
+                    self.consumed = 0;
+                    if unsafe {
+                        htslib::vcf_format(
+                            self.reader.header().inner,
+                            self.record.inner(),
+                            &mut self.buffer.inner,
+                        )
+                    } < 0
+                    {
+                        return Err(htslib_error("Failed to format BCF record"));
+                    }
+                }
+                Some(Err(e)) => return Err(io::Error::new(io::ErrorKind::InvalidData, e)),
+                None => return Ok(&[]),
+            }
+        }
+        Ok(&self.buffer.as_bytes()[self.consumed..])
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.consumed += amt;
+    }
+}
+
+/// Write VCF text as BCF file. The BCF writer is created when the `#CHROM` line is written.
+pub struct BCFTextWriter {
+    path: String,
+    header_lines: Vec<Vec<u8>>,
+    writer: Option<(bcf::Writer, bcf::Record)>,
+    line: Vec<u8>,
+}
+
+impl BCFTextWriter {
+    pub fn new(path: &str) -> Self {
+        BCFTextWriter {
+            path: path.to_string(),
+            header_lines: Vec::new(),
+            writer: None,
+            line: Vec::new(),
+        }
+    }
+
+    fn write_line(&mut self) -> io::Result<()> {
+        while self.line.ends_with(b"\n") || self.line.ends_with(b"\r") {
+            self.line.pop();
+        }
+        if self.line.is_empty() {
+            return Ok(());
+        }
+
+        if let Some((writer, record)) = self.writer.as_mut() {
+            // vcf_parse requires NUL terminated string
+            self.line.push(0);
+            let mut kstring = htslib::kstring_t {
+                l: (self.line.len() - 1) as _,
+                m: self.line.len() as _,
+                s: self.line.as_mut_ptr() as *mut std::os::raw::c_char,
+            };
+            if unsafe { htslib::vcf_parse(&mut kstring, writer.header().inner, record.inner_mut()) }
+                != 0
+            {
+                return Err(htslib_error("Failed to parse VCF record"));
+            }
+            return writer.write(record).map_err(io::Error::other);
+        }
+
+        if self.line.starts_with(b"#CHROM") {
+            let mut header = bcf::Header::new();
+            // fileformat line is added by htslib
+            for one in self
+                .header_lines
+                .iter()
+                .filter(|x| !x.starts_with(b"##fileformat="))
+            {
+                header.push_record(one);
+            }
+            for sample in self.line.split(|x| *x == b'\t').skip(9) {
+                header.push_sample(sample);
+            }
+            let writer = bcf::Writer::from_path(&self.path, &header, false, bcf::Format::Bcf)
+                .map_err(io::Error::other)?;
+            let record = writer.empty_record();
+            self.writer = Some((writer, record));
+        } else {
+            self.header_lines.push(self.line.clone());
+        }
+        Ok(())
+    }
+}
+
+impl Write for BCFTextWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut rest = buf;
+        while let Some(pos) = rest.iter().position(|x| *x == b'\n') {
+            self.line.extend_from_slice(&rest[..pos]);
+            self.write_line()?;
+            self.line.clear();
+            rest = &rest[pos + 1..];
+        }
+        self.line.extend_from_slice(rest);
+        Ok(buf.len())
+    }
+
+    /// Records are buffered by htslib until [`BCFTextWriter::close`]. Fails if the `#CHROM`
+    /// line is not written yet.
+    fn flush(&mut self) -> io::Result<()> {
+        if self.writer.is_none() {
+            return Err(missing_header_error());
+        }
+        Ok(())
+    }
+}
+
+fn missing_header_error() -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        "BCF header is incomplete: #CHROM line is not written",
+    )
+}
+
+impl BCFTextWriter {
+    /// Write the last line and close the BCF file. Fails if the `#CHROM` line is not written,
+    /// because no BCF file can be created without VCF header.
+    pub fn close(mut self) -> io::Result<()> {
+        if !self.line.is_empty() {
+            self.write_line()?;
+            self.line.clear();
+        }
+        if self.writer.is_none() {
+            return Err(missing_header_error());
+        }
+        // htslib closes the file when the writer is dropped
+        self.writer = None;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_is_bcf() {
+        assert!(is_bcf(b"BCF\x02\x02\x00\x00\x00"));
+        assert!(!is_bcf(b"##fileformat=VCFv4.2\n"));
+        assert!(!is_bcf(b"BC"));
+        assert!(is_bcf_path("output.bcf"));
+        assert!(!is_bcf_path("output.vcf.gz"));
+    }
+
+    #[test]
+    fn test_bcf_writer_without_header() {
+        let mut writer = BCFTextWriter::new("no-header.bcf");
+        writer.write_all(b"##fileformat=VCFv4.2\n").unwrap();
+        assert!(writer.flush().is_err());
+        assert!(writer.close().is_err());
+        assert!(!Path::new("no-header.bcf").exists());
+    }
+
+    #[test]
+    fn test_bcf_round_trip() -> io::Result<()> {
+        let vcf_data = include_bytes!("../../testfiles/bcf-roundtrip.vcf");
+        let path = std::env::temp_dir().join(format!(
+            "vcfutils-bcf-round-trip-{}.bcf",
+            std::process::id()
+        ));
+        let mut writer = BCFTextWriter::new(path.to_str().unwrap());
+        writer.write_all(vcf_data)?;
+        writer.flush()?;
+        writer.close()?;
+
+        let mut vcf_text = Vec::new();
+        BCFTextReader::from_path(&path)
+            .map_err(io::Error::other)?
+            .read_to_end(&mut vcf_text)?;
+        std::fs::remove_file(&path)?;
+        assert_eq!(
+            String::from_utf8_lossy(&vcf_text),
+            String::from_utf8_lossy(vcf_data)
+        );
+        Ok(())
+    }
+}
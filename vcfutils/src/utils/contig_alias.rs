@@ -0,0 +1,1055 @@
+//! Contig name dictionaries of human reference genomes between UCSC, Ensembl, RefSeq and GenBank
+//! naming conventions.
+//!
+//! The dictionaries contain primary chromosomes, mitochondrial genome, EBV, and alternative,
+//! unlocalized and unplaced contigs of the assembly reports. UCSC names of non-primary contigs
+//! contain their GenBank accessions, which Ensembl also uses, and decoy contigs of GRCh38
+//! (hs38d1) are converted by the accessions. RefSeq accessions of non-primary contigs are not
+//! listed, and the hs37d5 decoy has no alias.
+
+use std::collections::HashMap;
+use vcf::U8Vec;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GenomeBuild {
+    GRCh37,
+    GRCh38,
+    T2tChm13,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ContigNaming {
+    /// `chr1`, `chrM`, `chr1_KI270706v1_random`
+    Ucsc,
+    /// `1`, `MT`, `KI270706.1`
+    Ensembl,
+    /// `NC_000001.11`
+    RefSeq,
+    /// `CM000663.2`
+    GenBank,
+}
+
+pub const ALL_GENOME_BUILDS: [GenomeBuild; 3] = [
+    GenomeBuild::GRCh37,
+    GenomeBuild::GRCh38,
+    GenomeBuild::T2tChm13,
+];
+
+pub const ALL_CONTIG_NAMINGS: [ContigNaming; 4] = [
+    ContigNaming::Ucsc,
+    ContigNaming::Ensembl,
+    ContigNaming::RefSeq,
+    ContigNaming::GenBank,
+];
+
+/// Names of a contig in UCSC, Ensembl, RefSeq and GenBank conventions. Empty names are not
+/// available in the convention.
+type ContigAlias = [&'static str; 4];
+
+const GRCH37_CONTIGS: &[ContigAlias] = &[
+    ["chr1", "1", "NC_000001.10", "CM000663.1"],
+    ["chr2", "2", "NC_000002.11", "CM000664.1"],
+    ["chr3", "3", "NC_000003.11", "CM000665.1"],
+    ["chr4", "4", "NC_000004.11", "CM000666.1"],
+    ["chr5", "5", "NC_000005.9", "CM000667.1"],
+    ["chr6", "6", "NC_000006.11", "CM000668.1"],
+    ["chr7", "7", "NC_000007.13", "CM000669.1"],
+    ["chr8", "8", "NC_000008.10", "CM000670.1"],
+    ["chr9", "9", "NC_000009.11", "CM000671.1"],
+    ["chr10", "10", "NC_000010.10", "CM000672.1"],
+    ["chr11", "11", "NC_000011.9", "CM000673.1"],
+    ["chr12", "12", "NC_000012.11", "CM000674.1"],
+    ["chr13", "13", "NC_000013.10", "CM000675.1"],
+    ["chr14", "14", "NC_000014.8", "CM000676.1"],
+    ["chr15", "15", "NC_000015.9", "CM000677.1"],
+    ["chr16", "16", "NC_000016.9", "CM000678.1"],
+    ["chr17", "17", "NC_000017.10", "CM000679.1"],
+    ["chr18", "18", "NC_000018.9", "CM000680.1"],
+    ["chr19", "19", "NC_000019.9", "CM000681.1"],
+    ["chr20", "20", "NC_000020.10", "CM000682.1"],
+    ["chr21", "21", "NC_000021.8", "CM000683.1"],
+    ["chr22", "22", "NC_000022.10", "CM000684.1"],
+    ["chrX", "X", "NC_000023.10", "CM000685.1"],
+    ["chrY", "Y", "NC_000024.9", "CM000686.1"],
+    // chrM of hg19 is not rCRS but the Yoruba sequence NC_001807 (16571 bp)
+    ["", "MT", "NC_012920.1", "J01415.2"],
+    ["chrM", "", "NC_001807.4", ""],
+    // EBV of hs37d5
+    ["", "NC_007605", "NC_007605.1", "AJ507799.2"],
+    // alternative loci
+    ["chr6_apd_hap1", "", "", "GL000250.1"],
+    ["chr6_cox_hap2", "", "", "GL000251.1"],
+    ["chr6_dbb_hap3", "", "", "GL000252.1"],
+    ["chr6_mann_hap4", "", "", "GL000253.1"],
+    ["chr6_mcf_hap5", "", "", "GL000254.1"],
+    ["chr6_qbl_hap6", "", "", "GL000255.1"],
+    ["chr6_ssto_hap7", "", "", "GL000256.1"],
+    ["chr4_ctg9_hap1", "", "", "GL000257.1"],
+    ["chr17_ctg5_hap1", "", "", "GL000258.1"],
+];
+
+/// UCSC names of unlocalized and unplaced contigs of GRCh37 (hg19)
+const GRCH37_NON_PRIMARY_CONTIGS: &[&str] = &[
+    "chr1_gl000191_random",
+    "chr1_gl000192_random",
+    "chr4_gl000193_random",
+    "chr4_gl000194_random",
+    "chr7_gl000195_random",
+    "chr8_gl000196_random",
+    "chr8_gl000197_random",
+    "chr9_gl000198_random",
+    "chr9_gl000199_random",
+    "chr9_gl000200_random",
+    "chr9_gl000201_random",
+    "chr11_gl000202_random",
+    "chr17_gl000203_random",
+    "chr17_gl000204_random",
+    "chr17_gl000205_random",
+    "chr17_gl000206_random",
+    "chr18_gl000207_random",
+    "chr19_gl000208_random",
+    "chr19_gl000209_random",
+    "chr21_gl000210_random",
+    "chrUn_gl000211",
+    "chrUn_gl000212",
+    "chrUn_gl000213",
+    "chrUn_gl000214",
+    "chrUn_gl000215",
+    "chrUn_gl000216",
+    "chrUn_gl000217",
+    "chrUn_gl000218",
+    "chrUn_gl000219",
+    "chrUn_gl000220",
+    "chrUn_gl000221",
+    "chrUn_gl000222",
+    "chrUn_gl000223",
+    "chrUn_gl000224",
+    "chrUn_gl000225",
+    "chrUn_gl000226",
+    "chrUn_gl000227",
+    "chrUn_gl000228",
+    "chrUn_gl000229",
+    "chrUn_gl000230",
+    "chrUn_gl000231",
+    "chrUn_gl000232",
+    "chrUn_gl000233",
+    "chrUn_gl000234",
+    "chrUn_gl000235",
+    "chrUn_gl000236",
+    "chrUn_gl000237",
+    "chrUn_gl000238",
+    "chrUn_gl000239",
+    "chrUn_gl000240",
+    "chrUn_gl000241",
+    "chrUn_gl000242",
+    "chrUn_gl000243",
+    "chrUn_gl000244",
+    "chrUn_gl000245",
+    "chrUn_gl000246",
+    "chrUn_gl000247",
+    "chrUn_gl000248",
+    "chrUn_gl000249",
+];
+
+/// UCSC hg19 mitochondrial genome which differs from MT of GRCh37
+pub const HG19_CHRM: &str = "chrM";
+
+const GRCH38_CONTIGS: &[ContigAlias] = &[
+    ["chr1", "1", "NC_000001.11", "CM000663.2"],
+    ["chr2", "2", "NC_000002.12", "CM000664.2"],
+    ["chr3", "3", "NC_000003.12", "CM000665.2"],
+    ["chr4", "4", "NC_000004.12", "CM000666.2"],
+    ["chr5", "5", "NC_000005.10", "CM000667.2"],
+    ["chr6", "6", "NC_000006.12", "CM000668.2"],
+    ["chr7", "7", "NC_000007.14", "CM000669.2"],
+    ["chr8", "8", "NC_000008.11", "CM000670.2"],
+    ["chr9", "9", "NC_000009.12", "CM000671.2"],
+    ["chr10", "10", "NC_000010.11", "CM000672.2"],
+    ["chr11", "11", "NC_000011.10", "CM000673.2"],
+    ["chr12", "12", "NC_000012.12", "CM000674.2"],
+    ["chr13", "13", "NC_000013.11", "CM000675.2"],
+    ["chr14", "14", "NC_000014.9", "CM000676.2"],
+    ["chr15", "15", "NC_000015.10", "CM000677.2"],
+    ["chr16", "16", "NC_000016.10", "CM000678.2"],
+    ["chr17", "17", "NC_000017.11", "CM000679.2"],
+    ["chr18", "18", "NC_000018.10", "CM000680.2"],
+    ["chr19", "19", "NC_000019.10", "CM000681.2"],
+    ["chr20", "20", "NC_000020.11", "CM000682.2"],
+    ["chr21", "21", "NC_000021.9", "CM000683.2"],
+    ["chr22", "22", "NC_000022.11", "CM000684.2"],
+    ["chrX", "X", "NC_000023.11", "CM000685.2"],
+    ["chrY", "Y", "NC_000024.10", "CM000686.2"],
+    ["chrM", "MT", "NC_012920.1", "J01415.2"],
+    ["chrEBV", "", "NC_007605.1", "AJ507799.2"],
+];
+
+/// UCSC names of unlocalized, unplaced and alternative contigs of GRCh38 (hg38)
+const GRCH38_NON_PRIMARY_CONTIGS: &[&str] = &[
+    "chr1_KI270706v1_random",
+    "chr1_KI270707v1_random",
+    "chr1_KI270708v1_random",
+    "chr1_KI270709v1_random",
+    "chr1_KI270710v1_random",
+    "chr1_KI270711v1_random",
+    "chr1_KI270712v1_random",
+    "chr1_KI270713v1_random",
+    "chr1_KI270714v1_random",
+    "chr2_KI270715v1_random",
+    "chr2_KI270716v1_random",
+    "chr3_GL000221v1_random",
+    "chr4_GL000008v2_random",
+    "chr5_GL000208v1_random",
+    "chr9_KI270717v1_random",
+    "chr9_KI270718v1_random",
+    "chr9_KI270719v1_random",
+    "chr9_KI270720v1_random",
+    "chr11_KI270721v1_random",
+    "chr14_GL000009v2_random",
+    "chr14_GL000194v1_random",
+    "chr14_GL000225v1_random",
+    "chr14_KI270722v1_random",
+    "chr14_KI270723v1_random",
+    "chr14_KI270724v1_random",
+    "chr14_KI270725v1_random",
+    "chr14_KI270726v1_random",
+    "chr15_KI270727v1_random",
+    "chr16_KI270728v1_random",
+    "chr17_GL000205v2_random",
+    "chr17_KI270729v1_random",
+    "chr17_KI270730v1_random",
+    "chr22_KI270731v1_random",
+    "chr22_KI270732v1_random",
+    "chr22_KI270733v1_random",
+    "chr22_KI270734v1_random",
+    "chr22_KI270735v1_random",
+    "chr22_KI270736v1_random",
+    "chr22_KI270737v1_random",
+    "chr22_KI270738v1_random",
+    "chr22_KI270739v1_random",
+    "chrY_KI270740v1_random",
+    "chrUn_GL000195v1",
+    "chrUn_GL000213v1",
+    "chrUn_GL000214v1",
+    "chrUn_GL000216v2",
+    "chrUn_GL000218v1",
+    "chrUn_GL000219v1",
+    "chrUn_GL000220v1",
+    "chrUn_GL000224v1",
+    "chrUn_GL000226v1",
+    "chrUn_KI270302v1",
+    "chrUn_KI270303v1",
+    "chrUn_KI270304v1",
+    "chrUn_KI270305v1",
+    "chrUn_KI270310v1",
+    "chrUn_KI270311v1",
+    "chrUn_KI270312v1",
+    "chrUn_KI270315v1",
+    "chrUn_KI270316v1",
+    "chrUn_KI270317v1",
+    "chrUn_KI270320v1",
+    "chrUn_KI270322v1",
+    "chrUn_KI270329v1",
+    "chrUn_KI270330v1",
+    "chrUn_KI270333v1",
+    "chrUn_KI270334v1",
+    "chrUn_KI270335v1",
+    "chrUn_KI270336v1",
+    "chrUn_KI270337v1",
+    "chrUn_KI270338v1",
+    "chrUn_KI270340v1",
+    "chrUn_KI270362v1",
+    "chrUn_KI270363v1",
+    "chrUn_KI270364v1",
+    "chrUn_KI270366v1",
+    "chrUn_KI270371v1",
+    "chrUn_KI270372v1",
+    "chrUn_KI270373v1",
+    "chrUn_KI270374v1",
+    "chrUn_KI270375v1",
+    "chrUn_KI270376v1",
+    "chrUn_KI270378v1",
+    "chrUn_KI270379v1",
+    "chrUn_KI270381v1",
+    "chrUn_KI270382v1",
+    "chrUn_KI270383v1",
+    "chrUn_KI270384v1",
+    "chrUn_KI270385v1",
+    "chrUn_KI270386v1",
+    "chrUn_KI270387v1",
+    "chrUn_KI270388v1",
+    "chrUn_KI270389v1",
+    "chrUn_KI270390v1",
+    "chrUn_KI270391v1",
+    "chrUn_KI270392v1",
+    "chrUn_KI270393v1",
+    "chrUn_KI270394v1",
+    "chrUn_KI270395v1",
+    "chrUn_KI270396v1",
+    "chrUn_KI270411v1",
+    "chrUn_KI270412v1",
+    "chrUn_KI270414v1",
+    "chrUn_KI270417v1",
+    "chrUn_KI270418v1",
+    "chrUn_KI270419v1",
+    "chrUn_KI270420v1",
+    "chrUn_KI270422v1",
+    "chrUn_KI270423v1",
+    "chrUn_KI270424v1",
+    "chrUn_KI270425v1",
+    "chrUn_KI270429v1",
+    "chrUn_KI270435v1",
+    "chrUn_KI270438v1",
+    "chrUn_KI270442v1",
+    "chrUn_KI270448v1",
+    "chrUn_KI270465v1",
+    "chrUn_KI270466v1",
+    "chrUn_KI270467v1",
+    "chrUn_KI270468v1",
+    "chrUn_KI270507v1",
+    "chrUn_KI270508v1",
+    "chrUn_KI270509v1",
+    "chrUn_KI270510v1",
+    "chrUn_KI270511v1",
+    "chrUn_KI270512v1",
+    "chrUn_KI270515v1",
+    "chrUn_KI270516v1",
+    "chrUn_KI270517v1",
+    "chrUn_KI270518v1",
+    "chrUn_KI270519v1",
+    "chrUn_KI270521v1",
+    "chrUn_KI270522v1",
+    "chrUn_KI270528v1",
+    "chrUn_KI270529v1",
+    "chrUn_KI270530v1",
+    "chrUn_KI270538v1",
+    "chrUn_KI270539v1",
+    "chrUn_KI270544v1",
+    "chrUn_KI270548v1",
+    "chrUn_KI270579v1",
+    "chrUn_KI270580v1",
+    "chrUn_KI270581v1",
+    "chrUn_KI270582v1",
+    "chrUn_KI270583v1",
+    "chrUn_KI270584v1",
+    "chrUn_KI270587v1",
+    "chrUn_KI270588v1",
+    "chrUn_KI270589v1",
+    "chrUn_KI270590v1",
+    "chrUn_KI270591v1",
+    "chrUn_KI270593v1",
+    "chrUn_KI270741v1",
+    "chrUn_KI270742v1",
+    "chrUn_KI270743v1",
+    "chrUn_KI270744v1",
+    "chrUn_KI270745v1",
+    "chrUn_KI270746v1",
+    "chrUn_KI270747v1",
+    "chrUn_KI270748v1",
+    "chrUn_KI270749v1",
+    "chrUn_KI270750v1",
+    "chrUn_KI270751v1",
+    "chrUn_KI270752v1",
+    "chrUn_KI270753v1",
+    "chrUn_KI270754v1",
+    "chrUn_KI270755v1",
+    "chrUn_KI270756v1",
+    "chrUn_KI270757v1",
+    "chr1_GL383518v1_alt",
+    "chr1_GL383519v1_alt",
+    "chr1_GL383520v2_alt",
+    "chr1_KI270759v1_alt",
+    "chr1_KI270760v1_alt",
+    "chr1_KI270761v1_alt",
+    "chr1_KI270762v1_alt",
+    "chr1_KI270763v1_alt",
+    "chr1_KI270764v1_alt",
+    "chr1_KI270765v1_alt",
+    "chr1_KI270766v1_alt",
+    "chr1_KI270892v1_alt",
+    "chr2_GL383521v1_alt",
+    "chr2_GL383522v1_alt",
+    "chr2_GL582966v2_alt",
+    "chr2_KI270767v1_alt",
+    "chr2_KI270768v1_alt",
+    "chr2_KI270769v1_alt",
+    "chr2_KI270770v1_alt",
+    "chr2_KI270771v1_alt",
+    "chr2_KI270772v1_alt",
+    "chr2_KI270773v1_alt",
+    "chr2_KI270774v1_alt",
+    "chr2_KI270775v1_alt",
+    "chr2_KI270776v1_alt",
+    "chr2_KI270893v1_alt",
+    "chr2_KI270894v1_alt",
+    "chr3_GL383526v1_alt",
+    "chr3_JH636055v2_alt",
+    "chr3_KI270777v1_alt",
+    "chr3_KI270778v1_alt",
+    "chr3_KI270779v1_alt",
+    "chr3_KI270780v1_alt",
+    "chr3_KI270781v1_alt",
+    "chr3_KI270782v1_alt",
+    "chr3_KI270783v1_alt",
+    "chr3_KI270784v1_alt",
+    "chr3_KI270895v1_alt",
+    "chr3_KI270924v1_alt",
+    "chr3_KI270934v1_alt",
+    "chr3_KI270935v1_alt",
+    "chr3_KI270936v1_alt",
+    "chr3_KI270937v1_alt",
+    "chr4_GL000257v2_alt",
+    "chr4_GL383527v1_alt",
+    "chr4_GL383528v1_alt",
+    "chr4_KI270785v1_alt",
+    "chr4_KI270786v1_alt",
+    "chr4_KI270787v1_alt",
+    "chr4_KI270788v1_alt",
+    "chr4_KI270789v1_alt",
+    "chr4_KI270790v1_alt",
+    "chr4_KI270896v1_alt",
+    "chr4_KI270925v1_alt",
+    "chr5_GL339449v2_alt",
+    "chr5_GL383530v1_alt",
+    "chr5_GL383531v1_alt",
+    "chr5_GL383532v1_alt",
+    "chr5_GL949742v1_alt",
+    "chr5_KI270791v1_alt",
+    "chr5_KI270792v1_alt",
+    "chr5_KI270793v1_alt",
+    "chr5_KI270794v1_alt",
+    "chr5_KI270795v1_alt",
+    "chr5_KI270796v1_alt",
+    "chr5_KI270897v1_alt",
+    "chr5_KI270898v1_alt",
+    "chr6_GL000250v2_alt",
+    "chr6_GL000251v2_alt",
+    "chr6_GL000252v2_alt",
+    "chr6_GL000253v2_alt",
+    "chr6_GL000254v2_alt",
+    "chr6_GL000255v2_alt",
+    "chr6_GL000256v2_alt",
+    "chr6_GL383533v1_alt",
+    "chr6_KB021644v2_alt",
+    "chr6_KI270758v1_alt",
+    "chr6_KI270797v1_alt",
+    "chr6_KI270798v1_alt",
+    "chr6_KI270799v1_alt",
+    "chr6_KI270800v1_alt",
+    "chr6_KI270801v1_alt",
+    "chr6_KI270802v1_alt",
+    "chr7_GL383534v2_alt",
+    "chr7_KI270803v1_alt",
+    "chr7_KI270804v1_alt",
+    "chr7_KI270805v1_alt",
+    "chr7_KI270806v1_alt",
+    "chr7_KI270807v1_alt",
+    "chr7_KI270808v1_alt",
+    "chr7_KI270809v1_alt",
+    "chr7_KI270899v1_alt",
+    "chr8_KI270810v1_alt",
+    "chr8_KI270811v1_alt",
+    "chr8_KI270812v1_alt",
+    "chr8_KI270813v1_alt",
+    "chr8_KI270814v1_alt",
+    "chr8_KI270815v1_alt",
+    "chr8_KI270816v1_alt",
+    "chr8_KI270817v1_alt",
+    "chr8_KI270818v1_alt",
+    "chr8_KI270819v1_alt",
+    "chr8_KI270820v1_alt",
+    "chr8_KI270821v1_alt",
+    "chr8_KI270822v1_alt",
+    "chr8_KI270900v1_alt",
+    "chr8_KI270901v1_alt",
+    "chr8_KI270926v1_alt",
+    "chr9_GL383539v1_alt",
+    "chr9_GL383540v1_alt",
+    "chr9_GL383541v1_alt",
+    "chr9_GL383542v1_alt",
+    "chr9_KI270823v1_alt",
+    "chr10_GL383545v1_alt",
+    "chr10_GL383546v1_alt",
+    "chr10_KI270824v1_alt",
+    "chr10_KI270825v1_alt",
+    "chr11_GL383547v1_alt",
+    "chr11_JH159136v1_alt",
+    "chr11_JH159137v1_alt",
+    "chr11_KI270826v1_alt",
+    "chr11_KI270827v1_alt",
+    "chr11_KI270829v1_alt",
+    "chr11_KI270830v1_alt",
+    "chr11_KI270831v1_alt",
+    "chr11_KI270832v1_alt",
+    "chr11_KI270902v1_alt",
+    "chr11_KI270903v1_alt",
+    "chr11_KI270927v1_alt",
+    "chr12_GL383549v1_alt",
+    "chr12_GL383550v2_alt",
+    "chr12_GL383551v1_alt",
+    "chr12_GL383552v1_alt",
+    "chr12_GL383553v2_alt",
+    "chr12_GL877875v1_alt",
+    "chr12_GL877876v1_alt",
+    "chr12_KI270833v1_alt",
+    "chr12_KI270834v1_alt",
+    "chr12_KI270835v1_alt",
+    "chr12_KI270836v1_alt",
+    "chr12_KI270837v1_alt",
+    "chr12_KI270904v1_alt",
+    "chr13_KI270838v1_alt",
+    "chr13_KI270839v1_alt",
+    "chr13_KI270840v1_alt",
+    "chr13_KI270841v1_alt",
+    "chr13_KI270842v1_alt",
+    "chr13_KI270843v1_alt",
+    "chr14_KI270844v1_alt",
+    "chr14_KI270845v1_alt",
+    "chr14_KI270846v1_alt",
+    "chr14_KI270847v1_alt",
+    "chr15_GL383554v1_alt",
+    "chr15_GL383555v2_alt",
+    "chr15_KI270848v1_alt",
+    "chr15_KI270849v1_alt",
+    "chr15_KI270850v1_alt",
+    "chr15_KI270851v1_alt",
+    "chr15_KI270852v1_alt",
+    "chr15_KI270905v1_alt",
+    "chr15_KI270906v1_alt",
+    "chr16_GL383556v1_alt",
+    "chr16_GL383557v1_alt",
+    "chr16_KI270853v1_alt",
+    "chr16_KI270854v1_alt",
+    "chr16_KI270855v1_alt",
+    "chr16_KI270856v1_alt",
+    "chr17_GL000258v2_alt",
+    "chr17_GL383563v3_alt",
+    "chr17_GL383564v2_alt",
+    "chr17_GL383565v1_alt",
+    "chr17_GL383566v1_alt",
+    "chr17_JH159146v1_alt",
+    "chr17_JH159147v1_alt",
+    "chr17_JH159148v1_alt",
+    "chr17_KI270857v1_alt",
+    "chr17_KI270858v1_alt",
+    "chr17_KI270859v1_alt",
+    "chr17_KI270860v1_alt",
+    "chr17_KI270861v1_alt",
+    "chr17_KI270862v1_alt",
+    "chr17_KI270907v1_alt",
+    "chr17_KI270908v1_alt",
+    "chr17_KI270909v1_alt",
+    "chr17_KI270910v1_alt",
+    "chr18_GL383567v1_alt",
+    "chr18_GL383568v1_alt",
+    "chr18_GL383569v1_alt",
+    "chr18_GL383570v1_alt",
+    "chr18_GL383571v1_alt",
+    "chr18_GL383572v1_alt",
+    "chr18_KI270863v1_alt",
+    "chr18_KI270864v1_alt",
+    "chr18_KI270911v1_alt",
+    "chr18_KI270912v1_alt",
+    "chr19_GL000209v2_alt",
+    "chr19_GL383573v1_alt",
+    "chr19_GL383574v1_alt",
+    "chr19_GL383575v2_alt",
+    "chr19_GL383576v1_alt",
+    "chr19_GL949746v1_alt",
+    "chr19_GL949747v2_alt",
+    "chr19_GL949748v2_alt",
+    "chr19_GL949749v2_alt",
+    "chr19_GL949750v2_alt",
+    "chr19_GL949751v2_alt",
+    "chr19_GL949752v1_alt",
+    "chr19_GL949753v2_alt",
+    "chr19_KI270865v1_alt",
+    "chr19_KI270866v1_alt",
+    "chr19_KI270867v1_alt",
+    "chr19_KI270868v1_alt",
+    "chr19_KI270882v1_alt",
+    "chr19_KI270883v1_alt",
+    "chr19_KI270884v1_alt",
+    "chr19_KI270885v1_alt",
+    "chr19_KI270886v1_alt",
+    "chr19_KI270887v1_alt",
+    "chr19_KI270888v1_alt",
+    "chr19_KI270889v1_alt",
+    "chr19_KI270890v1_alt",
+    "chr19_KI270891v1_alt",
+    "chr19_KI270914v1_alt",
+    "chr19_KI270915v1_alt",
+    "chr19_KI270916v1_alt",
+    "chr19_KI270917v1_alt",
+    "chr19_KI270918v1_alt",
+    "chr19_KI270919v1_alt",
+    "chr19_KI270920v1_alt",
+    "chr19_KI270921v1_alt",
+    "chr19_KI270922v1_alt",
+    "chr19_KI270923v1_alt",
+    "chr19_KI270929v1_alt",
+    "chr19_KI270930v1_alt",
+    "chr19_KI270931v1_alt",
+    "chr19_KI270932v1_alt",
+    "chr19_KI270933v1_alt",
+    "chr19_KI270938v1_alt",
+    "chr20_GL383577v2_alt",
+    "chr20_KI270869v1_alt",
+    "chr20_KI270870v1_alt",
+    "chr20_KI270871v1_alt",
+    "chr21_GL383578v2_alt",
+    "chr21_GL383579v2_alt",
+    "chr21_GL383580v2_alt",
+    "chr21_GL383581v2_alt",
+    "chr21_KI270872v1_alt",
+    "chr21_KI270873v1_alt",
+    "chr21_KI270874v1_alt",
+    "chr22_GL383582v2_alt",
+    "chr22_GL383583v2_alt",
+    "chr22_KB663609v1_alt",
+    "chr22_KI270875v1_alt",
+    "chr22_KI270876v1_alt",
+    "chr22_KI270877v1_alt",
+    "chr22_KI270878v1_alt",
+    "chr22_KI270879v1_alt",
+    "chr22_KI270928v1_alt",
+    "chrX_KI270880v1_alt",
+    "chrX_KI270881v1_alt",
+    "chrX_KI270913v1_alt",
+];
+
+const T2T_CHM13_CONTIGS: &[ContigAlias] = &[
+    ["chr1", "1", "NC_060925.1", "CP068277.2"],
+    ["chr2", "2", "NC_060926.1", "CP068276.2"],
+    ["chr3", "3", "NC_060927.1", "CP068275.2"],
+    ["chr4", "4", "NC_060928.1", "CP068274.2"],
+    ["chr5", "5", "NC_060929.1", "CP068273.2"],
+    ["chr6", "6", "NC_060930.1", "CP068272.2"],
+    ["chr7", "7", "NC_060931.1", "CP068271.2"],
+    ["chr8", "8", "NC_060932.1", "CP068270.2"],
+    ["chr9", "9", "NC_060933.1", "CP068269.2"],
+    ["chr10", "10", "NC_060934.1", "CP068268.2"],
+    ["chr11", "11", "NC_060935.1", "CP068267.2"],
+    ["chr12", "12", "NC_060936.1", "CP068266.2"],
+    ["chr13", "13", "NC_060937.1", "CP068265.2"],
+    ["chr14", "14", "NC_060938.1", "CP068264.2"],
+    ["chr15", "15", "NC_060939.1", "CP068263.2"],
+    ["chr16", "16", "NC_060940.1", "CP068262.2"],
+    ["chr17", "17", "NC_060941.1", "CP068261.2"],
+    ["chr18", "18", "NC_060942.1", "CP068260.2"],
+    ["chr19", "19", "NC_060943.1", "CP068259.2"],
+    ["chr20", "20", "NC_060944.1", "CP068258.2"],
+    ["chr21", "21", "NC_060945.1", "CP068257.2"],
+    ["chr22", "22", "NC_060946.1", "CP068256.2"],
+    ["chrX", "X", "NC_060947.1", "CP068255.2"],
+    ["chrY", "Y", "NC_060948.1", "CP086569.2"],
+    ["chrM", "MT", "", "CP068254.1"],
+];
+
+/// Length of chromosome 1 to distinguish genome builds
+const CHR1_LENGTHS: [(GenomeBuild, u64); 3] = [
+    (GenomeBuild::GRCh37, 249250621),
+    (GenomeBuild::GRCh38, 248956422),
+    (GenomeBuild::T2tChm13, 248387328),
+];
+
+fn contigs(build: GenomeBuild) -> &'static [ContigAlias] {
+    match build {
+        GenomeBuild::GRCh37 => GRCH37_CONTIGS,
+        GenomeBuild::GRCh38 => GRCH38_CONTIGS,
+        GenomeBuild::T2tChm13 => T2T_CHM13_CONTIGS,
+    }
+}
+
+fn non_primary_contigs(build: GenomeBuild) -> &'static [&'static str] {
+    match build {
+        GenomeBuild::GRCh37 => GRCH37_NON_PRIMARY_CONTIGS,
+        GenomeBuild::GRCh38 => GRCH38_NON_PRIMARY_CONTIGS,
+        GenomeBuild::T2tChm13 => &[],
+    }
+}
+
+fn column(naming: ContigNaming) -> usize {
+    match naming {
+        ContigNaming::Ucsc => 0,
+        ContigNaming::Ensembl => 1,
+        ContigNaming::RefSeq => 2,
+        ContigNaming::GenBank => 3,
+    }
+}
+
+fn is_accession(name: &[u8]) -> bool {
+    let letters = name.iter().take_while(|x| x.is_ascii_uppercase()).count();
+    let (_, rest) = name.split_at(letters);
+    match rest.iter().position(|x| *x == b'.') {
+        Some(dot) => {
+            letters > 0
+                && dot > 0
+                && dot + 1 < rest.len()
+                && rest
+                    .iter()
+                    .enumerate()
+                    .all(|(i, x)| i == dot || x.is_ascii_digit())
+        }
+        None => false,
+    }
+}
+
+/// GenBank accession of alternative, unlocalized, unplaced and decoy contigs in UCSC naming
+/// (e.g. `chr1_KI270706v1_random` and `chrUn_gl000220` to `KI270706.1` and `GL000220.1`).
+fn ucsc_to_accession(name: &[u8]) -> Option<U8Vec> {
+    let mut elements = name.strip_prefix(b"chr")?.split(|x| *x == b'_');
+    elements.next()?;
+    let accession = elements.next()?;
+    if let Some(v) = accession.iter().rposition(|x| *x == b'v') {
+        let mut result = accession[..v].to_vec();
+        result.push(b'.');
+        result.extend_from_slice(&accession[v + 1..]);
+        Some(result).filter(|x| is_accession(x))
+    } else {
+        let mut result = accession.to_ascii_uppercase();
+        result.extend_from_slice(b".1");
+        Some(result).filter(|x| is_accession(x))
+    }
+}
+
+/// UCSC name of alternative, unlocalized, unplaced and decoy contigs from GenBank accession
+/// (e.g. `KI270706.1` to `chr1_KI270706v1_random`).
+fn accession_to_ucsc(build: GenomeBuild, accession: &[u8]) -> Option<U8Vec> {
+    if let Some(name) = non_primary_contigs(build)
+        .iter()
+        .find(|x| ucsc_to_accession(x.as_bytes()).as_deref() == Some(accession))
+    {
+        return Some(name.as_bytes().to_vec());
+    }
+    // decoy contigs of hs38d1
+    if build == GenomeBuild::GRCh38 && accession.starts_with(b"JTFH01") {
+        let dot = accession.iter().position(|x| *x == b'.')?;
+        let mut name = b"chrUn_".to_vec();
+        name.extend_from_slice(&accession[..dot]);
+        name.push(b'v');
+        name.extend_from_slice(&accession[dot + 1..]);
+        name.extend_from_slice(b"_decoy");
+        return Some(name);
+    }
+    None
+}
+
+/// Convert a contig name. Alternative, unlocalized, unplaced and decoy contigs are converted
+/// between UCSC names and GenBank accessions, which are used by both Ensembl and GenBank. `None`
+/// is returned if the name cannot be converted.
+pub fn convert_contig_name(
+    build: GenomeBuild,
+    from: ContigNaming,
+    to: ContigNaming,
+    name: &[u8],
+) -> Option<U8Vec> {
+    if let Some(alias) = contigs(build)
+        .iter()
+        .find(|x| !x[column(from)].is_empty() && x[column(from)].as_bytes() == name)
+    {
+        return Some(alias[column(to)].as_bytes().to_vec()).filter(|x| !x.is_empty());
+    }
+    if from == to {
+        return Some(name.to_vec());
+    }
+    let accession = match from {
+        ContigNaming::Ucsc => ucsc_to_accession(name)?,
+        ContigNaming::Ensembl | ContigNaming::GenBank if is_accession(name) => name.to_vec(),
+        _ => return None,
+    };
+    match to {
+        ContigNaming::Ensembl | ContigNaming::GenBank => Some(accession),
+        ContigNaming::Ucsc => accession_to_ucsc(build, &accession),
+        ContigNaming::RefSeq => None,
+    }
+}
+
+/// Create contig name mapping of all contigs in the dictionary and `extra_contigs`. Contigs which
+/// cannot be converted are not included.
+pub fn contig_name_mapping(
+    build: GenomeBuild,
+    from: ContigNaming,
+    to: ContigNaming,
+    extra_contigs: &[U8Vec],
+) -> HashMap<U8Vec, U8Vec> {
+    let non_primary_names = non_primary_contigs(build)
+        .iter()
+        .filter_map(|x| match from {
+            ContigNaming::Ucsc => Some(x.as_bytes().to_vec()),
+            ContigNaming::Ensembl | ContigNaming::GenBank => ucsc_to_accession(x.as_bytes()),
+            ContigNaming::RefSeq => None,
+        });
+    contigs(build)
+        .iter()
+        .map(|x| x[column(from)].as_bytes().to_vec())
+        .filter(|x| !x.is_empty())
+        .chain(non_primary_names)
+        .chain(extra_contigs.iter().cloned())
+        .filter_map(|x| convert_contig_name(build, from, to, &x).map(|y| (x, y)))
+        .collect()
+}
+
+/// Detect naming convention from contig names. The convention with the most contigs in the
+/// dictionary is selected.
+pub fn detect_contig_naming(contigs: &[U8Vec]) -> Option<ContigNaming> {
+    ALL_CONTIG_NAMINGS
+        .iter()
+        .map(|naming| {
+            let count = contigs
+                .iter()
+                .filter(|name| {
+                    ALL_GENOME_BUILDS.iter().any(|build| {
+                        self::contigs(*build)
+                            .iter()
+                            .any(|x| x[column(*naming)].as_bytes() == &name[..])
+                    })
+                })
+                .count();
+            (count, *naming)
+        })
+        .filter(|(count, _)| *count > 0)
+        .max_by_key(|(count, _)| *count)
+        .map(|(_, naming)| naming)
+}
+
+/// Detect genome build from RefSeq or GenBank accessions, or length of chromosome 1.
+pub fn detect_genome_build(contigs: &[(U8Vec, Option<u64>)]) -> Option<GenomeBuild> {
+    for (name, length) in contigs {
+        for (build, chr1_length) in CHR1_LENGTHS {
+            let chr1 = &self::contigs(build)[0];
+            if chr1.iter().any(|x| x.as_bytes() == &name[..]) {
+                if chr1[2..].iter().any(|x| x.as_bytes() == &name[..]) {
+                    return Some(build);
+                }
+                if *length == Some(chr1_length) {
+                    return Some(build);
+                }
+            }
+        }
+    }
+    ALL_GENOME_BUILDS
+        .iter()
+        .map(|build| {
+            let count = contigs
+                .iter()
+                .filter(|(name, _)| {
+                    self::contigs(*build)
+                        .iter()
+                        .any(|x| x[2..].iter().any(|y| y.as_bytes() == &name[..]))
+                })
+                .count();
+            (count, *build)
+        })
+        .filter(|(count, _)| *count > 0)
+        .max_by_key(|(count, _)| *count)
+        .map(|(_, build)| build)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_convert_contig_name() {
+        let convert = |build, from, to, name: &[u8]| convert_contig_name(build, from, to, name);
+        assert_eq!(
+            convert(
+                GenomeBuild::GRCh38,
+                ContigNaming::RefSeq,
+                ContigNaming::Ucsc,
+                b"NC_000001.11"
+            ),
+            Some(b"chr1".to_vec())
+        );
+        assert_eq!(
+            convert(
+                GenomeBuild::GRCh38,
+                ContigNaming::Ensembl,
+                ContigNaming::Ucsc,
+                b"MT"
+            ),
+            Some(b"chrM".to_vec())
+        );
+        assert_eq!(
+            convert(
+                GenomeBuild::GRCh37,
+                ContigNaming::Ensembl,
+                ContigNaming::Ucsc,
+                b"MT"
+            ),
+            None
+        );
+        assert_eq!(
+            convert(
+                GenomeBuild::GRCh37,
+                ContigNaming::Ucsc,
+                ContigNaming::Ensembl,
+                b"chrM"
+            ),
+            None
+        );
+        assert_eq!(
+            convert(
+                GenomeBuild::GRCh37,
+                ContigNaming::Ucsc,
+                ContigNaming::RefSeq,
+                b"chrM"
+            ),
+            Some(b"NC_001807.4".to_vec())
+        );
+        assert_eq!(
+            convert(
+                GenomeBuild::T2tChm13,
+                ContigNaming::Ucsc,
+                ContigNaming::GenBank,
+                b"chrX"
+            ),
+            Some(b"CP068255.2".to_vec())
+        );
+        assert_eq!(
+            convert(
+                GenomeBuild::GRCh38,
+                ContigNaming::Ucsc,
+                ContigNaming::Ensembl,
+                b"chr1_KI270706v1_random"
+            ),
+            Some(b"KI270706.1".to_vec())
+        );
+        assert_eq!(
+            convert(
+                GenomeBuild::GRCh38,
+                ContigNaming::Ucsc,
+                ContigNaming::GenBank,
+                b"chrUn_JTFH01000001v1_decoy"
+            ),
+            Some(b"JTFH01000001.1".to_vec())
+        );
+        assert_eq!(
+            convert(
+                GenomeBuild::GRCh37,
+                ContigNaming::Ucsc,
+                ContigNaming::Ensembl,
+                b"chrUn_gl000220"
+            ),
+            Some(b"GL000220.1".to_vec())
+        );
+        assert_eq!(
+            convert(
+                GenomeBuild::GRCh38,
+                ContigNaming::Ensembl,
+                ContigNaming::Ucsc,
+                b"KI270706.1"
+            ),
+            Some(b"chr1_KI270706v1_random".to_vec())
+        );
+        assert_eq!(
+            convert(
+                GenomeBuild::GRCh38,
+                ContigNaming::GenBank,
+                ContigNaming::Ucsc,
+                b"GL000250.2"
+            ),
+            Some(b"chr6_GL000250v2_alt".to_vec())
+        );
+        assert_eq!(
+            convert(
+                GenomeBuild::GRCh38,
+                ContigNaming::Ensembl,
+                ContigNaming::Ucsc,
+                b"JTFH01000001.1"
+            ),
+            Some(b"chrUn_JTFH01000001v1_decoy".to_vec())
+        );
+        assert_eq!(
+            convert(
+                GenomeBuild::GRCh37,
+                ContigNaming::Ensembl,
+                ContigNaming::Ucsc,
+                b"GL000192.1"
+            ),
+            Some(b"chr1_gl000192_random".to_vec())
+        );
+        assert_eq!(
+            convert(
+                GenomeBuild::GRCh37,
+                ContigNaming::Ensembl,
+                ContigNaming::Ucsc,
+                b"GL000220.1"
+            ),
+            Some(b"chrUn_gl000220".to_vec())
+        );
+        assert_eq!(
+            convert(
+                GenomeBuild::GRCh37,
+                ContigNaming::GenBank,
+                ContigNaming::Ucsc,
+                b"GL000250.1"
+            ),
+            Some(b"chr6_apd_hap1".to_vec())
+        );
+        assert_eq!(
+            convert(
+                GenomeBuild::GRCh37,
+                ContigNaming::RefSeq,
+                ContigNaming::Ensembl,
+                b"NC_007605.1"
+            ),
+            Some(b"NC_007605".to_vec())
+        );
+        assert_eq!(
+            convert(
+                GenomeBuild::GRCh38,
+                ContigNaming::Ensembl,
+                ContigNaming::RefSeq,
+                b"KI270706.1"
+            ),
+            None
+        );
+        assert_eq!(
+            convert(
+                GenomeBuild::GRCh37,
+                ContigNaming::Ensembl,
+                ContigNaming::Ucsc,
+                b"hs37d5"
+            ),
+            None
+        );
+        assert_eq!(
+            convert(
+                GenomeBuild::T2tChm13,
+                ContigNaming::Ucsc,
+                ContigNaming::RefSeq,
+                b"chrM"
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn test_detect() {
+        let contigs = [
+            (b"chr1".to_vec(), Some(248956422)),
+            (b"chr2".to_vec(), Some(242193529)),
+            (b"chr1_KI270706v1_random".to_vec(), Some(175055)),
+        ];
+        let names: Vec<_> = contigs.iter().map(|x| x.0.clone()).collect();
+        assert_eq!(detect_contig_naming(&names), Some(ContigNaming::Ucsc));
+        assert_eq!(detect_genome_build(&contigs), Some(GenomeBuild::GRCh38));
+
+        let contigs = [
+            (b"NC_000001.10".to_vec(), None),
+            (b"NC_000002.11".to_vec(), None),
+        ];
+        let names: Vec<_> = contigs.iter().map(|x| x.0.clone()).collect();
+        assert_eq!(detect_contig_naming(&names), Some(ContigNaming::RefSeq));
+        assert_eq!(detect_genome_build(&contigs), Some(GenomeBuild::GRCh37));
+
+        let contigs = [(b"1".to_vec(), Some(248387328)), (b"MT".to_vec(), None)];
+        let names: Vec<_> = contigs.iter().map(|x| x.0.clone()).collect();
+        assert_eq!(detect_contig_naming(&names), Some(ContigNaming::Ensembl));
+        assert_eq!(detect_genome_build(&contigs), Some(GenomeBuild::T2tChm13));
+
+        assert_eq!(detect_contig_naming(&[b"scaffold_1".to_vec()]), None);
+        assert_eq!(detect_genome_build(&[(b"1".to_vec(), None)]), None);
+    }
+}
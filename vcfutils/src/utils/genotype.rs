@@ -0,0 +1,74 @@
+//! Parsing of GT and integer FORMAT values of samples
+
+use crate::error::VCFUtilsError;
+use std::str;
+use vcf::VCFRecord;
+
+pub fn is_chrx(chromosome: &[u8]) -> bool {
+    chromosome == b"X" || chromosome == b"chrX"
+}
+
+/// Parse GT value into allele indexes. Missing alleles are `None`.
+pub fn parse_genotype(genotype: &[u8]) -> Result<Vec<Option<usize>>, VCFUtilsError> {
+    genotype
+        .split(|x| *x == b'/' || *x == b'|')
+        .map(|x| {
+            if x == b"." {
+                Ok(None)
+            } else {
+                str::from_utf8(x)?
+                    .parse::<usize>()
+                    .map(Some)
+                    .map_err(|_| VCFUtilsError::GenotypeParseError)
+            }
+        })
+        .collect()
+}
+
+/// Log a warning of malformed GT of a sample, which is treated as missing.
+pub fn warn_malformed_genotype(record: &VCFRecord, sample: &[u8], genotype: &[u8]) {
+    log::warn!(
+        "Malformed GT of {} at {}:{} is treated as missing: {}",
+        String::from_utf8_lossy(sample),
+        String::from_utf8_lossy(&record.chromosome),
+        record.position,
+        String::from_utf8_lossy(genotype)
+    );
+}
+
+/// Parse GT of a sample. Missing GT is `[None]`, and malformed GT is treated as missing with a
+/// warning.
+pub fn sample_genotype(record: &VCFRecord, sample: &[u8]) -> Vec<Option<usize>> {
+    match record.genotype(sample, b"GT").and_then(|x| x.first()) {
+        Some(gt) => parse_genotype(gt).unwrap_or_else(|_| {
+            warn_malformed_genotype(record, sample, gt);
+            vec![None]
+        }),
+        None => vec![None],
+    }
+}
+
+pub fn parse_format_integer(record: &VCFRecord, sample: &[u8], key: &[u8]) -> Option<u64> {
+    record
+        .genotype(sample, key)
+        .and_then(|x| x.first())
+        .and_then(|x| str::from_utf8(x).ok())
+        .and_then(|x| x.parse::<f64>().ok())
+        .filter(|x| *x >= 0.)
+        .map(|x| x.round() as u64)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_genotype() -> Result<(), VCFUtilsError> {
+        assert_eq!(parse_genotype(b"0/1")?, vec![Some(0), Some(1)]);
+        assert_eq!(parse_genotype(b"1|2")?, vec![Some(1), Some(2)]);
+        assert_eq!(parse_genotype(b"./.")?, vec![None, None]);
+        assert_eq!(parse_genotype(b"1")?, vec![Some(1)]);
+        assert!(parse_genotype(b"A/1").is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,246 @@
+//! gVCF reference blocks and non-reference (`<NON_REF>`, `<*>`) alleles.
+
+use super::recalc_af::{genotype_index, number_of_genotype};
+use std::str;
+use vcf::{Number, U8Vec, VCFHeaderContent, VCFHeaderLine, VCFRecord};
+
+/// Test whether the allele represents any unobserved alternative allele. `<NON_REF>` is used by
+/// GATK and DRAGEN, and `<*>` is used by bcftools.
+pub fn is_non_ref_allele(allele: &[u8]) -> bool {
+    allele == b"<NON_REF>" || allele == b"<*>"
+}
+
+/// Test whether the record is a reference block, which has only non-reference alleles.
+pub fn is_reference_block(record: &VCFRecord) -> bool {
+    !record.alternative.is_empty() && record.alternative.iter().all(|x| is_non_ref_allele(x))
+}
+
+/// Test whether the header line is only meaningful for gVCF, such as `##GVCFBlock` and ALT
+/// definition of `<NON_REF>`.
+pub fn is_gvcf_header_line(line: &VCFHeaderLine) -> bool {
+    match line.contents() {
+        VCFHeaderContent::ALT { id, .. } => id == b"NON_REF" || id == b"*",
+        _ => line.line().starts_with(b"##GVCFBlock"),
+    }
+}
+
+/// Sorted allele indexes of all genotypes in the VCF genotype ordering.
+fn genotypes(allele_count: usize, ploidy: usize) -> Vec<Vec<usize>> {
+    if ploidy == 0 {
+        return vec![Vec::new()];
+    }
+    let mut result = Vec::new();
+    for last in 0..allele_count {
+        for mut one in genotypes(last + 1, ploidy - 1) {
+            one.push(last);
+            result.push(one);
+        }
+    }
+    result
+}
+
+/// Select values of kept alleles. `kept_alleles` are allele indexes including reference.
+/// Values are not changed if the number of values does not match with the number of alleles.
+fn subset_values(
+    values: &mut Vec<U8Vec>,
+    number: &Number,
+    kept_alleles: &[usize],
+    allele_count: usize,
+) {
+    match number {
+        Number::Allele if values.len() == allele_count - 1 => {
+            *values = kept_alleles[1..]
+                .iter()
+                .map(|x| values[x - 1].clone())
+                .collect();
+        }
+        Number::Reference if values.len() == allele_count => {
+            *values = kept_alleles.iter().map(|x| values[*x].clone()).collect();
+        }
+        Number::Genotype => {
+            if let Some(ploidy) =
+                (1..=4).find(|x| number_of_genotype(allele_count - 1, *x) == values.len())
+            {
+                *values = genotypes(kept_alleles.len(), ploidy)
+                    .into_iter()
+                    .map(|x| {
+                        let old_alleles: Vec<_> = x.iter().map(|y| kept_alleles[*y]).collect();
+                        values[genotype_index(&old_alleles)].clone()
+                    })
+                    .collect();
+            }
+        }
+        _ => (),
+    }
+}
+
+/// Replace allele indexes in GT. Removed alleles are replaced with missing allele.
+fn remap_genotype(genotype: &[u8], allele_map: &[Option<usize>]) -> U8Vec {
+    let mut result = Vec::with_capacity(genotype.len());
+    let mut allele = Vec::new();
+    for one in genotype.iter().copied().chain(std::iter::once(b'/')) {
+        if one != b'/' && one != b'|' {
+            allele.push(one);
+            continue;
+        }
+        match str::from_utf8(&allele)
+            .ok()
+            .and_then(|x| x.parse::<usize>().ok())
+        {
+            Some(index) => match allele_map.get(index).copied().flatten() {
+                Some(new_index) => result.extend_from_slice(new_index.to_string().as_bytes()),
+                None => result.push(b'.'),
+            },
+            None => result.extend_from_slice(&allele),
+        }
+        result.push(one);
+        allele.clear();
+    }
+    result.pop();
+    result
+}
+
+/// Remove non-reference alleles from a record. Number=A, R and G values in INFO and FORMAT are
+/// subset, and allele indexes in GT are updated. Returns `false` if no alternative allele is
+/// left, that is, the record is a reference block.
+pub fn remove_non_ref_alleles(record: &mut VCFRecord) -> bool {
+    let kept_alleles: Vec<usize> = std::iter::once(0)
+        .chain(
+            record
+                .alternative
+                .iter()
+                .enumerate()
+                .filter(|(_, x)| !is_non_ref_allele(x))
+                .map(|(i, _)| i + 1),
+        )
+        .collect();
+    let allele_count = record.alternative.len() + 1;
+    if kept_alleles.len() == allele_count {
+        return true;
+    }
+    if kept_alleles.len() == 1 {
+        return false;
+    }
+
+    let info_numbers: Vec<_> = record
+        .info
+        .iter()
+        .map(|(key, _)| record.header().info(key).map(|x| x.number.clone()))
+        .collect();
+    let format_numbers: Vec<_> = record
+        .format
+        .iter()
+        .map(|key| record.header().format(key).map(|x| x.number.clone()))
+        .collect();
+    let mut allele_map = vec![None; allele_count];
+    for (new_index, old_index) in kept_alleles.iter().enumerate() {
+        allele_map[*old_index] = Some(new_index);
+    }
+
+    for ((_, values), number) in record.info.iter_mut().zip(info_numbers.iter()) {
+        if let Some(number) = number {
+            subset_values(values, number, &kept_alleles, allele_count);
+        }
+    }
+    for sample in record.genotype.iter_mut() {
+        for ((values, number), key) in sample
+            .iter_mut()
+            .zip(format_numbers.iter())
+            .zip(record.format.iter())
+        {
+            if key == b"GT" {
+                for one in values.iter_mut() {
+                    *one = remap_genotype(one, &allele_map);
+                }
+            } else if let Some(number) = number {
+                subset_values(values, number, &kept_alleles, allele_count);
+            }
+        }
+    }
+    record.alternative = kept_alleles[1..]
+        .iter()
+        .map(|x| record.alternative[x - 1].clone())
+        .collect();
+    true
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::error::VCFUtilsError;
+    use vcf::VCFReader;
+
+    #[test]
+    fn test_genotypes() {
+        for ploidy in 1..=3 {
+            for (i, one) in genotypes(4, ploidy).iter().enumerate() {
+                assert_eq!(genotype_index(one), i);
+            }
+            assert_eq!(genotypes(4, ploidy).len(), number_of_genotype(3, ploidy));
+        }
+    }
+
+    #[test]
+    fn test_remap_genotype() {
+        let allele_map = [Some(0), None, Some(1)];
+        assert_eq!(remap_genotype(b"0/2", &allele_map), b"0/1");
+        assert_eq!(remap_genotype(b"2|1", &allele_map), b"1|.");
+        assert_eq!(remap_genotype(b"./.", &allele_map), b"./.");
+        assert_eq!(remap_genotype(b"2", &allele_map), b"1");
+    }
+
+    #[test]
+    fn test_remove_non_ref_alleles() -> Result<(), VCFUtilsError> {
+        let data = b"##fileformat=VCFv4.2
+##ALT=<ID=NON_REF,Description=\"Represents any possible alternative allele\">
+##INFO=<ID=END,Number=1,Type=Integer,Description=\"End position\">
+##INFO=<ID=AC,Number=A,Type=Integer,Description=\"Allele count\">
+##FORMAT=<ID=GT,Number=1,Type=String,Description=\"Genotype\">
+##FORMAT=<ID=AD,Number=R,Type=Integer,Description=\"Allelic depths\">
+##FORMAT=<ID=PL,Number=G,Type=Integer,Description=\"Phred-scaled genotype likelihoods\">
+#CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO\tFORMAT\tS1
+chr1\t100\t.\tA\t<NON_REF>\t.\t.\tEND=120\tGT:AD:PL\t0/0:10,0:0,30,450
+chr1\t121\t.\tA\tG,<NON_REF>,T\t.\t.\tAC=1,0,1\tGT:AD:PL\t1/3:0,5,0,6:90,40,30,90,40,90,60,20,60,10
+chr1\t122\t.\tC\tT\t.\t.\tAC=1\tGT:AD:PL\t0/1:4,5:40,0,40
+";
+        let mut reader = VCFReader::new(&data[..])?;
+        let mut record = VCFRecord::new(reader.header().clone());
+
+        reader.next_record(&mut record)?;
+        assert!(is_reference_block(&record));
+        assert!(!remove_non_ref_alleles(&mut record));
+
+        reader.next_record(&mut record)?;
+        assert!(!is_reference_block(&record));
+        assert!(remove_non_ref_alleles(&mut record));
+        assert_eq!(record.alternative, vec![b"G".to_vec(), b"T".to_vec()]);
+        assert_eq!(
+            record.info(b"AC"),
+            Some(&vec![b"1".to_vec(), b"1".to_vec()])
+        );
+        assert_eq!(record.genotype(b"S1", b"GT"), Some(&vec![b"1/2".to_vec()]));
+        assert_eq!(
+            record.genotype(b"S1", b"AD"),
+            Some(&vec![b"0".to_vec(), b"5".to_vec(), b"6".to_vec()])
+        );
+        // 0/0, 0/1, 1/1, 0/3, 1/3, 3/3 of the original alleles
+        assert_eq!(
+            record.genotype(b"S1", b"PL"),
+            Some(
+                &[&b"90"[..], b"40", b"30", b"60", b"20", b"10"]
+                    .iter()
+                    .map(|x| x.to_vec())
+                    .collect::<Vec<_>>()
+            )
+        );
+
+        reader.next_record(&mut record)?;
+        assert!(remove_non_ref_alleles(&mut record));
+        assert_eq!(record.alternative, vec![b"T".to_vec()]);
+        assert_eq!(
+            record.genotype(b"S1", b"PL"),
+            Some(&vec![b"40".to_vec(), b"0".to_vec(), b"40".to_vec()])
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,133 @@
+// Hardy-Weinberg equilibrium statistics for biallelic sites
+
+/// Result of Hardy-Weinberg exact test
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+pub struct HweTestResult {
+    /// Two-sided HWE p-value
+    pub p_value: f64,
+    /// One-sided p-value for excess of heterozygotes
+    pub het_excess_p_value: f64,
+}
+
+/// Hardy-Weinberg exact test (Wigginton, Cutler and Abecasis 2005).
+///
+/// Returns `None` if no genotype is observed.
+pub fn hwe_exact_test(obs_het: u64, obs_hom1: u64, obs_hom2: u64) -> Option<HweTestResult> {
+    let obs_homr = obs_hom1.min(obs_hom2) as usize;
+    let obs_homc = obs_hom1.max(obs_hom2) as usize;
+    let obs_het = obs_het as usize;
+    let rare_copies = 2 * obs_homr + obs_het;
+    let genotypes = obs_het + obs_homc + obs_homr;
+    if genotypes == 0 {
+        return None;
+    }
+
+    let mut het_probs = vec![0.0f64; rare_copies + 1];
+
+    // start at the most likely number of heterozygotes
+    let mut mid = rare_copies * (2 * genotypes - rare_copies) / (2 * genotypes);
+    if mid % 2 != rare_copies % 2 {
+        mid += 1;
+    }
+
+    het_probs[mid] = 1.0;
+    let mut sum = 1.0;
+
+    let mut curr_hets = mid;
+    let mut curr_homr = (rare_copies - mid) / 2;
+    let mut curr_homc = genotypes - curr_hets - curr_homr;
+    while curr_hets >= 2 {
+        het_probs[curr_hets - 2] =
+            het_probs[curr_hets] * (curr_hets as f64) * ((curr_hets - 1) as f64)
+                / (4.0 * ((curr_homr + 1) as f64) * ((curr_homc + 1) as f64));
+        sum += het_probs[curr_hets - 2];
+        curr_hets -= 2;
+        curr_homr += 1;
+        curr_homc += 1;
+    }
+
+    let mut curr_hets = mid;
+    let mut curr_homr = (rare_copies - mid) / 2;
+    let mut curr_homc = genotypes - curr_hets - curr_homr;
+    while curr_hets + 2 <= rare_copies {
+        het_probs[curr_hets + 2] =
+            het_probs[curr_hets] * 4.0 * (curr_homr as f64) * (curr_homc as f64)
+                / (((curr_hets + 2) as f64) * ((curr_hets + 1) as f64));
+        sum += het_probs[curr_hets + 2];
+        curr_hets += 2;
+        curr_homr -= 1;
+        curr_homc -= 1;
+    }
+
+    for one in het_probs.iter_mut() {
+        *one /= sum;
+    }
+
+    let obs_prob = het_probs[obs_het];
+    let p_value: f64 = het_probs.iter().filter(|x| **x <= obs_prob).sum();
+    let het_excess_p_value: f64 = het_probs[obs_het..].iter().sum();
+
+    Some(HweTestResult {
+        p_value: p_value.min(1.0),
+        het_excess_p_value: het_excess_p_value.min(1.0),
+    })
+}
+
+/// Inbreeding coefficient calculated as `1 - (observed heterozygotes) / (expected heterozygotes)`.
+///
+/// Returns `None` if expected number of heterozygotes is zero.
+pub fn inbreeding_coefficient(obs_het: u64, obs_hom1: u64, obs_hom2: u64) -> Option<f64> {
+    let genotypes = (obs_het + obs_hom1 + obs_hom2) as f64;
+    let p = ((2 * obs_hom1 + obs_het) as f64) / (2.0 * genotypes);
+    let expected_het = 2.0 * p * (1.0 - p) * genotypes;
+    if genotypes == 0.0 || expected_het == 0.0 {
+        None
+    } else {
+        Some(1.0 - (obs_het as f64) / expected_het)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn assert_near(a: f64, b: f64) {
+        assert!((a - b).abs() < 1e-6, "{} != {}", a, b);
+    }
+
+    #[test]
+    fn test_hwe_exact_test() {
+        assert_eq!(hwe_exact_test(0, 0, 0), None);
+
+        let result = hwe_exact_test(0, 10, 0).unwrap();
+        assert_near(result.p_value, 1.0);
+        assert_near(result.het_excess_p_value, 1.0);
+
+        // all heterozygotes
+        let result = hwe_exact_test(4, 0, 0).unwrap();
+        assert_near(result.p_value, 0.314285714);
+        assert_near(result.het_excess_p_value, 0.228571429);
+
+        // no heterozygotes
+        let result = hwe_exact_test(0, 2, 2).unwrap();
+        assert_near(result.p_value, 0.085714286);
+        assert_near(result.het_excess_p_value, 1.0);
+
+        let result = hwe_exact_test(1, 1, 1).unwrap();
+        assert_near(result.p_value, 1.0);
+        assert_near(result.het_excess_p_value, 1.0);
+
+        let result = hwe_exact_test(57, 14, 29).unwrap();
+        assert_near(result.p_value, 0.150680077);
+        assert_near(result.het_excess_p_value, 0.078634035);
+    }
+
+    #[test]
+    fn test_inbreeding_coefficient() {
+        assert_eq!(inbreeding_coefficient(0, 0, 0), None);
+        assert_eq!(inbreeding_coefficient(0, 10, 0), None);
+        assert_near(inbreeding_coefficient(4, 0, 0).unwrap(), -1.0);
+        assert_near(inbreeding_coefficient(0, 2, 2).unwrap(), 1.0);
+        assert_near(inbreeding_coefficient(2, 1, 1).unwrap(), 0.0);
+    }
+}
@@ -1,22 +1,158 @@
+pub mod annotation;
+pub mod arrowwriter;
+pub mod bcf;
+pub mod contig_alias;
+pub mod genotype;
+pub mod gvcf;
+pub mod hwe;
 pub mod recalc_af;
+pub mod structural_variant;
+pub mod tabix;
 pub mod tablewriter;
 
 use crate::error::VCFUtilsError;
+use autocompress::io::RayonWriter;
 use csv::Reader as CSVReader;
 use std::fs::File;
 use std::io::prelude::*;
 use std::path::Path;
 use std::{collections::HashMap, io::BufReader};
-use vcf::{U8Vec, VCFReader};
+use vcf::{U8Vec, VCFReader, VCFRecord};
+
+/// Open VCF text from file or standard input. BCF files are detected by magic bytes and
+/// converted to VCF text.
+pub fn open_vcf_stream<P: AsRef<Path>>(
+    path: Option<P>,
+) -> Result<Box<dyn BufRead + Send>, VCFUtilsError> {
+    let path = path.map(|x| x.as_ref().to_path_buf());
+    let mut reader = BufReader::new(autocompress::autodetect_open_or_stdin(path.clone())?);
+    if !bcf::is_bcf(reader.fill_buf()?) {
+        return Ok(Box::new(reader));
+    }
+    let path = path.ok_or(VCFUtilsError::OtherError(
+        "BCF from standard input is not supported",
+    ))?;
+    Ok(Box::new(bcf::BCFTextReader::from_path(path)?))
+}
 
 pub fn open_vcf_from_path<P: AsRef<Path>>(
     path: Option<P>,
 ) -> Result<VCFReader<impl BufRead>, VCFUtilsError> {
-    let path = path.map(|x| x.as_ref().to_path_buf());
-    VCFReader::new(BufReader::new(autocompress::autodetect_open_or_stdin(
-        path,
-    )?))
-    .map_err(|e| e.into())
+    VCFReader::new(open_vcf_stream(path)?).map_err(|e| e.into())
+}
+
+/// Maximum number of records processed in parallel at once
+pub const RECORD_CHUNK_SIZE: usize = 1000;
+
+/// Approximate number of genotypes held in memory by one chunk of records
+pub const RECORD_CHUNK_GENOTYPES: usize = 1_000_000;
+
+/// Number of records processed in parallel at once. Chunks of VCFs with many samples are
+/// smaller to bound memory usage, but have at least one record per thread.
+pub fn record_chunk_size(sample_count: usize) -> usize {
+    (RECORD_CHUNK_GENOTYPES / sample_count.max(1))
+        .max(rayon::current_num_threads())
+        .min(RECORD_CHUNK_SIZE)
+}
+
+/// Read up to `chunk_size` records into `chunk` and return the number of records read.
+/// Records in `chunk` are reused to avoid cloning VCF header for each record.
+pub fn read_record_chunk<R: BufRead>(
+    reader: &mut VCFReader<R>,
+    chunk: &mut Vec<VCFRecord>,
+    chunk_size: usize,
+) -> Result<usize, VCFUtilsError> {
+    let mut count = 0;
+    while count < chunk_size {
+        if chunk.len() <= count {
+            chunk.push(reader.empty_record());
+        }
+        if !reader.next_record(&mut chunk[count])? {
+            break;
+        }
+        count += 1;
+    }
+    Ok(count)
+}
+
+/// Set the number of threads for record-level parallel processing. `0` uses all CPUs.
+pub fn set_threads(threads: usize) -> Result<(), rayon::ThreadPoolBuildError> {
+    rayon::ThreadPoolBuilder::new()
+        .num_threads(threads)
+        .build_global()
+}
+
+/// VCF text or BCF output created by [`create_vcf_writer`]. Call [`VCFOutput::close`] after
+/// writing to get errors on flushing and closing the file.
+pub enum VCFOutput {
+    Text(Box<dyn Write + Send>),
+    Bcf(bcf::BCFTextWriter),
+}
+
+impl VCFOutput {
+    pub fn close(self) -> Result<(), VCFUtilsError> {
+        match self {
+            VCFOutput::Text(mut x) => x.flush()?,
+            VCFOutput::Bcf(x) => x.close()?,
+        }
+        Ok(())
+    }
+}
+
+impl Write for VCFOutput {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        match self {
+            VCFOutput::Text(x) => x.write(buf),
+            VCFOutput::Bcf(x) => x.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        match self {
+            VCFOutput::Text(x) => x.flush(),
+            VCFOutput::Bcf(x) => x.flush(),
+        }
+    }
+}
+
+/// Create VCF output to file or standard output. BCF is written if the file name ends with
+/// `.bcf`.
+pub fn create_vcf_writer(path: Option<&str>) -> Result<VCFOutput, VCFUtilsError> {
+    match path {
+        Some(x) if bcf::is_bcf_path(x) => Ok(VCFOutput::Bcf(bcf::BCFTextWriter::new(x))),
+        _ => Ok(VCFOutput::Text(Box::new(RayonWriter::new(
+            autocompress::autodetect_create_or_stdout_prefer_bgzip(
+                path,
+                autocompress::CompressionLevel::Default,
+            )?,
+        )))),
+    }
+}
+
+/// `numerator / denominator`, or `None` if the denominator is 0
+pub fn ratio(numerator: u64, denominator: u64) -> Option<f64> {
+    if denominator == 0 {
+        None
+    } else {
+        Some(numerator as f64 / denominator as f64)
+    }
+}
+
+/// Load a list file which contains one item per line. Empty lines are ignored.
+pub fn load_list<P: AsRef<Path>>(path: P) -> Result<Vec<U8Vec>, VCFUtilsError> {
+    let mut reader = BufReader::new(autocompress::autodetect_open(path)?);
+    let mut list = Vec::new();
+    let mut buffer = Vec::new();
+    while reader.read_until(b'\n', &mut buffer)? > 0 {
+        while buffer.ends_with(b"\n") || buffer.ends_with(b"\r") {
+            buffer.pop();
+        }
+        if !buffer.is_empty() {
+            list.push(buffer.clone());
+        }
+        buffer.clear();
+    }
+    Ok(list)
 }
 
 pub fn tsv_reader_builder() -> csv::ReaderBuilder {
@@ -93,6 +229,49 @@ mod test {
         Ok(())
     }
 
+    #[test]
+    fn test_load_list() -> Result<(), VCFUtilsError> {
+        let list = load_list("./testfiles/canonical.txt")?;
+        assert!(list.contains(&b"ENST00000384061.1".to_vec()));
+        assert!(list.iter().all(|x| !x.is_empty() && !x.ends_with(b"\r")));
+        Ok(())
+    }
+
+    #[test]
+    fn test_record_chunk_size() {
+        assert_eq!(record_chunk_size(0), RECORD_CHUNK_SIZE);
+        assert_eq!(record_chunk_size(1000), RECORD_CHUNK_SIZE);
+        assert_eq!(
+            record_chunk_size(20_000),
+            50.max(rayon::current_num_threads()).min(RECORD_CHUNK_SIZE)
+        );
+        assert!(record_chunk_size(10_000_000) >= 1);
+    }
+
+    #[test]
+    fn test_read_record_chunk() -> Result<(), VCFUtilsError> {
+        let mut reader = open_vcf_from_path(Some("./testfiles/1kGP-subset.vcf"))?;
+        let mut expected_reader = open_vcf_from_path(Some("./testfiles/1kGP-subset.vcf"))?;
+        let mut expected = expected_reader.empty_record();
+        let mut chunk = Vec::new();
+        let mut total = 0;
+        loop {
+            let count = read_record_chunk(&mut reader, &mut chunk, 7)?;
+            assert!(count <= 7);
+            for record in chunk[..count].iter() {
+                assert!(expected_reader.next_record(&mut expected)?);
+                assert_eq!(record.position, expected.position);
+            }
+            total += count;
+            if count < 7 {
+                break;
+            }
+        }
+        assert!(!expected_reader.next_record(&mut expected)?);
+        assert!(total > 7);
+        Ok(())
+    }
+
     #[test]
     fn test_open_vcf_from_path() -> Result<(), VCFUtilsError> {
         open_vcf_from_path(Some("./testfiles/simple1.vcf"))?;
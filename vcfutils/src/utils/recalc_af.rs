@@ -1,6 +1,7 @@
 // Recalculate AF/AC/AN
 
 use crate::error::VCFUtilsError;
+use crate::utils::genotype::warn_malformed_genotype;
 use nom::{
     self, branch::alt, bytes::complete::tag, bytes::complete::take_while1, character::is_digit,
     eof, multi::separated_list, named, sequence::tuple,
@@ -15,6 +16,10 @@ pub struct AlleleCount {
     pub allele_number: u64,
     pub genotype_count: Vec<u64>,
     pub alt_hom_count: Vec<u64>,
+    /// Number of hemizygous samples for each allele
+    pub hemi_count: Vec<u64>,
+    /// Number of samples without fully called genotype
+    pub missing_count: u64,
     pub ploidy: usize,
     cache: Vec<usize>,
 }
@@ -32,18 +37,23 @@ impl AlleleCount {
             alt_hom_count.push(0);
         }
         let genotype_count = vec![0; number_of_genotype(alt_count, ploidy)];
+        let hemi_count = vec![0; alt_count + 1];
 
         AlleleCount {
             allele_count,
             allele_number: 0,
             genotype_count,
             alt_hom_count,
+            hemi_count,
+            missing_count: 0,
             ploidy,
             cache: Vec::new(),
         }
     }
 
-    pub fn add_genotype(&mut self, genotype: &[u8]) -> Result<(), VCFUtilsError> {
+    /// Parse called alleles of genotype into `cache`. Returns the number of alleles including
+    /// missing alleles.
+    fn parse_genotype(&mut self, genotype: &[u8]) -> Result<usize, VCFUtilsError> {
         match tuple((
             separated_list(alt((tag("|"), tag("/"))), take_while1(is_digit_or_dot)),
             eof_parser,
@@ -53,34 +63,72 @@ impl AlleleCount {
                 self.cache.clear();
                 for one_allele in &parsed_genotype {
                     if one_allele != b"." {
-                        let allele: usize = str::from_utf8(one_allele)?.parse::<usize>()?;
-                        self.cache.push(allele);
-                        if let Some(x) = self.allele_count.get_mut(allele) {
-                            *x += 1;
-                            self.allele_number += 1;
-                        }
+                        self.cache
+                            .push(str::from_utf8(one_allele)?.parse::<usize>()?);
                     }
                 }
-                self.cache.sort_unstable();
-                if self.cache.len() == self.ploidy {
-                    if let Some(x) = self.genotype_count.get_mut(genotype_index(&self.cache)) {
+                Ok(parsed_genotype.len())
+            }
+            Err(_) => Err(VCFUtilsError::GenotypeParseError),
+        }
+    }
+
+    /// Count genotype of a sample. A fully called haploid genotype in a call with higher
+    /// ploidy, such as `1` on chromosome X of a male, is counted as hemizygous.
+    pub fn add_genotype(&mut self, genotype: &[u8]) -> Result<(), VCFUtilsError> {
+        let allele_number = self.parse_genotype(genotype)?;
+        for allele in self.cache.iter() {
+            if let Some(x) = self.allele_count.get_mut(*allele) {
+                *x += 1;
+                self.allele_number += 1;
+            }
+        }
+        self.cache.sort_unstable();
+        if self.cache.len() == self.ploidy {
+            if let Some(x) = self.genotype_count.get_mut(genotype_index(&self.cache)) {
+                *x += 1;
+            }
+            if let Some(first_allele) = self.cache.get(0) {
+                if self.cache.iter().all(|x| x == first_allele) {
+                    if let Some(x) = self.alt_hom_count.get_mut(*first_allele) {
                         *x += 1;
                     }
-                    if let Some(first_allele) = self.cache.get(0) {
-                        if self.cache.iter().all(|x| x == first_allele) {
-                            if let Some(x) = self.alt_hom_count.get_mut(*first_allele) {
-                                *x += 1;
-                            }
-                        }
-                    }
                 }
+            }
+        } else if allele_number == 1 && self.cache.len() == 1 {
+            if let Some(x) = self.hemi_count.get_mut(self.cache[0]) {
+                *x += 1;
+            }
+        } else {
+            self.missing_count += 1;
+        }
 
-                Ok(())
+        Ok(())
+    }
+
+    /// Count genotype of a hemizygous sample, such as a male on chromosome X outside of
+    /// pseudo-autosomal regions. Diploid homozygous genotypes (e.g. `1/1`) are counted as
+    /// haploid, and heterozygous genotypes are counted as missing.
+    pub fn add_hemizygous_genotype(&mut self, genotype: &[u8]) -> Result<(), VCFUtilsError> {
+        let allele_number = self.parse_genotype(genotype)?;
+        match self.cache.first().copied() {
+            Some(allele)
+                if self.cache.len() == allele_number && self.cache.iter().all(|x| *x == allele) =>
+            {
+                if let Some(x) = self.allele_count.get_mut(allele) {
+                    *x += 1;
+                    self.allele_number += 1;
+                }
+                if let Some(x) = self.hemi_count.get_mut(allele) {
+                    *x += 1;
+                }
             }
-            Err(_) => Err(VCFUtilsError::GenotypeParseError),
+            _ => self.missing_count += 1,
         }
+        Ok(())
     }
 
+    /// Count genotypes of samples. Malformed genotypes are counted as missing with a warning.
     pub fn add_record<'a, I: Iterator<Item = &'a U8Vec>>(
         &mut self,
         record: &VCFRecord,
@@ -89,11 +137,44 @@ impl AlleleCount {
     ) -> Result<(), VCFUtilsError> {
         self.clear(record.alternative.len(), ploidy);
         for one_sample in samples {
-            record
-                .genotype(one_sample, b"GT")
-                .map(|x| x.get(0))
-                .flatten()
-                .map(|x| self.add_genotype(x));
+            if let Some(x) = record.genotype(one_sample, b"GT").and_then(|x| x.first()) {
+                if self.add_genotype(x).is_err() {
+                    warn_malformed_genotype(record, one_sample, x);
+                    self.missing_count += 1;
+                }
+            } else {
+                self.missing_count += 1;
+            }
+        }
+        Ok(())
+    }
+
+    /// Count genotypes of samples with ploidy of each sample. Samples with ploidy 0, such as
+    /// females on chromosome Y, are ignored. Malformed genotypes are counted as missing with a
+    /// warning.
+    pub fn add_record_with_ploidy<'a, I: Iterator<Item = (&'a U8Vec, usize)>>(
+        &mut self,
+        record: &VCFRecord,
+        samples: I,
+    ) -> Result<(), VCFUtilsError> {
+        self.clear(record.alternative.len(), 2);
+        for (one_sample, ploidy) in samples {
+            if ploidy == 0 {
+                continue;
+            }
+            if let Some(x) = record.genotype(one_sample, b"GT").and_then(|x| x.first()) {
+                let result = if ploidy == 1 {
+                    self.add_hemizygous_genotype(x)
+                } else {
+                    self.add_genotype(x)
+                };
+                if result.is_err() {
+                    warn_malformed_genotype(record, one_sample, x);
+                    self.missing_count += 1;
+                }
+            } else {
+                self.missing_count += 1;
+            }
         }
         Ok(())
     }
@@ -101,14 +182,49 @@ impl AlleleCount {
     pub fn clear(&mut self, new_alt_allele_count: usize, new_ploidy: usize) {
         self.allele_count.clear();
         self.alt_hom_count.clear();
+        self.hemi_count.clear();
         for _ in 0..(new_alt_allele_count + 1) {
             self.allele_count.push(0);
             self.alt_hom_count.push(0);
+            self.hemi_count.push(0);
         }
         self.genotype_count.clear();
         self.genotype_count
             .resize(number_of_genotype(new_alt_allele_count, new_ploidy), 0);
         self.allele_number = 0;
+        self.missing_count = 0;
+        self.ploidy = new_ploidy;
+    }
+
+    /// Number of samples with fully called genotype
+    pub fn called_count(&self) -> u64 {
+        self.genotype_count.iter().sum::<u64>() + self.hemi_count.iter().sum::<u64>()
+    }
+
+    /// Collapse diploid genotype counts into (non-`allele` homozygotes, heterozygotes, `allele`
+    /// homozygotes). Other alleles are treated as reference. Returns `None` if ploidy is not 2.
+    pub fn biallelic_genotype_count(&self, allele: usize) -> Option<(u64, u64, u64)> {
+        if self.ploidy != 2 {
+            return None;
+        }
+        let mut result = (0, 0, 0);
+        for allele2 in 0..self.allele_count.len() {
+            for allele1 in 0..=allele2 {
+                let count = self
+                    .genotype_count
+                    .get(genotype_index(&[allele1, allele2]))
+                    .copied()
+                    .unwrap_or(0);
+                if allele1 == allele && allele2 == allele {
+                    result.2 += count;
+                } else if allele1 == allele || allele2 == allele {
+                    result.1 += count;
+                } else {
+                    result.0 += count;
+                }
+            }
+        }
+        Some(result)
     }
 }
 
@@ -254,6 +370,84 @@ mod test {
         assert_eq!(allele_count.allele_count, vec![3, 3, 0]);
         assert_eq!(allele_count.genotype_count, vec![1, 1, 1, 0, 0, 0]);
         assert_eq!(allele_count.alt_hom_count, vec![1, 1, 0]);
+        assert_eq!(allele_count.missing_count, 1);
+        assert_eq!(allele_count.called_count(), 3);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_hemizygous_allele_count() -> Result<(), VCFUtilsError> {
+        let mut allele_count = AlleleCount::new(1, 2);
+        allele_count.add_genotype(b"0/1")?;
+        allele_count.add_hemizygous_genotype(b"1")?;
+        allele_count.add_hemizygous_genotype(b"1/1")?;
+        allele_count.add_hemizygous_genotype(b"0|0")?;
+        allele_count.add_hemizygous_genotype(b"0/1")?;
+        allele_count.add_hemizygous_genotype(b"./.")?;
+        assert_eq!(allele_count.allele_count, vec![2, 3]);
+        assert_eq!(allele_count.allele_number, 5);
+        assert_eq!(allele_count.genotype_count, vec![0, 1, 0]);
+        assert_eq!(allele_count.alt_hom_count, vec![0, 0]);
+        assert_eq!(allele_count.hemi_count, vec![1, 2]);
+        assert_eq!(allele_count.missing_count, 2);
+        assert_eq!(allele_count.called_count(), 4);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_haploid_genotype_in_diploid_call() -> Result<(), VCFUtilsError> {
+        let mut allele_count = AlleleCount::new(1, 2);
+        allele_count.add_genotype(b"0/1")?;
+        allele_count.add_genotype(b"1")?;
+        allele_count.add_genotype(b".")?;
+        assert_eq!(allele_count.allele_count, vec![1, 2]);
+        assert_eq!(allele_count.allele_number, 3);
+        assert_eq!(allele_count.genotype_count, vec![0, 1, 0]);
+        assert_eq!(allele_count.hemi_count, vec![0, 1]);
+        assert_eq!(allele_count.missing_count, 1);
+        assert_eq!(allele_count.called_count(), 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_malformed_genotype() -> Result<(), VCFUtilsError> {
+        let vcf_data = b"##fileformat=VCFv4.2\n##FORMAT=<ID=GT,Number=1,Type=String,Description=\"Genotype\">\n#CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO\tFORMAT\tS1\tS2\n1\t100\t.\tA\tG\t.\t.\t.\tGT\t0/1\t0/x\n";
+        let mut vcf_reader = VCFReader::new(&vcf_data[..])?;
+        let mut record = VCFRecord::new(vcf_reader.header().clone());
+        vcf_reader.next_record(&mut record)?;
+        let mut allele_count = AlleleCount::new(1, 2);
+        assert!(allele_count.add_genotype(b"0/x").is_err());
+        allele_count.add_record(&record, 2, vcf_reader.header().samples().iter())?;
+        assert_eq!(allele_count.allele_count, vec![1, 1]);
+        assert_eq!(allele_count.allele_number, 2);
+        assert_eq!(allele_count.missing_count, 1);
+        allele_count.add_record_with_ploidy(
+            &record,
+            vcf_reader.header().samples().iter().map(|x| (x, 1)),
+        )?;
+        assert_eq!(allele_count.allele_number, 0);
+        assert_eq!(allele_count.missing_count, 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_biallelic_genotype_count() -> Result<(), VCFUtilsError> {
+        let mut allele_count = AlleleCount::new(2, 2);
+        allele_count.add_genotype(b"0/0")?;
+        allele_count.add_genotype(b"0/1")?;
+        allele_count.add_genotype(b"1/2")?;
+        allele_count.add_genotype(b"2/2")?;
+        allele_count.add_genotype(b"0/.")?;
+        assert_eq!(allele_count.missing_count, 1);
+        assert_eq!(allele_count.biallelic_genotype_count(1), Some((2, 2, 0)));
+        assert_eq!(allele_count.biallelic_genotype_count(2), Some((2, 1, 1)));
+
+        let allele_count = AlleleCount::new(1, 3);
+        assert_eq!(allele_count.biallelic_genotype_count(1), None);
 
         Ok(())
     }
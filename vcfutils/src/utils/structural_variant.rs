@@ -0,0 +1,357 @@
+//! Structural variant representation of symbolic (`<DEL>`), breakend (`]chr2:123]N`) and
+//! sequence resolved alleles, parsed from ALT, SVTYPE, END, SVLEN and CHR2 fields.
+
+use crate::error::VCFUtilsError;
+use std::collections::{BTreeSet, HashMap};
+use std::str;
+use vcf::{U8Vec, VCFRecord};
+
+/// Position of mate breakend
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Breakend {
+    pub chromosome: U8Vec,
+    pub position: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct StructuralVariant {
+    pub sv_type: U8Vec,
+    /// 1-based start position (POS)
+    pub start: u64,
+    /// 1-based end position. Not available for breakends.
+    pub end: Option<u64>,
+    /// Absolute length of SV
+    pub length: Option<u64>,
+    pub mate: Option<Breakend>,
+    /// Genes overlapping SV or breakends
+    pub genes: Vec<U8Vec>,
+}
+
+/// Parse mate position of breakend notation (`t[p[`, `t]p]`, `]p]t` and `[p[t`).
+pub fn parse_breakend(alt: &[u8]) -> Option<Breakend> {
+    let first = alt.iter().position(|x| *x == b'[' || *x == b']')?;
+    let bracket = alt[first];
+    let length = alt[first + 1..].iter().position(|x| *x == bracket)?;
+    let mate = &alt[first + 1..first + 1 + length];
+    let separator = mate.iter().rposition(|x| *x == b':')?;
+    let position = str::from_utf8(&mate[separator + 1..]).ok()?.parse().ok()?;
+    Some(Breakend {
+        chromosome: mate[..separator].to_vec(),
+        position,
+    })
+}
+
+fn is_single_breakend(alt: &[u8]) -> bool {
+    alt.len() > 1 && (alt.starts_with(b".") || alt.ends_with(b"."))
+}
+
+fn is_symbolic(alt: &[u8]) -> bool {
+    alt.starts_with(b"<") && alt.ends_with(b">")
+}
+
+fn single_info_value<'a>(record: &'a VCFRecord, key: &[u8]) -> Option<&'a [u8]> {
+    record
+        .info(key)
+        .and_then(|x| x.first())
+        .map(|x| &x[..])
+        .filter(|x| !x.is_empty() && *x != b".")
+}
+
+fn parse_info_number<T: str::FromStr>(value: Option<&[u8]>) -> Result<Option<T>, VCFUtilsError>
+where
+    VCFUtilsError: From<T::Err>,
+{
+    value
+        .map(|x| Ok(str::from_utf8(x)?.parse::<T>()?))
+        .transpose()
+}
+
+/// Parse structural variant of an alternative allele. `None` is returned for short variants and
+/// non-variant symbolic alleles such as `<*>` and `<NON_REF>`.
+pub fn parse_structural_variant(
+    record: &VCFRecord,
+    alt_index: usize,
+) -> Result<Option<StructuralVariant>, VCFUtilsError> {
+    let alt = if let Some(alt) = record.alternative.get(alt_index) {
+        &alt[..]
+    } else {
+        return Ok(None);
+    };
+    if alt == b"<*>" || alt == b"<NON_REF>" || alt == b"*" || alt == b"." {
+        return Ok(None);
+    }
+    let breakend = parse_breakend(alt);
+    let symbolic = is_symbolic(alt);
+    let info_sv_type = single_info_value(record, b"SVTYPE");
+    let sv_type = if let Some(sv_type) = info_sv_type {
+        sv_type.to_vec()
+    } else if breakend.is_some() || is_single_breakend(alt) {
+        b"BND".to_vec()
+    } else if symbolic {
+        alt[1..alt.len() - 1].to_vec()
+    } else {
+        return Ok(None);
+    };
+
+    let mate = if let Some(breakend) = breakend {
+        Some(breakend)
+    } else if let Some(chromosome) = single_info_value(record, b"CHR2") {
+        let position = parse_info_number(single_info_value(record, b"POS2"))?
+            .or(parse_info_number(single_info_value(record, b"END"))?);
+        position.map(|position| Breakend {
+            chromosome: chromosome.to_vec(),
+            position,
+        })
+    } else {
+        None
+    };
+    let is_breakend = sv_type == b"BND" || sv_type == b"TRA";
+
+    let end = if is_breakend {
+        None
+    } else if let Some(end) = parse_info_number(single_info_value(record, b"END"))? {
+        Some(end)
+    } else if symbolic && sv_type.starts_with(b"INS") {
+        Some(record.position)
+    } else {
+        Some(record.position + (record.reference.len().max(1) as u64) - 1)
+    };
+
+    let svlen = record.info(b"SVLEN").and_then(|x| {
+        if x.len() == record.alternative.len() {
+            x.get(alt_index)
+        } else {
+            x.first()
+        }
+    });
+    let length = if let Some(svlen) = svlen.filter(|x| !x.is_empty() && *x != b".") {
+        Some(str::from_utf8(svlen)?.parse::<i64>()?.unsigned_abs())
+    } else if is_breakend {
+        None
+    } else if !symbolic {
+        Some((alt.len() as i64 - record.reference.len() as i64).unsigned_abs())
+    } else if sv_type.starts_with(b"INS") {
+        None
+    } else {
+        end.map(|x| x.saturating_sub(record.position))
+    };
+
+    Ok(Some(StructuralVariant {
+        sv_type,
+        start: record.position,
+        end,
+        length,
+        mate,
+        genes: Vec::new(),
+    }))
+}
+
+/// Regions of one chromosome sorted by start position. `max_end[i]` is the maximum end of
+/// regions up to `i`, so that regions ending before a query are skipped by binary search.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+struct IntervalIndex {
+    // 0-based half-open regions and names
+    regions: Vec<(u64, u64, U8Vec)>,
+    max_end: Vec<u64>,
+}
+
+impl IntervalIndex {
+    fn new(mut regions: Vec<(u64, u64, U8Vec)>) -> Self {
+        regions.sort();
+        let max_end = regions
+            .iter()
+            .scan(0, |max_end, x| {
+                *max_end = x.1.max(*max_end);
+                Some(*max_end)
+            })
+            .collect();
+        IntervalIndex { regions, max_end }
+    }
+
+    fn overlapping(&self, start: u64, end: u64) -> impl Iterator<Item = &(u64, u64, U8Vec)> {
+        let first = self.max_end.partition_point(|x| *x <= start);
+        let last = self.regions.partition_point(|x| x.0 < end);
+        self.regions[first..last.max(first)]
+            .iter()
+            .filter(move |x| x.1 > start)
+    }
+}
+
+/// Named regions such as genes, to find names overlapping structural variants.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct GeneRegions {
+    regions: HashMap<U8Vec, IntervalIndex>,
+}
+
+impl GeneRegions {
+    pub fn new(regions: &[(U8Vec, u64, u64, U8Vec)]) -> Self {
+        let mut chromosome_regions: HashMap<U8Vec, Vec<(u64, u64, U8Vec)>> = HashMap::new();
+        for (chromosome, start, end, name) in regions {
+            chromosome_regions
+                .entry(chromosome.clone())
+                .or_default()
+                .push((*start, *end, name.clone()));
+        }
+        GeneRegions {
+            regions: chromosome_regions
+                .into_iter()
+                .map(|(k, v)| (k, IntervalIndex::new(v)))
+                .collect(),
+        }
+    }
+
+    /// Names of regions overlapping 0-based half-open region
+    pub fn overlapping<'a>(
+        &'a self,
+        chromosome: &[u8],
+        start: u64,
+        end: u64,
+    ) -> impl Iterator<Item = &'a [u8]> + 'a {
+        self.regions
+            .get(chromosome)
+            .into_iter()
+            .flat_map(move |x| x.overlapping(start, end))
+            .map(|x| &x.2[..])
+    }
+
+    /// Set sorted names of genes overlapping SV span, or both breakends for BND and insertion.
+    pub fn annotate(&self, chromosome: &[u8], sv: &mut StructuralVariant) {
+        let mut genes = BTreeSet::new();
+        let start = sv.start.saturating_sub(1);
+        let end = sv.end.unwrap_or(sv.start).max(sv.start);
+        genes.extend(self.overlapping(chromosome, start, end));
+        if let Some(mate) = sv.mate.as_ref() {
+            let mate_start = mate.position.saturating_sub(1);
+            genes.extend(self.overlapping(&mate.chromosome, mate_start, mate_start + 1));
+        }
+        sv.genes = genes
+            .into_iter()
+            .filter(|x| !x.is_empty())
+            .map(|x| x.to_vec())
+            .collect();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use vcf::VCFReader;
+
+    #[test]
+    fn test_parse_breakend() {
+        let expected = Some(Breakend {
+            chromosome: b"chr2".to_vec(),
+            position: 321682,
+        });
+        assert_eq!(parse_breakend(b"G]chr2:321682]"), expected);
+        assert_eq!(parse_breakend(b"]chr2:321682]T"), expected);
+        assert_eq!(parse_breakend(b"[chr2:321682[T"), expected);
+        assert_eq!(parse_breakend(b"T[chr2:321682["), expected);
+        assert_eq!(
+            parse_breakend(b"C[HLA-A*01:01:01:01:2000["),
+            Some(Breakend {
+                chromosome: b"HLA-A*01:01:01:01".to_vec(),
+                position: 2000
+            })
+        );
+        assert_eq!(parse_breakend(b"<DEL>"), None);
+        assert_eq!(parse_breakend(b"A."), None);
+    }
+
+    #[test]
+    fn test_parse_structural_variant() -> Result<(), VCFUtilsError> {
+        let data = b"##fileformat=VCFv4.2
+##INFO=<ID=END,Number=1,Type=Integer,Description=\"End position\">
+##INFO=<ID=SVTYPE,Number=1,Type=String,Description=\"Type of SV\">
+##INFO=<ID=SVLEN,Number=.,Type=Integer,Description=\"Length of SV\">
+##INFO=<ID=CHR2,Number=1,Type=String,Description=\"Mate chromosome\">
+#CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO
+chr1\t1000\tdel\tN\t<DEL>\t.\tPASS\tSVTYPE=DEL;END=1500;SVLEN=-500
+chr1\t2000\tdup\tN\t<DUP:TANDEM>\t.\tPASS\tEND=2300
+chr1\t3000\tbnd\tG\tG]chr2:5000]\t.\tPASS\tSVTYPE=BND
+chr1\t4000\ttra\tN\t<TRA>\t.\tPASS\tSVTYPE=TRA;CHR2=chr3;END=7000
+chr1\t5000\tins\tA\tATTTTTTTTTT\t.\tPASS\tSVTYPE=INS
+chr1\t6000\tsnv\tA\tG,<*>\t.\tPASS\t.
+";
+        let mut reader = VCFReader::new(&data[..])?;
+        let mut record = VCFRecord::new(reader.header().clone());
+        let mut svs = Vec::new();
+        while reader.next_record(&mut record)? {
+            for i in 0..record.alternative.len() {
+                svs.push(parse_structural_variant(&record, i)?);
+            }
+        }
+
+        let gene_regions = GeneRegions::new(&[
+            (b"chr1".to_vec(), 1200, 1300, b"GENE1".to_vec()),
+            (b"chr1".to_vec(), 0, 1000, b"GENE0".to_vec()),
+            (b"chr1".to_vec(), 1500, 1600, b"GENE2".to_vec()),
+            (b"chr2".to_vec(), 4000, 6000, b"GENE3".to_vec()),
+        ]);
+        let names = |start, end| -> Vec<_> {
+            gene_regions
+                .overlapping(b"chr1", start, end)
+                .map(|x| x.to_vec())
+                .collect()
+        };
+        assert_eq!(names(999, 1200), vec![b"GENE0".to_vec()]);
+        assert_eq!(names(1000, 1200), Vec::<U8Vec>::new());
+        assert_eq!(
+            names(500, 1550),
+            vec![b"GENE0".to_vec(), b"GENE1".to_vec(), b"GENE2".to_vec()]
+        );
+        assert_eq!(names(1300, 1500), Vec::<U8Vec>::new());
+        assert_eq!(gene_regions.overlapping(b"chr3", 0, 100).count(), 0);
+
+        let mut deletion = svs[0].clone().unwrap();
+        gene_regions.annotate(b"chr1", &mut deletion);
+        assert_eq!(
+            deletion,
+            StructuralVariant {
+                sv_type: b"DEL".to_vec(),
+                start: 1000,
+                end: Some(1500),
+                length: Some(500),
+                mate: None,
+                genes: vec![b"GENE0".to_vec(), b"GENE1".to_vec()],
+            }
+        );
+
+        let duplication = svs[1].clone().unwrap();
+        assert_eq!(duplication.sv_type, b"DUP:TANDEM");
+        assert_eq!(duplication.end, Some(2300));
+        assert_eq!(duplication.length, Some(300));
+
+        let mut breakend = svs[2].clone().unwrap();
+        gene_regions.annotate(b"chr1", &mut breakend);
+        assert_eq!(breakend.sv_type, b"BND");
+        assert_eq!(breakend.end, None);
+        assert_eq!(breakend.length, None);
+        assert_eq!(
+            breakend.mate,
+            Some(Breakend {
+                chromosome: b"chr2".to_vec(),
+                position: 5000
+            })
+        );
+        assert_eq!(breakend.genes, vec![b"GENE3".to_vec()]);
+
+        let translocation = svs[3].clone().unwrap();
+        assert_eq!(
+            translocation.mate,
+            Some(Breakend {
+                chromosome: b"chr3".to_vec(),
+                position: 7000
+            })
+        );
+        assert_eq!(translocation.end, None);
+
+        let insertion = svs[4].clone().unwrap();
+        assert_eq!(insertion.end, Some(5000));
+        assert_eq!(insertion.length, Some(10));
+
+        assert_eq!(svs[5], None);
+        assert_eq!(svs[6], None);
+        Ok(())
+    }
+}
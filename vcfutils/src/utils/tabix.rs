@@ -0,0 +1,298 @@
+//! Build tabix (`.tbi`) and CSI (`.csi`) indexes of BGZF compressed VCF while writing records.
+
+use crate::error::VCFUtilsError;
+use bgzip::{BGZFWriter, Compression};
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+
+/// Minimum shift of tabix index
+pub const TABIX_MIN_SHIFT: u32 = 14;
+/// Depth of tabix index
+pub const TABIX_DEPTH: u32 = 5;
+/// Format code of VCF in tabix header
+const TABIX_FORMAT_VCF: i32 = 2;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum IndexFormat {
+    Tbi,
+    Csi,
+}
+
+impl IndexFormat {
+    pub fn extension(&self) -> &str {
+        match self {
+            IndexFormat::Tbi => "tbi",
+            IndexFormat::Csi => "csi",
+        }
+    }
+}
+
+/// Index of one contig
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct ReferenceIndex {
+    bins: BTreeMap<u32, Vec<(u64, u64)>>,
+    /// Minimum virtual offset of records overlapping each window of `1 << min_shift` bases
+    linear: Vec<Option<u64>>,
+    first_offset: u64,
+    last_offset: u64,
+    record_count: u64,
+    last_position: u64,
+}
+
+/// Collect virtual offsets of records, and write tabix or CSI index. Records must be sorted.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TabixIndexBuilder {
+    names: Vec<Vec<u8>>,
+    references: Vec<ReferenceIndex>,
+    max_end: u64,
+}
+
+impl Default for TabixIndexBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Calculate bin of zero-based half-open region.
+pub fn reg2bin(beg: u64, end: u64, min_shift: u32, depth: u32) -> u32 {
+    let end = end.max(beg + 1) - 1;
+    let mut shift = min_shift;
+    let mut offset = ((1u64 << (depth * 3)) - 1) / 7;
+    for level in (1..=depth).rev() {
+        if beg >> shift == end >> shift {
+            return (offset + (beg >> shift)) as u32;
+        }
+        shift += 3;
+        offset -= 1 << ((level - 1) * 3);
+    }
+    0
+}
+
+/// Return level and first position of the bin.
+fn bin_level_start(bin: u32, min_shift: u32, depth: u32) -> (u32, u64) {
+    let mut offset = 0u64;
+    for level in 0..=depth {
+        let next_offset = offset + (1 << (level * 3));
+        if (bin as u64) < next_offset {
+            return (
+                level,
+                (bin as u64 - offset) << (min_shift + 3 * (depth - level)),
+            );
+        }
+        offset = next_offset;
+    }
+    (depth, 0)
+}
+
+impl TabixIndexBuilder {
+    pub fn new() -> Self {
+        TabixIndexBuilder {
+            names: Vec::new(),
+            references: Vec::new(),
+            max_end: 0,
+        }
+    }
+
+    /// Add a record at zero-based half-open region `[beg, end)`. `start_offset` and `end_offset`
+    /// are BGZF virtual offsets of the start and the end of the record.
+    pub fn add_record(
+        &mut self,
+        contig: &[u8],
+        beg: u64,
+        end: u64,
+        start_offset: u64,
+        end_offset: u64,
+    ) -> Result<(), VCFUtilsError> {
+        if self.names.last().map(|x| x.as_slice()) != Some(contig) {
+            if self.names.iter().any(|x| x == contig) {
+                return Err(VCFUtilsError::UnsortedRecord(format!(
+                    "{} appears again",
+                    String::from_utf8_lossy(contig)
+                )));
+            }
+            self.names.push(contig.to_vec());
+            self.references.push(ReferenceIndex {
+                first_offset: start_offset,
+                ..Default::default()
+            });
+        }
+        let reference = self.references.last_mut().expect("reference is added");
+        if beg < reference.last_position {
+            return Err(VCFUtilsError::UnsortedRecord(format!(
+                "{}:{} is found after {}",
+                String::from_utf8_lossy(contig),
+                beg + 1,
+                reference.last_position + 1
+            )));
+        }
+        reference.last_position = beg;
+        reference.last_offset = end_offset;
+        reference.record_count += 1;
+        let end = end.max(beg + 1);
+        self.max_end = self.max_end.max(end);
+
+        // Bins are calculated when the index is written, because depth of CSI depends on the
+        // maximum position. Regions are kept with TABIX_MIN_SHIFT level windows.
+        let first_window = (beg >> TABIX_MIN_SHIFT) as usize;
+        let last_window = ((end - 1) >> TABIX_MIN_SHIFT) as usize;
+        if reference.linear.len() <= last_window {
+            reference.linear.resize(last_window + 1, None);
+        }
+        for one in reference.linear[first_window..=last_window].iter_mut() {
+            if one.is_none() {
+                *one = Some(start_offset);
+            }
+        }
+        let chunks = reference.bins.entry(reg2bin_key(beg, end)).or_default();
+        chunks.push((start_offset, end_offset));
+        Ok(())
+    }
+
+    fn write_names(&self, data: &mut Vec<u8>) -> io::Result<()> {
+        let names_length: usize = self.names.iter().map(|x| x.len() + 1).sum();
+        data.write_all(&TABIX_FORMAT_VCF.to_le_bytes())?;
+        data.write_all(&1i32.to_le_bytes())?; // column of sequence name
+        data.write_all(&2i32.to_le_bytes())?; // column of begin position
+        data.write_all(&0i32.to_le_bytes())?; // column of end position
+        data.write_all(&(b'#' as i32).to_le_bytes())?;
+        data.write_all(&0i32.to_le_bytes())?; // lines to skip
+        data.write_all(&(names_length as i32).to_le_bytes())?;
+        for one in self.names.iter() {
+            data.write_all(one)?;
+            data.write_all(&[0])?;
+        }
+        Ok(())
+    }
+
+    /// Write index with BGZF compression.
+    pub fn write<W: Write>(&self, mut output: W, format: IndexFormat) -> Result<(), VCFUtilsError> {
+        let (min_shift, depth) = match format {
+            IndexFormat::Tbi => {
+                if self.max_end > 1 << (TABIX_MIN_SHIFT + 3 * TABIX_DEPTH) {
+                    return Err(VCFUtilsError::OtherError(
+                        "Position is too large for tabix index. Please use CSI index.",
+                    ));
+                }
+                (TABIX_MIN_SHIFT, TABIX_DEPTH)
+            }
+            IndexFormat::Csi => {
+                let mut depth = TABIX_DEPTH;
+                while self.max_end > 1 << (TABIX_MIN_SHIFT + 3 * depth) {
+                    depth += 1;
+                }
+                (TABIX_MIN_SHIFT, depth)
+            }
+        };
+
+        let mut data = Vec::new();
+        match format {
+            IndexFormat::Tbi => {
+                data.write_all(b"TBI\x01")?;
+                data.write_all(&(self.names.len() as i32).to_le_bytes())?;
+                self.write_names(&mut data)?;
+            }
+            IndexFormat::Csi => {
+                let mut aux = Vec::new();
+                self.write_names(&mut aux)?;
+                data.write_all(b"CSI\x01")?;
+                data.write_all(&(min_shift as i32).to_le_bytes())?;
+                data.write_all(&(depth as i32).to_le_bytes())?;
+                data.write_all(&(aux.len() as i32).to_le_bytes())?;
+                data.write_all(&aux)?;
+                data.write_all(&(self.names.len() as i32).to_le_bytes())?;
+            }
+        }
+
+        let meta_bin = (((1u64 << ((depth + 1) * 3)) - 1) / 7 + 1) as u32;
+        for reference in self.references.iter() {
+            // Fill empty windows with offset of the next window
+            let mut linear = reference.linear.clone();
+            let mut next = reference.last_offset;
+            for one in linear.iter_mut().rev() {
+                match one {
+                    Some(x) => next = *x,
+                    None => *one = Some(next),
+                }
+            }
+            let linear: Vec<u64> = linear.into_iter().flatten().collect();
+
+            let mut bins: BTreeMap<u32, Vec<(u64, u64)>> = BTreeMap::new();
+            for (key, chunks) in reference.bins.iter() {
+                let (beg, end) = key_to_region(*key);
+                bins.entry(reg2bin(beg, end, min_shift, depth))
+                    .or_default()
+                    .extend_from_slice(chunks);
+            }
+
+            data.write_all(&(bins.len() as i32 + 1).to_le_bytes())?;
+            for (bin, chunks) in bins.iter_mut() {
+                chunks.sort_unstable();
+                let mut merged: Vec<(u64, u64)> = Vec::new();
+                for chunk in chunks.iter() {
+                    match merged.last_mut() {
+                        Some(last) if chunk.0 <= last.1 => last.1 = last.1.max(chunk.1),
+                        _ => merged.push(*chunk),
+                    }
+                }
+                data.write_all(&bin.to_le_bytes())?;
+                if format == IndexFormat::Csi {
+                    let (_, start) = bin_level_start(*bin, min_shift, depth);
+                    let window = (start >> TABIX_MIN_SHIFT) as usize;
+                    let loffset = linear
+                        .get(window)
+                        .copied()
+                        .unwrap_or(reference.last_offset)
+                        .min(merged[0].0);
+                    data.write_all(&loffset.to_le_bytes())?;
+                }
+                data.write_all(&(merged.len() as i32).to_le_bytes())?;
+                for (start, end) in merged {
+                    data.write_all(&start.to_le_bytes())?;
+                    data.write_all(&end.to_le_bytes())?;
+                }
+            }
+
+            // Pseudo bin for meta data
+            data.write_all(&meta_bin.to_le_bytes())?;
+            if format == IndexFormat::Csi {
+                data.write_all(&0u64.to_le_bytes())?;
+            }
+            data.write_all(&2i32.to_le_bytes())?;
+            data.write_all(&reference.first_offset.to_le_bytes())?;
+            data.write_all(&reference.last_offset.to_le_bytes())?;
+            data.write_all(&reference.record_count.to_le_bytes())?;
+            data.write_all(&0u64.to_le_bytes())?;
+
+            if format == IndexFormat::Tbi {
+                data.write_all(&(linear.len() as i32).to_le_bytes())?;
+                for one in linear.iter() {
+                    data.write_all(&one.to_le_bytes())?;
+                }
+            }
+        }
+        // Number of records without coordinate
+        data.write_all(&0u64.to_le_bytes())?;
+
+        let mut writer = BGZFWriter::new(&mut output, Compression::default());
+        writer.write_all(&data)?;
+        writer.close()?;
+        output.flush()?;
+        Ok(())
+    }
+}
+
+/// Regions are stored with the finest bin which can hold them in the deepest possible index, so
+/// that bins can be recalculated for any depth.
+const KEY_DEPTH: u32 = 10;
+
+fn reg2bin_key(beg: u64, end: u64) -> u32 {
+    reg2bin(beg, end, TABIX_MIN_SHIFT, KEY_DEPTH)
+}
+
+fn key_to_region(key: u32) -> (u64, u64) {
+    let (level, start) = bin_level_start(key, TABIX_MIN_SHIFT, KEY_DEPTH);
+    (
+        start,
+        start + (1 << (TABIX_MIN_SHIFT + 3 * (KEY_DEPTH - level))),
+    )
+}
@@ -1,5 +1,5 @@
 use crate::error::VCFUtilsError;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::io::Write;
 use std::str;
 
@@ -172,86 +172,138 @@ impl<W: Write> TableWriter for CSVWriter<W> {
     }
 }
 
-pub struct XlsxSheetWriter<'a, 'b> {
-    writer: &'a mut xlsxwriter::Worksheet<'b>,
+/// Maximum number of hyperlinks in a worksheet
+pub const XLSX_MAX_HYPERLINKS: usize = 65530;
+/// Maximum number of rows in a worksheet
+pub const XLSX_MAX_ROWS: u32 = 1_048_576;
+/// Maximum length of worksheet name
+pub const XLSX_MAX_SHEET_NAME_LENGTH: usize = 31;
+
+/// URL template to create hyperlinks from cell values
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum HyperlinkTemplate {
+    /// dbSNP page for rsID
+    DbSnp,
+    /// ClinVar page for variation ID
+    ClinVar,
+    /// gnomAD page for gene symbol or Ensembl gene ID
+    GnomadGene,
+    /// URL with `{}` placeholder
+    Custom(String),
+}
+
+impl str::FromStr for HyperlinkTemplate {
+    type Err = VCFUtilsError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "dbsnp" => Ok(HyperlinkTemplate::DbSnp),
+            "clinvar" => Ok(HyperlinkTemplate::ClinVar),
+            "gnomad" => Ok(HyperlinkTemplate::GnomadGene),
+            _ if s.contains("{}") => Ok(HyperlinkTemplate::Custom(s.to_string())),
+            _ => Err(VCFUtilsError::InvalidHyperlinkTemplate(s.to_string())),
+        }
+    }
+}
+
+impl HyperlinkTemplate {
+    /// Return URL for the value. Missing values and lists of values are not linked.
+    pub fn url(&self, value: &str) -> Option<String> {
+        if value.is_empty()
+            || value == "."
+            || value
+                .chars()
+                .any(|x| x.is_whitespace() || matches!(x, ',' | ';' | '|' | '&' | '"'))
+        {
+            return None;
+        }
+        let url = match self {
+            HyperlinkTemplate::DbSnp => {
+                let number = value.strip_prefix("rs")?;
+                if number.is_empty() || !number.bytes().all(|x| x.is_ascii_digit()) {
+                    return None;
+                }
+                format!("https://www.ncbi.nlm.nih.gov/snp/{}", value)
+            }
+            HyperlinkTemplate::ClinVar => {
+                if !value.bytes().all(|x| x.is_ascii_digit()) {
+                    return None;
+                }
+                format!("https://www.ncbi.nlm.nih.gov/clinvar/variation/{}/", value)
+            }
+            HyperlinkTemplate::GnomadGene => {
+                format!("https://gnomad.broadinstitute.org/gene/{}", value)
+            }
+            HyperlinkTemplate::Custom(template) => template.replace("{}", value),
+        };
+        // Excel does not accept URLs longer than 2079 characters
+        if url.len() > 2079 {
+            None
+        } else {
+            Some(url)
+        }
+    }
+}
+
+/// Column settings and cell formats shared by XLSX writers
+struct XlsxColumns {
     header: Vec<String>,
     header_comment: Vec<String>,
     data_type: Vec<XlsxDataType>,
-    current_row: u32,
+    hyperlinks: Vec<Option<HyperlinkTemplate>>,
     number_format: xlsxwriter::Format,
+    header_format: xlsxwriter::Format,
+    hyperlink_format: xlsxwriter::Format,
 }
 
-impl<'a, 'b> XlsxSheetWriter<'a, 'b> {
-    pub fn new(workhseet: &'a mut xlsxwriter::Worksheet<'b>) -> Self {
+impl XlsxColumns {
+    fn new() -> Self {
         let mut number_format = xlsxwriter::Format::new();
         number_format.set_num_format("0.00");
-
-        XlsxSheetWriter {
-            writer: workhseet,
+        let mut header_format = xlsxwriter::Format::new();
+        header_format.set_bold();
+        let mut hyperlink_format = xlsxwriter::Format::new();
+        hyperlink_format
+            .set_font_color(xlsxwriter::format::FormatColor::Blue)
+            .set_underline(xlsxwriter::format::FormatUnderline::Single);
+
+        XlsxColumns {
             header: Vec::new(),
             header_comment: Vec::new(),
             data_type: Vec::new(),
-            current_row: 0,
+            hyperlinks: Vec::new(),
             number_format,
+            header_format,
+            hyperlink_format,
         }
     }
 
-    pub fn set_data_type(&mut self, data_type: &[XlsxDataType]) {
-        self.data_type.clear();
-        self.data_type.extend_from_slice(data_type);
-    }
-
-    pub fn set_header_comment(&mut self, items: &[String]) {
-        self.header_comment.clear();
-        self.header_comment.extend_from_slice(items);
-    }
-
-    pub fn set_column_sizes(&mut self, widths: &[f64]) -> Result<(), VCFUtilsError> {
-        for (column_index, one_width) in widths.iter().enumerate() {
-            self.writer
-                .set_column(column_index as u16, column_index as u16, *one_width, None)?;
-        }
-        Ok(())
-    }
-}
-
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
-pub enum XlsxDataType {
-    String,
-    Boolean,
-    Number,
-    Formula,
-}
-
-impl<'a, 'b> TableWriter for XlsxSheetWriter<'a, 'b> {
-    fn set_header(&mut self, items: &[String]) {
-        self.header.clear();
-        self.header.extend_from_slice(items);
-    }
-    fn header(&self) -> &[String] {
-        &self.header
-    }
-
-    fn write_header(&mut self) -> Result<(), VCFUtilsError> {
-        let header: Vec<_> = self.header().iter().map(|x| x.to_string()).collect();
-        for (i, column) in header.iter().enumerate() {
-            self.writer
-                .write_string(self.current_row, i as u16, column, None)?;
+    fn write_header(
+        &self,
+        sheet: &mut xlsxwriter::Worksheet,
+        row: u32,
+    ) -> Result<(), VCFUtilsError> {
+        for (i, column) in self.header.iter().enumerate() {
+            sheet.write_string(row, i as u16, column, Some(&self.header_format))?;
             if let Some(comment) = self.header_comment.get(i) {
-                if comment != "" {
-                    self.writer
-                        .write_comment(self.current_row, i as u16, &comment)?;
+                if !comment.is_empty() {
+                    sheet.write_comment(row, i as u16, comment)?;
                 }
             }
         }
-        self.current_row += 1;
         Ok(())
     }
 
-    fn write_row(&mut self, items: &[&str]) -> Result<(), VCFUtilsError> {
+    fn write_row(
+        &self,
+        sheet: &mut xlsxwriter::Worksheet,
+        row: u32,
+        items: &[&str],
+        hyperlink_count: &mut usize,
+    ) -> Result<(), VCFUtilsError> {
         for (i, column) in items.iter().enumerate() {
             if column.is_empty() {
-                self.writer.write_blank(self.current_row, i as u16, None)?;
+                sheet.write_blank(row, i as u16, None)?;
             } else {
                 match self
                     .data_type
@@ -260,46 +312,152 @@ impl<'a, 'b> TableWriter for XlsxSheetWriter<'a, 'b> {
                     .unwrap_or(XlsxDataType::String)
                 {
                     XlsxDataType::String => {
-                        if column.len() > 32766 {
-                            self.writer.write_string(
-                                self.current_row,
+                        let url = self
+                            .hyperlinks
+                            .get(i)
+                            .and_then(|x| x.as_ref())
+                            .and_then(|x| x.url(column));
+                        if let Some(url) = url.filter(|_| *hyperlink_count < XLSX_MAX_HYPERLINKS) {
+                            *hyperlink_count += 1;
+                            if *hyperlink_count == XLSX_MAX_HYPERLINKS {
+                                log::warn!("Too many hyperlinks. Remaining cells are written without hyperlinks.");
+                            }
+                            // Overwrite the cell to show the value instead of the URL
+                            sheet.write_url(row, i as u16, &url, Some(&self.hyperlink_format))?;
+                            sheet.write_string(
+                                row,
+                                i as u16,
+                                column,
+                                Some(&self.hyperlink_format),
+                            )?;
+                        } else if column.len() > 32766 {
+                            sheet.write_string(
+                                row,
                                 i as u16,
                                 &format!("{}...", &column[0..32763]),
                                 None,
                             )?;
                         } else {
-                            self.writer
-                                .write_string(self.current_row, i as u16, column, None)?;
+                            sheet.write_string(row, i as u16, column, None)?;
                         }
                     }
                     XlsxDataType::Number => {
                         if let Ok(f) = column.parse() {
-                            self.writer
-                                .write_number(self.current_row, i as u16, f, None)?;
+                            sheet.write_number(row, i as u16, f, None)?;
                         } else {
-                            self.writer
-                                .write_string(self.current_row, i as u16, column, None)?;
+                            sheet.write_string(row, i as u16, column, None)?;
                         }
                     }
                     XlsxDataType::Boolean => {
-                        self.writer.write_boolean(
-                            self.current_row,
+                        sheet.write_boolean(
+                            row,
                             i as u16,
                             *column == "TRUE" || *column == "True" || *column == "true",
                             None,
                         )?;
                     }
                     XlsxDataType::Formula => {
-                        self.writer.write_formula(
-                            self.current_row,
-                            i as u16,
-                            &column,
-                            Some(&self.number_format),
-                        )?;
+                        sheet.write_formula(row, i as u16, column, Some(&self.number_format))?;
                     }
                 }
             }
         }
+        Ok(())
+    }
+}
+
+pub struct XlsxSheetWriter<'a, 'b> {
+    writer: &'a mut xlsxwriter::Worksheet<'b>,
+    columns: XlsxColumns,
+    hyperlink_count: usize,
+    current_row: u32,
+}
+
+impl<'a, 'b> XlsxSheetWriter<'a, 'b> {
+    pub fn new(workhseet: &'a mut xlsxwriter::Worksheet<'b>) -> Self {
+        XlsxSheetWriter {
+            writer: workhseet,
+            columns: XlsxColumns::new(),
+            hyperlink_count: 0,
+            current_row: 0,
+        }
+    }
+
+    /// Set URL templates of columns. Cells in columns with a template are written as hyperlinks.
+    pub fn set_hyperlinks(&mut self, hyperlinks: &[Option<HyperlinkTemplate>]) {
+        self.columns.hyperlinks.clear();
+        self.columns.hyperlinks.extend_from_slice(hyperlinks);
+    }
+
+    pub fn set_data_type(&mut self, data_type: &[XlsxDataType]) {
+        self.columns.data_type.clear();
+        self.columns.data_type.extend_from_slice(data_type);
+    }
+
+    pub fn set_header_comment(&mut self, items: &[String]) {
+        self.columns.header_comment.clear();
+        self.columns.header_comment.extend_from_slice(items);
+    }
+
+    pub fn set_column_sizes(&mut self, widths: &[f64]) -> Result<(), VCFUtilsError> {
+        for (column_index, one_width) in widths.iter().enumerate() {
+            self.writer
+                .set_column(column_index as u16, column_index as u16, *one_width, None)?;
+        }
+        Ok(())
+    }
+
+    /// Number of written rows including header
+    pub fn current_row(&self) -> u32 {
+        self.current_row
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum XlsxDataType {
+    String,
+    Boolean,
+    Number,
+    Formula,
+}
+
+/// Column value type for writers with typed columns
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ColumnType {
+    String,
+    Integer,
+    Float,
+    Boolean,
+}
+
+impl<'a, 'b> TableWriter for XlsxSheetWriter<'a, 'b> {
+    fn set_header(&mut self, items: &[String]) {
+        self.columns.header.clear();
+        self.columns.header.extend_from_slice(items);
+    }
+    fn header(&self) -> &[String] {
+        &self.columns.header
+    }
+
+    fn write_header(&mut self) -> Result<(), VCFUtilsError> {
+        if self.current_row >= XLSX_MAX_ROWS {
+            return Err(VCFUtilsError::OtherError("Too many rows for a worksheet"));
+        }
+        self.columns.write_header(self.writer, self.current_row)?;
+        self.current_row += 1;
+        Ok(())
+    }
+
+    fn write_row(&mut self, items: &[&str]) -> Result<(), VCFUtilsError> {
+        if self.current_row >= XLSX_MAX_ROWS {
+            return Err(VCFUtilsError::OtherError("Too many rows for a worksheet"));
+        }
+        self.columns.write_row(
+            self.writer,
+            self.current_row,
+            items,
+            &mut self.hyperlink_count,
+        )?;
         self.current_row += 1;
         Ok(())
     }
@@ -309,6 +467,241 @@ impl<'a, 'b> TableWriter for XlsxSheetWriter<'a, 'b> {
     }
 }
 
+/// Create a worksheet name from `base` and `suffix`. Characters not allowed in worksheet names
+/// are replaced with `_`, and `base` is truncated to fit in 31 characters.
+pub fn xlsx_sheet_name(base: &str, suffix: &str) -> String {
+    let base: String = base
+        .trim_matches('\'')
+        .chars()
+        .map(|x| {
+            if matches!(x, '[' | ']' | ':' | '*' | '?' | '/' | '\\') {
+                '_'
+            } else {
+                x
+            }
+        })
+        .collect();
+    let base = if base.is_empty() { "NA" } else { &base };
+    let max_length = XLSX_MAX_SHEET_NAME_LENGTH - suffix.chars().count();
+    let mut name: String = base.chars().take(max_length).collect();
+    name.push_str(suffix);
+    name
+}
+
+struct XlsxSheetState<'a> {
+    name: String,
+    worksheet: xlsxwriter::Worksheet<'a>,
+    current_row: u32,
+    hyperlink_count: usize,
+}
+
+/// Write rows into worksheets of a workbook. A continuation sheet is added when a worksheet is
+/// full, and rows can be split into worksheets by values of a column.
+pub struct XlsxWorkbookWriter<'a> {
+    workbook: &'a xlsxwriter::Workbook,
+    columns: XlsxColumns,
+    column_sizes: Vec<f64>,
+    sheet_name: String,
+    split_column_name: Option<String>,
+    split_column: Option<usize>,
+    max_rows: u32,
+    sheets: Vec<XlsxSheetState<'a>>,
+    /// Index of the last sheet and number of sheets for each split value
+    groups: HashMap<String, (usize, usize)>,
+    used_names: HashSet<String>,
+}
+
+impl<'a> XlsxWorkbookWriter<'a> {
+    /// `sheet_name` is used for worksheets when rows are not split by a column.
+    pub fn new(workbook: &'a xlsxwriter::Workbook, sheet_name: &str) -> Self {
+        XlsxWorkbookWriter {
+            workbook,
+            columns: XlsxColumns::new(),
+            column_sizes: Vec::new(),
+            sheet_name: sheet_name.to_string(),
+            split_column_name: None,
+            split_column: None,
+            max_rows: XLSX_MAX_ROWS,
+            sheets: Vec::new(),
+            groups: HashMap::new(),
+            used_names: HashSet::new(),
+        }
+    }
+
+    /// Write rows into worksheets named with values of the column.
+    pub fn set_split_column(&mut self, column: Option<&str>) {
+        self.split_column_name = column.map(|x| x.to_string());
+        self.split_column = column.and_then(|x| self.columns.header.iter().position(|y| y == x));
+    }
+
+    /// Set maximum number of rows in a worksheet including the header row.
+    pub fn set_max_rows(&mut self, max_rows: u32) {
+        self.max_rows = max_rows.clamp(2, XLSX_MAX_ROWS);
+    }
+
+    /// Reserve a worksheet name, such as a summary sheet created outside of this writer.
+    pub fn reserve_sheet_name(&mut self, name: &str) {
+        self.used_names.insert(name.to_lowercase());
+    }
+
+    pub fn set_hyperlinks(&mut self, hyperlinks: &[Option<HyperlinkTemplate>]) {
+        self.columns.hyperlinks.clear();
+        self.columns.hyperlinks.extend_from_slice(hyperlinks);
+    }
+
+    pub fn set_data_type(&mut self, data_type: &[XlsxDataType]) {
+        self.columns.data_type.clear();
+        self.columns.data_type.extend_from_slice(data_type);
+    }
+
+    pub fn set_header_comment(&mut self, items: &[String]) {
+        self.columns.header_comment.clear();
+        self.columns.header_comment.extend_from_slice(items);
+    }
+
+    /// Set column widths of existing and new worksheets.
+    pub fn set_column_sizes(&mut self, widths: &[f64]) -> Result<(), VCFUtilsError> {
+        self.column_sizes.clear();
+        self.column_sizes.extend_from_slice(widths);
+        for one in self.sheets.iter_mut() {
+            for (column_index, one_width) in widths.iter().enumerate() {
+                one.worksheet.set_column(
+                    column_index as u16,
+                    column_index as u16,
+                    *one_width,
+                    None,
+                )?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Names and number of data rows of worksheets in creation order
+    pub fn sheet_rows(&self) -> Vec<(&str, u32)> {
+        self.sheets
+            .iter()
+            .map(|x| (x.name.as_str(), x.current_row - 1))
+            .collect()
+    }
+
+    /// Worksheets and number of data rows in creation order
+    pub fn worksheets_mut(
+        &mut self,
+    ) -> impl Iterator<Item = (&mut xlsxwriter::Worksheet<'a>, u32)> + '_ {
+        self.sheets
+            .iter_mut()
+            .map(|x| (&mut x.worksheet, x.current_row - 1))
+    }
+
+    fn unique_sheet_name(&mut self, base: &str, suffix: &str) -> String {
+        let mut name = xlsx_sheet_name(base, suffix);
+        let mut count = 1;
+        while self.used_names.contains(&name.to_lowercase()) {
+            count += 1;
+            name = xlsx_sheet_name(base, &format!("{}_{}", suffix, count));
+        }
+        self.used_names.insert(name.to_lowercase());
+        name
+    }
+
+    /// Return index of a worksheet to write a row of the group.
+    fn sheet_for(&mut self, group: &str) -> Result<usize, VCFUtilsError> {
+        let (last_index, sheet_count) = match self.groups.get(group) {
+            Some((index, count)) => {
+                if self.sheets[*index].current_row < self.max_rows {
+                    return Ok(*index);
+                }
+                (Some(*index), *count)
+            }
+            None => (None, 0),
+        };
+        let base = if self.split_column.is_some() {
+            group.to_string()
+        } else {
+            self.sheet_name.clone()
+        };
+        let suffix = if last_index.is_some() {
+            format!(" ({})", sheet_count + 1)
+        } else {
+            String::new()
+        };
+        let name = self.unique_sheet_name(&base, &suffix);
+        if let Some(index) = last_index {
+            log::info!(
+                "Worksheet {} is full. Continue to {}",
+                self.sheets[index].name,
+                name
+            );
+        }
+
+        let mut worksheet = self.workbook.add_worksheet(Some(&name))?;
+        for (column_index, one_width) in self.column_sizes.iter().enumerate() {
+            worksheet.set_column(column_index as u16, column_index as u16, *one_width, None)?;
+        }
+        self.columns.write_header(&mut worksheet, 0)?;
+        self.sheets.push(XlsxSheetState {
+            name,
+            worksheet,
+            current_row: 1,
+            hyperlink_count: 0,
+        });
+        let index = self.sheets.len() - 1;
+        self.groups
+            .insert(group.to_string(), (index, sheet_count + 1));
+        Ok(index)
+    }
+}
+
+impl<'a> TableWriter for XlsxWorkbookWriter<'a> {
+    fn set_header(&mut self, items: &[String]) {
+        self.columns.header.clear();
+        self.columns.header.extend_from_slice(items);
+        self.split_column = self
+            .split_column_name
+            .as_ref()
+            .and_then(|x| items.iter().position(|y| y == x));
+    }
+
+    fn header(&self) -> &[String] {
+        &self.columns.header
+    }
+
+    /// Header rows are written when worksheets are created. A worksheet is created here if rows
+    /// are not split.
+    fn write_header(&mut self) -> Result<(), VCFUtilsError> {
+        if let Some(name) = self.split_column_name.as_ref() {
+            if self.split_column.is_none() {
+                return Err(VCFUtilsError::ColumnNotFound(name.to_string()));
+            }
+        } else {
+            self.sheet_for("")?;
+        }
+        Ok(())
+    }
+
+    fn write_row(&mut self, items: &[&str]) -> Result<(), VCFUtilsError> {
+        let group = match (self.split_column_name.as_ref(), self.split_column) {
+            (Some(_), Some(index)) => items.get(index).copied().unwrap_or(""),
+            (Some(name), None) => return Err(VCFUtilsError::ColumnNotFound(name.to_string())),
+            (None, _) => "",
+        };
+        let index = self.sheet_for(group)?;
+        let sheet = &mut self.sheets[index];
+        self.columns.write_row(
+            &mut sheet.worksheet,
+            sheet.current_row,
+            items,
+            &mut sheet.hyperlink_count,
+        )?;
+        sheet.current_row += 1;
+        Ok(())
+    }
+
+    fn is_formula_compatible(&self) -> bool {
+        true
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -355,6 +748,45 @@ hash1		hash3
         Ok(())
     }
 
+    #[test]
+    fn test_hyperlink_template() -> Result<(), VCFUtilsError> {
+        assert_eq!(
+            HyperlinkTemplate::DbSnp.url("rs123"),
+            Some("https://www.ncbi.nlm.nih.gov/snp/rs123".to_string())
+        );
+        assert_eq!(HyperlinkTemplate::DbSnp.url("COSV123"), None);
+        assert_eq!(HyperlinkTemplate::DbSnp.url("rs123;rs456"), None);
+        assert_eq!(HyperlinkTemplate::DbSnp.url("."), None);
+        assert_eq!(
+            HyperlinkTemplate::ClinVar.url("12345"),
+            Some("https://www.ncbi.nlm.nih.gov/clinvar/variation/12345/".to_string())
+        );
+        assert_eq!(HyperlinkTemplate::ClinVar.url("VCV12345"), None);
+        assert_eq!(
+            "gnomad".parse::<HyperlinkTemplate>()?.url("BRCA2"),
+            Some("https://gnomad.broadinstitute.org/gene/BRCA2".to_string())
+        );
+        assert_eq!(
+            "https://example.com/?q={}"
+                .parse::<HyperlinkTemplate>()?
+                .url("BRCA2"),
+            Some("https://example.com/?q=BRCA2".to_string())
+        );
+        assert!("https://example.com/".parse::<HyperlinkTemplate>().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_xlsx_sheet_name() {
+        assert_eq!(xlsx_sheet_name("chr1", ""), "chr1");
+        assert_eq!(xlsx_sheet_name("", ""), "NA");
+        assert_eq!(xlsx_sheet_name("'A/B:C'", " (2)"), "A_B_C (2)");
+        assert_eq!(
+            xlsx_sheet_name("ABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789", " (10)"),
+            "ABCDEFGHIJKLMNOPQRSTUVWXYZ (10)"
+        );
+    }
+
     #[test]
     fn test_csv_writer() -> Result<(), VCFUtilsError> {
         let mut write_buf: Vec<u8> = Vec::new();